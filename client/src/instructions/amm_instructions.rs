@@ -2,13 +2,17 @@ use anchor_client::{Client, Cluster};
 use anyhow::Result;
 use gamma::states::USER_POOL_LIQUIDITY_SEED;
 use solana_sdk::signer::Signer;
-use solana_sdk::{instruction::Instruction, pubkey::Pubkey, system_program, sysvar};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
 
 use gamma::accounts as gamma_accounts;
 use gamma::instruction as gamma_instructions;
 use gamma::{
     states::{AMM_CONFIG_SEED, OBSERVATION_SEED, POOL_LP_MINT_SEED, POOL_SEED, POOL_VAULT_SEED},
-    AUTH_SEED,
+    AUTH_SEED, REWARD_VAULT_SEED, USER_REWARD_INFO_SEED,
 };
 use std::rc::Rc;
 
@@ -65,6 +69,12 @@ pub fn initialize_pool_instr(
     init_amount_0: u64,
     init_amount_1: u64,
     open_time: u64,
+    curve_type: u8,
+    amp: u64,
+    token_0_weight: u32,
+    launch_fee_rate: u64,
+    launch_fee_duration: u64,
+    launch_fee_decay: u8,
 ) -> Result<Vec<Instruction>> {
     let payer = read_keypair_file(&config.payer_path)?;
     let user_pubkey = payer.pubkey();
@@ -157,12 +167,18 @@ pub fn initialize_pool_instr(
             system_program: system_program::id(),
             rent: sysvar::rent::id(),
         })
-        .args(gamma_instructions::Initialize {
+        .args(gamma_instructions::InitializeV2 {
             init_amount_0,
             init_amount_1,
             open_time,
             max_trade_fee_rate: 1000000,
             volatility_factor: 0,
+            curve_type,
+            amp,
+            token_0_weight,
+            launch_fee_rate,
+            launch_fee_duration,
+            launch_fee_decay,
         })
         .instructions()?;
     Ok(instructions)
@@ -246,6 +262,8 @@ pub fn deposit_instr(
             vault_0_mint: token_0_mint,
             vault_1_mint: token_1_mint,
             // lp_mint: token_lp_mint,
+            system_program: Some(system_program::id()),
+            partner_info: None,
         })
         .args(gamma_instructions::Deposit {
             lp_token_amount,
@@ -306,6 +324,8 @@ pub fn withdraw_instr(
             vault_1_mint: token_1_mint,
             // lp_mint: token_lp_mint,
             memo_program: spl_memo::id(),
+            system_program: Some(system_program::id()),
+            partner_info: None,
         })
         .args(gamma_instructions::Withdraw {
             lp_token_amount,
@@ -414,6 +434,29 @@ pub fn swap_base_output_instr(
     Ok(instructions)
 }
 
+pub fn get_twap_instr(
+    config: &ClientConfig,
+    pool_id: Pubkey,
+    observation_account: Pubkey,
+    window_seconds: u64,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.gamma_program)?;
+
+    let instructions = program
+        .request()
+        .accounts(gamma_accounts::GetTwap {
+            pool_state: pool_id,
+            observation_state: observation_account,
+        })
+        .args(gamma_instructions::GetTwap { window_seconds })
+        .instructions()?;
+    Ok(instructions)
+}
+
 pub fn init_user_pool_liquidity_instr(
     config: &ClientConfig,
     pool_id: Pubkey,
@@ -443,9 +486,160 @@ pub fn init_user_pool_liquidity_instr(
             user_pool_liquidity,
             system_program: system_program::id(),
         })
-        .args(gamma_instructions::InitUserPoolLiquidity {
-            partner: None,
+        .args(gamma_instructions::InitUserPoolLiquidity { partner: None })
+        .instructions()?;
+    Ok(instructions)
+}
+
+/// Migrates a reward created before the rewards were accrued per lp token
+pub fn migrate_reward_info_instr(
+    config: &ClientConfig,
+    pool_id: Pubkey,
+    reward_info: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let payer_pubkey = payer.pubkey();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.gamma_program)?;
+
+    let instructions = program
+        .request()
+        .accounts(gamma_accounts::MigrateRewardInfo {
+            payer: payer_pubkey,
+            pool_state: pool_id,
+            reward_info,
+            system_program: system_program::id(),
         })
+        .args(gamma_instructions::MigrateRewardInfo {})
         .instructions()?;
     Ok(instructions)
 }
+
+/// Migrates the user reward of the payer created before the rewards were accrued per lp token,
+/// `reward_info` must be migrated first
+pub fn migrate_user_reward_info_instr(
+    config: &ClientConfig,
+    reward_info: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let user_pubkey = payer.pubkey();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.gamma_program)?;
+
+    let user_reward_info = Pubkey::find_program_address(
+        &[
+            USER_REWARD_INFO_SEED.as_bytes(),
+            reward_info.to_bytes().as_ref(),
+            user_pubkey.to_bytes().as_ref(),
+        ],
+        &program.id(),
+    )
+    .0;
+    let instructions = program
+        .request()
+        .accounts(gamma_accounts::MigrateUserRewardInfo {
+            payer: user_pubkey,
+            user: user_pubkey,
+            reward_info,
+            user_reward_info,
+            system_program: system_program::id(),
+        })
+        .args(gamma_instructions::MigrateUserRewardInfo {})
+        .instructions()?;
+    Ok(instructions)
+}
+
+/// Claims the rewards of `claims`, given as `(pool_id, reward_info, reward_mint, reward_token_program)`.
+/// The rewards are calculated first, which creates the user reward infos the user does not have yet.
+/// Legacy rewards and user rewards must be migrated first.
+pub fn claim_all_rewards_instr(
+    config: &ClientConfig,
+    claims: &[(Pubkey, Pubkey, Pubkey, Pubkey)],
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let user_pubkey = payer.pubkey();
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.gamma_program)?;
+
+    let (authority, __bump) = Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &program.id());
+    let mut instructions = Vec::new();
+    let mut remaining_accounts = Vec::new();
+    for (pool_id, reward_info, reward_mint, reward_token_program) in claims {
+        let user_reward_info = Pubkey::find_program_address(
+            &[
+                USER_REWARD_INFO_SEED.as_bytes(),
+                reward_info.to_bytes().as_ref(),
+                user_pubkey.to_bytes().as_ref(),
+            ],
+            &program.id(),
+        )
+        .0;
+        let reward_vault = Pubkey::find_program_address(
+            &[
+                REWARD_VAULT_SEED.as_bytes(),
+                reward_info.to_bytes().as_ref(),
+            ],
+            &program.id(),
+        )
+        .0;
+        let user_token_account =
+            spl_associated_token_account::get_associated_token_address_with_program_id(
+                &user_pubkey,
+                reward_mint,
+                reward_token_program,
+            );
+        let user_pool_liquidity = Pubkey::find_program_address(
+            &[
+                USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                user_pubkey.to_bytes().as_ref(),
+            ],
+            &program.id(),
+        )
+        .0;
+        instructions.extend(
+            program
+                .request()
+                .accounts(gamma_accounts::CalculateRewards {
+                    user: user_pubkey,
+                    pool_state: *pool_id,
+                    reward_info: *reward_info,
+                    user_reward_info,
+                    user_pool_liquidity,
+                    system_program: system_program::id(),
+                })
+                .args(gamma_instructions::CalculateRewards {})
+                .instructions()?,
+        );
+        remaining_accounts.extend([
+            AccountMeta::new(*reward_info, false),
+            AccountMeta::new(user_reward_info, false),
+            AccountMeta::new(reward_vault, false),
+            AccountMeta::new(user_token_account, false),
+            AccountMeta::new_readonly(*reward_mint, false),
+            AccountMeta::new_readonly(*pool_id, false),
+            AccountMeta::new_readonly(user_pool_liquidity, false),
+        ]);
+    }
+
+    instructions.extend(
+        program
+            .request()
+            .accounts(gamma_accounts::ClaimAllRewards {
+                user: user_pubkey,
+                authority,
+                token_program: spl_token::id(),
+                token_program_2022: spl_token_2022::id(),
+            })
+            .accounts(remaining_accounts)
+            .args(gamma_instructions::ClaimAllRewards {})
+            .instructions()?,
+    );
+    Ok(instructions)
+}
@@ -143,6 +143,18 @@ pub fn handle_program_log(
             LpChangeEvent::DISCRIMINATOR => {
                 println!("{:#?}", decode_event::<LpChangeEvent>(&mut slice)?);
             }
+            RewardsClaimedEvent::DISCRIMINATOR => {
+                println!("{:#?}", decode_event::<RewardsClaimedEvent>(&mut slice)?);
+            }
+            PartnerFeesClaimedEvent::DISCRIMINATOR => {
+                println!(
+                    "{:#?}",
+                    decode_event::<PartnerFeesClaimedEvent>(&mut slice)?
+                );
+            }
+            FlashLoanEvent::DISCRIMINATOR => {
+                println!("{:#?}", decode_event::<FlashLoanEvent>(&mut slice)?);
+            }
             _ => {
                 println!("unknow event: {}", l);
             }
@@ -366,6 +378,37 @@ pub fn handle_program_instruction(
             }
             println!("{:#?}", Initialize::from(ix));
         }
+        instruction::InitializeV2::DISCRIMINATOR => {
+            let ix = decode_instruction::<instruction::InitializeV2>(&mut ix_data).unwrap();
+            #[derive(Debug)]
+            pub struct InitializeV2 {
+                pub init_amount_0: u64,
+                pub init_amount_1: u64,
+                pub open_time: u64,
+                pub curve_type: u8,
+                pub amp: u64,
+                pub token_0_weight: u32,
+                pub launch_fee_rate: u64,
+                pub launch_fee_duration: u64,
+                pub launch_fee_decay: u8,
+            }
+            impl From<instruction::InitializeV2> for InitializeV2 {
+                fn from(instr: instruction::InitializeV2) -> InitializeV2 {
+                    InitializeV2 {
+                        init_amount_0: instr.init_amount_0,
+                        init_amount_1: instr.init_amount_1,
+                        open_time: instr.open_time,
+                        curve_type: instr.curve_type,
+                        amp: instr.amp,
+                        token_0_weight: instr.token_0_weight,
+                        launch_fee_rate: instr.launch_fee_rate,
+                        launch_fee_duration: instr.launch_fee_duration,
+                        launch_fee_decay: instr.launch_fee_decay,
+                    }
+                }
+            }
+            println!("{:#?}", InitializeV2::from(ix));
+        }
         // instruction::UpdatePoolStatus::DISCRIMINATOR => {
         //     let ix = decode_instruction::<instruction::UpdatePoolStatus>(&mut ix_data).unwrap();
         //     #[derive(Debug)]
@@ -487,6 +530,48 @@ pub fn handle_program_instruction(
             }
             println!("{:#?}", SwapBaseOutput::from(ix));
         }
+        instruction::SwapBaseInputV2::DISCRIMINATOR => {
+            let ix = decode_instruction::<instruction::SwapBaseInputV2>(&mut ix_data).unwrap();
+            #[derive(Debug)]
+            pub struct SwapBaseInputV2 {
+                pub amount_in: u64,
+                pub minimum_amount_out: u64,
+                pub max_price_x32: u128,
+                pub deadline: u64,
+            }
+            impl From<instruction::SwapBaseInputV2> for SwapBaseInputV2 {
+                fn from(instr: instruction::SwapBaseInputV2) -> SwapBaseInputV2 {
+                    SwapBaseInputV2 {
+                        amount_in: instr.amount_in,
+                        minimum_amount_out: instr.minimum_amount_out,
+                        max_price_x32: instr.max_price_x32,
+                        deadline: instr.deadline,
+                    }
+                }
+            }
+            println!("{:#?}", SwapBaseInputV2::from(ix));
+        }
+        instruction::SwapBaseOutputV2::DISCRIMINATOR => {
+            let ix = decode_instruction::<instruction::SwapBaseOutputV2>(&mut ix_data).unwrap();
+            #[derive(Debug)]
+            pub struct SwapBaseOutputV2 {
+                pub max_amount_in: u64,
+                pub amount_out: u64,
+                pub max_price_x32: u128,
+                pub deadline: u64,
+            }
+            impl From<instruction::SwapBaseOutputV2> for SwapBaseOutputV2 {
+                fn from(instr: instruction::SwapBaseOutputV2) -> SwapBaseOutputV2 {
+                    SwapBaseOutputV2 {
+                        max_amount_in: instr.max_amount_in,
+                        amount_out: instr.amount_out,
+                        max_price_x32: instr.max_price_x32,
+                        deadline: instr.deadline,
+                    }
+                }
+            }
+            println!("{:#?}", SwapBaseOutputV2::from(ix));
+        }
         _ => {
             println!("unknow instruction: {}", instr_data);
         }
@@ -1,4 +1,6 @@
+use anchor_lang::AnchorDeserialize;
 use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use solana_client::{
     rpc_client::RpcClient,
     rpc_config::RpcSendTransactionConfig,
@@ -26,6 +28,27 @@ pub fn simulate_transaction(
     )
 }
 
+/// Simulates the transaction and decodes the data returned by its last instruction
+pub fn simulate_return_data<T: AnchorDeserialize>(
+    client: &RpcClient,
+    transaction: &Transaction,
+) -> Result<T> {
+    let result = simulate_transaction(client, transaction, false, CommitmentConfig::confirmed())?;
+    if let Some(err) = result.value.err {
+        return Err(anyhow!(
+            "simulation failed: {}, logs: {:?}",
+            err,
+            result.value.logs
+        ));
+    }
+    let return_data = result
+        .value
+        .return_data
+        .ok_or(anyhow!("transaction returned no data"))?;
+    let data = STANDARD.decode(return_data.data.0)?;
+    Ok(T::try_from_slice(&data)?)
+}
+
 pub fn send_txn(client: &RpcClient, txn: &Transaction, wait_confirm: bool) -> Result<Signature> {
     Ok(client.send_and_confirm_transaction_with_spinner_and_config(
         txn,
@@ -1,10 +1,16 @@
 #![allow(dead_code)]
 use anchor_client::{Client, Cluster};
+use anchor_lang::{AnchorDeserialize, Discriminator};
 use anyhow::{format_err, Result};
 use arrayref::array_ref;
 use clap::{Parser, Subcommand};
 use dotenv::dotenv;
-use solana_client::{rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
@@ -139,6 +145,24 @@ pub enum GammaCommands {
         init_amount_1: u64,
         #[clap(short, long, default_value_t = 0)]
         open_time: u64,
+        /// 0 for constant product, 1 for stable swap, 2 for weighted
+        #[clap(long, default_value_t = 0)]
+        curve_type: u8,
+        /// Amplification coefficient of stable swap pools
+        #[clap(long, default_value_t = 0)]
+        amp: u64,
+        /// Weight of mint0 out of 100 in weighted pools
+        #[clap(long, default_value_t = 0)]
+        mint0_weight: u32,
+        /// Trade fee charged at open time, decaying to the normal fee, 0 to disable
+        #[clap(long, default_value_t = 0)]
+        launch_fee_rate: u64,
+        /// Seconds it takes the launch fee to decay
+        #[clap(long, default_value_t = 0)]
+        launch_fee_duration: u64,
+        /// 0 for linear launch fee decay, 1 for exponential
+        #[clap(long, default_value_t = 0)]
+        launch_fee_decay: u8,
     },
     InitUserPoolLiquidity {
         pool_id: Pubkey,
@@ -161,6 +185,11 @@ pub enum GammaCommands {
         user_input_token: Pubkey,
         amount_out_less_fee: u64,
     },
+    GetTwap {
+        pool_id: Pubkey,
+        window_seconds: u64,
+    },
+    ClaimAllRewards,
     DecodeInstruction {
         instr_hex_data: String,
     },
@@ -252,11 +281,27 @@ fn main() -> Result<()> {
             init_amount_0,
             init_amount_1,
             open_time,
+            curve_type,
+            amp,
+            mint0_weight,
+            launch_fee_rate,
+            launch_fee_duration,
+            launch_fee_decay,
         } => {
-            let (mint0, mint1, init_amount_0, init_amount_1) = if mint0 > mint1 {
-                (mint1, mint0, init_amount_1, init_amount_0)
+            let (mint0, mint1, init_amount_0, init_amount_1, token_0_weight) = if mint0 > mint1 {
+                (
+                    mint1,
+                    mint0,
+                    init_amount_1,
+                    init_amount_0,
+                    if mint0_weight == 0 {
+                        0
+                    } else {
+                        gamma::curve::WEIGHT_DENOMINATOR - mint0_weight
+                    },
+                )
             } else {
-                (mint0, mint1, init_amount_0, init_amount_1)
+                (mint0, mint1, init_amount_0, init_amount_1, mint0_weight)
             };
             let load_pubkeys = vec![mint0, mint1];
             let rsps = rpc_client.get_multiple_accounts(&load_pubkeys)?;
@@ -275,6 +320,12 @@ fn main() -> Result<()> {
                 init_amount_0,
                 init_amount_1,
                 open_time,
+                curve_type,
+                amp,
+                token_0_weight,
+                launch_fee_rate,
+                launch_fee_duration,
+                launch_fee_decay,
             )?;
 
             let signers = vec![&payer];
@@ -617,13 +668,15 @@ fn main() -> Result<()> {
                 .as_secs();
 
             // here we make a RPC call again, we can optimize this later by making it part of `get_multiple_accounts` call.
-            let observation: gamma::states::ObservationState =
-                program.account(pool_state.observation_key)?;
+            let observation_data = rpc_client.get_account_data(&pool_state.observation_key)?;
+            let observation =
+                gamma::states::ObservationBuffer::from_account_data(&observation_data)?;
 
             let result = gamma::curve::CurveCalculator::swap_base_input(
                 u128::from(actual_amount_in),
                 u128::from(total_input_token_amount),
                 u128::from(total_output_token_amount),
+                trade_direction,
                 &amm_config_state,
                 &pool_state,
                 current_unix_timestamp,
@@ -778,13 +831,15 @@ fn main() -> Result<()> {
                 .as_secs();
 
             // here we make a RPC call again, we can optimize this later by making it part of `get_multiple_accounts` call.
-            let observation: gamma::states::ObservationState =
-                program.account(pool_state.observation_key)?;
+            let observation_data = rpc_client.get_account_data(&pool_state.observation_key)?;
+            let observation =
+                gamma::states::ObservationBuffer::from_account_data(&observation_data)?;
 
             let result = gamma::curve::CurveCalculator::swap_base_output(
                 u128::from(actual_amount_out),
                 u128::from(total_input_token_amount),
                 u128::from(total_output_token_amount),
+                trade_direction,
                 &amm_config_state,
                 &pool_state,
                 current_unix_timestamp,
@@ -844,6 +899,194 @@ fn main() -> Result<()> {
             let signature = send_txn(&rpc_client, &txn, true)?;
             println!("{}", signature);
         }
+        GammaCommands::GetTwap {
+            pool_id,
+            window_seconds,
+        } => {
+            let pool_state: gamma::states::PoolState = program.account(pool_id)?;
+            let get_twap_instr = get_twap_instr(
+                &pool_config,
+                pool_id,
+                pool_state.observation_key,
+                window_seconds,
+            )?;
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &get_twap_instr,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            let twap: gamma::states::Twap = simulate_return_data(&rpc_client, &txn)?;
+            println!("{:#?}", twap);
+        }
+        GammaCommands::ClaimAllRewards => {
+            // Rewards of the legacy layout are read as such, they are migrated before the claim
+            let reward_info_accounts = rpc_client.get_program_accounts_with_config(
+                &pool_config.gamma_program,
+                RpcProgramAccountsConfig {
+                    filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                        0,
+                        &gamma::states::RewardInfo::DISCRIMINATOR,
+                    ))]),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        ..RpcAccountInfoConfig::default()
+                    },
+                    ..RpcProgramAccountsConfig::default()
+                },
+            )?;
+            let mut reward_infos = Vec::new();
+            for (reward_info_key, reward_info_account) in reward_info_accounts.iter() {
+                if reward_info_account.data.len() == gamma::states::RewardInfo::LEGACY_LEN {
+                    let legacy = gamma::states::LegacyRewardInfo::deserialize(
+                        &mut &reward_info_account.data[8..],
+                    )?;
+                    reward_infos.push((*reward_info_key, legacy.pool, legacy.mint, true));
+                } else {
+                    let reward_info = deserialize_anchor_account::<gamma::states::RewardInfo>(
+                        reward_info_account,
+                    )?;
+                    reward_infos.push((
+                        *reward_info_key,
+                        reward_info.pool,
+                        reward_info.mint,
+                        false,
+                    ));
+                }
+            }
+
+            let mut load_pubkeys = Vec::new();
+            for (reward_info_key, pool_id, reward_mint, _) in reward_infos.iter() {
+                load_pubkeys.push(
+                    Pubkey::find_program_address(
+                        &[
+                            gamma::USER_REWARD_INFO_SEED.as_bytes(),
+                            reward_info_key.to_bytes().as_ref(),
+                            payer.pubkey().to_bytes().as_ref(),
+                        ],
+                        &pool_config.gamma_program,
+                    )
+                    .0,
+                );
+                load_pubkeys.push(
+                    Pubkey::find_program_address(
+                        &[
+                            gamma::states::USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                            pool_id.to_bytes().as_ref(),
+                            payer.pubkey().to_bytes().as_ref(),
+                        ],
+                        &pool_config.gamma_program,
+                    )
+                    .0,
+                );
+                load_pubkeys.push(*reward_mint);
+            }
+            let mut rsps = Vec::new();
+            for keys in load_pubkeys.chunks(99) {
+                rsps.extend(rpc_client.get_multiple_accounts(keys)?);
+            }
+
+            // Campaigns the user still has liquidity in or rewards to claim. The user rewards
+            // missing are created by the claim, the legacy ones are migrated first
+            let mut claims = Vec::new();
+            let mut migrate_instructions = Vec::new();
+            for ((reward_info_key, pool_id, reward_mint, reward_info_is_legacy), accounts) in
+                reward_infos.iter().zip(rsps.chunks(3))
+            {
+                let (user_reward_info_account, user_pool_liquidity_account, reward_mint_account) =
+                    match accounts {
+                        [user_reward_info, Some(user_pool_liquidity), Some(reward_mint)] => {
+                            (user_reward_info, user_pool_liquidity, reward_mint)
+                        }
+                        _ => continue,
+                    };
+                let user_pool_liquidity = deserialize_anchor_account::<
+                    gamma::states::UserPoolLiquidity,
+                >(user_pool_liquidity_account)?;
+                let user_reward_info_is_legacy = match user_reward_info_account {
+                    Some(account)
+                        if account.data.len() == gamma::states::UserRewardInfo::LEGACY_LEN =>
+                    {
+                        true
+                    }
+                    Some(account) => {
+                        let user_reward_info =
+                            deserialize_anchor_account::<gamma::states::UserRewardInfo>(account)?;
+                        if user_pool_liquidity.lp_tokens_owned == 0
+                            && user_reward_info.get_total_claimable_rewards() == 0
+                        {
+                            continue;
+                        }
+                        false
+                    }
+                    None if user_pool_liquidity.lp_tokens_owned == 0 => continue,
+                    None => false,
+                };
+                if *reward_info_is_legacy {
+                    migrate_instructions.push(migrate_reward_info_instr(
+                        &pool_config,
+                        *pool_id,
+                        *reward_info_key,
+                    )?);
+                }
+                if user_reward_info_is_legacy {
+                    migrate_instructions.push(migrate_user_reward_info_instr(
+                        &pool_config,
+                        *reward_info_key,
+                    )?);
+                }
+                claims.push((
+                    *pool_id,
+                    *reward_info_key,
+                    *reward_mint,
+                    reward_mint_account.owner,
+                ));
+            }
+            println!(
+                "migrating {} rewards, claiming {} rewards",
+                migrate_instructions.len(),
+                claims.len()
+            );
+
+            for instructions in migrate_instructions {
+                let signers = vec![&payer];
+                let recent_hash = rpc_client.get_latest_blockhash()?;
+                let txn = Transaction::new_signed_with_payer(
+                    &instructions,
+                    Some(&payer.pubkey()),
+                    &signers,
+                    recent_hash,
+                );
+                let signature = send_txn(&rpc_client, &txn, true)?;
+                println!("{}", signature);
+            }
+
+            // The calculations of the rewards come with the claims, two of them fit a transaction
+            for claims in claims.chunks(2) {
+                let mut instructions = Vec::new();
+                for (_, _, reward_mint, reward_token_program) in claims {
+                    instructions.extend(create_ata_token_account_instr(
+                        &pool_config,
+                        *reward_token_program,
+                        reward_mint,
+                        &payer.pubkey(),
+                    )?);
+                }
+                instructions.extend(claim_all_rewards_instr(&pool_config, claims)?);
+                let signers = vec![&payer];
+                let recent_hash = rpc_client.get_latest_blockhash()?;
+                let txn = Transaction::new_signed_with_payer(
+                    &instructions,
+                    Some(&payer.pubkey()),
+                    &signers,
+                    recent_hash,
+                );
+                let signature = send_txn(&rpc_client, &txn, true)?;
+                println!("{}", signature);
+            }
+        }
         GammaCommands::DecodeInstruction { instr_hex_data } => {
             handle_program_instruction(&instr_hex_data, InstructionDecodeType::BaseHex)?;
         }
@@ -1,12 +1,23 @@
 //! Swap calculations
 
 use crate::error::GammaError;
-use crate::fees::{DynamicFee, FeeType};
-use crate::states::{AmmConfig, ObservationState, PoolState};
-use crate::{curve::constant_product::ConstantProductCurve, fees::StaticFee};
+use crate::fees::DynamicFee;
+use crate::states::{AmmConfig, ObservationBuffer, PoolState};
+use crate::utils::math::U256;
+use crate::{
+    curve::{
+        constant_product::ConstantProductCurve, stable_swap::StableSwapCurve,
+        weighted::WeightedCurve,
+    },
+    fees::{ceil_div, StaticFee, FEE_RATE_DENOMINATOR_VALUE},
+};
 use anchor_lang::prelude::*;
 use std::fmt::Debug;
 
+/// Swaps evaluated by `CurveCalculator::max_source_amount_within_price` to correct the rounding of
+/// the curve around the amount solved from its invariant
+pub const MAX_PRICE_LIMIT_EVALUATIONS: u32 = 16;
+
 /// Helper function for mapping to GammaError::CalculationFailure
 pub fn map_zero_to_none(x: u128) -> Option<u128> {
     if x == 0 {
@@ -48,6 +59,28 @@ pub enum RoundDirection {
     Ceiling,
 }
 
+/// The invariant of a pool, chosen when the pool is created
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CurveType {
+    /// Uniswap style x * y = k
+    ConstantProduct,
+    /// Curve style StableSwap with amplification, for pegged pairs
+    StableSwap,
+    /// Balancer style x^w0 * y^w1 = k, with weights fixed when the pool is created
+    Weighted,
+}
+
+impl CurveType {
+    pub fn new(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(CurveType::ConstantProduct),
+            1 => Ok(CurveType::StableSwap),
+            2 => Ok(CurveType::Weighted),
+            _ => err!(GammaError::InvalidCurveType),
+        }
+    }
+}
+
 /// Encodes results of depositing both sides at once
 #[derive(Debug, PartialEq)]
 pub struct TradingTokenResult {
@@ -100,25 +133,51 @@ impl CurveCalculator {
         source_amount_to_be_swapped: u128,
         swap_source_amount: u128,
         swap_destination_amount: u128,
+        trade_direction: TradeDirection,
         amm_config: &AmmConfig,
         pool_state: &PoolState,
         block_timestamp: u64,
-        observation_state: &ObservationState,
+        observation_state: &ObservationBuffer,
         // This is to indicate that the trade is not a toxic trade and is coming to us from a signed dflow segmenter.
         // It is planed to charge an additional fee for this trade if it is false in future.
         is_invoked_by_signed_segmenter: bool,
-        // TODO: add fee type here once that is configurable on pool level/ or we can use it from pool_state
     ) -> Result<SwapResult> {
         let (dynamic_fee, dynamic_fee_rate) = DynamicFee::dynamic_fee(
             source_amount_to_be_swapped,
+            swap_source_amount,
             block_timestamp,
             observation_state,
-            FeeType::Volatility,
+            pool_state.fee_type()?,
             amm_config.trade_fee_rate,
             pool_state,
             is_invoked_by_signed_segmenter,
         )?;
+        Self::swap_base_input_with_fee(
+            source_amount_to_be_swapped,
+            swap_source_amount,
+            swap_destination_amount,
+            trade_direction,
+            amm_config,
+            pool_state,
+            block_timestamp,
+            dynamic_fee,
+            dynamic_fee_rate,
+        )
+    }
 
+    /// Subtract the given fee and calculate how much destination token will be received
+    /// for a given amount of source token
+    fn swap_base_input_with_fee(
+        source_amount_to_be_swapped: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+        amm_config: &AmmConfig,
+        pool_state: &PoolState,
+        block_timestamp: u64,
+        dynamic_fee: u128,
+        dynamic_fee_rate: u64,
+    ) -> Result<SwapResult> {
         let protocol_fee = StaticFee::protocol_fee(dynamic_fee, amm_config.protocol_fee_rate)
             .ok_or(GammaError::InvalidFee)?;
         let fund_fee = StaticFee::fund_fee(dynamic_fee, amm_config.fund_fee_rate)
@@ -127,10 +186,13 @@ impl CurveCalculator {
         let source_amount_after_fees = source_amount_to_be_swapped
             .checked_sub(dynamic_fee)
             .ok_or(GammaError::MathOverflow)?;
-        let destination_amount_swapped = ConstantProductCurve::swap_base_input_without_fees(
+        let destination_amount_swapped = Self::swap_base_input_without_fees(
             source_amount_after_fees,
             swap_source_amount,
             swap_destination_amount,
+            trade_direction,
+            pool_state,
+            block_timestamp,
         )?;
 
         #[cfg(feature = "enable-log")]
@@ -157,23 +219,28 @@ impl CurveCalculator {
         destination_amount_to_be_swapped: u128,
         swap_source_amount: u128,
         swap_destination_amount: u128,
+        trade_direction: TradeDirection,
         amm_config: &AmmConfig,
         pool_state: &PoolState,
         block_timestamp: u64,
-        observation_state: &ObservationState,
+        observation_state: &ObservationBuffer,
         is_invoked_by_signed_segmenter: bool,
     ) -> Result<SwapResult> {
-        let source_amount_swapped = ConstantProductCurve::swap_base_output_without_fees(
+        let source_amount_swapped = Self::swap_base_output_without_fees(
             destination_amount_to_be_swapped,
             swap_source_amount,
             swap_destination_amount,
+            trade_direction,
+            pool_state,
+            block_timestamp,
         )?;
 
         let (source_amount, dynamic_fee_rate) = DynamicFee::calculate_pre_fee_amount(
             block_timestamp,
             source_amount_swapped,
+            swap_source_amount,
             observation_state,
-            FeeType::Volatility,
+            pool_state.fee_type()?,
             amm_config.trade_fee_rate,
             pool_state,
             is_invoked_by_signed_segmenter,
@@ -203,8 +270,361 @@ impl CurveCalculator {
         })
     }
 
+    /// Swap the part of a single sided deposit that has to be converted into the other token,
+    /// charging the current dynamic fee on the swapped amount only.
+    ///
+    /// The fee rate can depend on the amount swapped, so the amount is solved with the rate of the
+    /// whole deposit first, then solved again and charged with the rate of that first solution.
+    pub fn swap_for_single_sided_deposit(
+        source_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+        amm_config: &AmmConfig,
+        pool_state: &PoolState,
+        block_timestamp: u64,
+        observation_state: &ObservationBuffer,
+    ) -> Result<SwapResult> {
+        let dynamic_fee_rate_of = |amount: u128| -> Result<u64> {
+            let (_, dynamic_fee_rate) = DynamicFee::dynamic_fee(
+                amount,
+                swap_source_amount,
+                block_timestamp,
+                observation_state,
+                pool_state.fee_type()?,
+                amm_config.trade_fee_rate,
+                pool_state,
+                false,
+            )?;
+            Ok(dynamic_fee_rate)
+        };
+        let swap_amount_for = |fee_rate: u64| -> Result<u128> {
+            match pool_state.curve_type()? {
+                CurveType::ConstantProduct => ConstantProductCurve::single_sided_swap_amount(
+                    source_amount,
+                    swap_source_amount,
+                    fee_rate,
+                ),
+                _ => Self::single_sided_swap_amount(
+                    source_amount,
+                    swap_source_amount,
+                    swap_destination_amount,
+                    trade_direction,
+                    fee_rate,
+                    pool_state,
+                    block_timestamp,
+                ),
+            }
+        };
+
+        let first_swap_amount = swap_amount_for(dynamic_fee_rate_of(source_amount)?)?;
+        let dynamic_fee_rate = dynamic_fee_rate_of(first_swap_amount)?;
+        let source_amount_to_be_swapped = swap_amount_for(dynamic_fee_rate)?;
+        let dynamic_fee = ceil_div(
+            source_amount_to_be_swapped,
+            u128::from(dynamic_fee_rate),
+            u128::from(FEE_RATE_DENOMINATOR_VALUE),
+        )
+        .ok_or(GammaError::MathOverflow)?;
+
+        Self::swap_base_input_with_fee(
+            source_amount_to_be_swapped,
+            swap_source_amount,
+            swap_destination_amount,
+            trade_direction,
+            amm_config,
+            pool_state,
+            block_timestamp,
+            dynamic_fee,
+            dynamic_fee_rate,
+        )
+    }
+
+    /// Calculate how much destination token will be received for a given amount of source token
+    /// after fees, with the invariant of the pool
+    pub fn swap_base_input_without_fees(
+        source_amount_to_be_swapped: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+        pool_state: &PoolState,
+        block_timestamp: u64,
+    ) -> Result<u128> {
+        match pool_state.curve_type()? {
+            CurveType::ConstantProduct => ConstantProductCurve::swap_base_input_without_fees(
+                source_amount_to_be_swapped,
+                swap_source_amount,
+                swap_destination_amount,
+            ),
+            CurveType::StableSwap => StableSwapCurve::swap_base_input_without_fees(
+                source_amount_to_be_swapped,
+                swap_source_amount,
+                swap_destination_amount,
+                pool_state.current_amp(block_timestamp),
+            ),
+            CurveType::Weighted => {
+                let (source_weight, destination_weight) = pool_state.weights(trade_direction);
+                WeightedCurve::swap_base_input_without_fees(
+                    source_amount_to_be_swapped,
+                    swap_source_amount,
+                    swap_destination_amount,
+                    source_weight,
+                    destination_weight,
+                )
+            }
+        }
+    }
+
+    /// Calculate how much source token, before fees, is required for a given amount of
+    /// destination token, with the invariant of the pool
+    pub fn swap_base_output_without_fees(
+        destination_amount_to_be_swapped: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+        pool_state: &PoolState,
+        block_timestamp: u64,
+    ) -> Result<u128> {
+        match pool_state.curve_type()? {
+            CurveType::ConstantProduct => ConstantProductCurve::swap_base_output_without_fees(
+                destination_amount_to_be_swapped,
+                swap_source_amount,
+                swap_destination_amount,
+            ),
+            CurveType::StableSwap => StableSwapCurve::swap_base_output_without_fees(
+                destination_amount_to_be_swapped,
+                swap_source_amount,
+                swap_destination_amount,
+                pool_state.current_amp(block_timestamp),
+            ),
+            CurveType::Weighted => {
+                let (source_weight, destination_weight) = pool_state.weights(trade_direction);
+                WeightedCurve::swap_base_output_without_fees(
+                    destination_amount_to_be_swapped,
+                    swap_source_amount,
+                    swap_destination_amount,
+                    source_weight,
+                    destination_weight,
+                )
+            }
+        }
+    }
+
+    /// The largest amount of source token, at most `max_source_amount`, that can be swapped
+    /// without fees before the price of the destination token in source token goes over
+    /// `max_price_x32`. Fees only lower the price reached, so the amount is safe to swap with fees.
+    ///
+    /// The amount is solved from the invariant of the curve, approximated along it for stable pools,
+    /// then corrected for the rounding of the curve with at most `MAX_PRICE_LIMIT_EVALUATIONS` swaps.
+    pub fn max_source_amount_within_price(
+        max_source_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+        max_price_x32: u128,
+        pool_state: &PoolState,
+        block_timestamp: u64,
+    ) -> Result<u128> {
+        // Amounts the curve can not quote are past any limit
+        let is_within_price = |source_amount: u128| -> bool {
+            Self::swap_base_input_without_fees(
+                source_amount,
+                swap_source_amount,
+                swap_destination_amount,
+                trade_direction,
+                pool_state,
+                block_timestamp,
+            )
+            .and_then(|destination_amount_swapped| {
+                pool_state.price_x32(
+                    swap_source_amount
+                        .checked_add(source_amount)
+                        .ok_or(GammaError::MathOverflow)?,
+                    swap_destination_amount.saturating_sub(destination_amount_swapped),
+                    trade_direction,
+                    block_timestamp,
+                )
+            })
+            .is_ok_and(|price_x32| price_x32 <= max_price_x32)
+        };
+        if is_within_price(max_source_amount) {
+            return Ok(max_source_amount);
+        }
+        let price_x32 = pool_state.price_x32(
+            swap_source_amount,
+            swap_destination_amount,
+            trade_direction,
+            block_timestamp,
+        )?;
+        if price_x32 >= max_price_x32 {
+            return Ok(0);
+        }
+        let new_swap_source_amount = match pool_state.curve_type()? {
+            CurveType::ConstantProduct => {
+                // The price grows as (x + dx)^2 / (x * y)
+                let new_swap_source_amount = (U256::from(max_price_x32)
+                    .checked_mul(U256::from(swap_source_amount))
+                    .and_then(|v| v.checked_mul(U256::from(swap_destination_amount)))
+                    .ok_or(GammaError::MathOverflow)?
+                    >> 32)
+                    .integer_sqrt();
+                u128::try_from(new_swap_source_amount).map_err(|_| GammaError::MathOverflow)?
+            }
+            CurveType::Weighted if price_x32 == 0 => swap_source_amount,
+            CurveType::Weighted => {
+                // The price grows as ((x + dx) / x)^((wx + wy) / wy)
+                let (source_weight, destination_weight) = pool_state.weights(trade_direction);
+                let ratio_x64 = WeightedCurve::pow_ratio_x64(
+                    price_x32,
+                    max_price_x32,
+                    destination_weight,
+                    source_weight
+                        .checked_add(destination_weight)
+                        .ok_or(GammaError::MathOverflow)?,
+                )?;
+                if ratio_x64 == 0 {
+                    u128::MAX
+                } else {
+                    u128::try_from((U256::from(swap_source_amount) << 64) / U256::from(ratio_x64))
+                        .unwrap_or(u128::MAX)
+                }
+            }
+            CurveType::StableSwap => StableSwapCurve::source_amount_at_price_x32(
+                pool_state.current_amp(block_timestamp),
+                swap_source_amount,
+                swap_destination_amount,
+                max_source_amount,
+                max_price_x32,
+            )?
+            .checked_add(swap_source_amount)
+            .ok_or(GammaError::MathOverflow)?,
+        };
+        let source_amount = new_swap_source_amount
+            .saturating_sub(swap_source_amount)
+            .min(max_source_amount);
+
+        // The price only grows with the amount swapped, `low` stays within it and `high` does not.
+        // Both move away from the solved amount by doubling steps until they bracket the limit.
+        let mut evaluations = 1;
+        let (mut low, mut high) = if is_within_price(source_amount) {
+            let (mut low, mut step) = (source_amount, 1u128);
+            loop {
+                let next = low.saturating_add(step);
+                if next >= max_source_amount || evaluations == MAX_PRICE_LIMIT_EVALUATIONS {
+                    break (low, max_source_amount);
+                }
+                evaluations += 1;
+                if !is_within_price(next) {
+                    break (low, next);
+                }
+                low = next;
+                step = step.saturating_mul(2);
+            }
+        } else {
+            let (mut high, mut step) = (source_amount, 1u128);
+            loop {
+                let next = high.saturating_sub(step);
+                if next == 0 || evaluations == MAX_PRICE_LIMIT_EVALUATIONS {
+                    break (0, high);
+                }
+                evaluations += 1;
+                if is_within_price(next) {
+                    break (next, high);
+                }
+                high = next;
+                step = step.saturating_mul(2);
+            }
+        };
+        while high - low > 1 && evaluations < MAX_PRICE_LIMIT_EVALUATIONS {
+            let mid = low + (high - low) / 2;
+            evaluations += 1;
+            if is_within_price(mid) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low)
+    }
+
+    /// The invariant of the pool for the given amounts, it must never decrease with a swap.
+    /// Only the order of the values matters, weighted pools return the logarithm of the invariant.
+    pub fn invariant(
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+        pool_state: &PoolState,
+        block_timestamp: u64,
+    ) -> Result<u128> {
+        match pool_state.curve_type()? {
+            CurveType::ConstantProduct => swap_source_amount
+                .checked_mul(swap_destination_amount)
+                .ok_or(GammaError::MathOverflow.into()),
+            CurveType::StableSwap => StableSwapCurve::compute_d(
+                pool_state.current_amp(block_timestamp),
+                swap_source_amount,
+                swap_destination_amount,
+            ),
+            CurveType::Weighted => {
+                let (source_weight, destination_weight) = pool_state.weights(trade_direction);
+                WeightedCurve::log2_invariant(
+                    swap_source_amount,
+                    swap_destination_amount,
+                    source_weight,
+                    destination_weight,
+                )
+            }
+        }
+    }
+
+    /// Find the part of `source_amount` to swap for a single sided deposit by bisection,
+    /// for curves without a closed form. The swap output and the rest of the input must
+    /// be in the pool ratio after the swap:
+    /// (source_amount - s) * (y - out(s)) = out(s) * (x + s)
+    fn single_sided_swap_amount(
+        source_amount: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        trade_direction: TradeDirection,
+        fee_rate: u64,
+        pool_state: &PoolState,
+        block_timestamp: u64,
+    ) -> Result<u128> {
+        let (mut low, mut high) = (0u128, source_amount);
+        while low < high {
+            let middle = low + (high - low + 1) / 2;
+            let fee = StaticFee::trading_fee(middle, fee_rate).ok_or(GammaError::MathOverflow)?;
+            let amount_out = Self::swap_base_input_without_fees(
+                middle.checked_sub(fee).ok_or(GammaError::MathOverflow)?,
+                swap_source_amount,
+                swap_destination_amount,
+                trade_direction,
+                pool_state,
+                block_timestamp,
+            )?;
+            let left = U256::from(source_amount - middle)
+                * U256::from(
+                    swap_destination_amount
+                        .checked_sub(amount_out)
+                        .ok_or(GammaError::MathOverflow)?,
+                );
+            let right = U256::from(amount_out) * U256::from(swap_source_amount + middle);
+            if left >= right {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        Ok(low)
+    }
+
     /// Get the amount of trading tokens for the given amount of pool tokens
     /// provided the total trading tokens and supply of pool tokens
+    ///
+    /// Every supported invariant scales linearly with the pool amounts, so pool tokens are a
+    /// proportional share of both trading tokens whatever the curve of the pool. For weighted
+    /// pools this keeps the vaults at their weights, the value of the deposit is split between
+    /// the tokens in the same proportions as the pool.
     pub fn lp_tokens_to_trading_tokens(
         lp_token_amount_to_be_exchanged: u128,
         lp_token_supply: u128,
@@ -220,4 +640,30 @@ impl CurveCalculator {
             round_direction,
         )
     }
+
+    /// Get the amount of lp tokens for the given amount of token_0, see `lp_tokens_to_trading_tokens`
+    pub fn token_0_to_lp_tokens(
+        trading_token_0_amount: u128,
+        total_token_0_amount: u128,
+        lp_token_supply: u128,
+    ) -> Option<u128> {
+        ConstantProductCurve::token_0_to_lp_tokens(
+            trading_token_0_amount,
+            total_token_0_amount,
+            lp_token_supply,
+        )
+    }
+
+    /// Get the amount of lp tokens for the given amount of token_1, see `lp_tokens_to_trading_tokens`
+    pub fn token_1_to_lp_tokens(
+        trading_token_1_amount: u128,
+        total_token_1_amount: u128,
+        lp_token_supply: u128,
+    ) -> Option<u128> {
+        ConstantProductCurve::token_1_to_lp_tokens(
+            trading_token_1_amount,
+            total_token_1_amount,
+            lp_token_supply,
+        )
+    }
 }
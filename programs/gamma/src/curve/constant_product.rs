@@ -1,6 +1,7 @@
 //! The Uniswap invariantConstantProductCurve::
 
-use crate::utils::math::CheckedCeilDiv;
+use crate::fees::FEE_RATE_DENOMINATOR_VALUE;
+use crate::utils::math::{CheckedCeilDiv, U256};
 use crate::{
     curve::calculator::{RoundDirection, TradingTokenResult},
     error::GammaError,
@@ -56,6 +57,57 @@ impl ConstantProductCurve {
         Ok(source_amount_swapped)
     }
 
+    /// Get the part of `source_amount` that has to be swapped so that the rest of it and
+    /// the swap output are in the pool ratio after the swap, for single sided deposits.
+    ///
+    /// With x = swap_source_amount, a = source_amount and f = fee_rate / D, solving
+    /// (a - s) / (x + s) = (1 - f) * s / x for s gives
+    /// s = (sqrt(x^2 * (2 - f)^2 + 4 * (1 - f) * x * a) - x * (2 - f)) / (2 * (1 - f))
+    pub fn single_sided_swap_amount(
+        source_amount: u128,
+        swap_source_amount: u128,
+        fee_rate: u64,
+    ) -> Result<u128> {
+        let denominator_value = U256::from(FEE_RATE_DENOMINATOR_VALUE);
+        let fee_rate = U256::from(fee_rate);
+        let x = U256::from(swap_source_amount);
+        let a = U256::from(source_amount);
+
+        // Everything is multiplied by D to stay in integers
+        let two_minus_f = denominator_value
+            .checked_mul(U256::from(2))
+            .and_then(|v| v.checked_sub(fee_rate))
+            .ok_or(GammaError::MathOverflow)?;
+        let one_minus_f = denominator_value
+            .checked_sub(fee_rate)
+            .ok_or(GammaError::MathOverflow)?;
+        let x_times_two_minus_f = x.checked_mul(two_minus_f).ok_or(GammaError::MathOverflow)?;
+        let discriminant = x_times_two_minus_f
+            .checked_mul(x_times_two_minus_f)
+            .and_then(|v| {
+                v.checked_add(
+                    U256::from(4)
+                        .checked_mul(one_minus_f)?
+                        .checked_mul(denominator_value)?
+                        .checked_mul(x)?
+                        .checked_mul(a)?,
+                )
+            })
+            .ok_or(GammaError::MathOverflow)?;
+        let swap_amount = discriminant
+            .integer_sqrt()
+            .checked_sub(x_times_two_minus_f)
+            .ok_or(GammaError::MathOverflow)?
+            .checked_div(
+                one_minus_f
+                    .checked_mul(U256::from(2))
+                    .ok_or(GammaError::MathOverflow)?,
+            )
+            .ok_or(GammaError::MathOverflow)?;
+
+        Ok(swap_amount.as_u128().min(source_amount))
+    }
+
     /// Get the amount of trading tokens(token_0 and token_1) for a given amount of pool tokens(lp_tokens)
     /// provided the total trading tokens and supply of pool tokens
     ///
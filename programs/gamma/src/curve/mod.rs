@@ -2,6 +2,10 @@
 
 pub mod calculator;
 pub mod constant_product;
+pub mod stable_swap;
+pub mod weighted;
 
 pub use calculator::*;
 pub use constant_product::*;
+pub use stable_swap::*;
+pub use weighted::*;
@@ -0,0 +1,314 @@
+//! The StableSwap invariant with amplification, for pegged pairs
+
+use crate::{error::GammaError, states::Q32, utils::math::U256};
+use anchor_lang::prelude::*;
+
+/// Minimum amplification coefficient
+pub const MIN_AMP: u64 = 1;
+/// Maximum amplification coefficient
+pub const MAX_AMP: u64 = 1_000_000;
+/// Maximum factor the amplification coefficient can change by in one ramp
+pub const MAX_AMP_CHANGE: u64 = 10;
+/// Minimum duration of an amplification ramp, 1 day
+pub const MIN_RAMP_DURATION: u64 = 86400;
+
+/// Number of coins in the pool
+const N_COINS: u64 = 2;
+/// Maximum number of newton iterations before giving up
+const MAX_ITERATIONS: u8 = 255;
+/// Units kept in the pool on every swap so that the invariant never decreases
+/// because of the rounding of the newton iterations
+const NEWTON_ROUNDING_MARGIN: u128 = 2;
+/// Bisection steps of `StableSwapCurve::source_amount_at_price_x32` once the magnitude of the amount
+/// is known, enough for the rounding correction of `CurveCalculator::max_source_amount_within_price`
+const MAX_PRICE_SEARCH_ITERATIONS: u32 = 24;
+
+/// StableSwapCurve struct implementing CurveCalculator
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StableSwapCurve;
+
+impl StableSwapCurve {
+    /// Amplification coefficient at `block_timestamp`, linearly interpolated while a ramp is running
+    pub fn current_amp(
+        initial_amp: u64,
+        target_amp: u64,
+        ramp_start_time: u64,
+        ramp_stop_time: u64,
+        block_timestamp: u64,
+    ) -> u64 {
+        if block_timestamp >= ramp_stop_time || ramp_stop_time <= ramp_start_time {
+            return target_amp;
+        }
+        if block_timestamp <= ramp_start_time {
+            return initial_amp;
+        }
+        let elapsed = block_timestamp - ramp_start_time;
+        let duration = ramp_stop_time - ramp_start_time;
+        // The amplification is at most MAX_AMP and the duration fits into u64, this can not overflow u128
+        if target_amp > initial_amp {
+            let change =
+                u128::from(target_amp - initial_amp) * u128::from(elapsed) / u128::from(duration);
+            initial_amp + change as u64
+        } else {
+            let change =
+                u128::from(initial_amp - target_amp) * u128::from(elapsed) / u128::from(duration);
+            initial_amp - change as u64
+        }
+    }
+
+    /// Compute the invariant D of the pool with newton's method
+    ///
+    /// A * n^n * sum(x_i) + D = A * D * n^n + D^(n+1) / (n^n * prod(x_i))
+    pub fn compute_d(
+        amp: u64,
+        swap_token_0_amount: u128,
+        swap_token_1_amount: u128,
+    ) -> Result<u128> {
+        let sum = U256::from(swap_token_0_amount)
+            .checked_add(U256::from(swap_token_1_amount))
+            .ok_or(GammaError::MathOverflow)?;
+        if sum.is_zero() {
+            return Ok(0);
+        }
+        if swap_token_0_amount == 0 || swap_token_1_amount == 0 {
+            return err!(GammaError::EmptySupply);
+        }
+        // Ann = A * n^n
+        let ann = U256::from(amp) * U256::from(N_COINS * N_COINS);
+        let x_times_n = U256::from(swap_token_0_amount) * U256::from(N_COINS);
+        let y_times_n = U256::from(swap_token_1_amount) * U256::from(N_COINS);
+
+        let mut d = sum;
+        for _ in 0..MAX_ITERATIONS {
+            // d_product = D^(n+1) / (n^n * prod(x_i))
+            let d_product = d
+                .checked_mul(d)
+                .and_then(|v| v.checked_div(x_times_n))
+                .and_then(|v| v.checked_mul(d))
+                .and_then(|v| v.checked_div(y_times_n))
+                .ok_or(GammaError::MathOverflow)?;
+            let d_previous = d;
+            // D = (Ann * S + D_P * n) * D / ((Ann - 1) * D + (n + 1) * D_P)
+            let numerator = ann
+                .checked_mul(sum)
+                .and_then(|v| v.checked_add(d_product.checked_mul(U256::from(N_COINS))?))
+                .and_then(|v| v.checked_mul(d))
+                .ok_or(GammaError::MathOverflow)?;
+            let denominator = ann
+                .checked_sub(U256::one())
+                .and_then(|v| v.checked_mul(d))
+                .and_then(|v| v.checked_add(d_product.checked_mul(U256::from(N_COINS + 1))?))
+                .ok_or(GammaError::MathOverflow)?;
+            d = numerator
+                .checked_div(denominator)
+                .ok_or(GammaError::MathOverflow)?;
+
+            let difference = if d > d_previous {
+                d - d_previous
+            } else {
+                d_previous - d
+            };
+            if difference <= U256::one() {
+                return u128::try_from(d).map_err(|_| GammaError::MathOverflow.into());
+            }
+        }
+        err!(GammaError::MathError)
+    }
+
+    /// Compute the balance of the other token that keeps the invariant `d` with newton's method,
+    /// given the new balance of one token
+    pub fn compute_y(amp: u64, new_swap_source_amount: u128, d: u128) -> Result<u128> {
+        require_gt!(new_swap_source_amount, 0, GammaError::MathError);
+        // Ann = A * n^n
+        let ann = U256::from(amp) * U256::from(N_COINS * N_COINS);
+        let d = U256::from(d);
+        let x = U256::from(new_swap_source_amount);
+
+        // c = D^(n+1) / (n^n * x * Ann)
+        let c = d
+            .checked_mul(d)
+            .and_then(|v| v.checked_div(x.checked_mul(U256::from(N_COINS))?))
+            .and_then(|v| v.checked_mul(d))
+            .and_then(|v| v.checked_div(ann.checked_mul(U256::from(N_COINS))?))
+            .ok_or(GammaError::MathOverflow)?;
+        // b = x + D / Ann
+        let b = d
+            .checked_div(ann)
+            .and_then(|v| v.checked_add(x))
+            .ok_or(GammaError::MathOverflow)?;
+
+        let mut y = d;
+        for _ in 0..MAX_ITERATIONS {
+            let y_previous = y;
+            // y = (y^2 + c) / (2y + b - D)
+            let numerator = y
+                .checked_mul(y)
+                .and_then(|v| v.checked_add(c))
+                .ok_or(GammaError::MathOverflow)?;
+            let denominator = y
+                .checked_mul(U256::from(2))
+                .and_then(|v| v.checked_add(b))
+                .and_then(|v| v.checked_sub(d))
+                .ok_or(GammaError::MathOverflow)?;
+            y = numerator
+                .checked_div(denominator)
+                .ok_or(GammaError::MathOverflow)?;
+
+            let difference = if y > y_previous {
+                y - y_previous
+            } else {
+                y_previous - y
+            };
+            if difference <= U256::one() {
+                return u128::try_from(y).map_err(|_| GammaError::MathOverflow.into());
+            }
+        }
+        err!(GammaError::MathError)
+    }
+
+    /// Spot price of token 0 in token 1 as a Q32.32 number, the slope of the invariant at the given balances
+    pub fn spot_price_x32(
+        amp: u64,
+        swap_token_0_amount: u128,
+        swap_token_1_amount: u128,
+    ) -> Result<u128> {
+        let d = Self::compute_d(amp, swap_token_0_amount, swap_token_1_amount)?;
+        Self::spot_price_with_d_x32(amp, d, swap_token_0_amount, swap_token_1_amount)
+    }
+
+    /// Spot price of token 0 in token 1 as a Q32.32 number for balances on the invariant `d`
+    ///
+    /// -dy/dx = (Ann + D_P / x) / (Ann + D_P / y) = y * (Ann * x + D_P) / (x * (Ann * y + D_P)),
+    /// with D_P = D^(n+1) / (n^n * prod(x_i))
+    fn spot_price_with_d_x32(
+        amp: u64,
+        d: u128,
+        swap_token_0_amount: u128,
+        swap_token_1_amount: u128,
+    ) -> Result<u128> {
+        // Ann = A * n^n
+        let ann = U256::from(amp) * U256::from(N_COINS * N_COINS);
+        let d = U256::from(d);
+        let x = U256::from(swap_token_0_amount);
+        let y = U256::from(swap_token_1_amount);
+
+        let d_product = d
+            .checked_mul(d)
+            .and_then(|v| v.checked_div(x.checked_mul(U256::from(N_COINS))?))
+            .and_then(|v| v.checked_mul(d))
+            .and_then(|v| v.checked_div(y.checked_mul(U256::from(N_COINS))?))
+            .ok_or(GammaError::MathOverflow)?;
+        let numerator = ann
+            .checked_mul(x)
+            .and_then(|v| v.checked_add(d_product))
+            .and_then(|v| v.checked_mul(y))
+            .and_then(|v| v.checked_mul(U256::from(Q32)))
+            .ok_or(GammaError::MathOverflow)?;
+        let denominator = ann
+            .checked_mul(y)
+            .and_then(|v| v.checked_add(d_product))
+            .and_then(|v| v.checked_mul(x))
+            .ok_or(GammaError::MathOverflow)?;
+        let price_x32 = numerator
+            .checked_div(denominator)
+            .ok_or(GammaError::MathOverflow)?;
+        u128::try_from(price_x32).map_err(|_| GammaError::MathOverflow.into())
+    }
+
+    /// Approximates the amount of source token, at most `max_source_amount`, that moves the balances
+    /// along the invariant until the price of the destination token in source token reaches
+    /// `max_price_x32`. The amount returned stays within the price.
+    ///
+    /// The invariant has no closed form for it, so the magnitude of the amount is bisected first,
+    /// then the amount itself with at most `MAX_PRICE_SEARCH_ITERATIONS` more evaluations.
+    pub fn source_amount_at_price_x32(
+        amp: u64,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        max_source_amount: u128,
+        max_price_x32: u128,
+    ) -> Result<u128> {
+        let d = Self::compute_d(amp, swap_source_amount, swap_destination_amount)?;
+        // Amounts the invariant can not be solved for are past any limit
+        let is_within_price = |source_amount: u128| -> bool {
+            swap_source_amount
+                .checked_add(source_amount)
+                .ok_or(GammaError::MathOverflow.into())
+                .and_then(|new_swap_source_amount| {
+                    let new_swap_destination_amount =
+                        Self::compute_y(amp, new_swap_source_amount, d)?;
+                    Self::spot_price_with_d_x32(
+                        amp,
+                        d,
+                        new_swap_destination_amount,
+                        new_swap_source_amount,
+                    )
+                })
+                .is_ok_and(|price_x32| price_x32 <= max_price_x32)
+        };
+        if is_within_price(max_source_amount) {
+            return Ok(max_source_amount);
+        }
+
+        // The amounts between `low` and `high` are powers of two, `low` stays within the price and `high` does not
+        let (mut low, mut high) = (0u128, max_source_amount);
+        let (mut low_bits, mut high_bits) = (0u32, u128::BITS - max_source_amount.leading_zeros());
+        while high_bits - low_bits > 1 {
+            let mid_bits = low_bits + (high_bits - low_bits) / 2;
+            let mid = 1u128 << mid_bits;
+            if is_within_price(mid) {
+                low = mid;
+                low_bits = mid_bits;
+            } else {
+                high = mid;
+                high_bits = mid_bits;
+            }
+        }
+        for _ in 0..MAX_PRICE_SEARCH_ITERATIONS {
+            if high - low <= 1 {
+                break;
+            }
+            let mid = low + (high - low) / 2;
+            if is_within_price(mid) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low)
+    }
+
+    /// StableSwap swap keeps the invariant D constant, rounding against the trader
+    pub fn swap_base_input_without_fees(
+        source_amount_to_be_swapped: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        amp: u64,
+    ) -> Result<u128> {
+        let d = Self::compute_d(amp, swap_source_amount, swap_destination_amount)?;
+        let new_swap_source_amount = swap_source_amount
+            .checked_add(source_amount_to_be_swapped)
+            .ok_or(GammaError::MathOverflow)?;
+        let new_swap_destination_amount = Self::compute_y(amp, new_swap_source_amount, d)?;
+        Ok(swap_destination_amount
+            .saturating_sub(new_swap_destination_amount)
+            .saturating_sub(NEWTON_ROUNDING_MARGIN))
+    }
+
+    pub fn swap_base_output_without_fees(
+        destination_amount_to_be_swapped: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        amp: u64,
+    ) -> Result<u128> {
+        let d = Self::compute_d(amp, swap_source_amount, swap_destination_amount)?;
+        let new_swap_destination_amount = swap_destination_amount
+            .checked_sub(destination_amount_to_be_swapped)
+            .ok_or(GammaError::MathOverflow)?;
+        let new_swap_source_amount = Self::compute_y(amp, new_swap_destination_amount, d)?;
+        new_swap_source_amount
+            .checked_sub(swap_source_amount)
+            .and_then(|v| v.checked_add(NEWTON_ROUNDING_MARGIN))
+            .ok_or(GammaError::MathOverflow.into())
+    }
+}
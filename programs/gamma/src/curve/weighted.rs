@@ -0,0 +1,123 @@
+//! The weighted constant mean invariant, for pools with uneven weights like 80/20
+
+use crate::error::GammaError;
+use crate::utils::math::{exp2_neg_x64, log2_x64, Q64, U256};
+use anchor_lang::prelude::*;
+
+/// Sum of the weights of both tokens
+pub const WEIGHT_DENOMINATOR: u32 = 100;
+/// Minimum weight of a token
+pub const MIN_WEIGHT: u32 = 1;
+/// Maximum weight of a token
+pub const MAX_WEIGHT: u32 = WEIGHT_DENOMINATOR - MIN_WEIGHT;
+
+/// Every swap keeps 2^-40 of the computed price ratio, plus a few units of 2^-64, in the pool,
+/// so that the fixed point rounding can never decrease the invariant
+const ROUNDING_MARGIN_SHIFT: u32 = 40;
+const ROUNDING_MARGIN_X64: u128 = 16;
+
+/// WeightedCurve struct implementing CurveCalculator
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WeightedCurve;
+
+impl WeightedCurve {
+    /// (base_numerator / base_denominator)^(exponent_numerator / exponent_denominator)
+    /// as a Q64.64 number, for a base of at most 1
+    pub(crate) fn pow_ratio_x64(
+        base_numerator: u128,
+        base_denominator: u128,
+        exponent_numerator: u32,
+        exponent_denominator: u32,
+    ) -> Result<u128> {
+        require_gte!(base_denominator, base_numerator, GammaError::MathError);
+        // log2(base) <= 0, so work with its opposite
+        let log2_base = log2_x64(base_denominator)
+            .ok_or(GammaError::MathError)?
+            .saturating_sub(log2_x64(base_numerator).ok_or(GammaError::MathError)?);
+        let exponent = log2_base
+            .checked_mul(u128::from(exponent_numerator))
+            .and_then(|v| v.checked_div(u128::from(exponent_denominator)))
+            .ok_or(GammaError::MathOverflow)?;
+        Ok(exp2_neg_x64(exponent))
+    }
+
+    /// Weighted swap ensures x^wx * y^wy = constant
+    pub fn swap_base_input_without_fees(
+        source_amount_to_be_swapped: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        source_weight: u32,
+        destination_weight: u32,
+    ) -> Result<u128> {
+        // (x + delta_x)^wx * (y - delta_y)^wy = x^wx * y^wy
+        // delta_y = y * (1 - (x / (x + delta_x))^(wx / wy))
+        let new_swap_source_amount = swap_source_amount
+            .checked_add(source_amount_to_be_swapped)
+            .ok_or(GammaError::MathOverflow)?;
+        let ratio = Self::pow_ratio_x64(
+            swap_source_amount,
+            new_swap_source_amount,
+            source_weight,
+            destination_weight,
+        )?;
+        // Round the ratio up, against the trader
+        let ratio = ratio + (ratio >> ROUNDING_MARGIN_SHIFT) + ROUNDING_MARGIN_X64;
+        let new_swap_destination_amount = U256::from(swap_destination_amount)
+            .checked_mul(U256::from(ratio))
+            .and_then(|v| v.checked_add(U256::from(Q64 - 1)))
+            .ok_or(GammaError::MathOverflow)?
+            >> 64;
+        let new_swap_destination_amount =
+            u128::try_from(new_swap_destination_amount).map_err(|_| GammaError::MathOverflow)?;
+        Ok(swap_destination_amount.saturating_sub(new_swap_destination_amount))
+    }
+
+    pub fn swap_base_output_without_fees(
+        destination_amount_to_be_swapped: u128,
+        swap_source_amount: u128,
+        swap_destination_amount: u128,
+        source_weight: u32,
+        destination_weight: u32,
+    ) -> Result<u128> {
+        // (x + delta_x)^wx * (y - delta_y)^wy = x^wx * y^wy
+        // delta_x = x * ((y / (y - delta_y))^(wy / wx) - 1)
+        let new_swap_destination_amount = swap_destination_amount
+            .checked_sub(destination_amount_to_be_swapped)
+            .ok_or(GammaError::MathOverflow)?;
+        let ratio = Self::pow_ratio_x64(
+            new_swap_destination_amount,
+            swap_destination_amount,
+            destination_weight,
+            source_weight,
+        )?;
+        // Round the ratio down, against the trader
+        let ratio = ratio
+            .checked_sub((ratio >> ROUNDING_MARGIN_SHIFT) + ROUNDING_MARGIN_X64)
+            .filter(|ratio| *ratio > 0)
+            .ok_or(GammaError::MathOverflow)?;
+        let new_swap_source_amount = (U256::from(swap_source_amount) << 64)
+            .checked_add(U256::from(ratio - 1))
+            .and_then(|v| v.checked_div(U256::from(ratio)))
+            .ok_or(GammaError::MathOverflow)?;
+        u128::try_from(new_swap_source_amount)
+            .map_err(|_| GammaError::MathOverflow)?
+            .checked_sub(swap_source_amount)
+            .ok_or(GammaError::MathOverflow.into())
+    }
+
+    /// The base 2 logarithm of x^wx * y^wy as a Q64.64 number, which has the same order as the
+    /// invariant itself without overflowing
+    pub fn log2_invariant(
+        swap_token_0_amount: u128,
+        swap_token_1_amount: u128,
+        token_0_weight: u32,
+        token_1_weight: u32,
+    ) -> Result<u128> {
+        let log2_token_0 = log2_x64(swap_token_0_amount).ok_or(GammaError::EmptySupply)?;
+        let log2_token_1 = log2_x64(swap_token_1_amount).ok_or(GammaError::EmptySupply)?;
+        log2_token_0
+            .checked_mul(u128::from(token_0_weight))
+            .and_then(|v| v.checked_add(log2_token_1.checked_mul(u128::from(token_1_weight))?))
+            .ok_or(GammaError::MathOverflow.into())
+    }
+}
@@ -48,4 +48,43 @@ pub enum GammaError {
 
     #[msg("Invalid reward time")]
     InvalidRewardTime,
+
+    #[msg("Invalid curve type")]
+    InvalidCurveType,
+    #[msg("Invalid amplification coefficient")]
+    InvalidAmp,
+    #[msg("Invalid token weight")]
+    InvalidWeight,
+    #[msg("Observations do not cover the requested window")]
+    ObservationCoverageTooShort,
+    #[msg("Every reward info of the pool must be provided once")]
+    IncompleteRewardInfos,
+    #[msg("The pool has reached the maximum number of rewards")]
+    MaxRewardsReached,
+    #[msg("The partner ledger of the position was not provided")]
+    PartnerNotFound,
+    #[msg("The unlock time must be in the future and can not be brought forward")]
+    InvalidUnlockTime,
+    #[msg("The liquidity is still locked")]
+    LiquidityLocked,
+    #[msg("The vesting duration must be positive and not shorter than the cliff")]
+    InvalidVestingSchedule,
+    #[msg("A flash loan is open on the pool")]
+    FlashLoanOpen,
+    #[msg("The flash loan is not repaid in the same transaction")]
+    FlashLoanNotRepaid,
+    #[msg("The input of the flash swap is not paid after the callback")]
+    FlashSwapNotPaid,
+    #[msg("The swap deadline has passed")]
+    DeadlineExceeded,
+    #[msg("The pool price is already at the swap price limit")]
+    PriceLimitReached,
+    #[msg("The partner slot of the pool must be migrated first")]
+    PoolNotMigrated,
+    #[msg("The pool is already migrated")]
+    PoolAlreadyMigrated,
+    #[msg("The reward account is already migrated")]
+    RewardAlreadyMigrated,
+    #[msg("The reward is closed")]
+    RewardClosed,
 }
@@ -2,10 +2,10 @@ use super::{ceil_div, FEE_RATE_DENOMINATOR_VALUE};
 use crate::{
     error::GammaError,
     fees::ONE_BASIS_POINT,
-    states::{Observation, ObservationState, PoolState, OBSERVATION_NUM},
+    states::{Observation, ObservationBuffer, PoolState},
+    utils::math::{exp2_neg_x64, log2_x64, Q64, U256},
 };
 use anchor_lang::prelude::*;
-use rust_decimal::prelude::*;
 
 // Volatility-based fee constants
 pub const MAX_FEE_VOLATILITY: u64 = 10000; // 1% max fee
@@ -14,13 +14,52 @@ pub const VOLATILITY_WINDOW: u64 = 3600; // 1 hour window for volatility calcula
 const DEFAULT_MAX_FEE: u64 = 100000; // 10% max fee
 const DEFAULT_VOLATILITY_FACTOR: u64 = 300_000; // Adjust based on desired sensitivity
 
+// Imbalance-based fee constants
+pub const IMBALANCE_FACTOR: u64 = 100_000; // 10% of the price impact is added to the fee
+
+// Launch fee constants
+pub const MAX_LAUNCH_FEE_DURATION: u64 = 86400; // The launch fee decays within a day at most
+pub const LAUNCH_FEE_HALF_LIVES: u64 = 10; // An exponential launch fee halves 10 times over its duration
+
+/// The fee model of a pool, stored in `PoolState::fee_type`
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FeeType {
+    /// Base fee plus a component growing with the recent price volatility
     Volatility,
+    /// Only the `trade_fee_rate` of the amm config
+    Static,
+    /// Base fee plus a component growing with the price impact of the trade on the vault reserves
+    Imbalance,
+}
+
+impl FeeType {
+    pub fn new(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(FeeType::Volatility),
+            1 => Ok(FeeType::Static),
+            2 => Ok(FeeType::Imbalance),
+            _ => err!(GammaError::InvalidFee),
+        }
+    }
 }
 
-struct ObservationWithIndex {
-    observation: Observation,
-    index: u16,
+/// How the launch fee of a pool decays to the normal fee, stored in `PoolState::launch_fee_decay`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LaunchFeeDecay {
+    /// The extra fee drops by the same amount every second
+    Linear,
+    /// The extra fee halves `LAUNCH_FEE_HALF_LIVES` times over the duration
+    Exponential,
+}
+
+impl LaunchFeeDecay {
+    pub fn new(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(LaunchFeeDecay::Linear),
+            1 => Ok(LaunchFeeDecay::Exponential),
+            _ => err!(GammaError::InvalidFee),
+        }
+    }
 }
 
 pub struct DynamicFee {}
@@ -30,6 +69,7 @@ impl DynamicFee {
     ///
     /// # Arguments
     /// * `amount` - The input amount
+    /// * `swap_source_amount` - The amount of the input token in the vault before the trade
     /// * `block_timestamp` - The current block timestamp
     /// * `observation_state` - Historical price observations
     /// * `fee_type` - The type of fee calculation to use
@@ -40,8 +80,9 @@ impl DynamicFee {
 
     pub fn dynamic_fee(
         amount: u128,
+        swap_source_amount: u128,
         block_timestamp: u64,
-        observation_state: &ObservationState,
+        observation_state: &ObservationBuffer,
         fee_type: FeeType,
         base_fees: u64,
         pool_state: &PoolState,
@@ -49,6 +90,8 @@ impl DynamicFee {
     ) -> Result<(u128, u64)> {
         // TODO: use is_invoked_by_signed_segmenter to charge less fees for signed segmenter, once they are implemented across all protocols and this is also used by the segmenter.
         let dynamic_fee_rate = Self::calculate_dynamic_fee(
+            amount,
+            swap_source_amount,
             block_timestamp,
             observation_state,
             fee_type,
@@ -71,6 +114,8 @@ impl DynamicFee {
     /// Calculates the dynamic fee based on the specified fee type
     ///
     /// # Arguments
+    /// * `amount` - The input amount
+    /// * `swap_source_amount` - The amount of the input token in the vault before the trade
     /// * `pool_state` - The current state of the pool
     /// * `observation_state` - Historical price observations
     /// * `vault_0` - Amount of token 0 in the vault
@@ -80,22 +125,154 @@ impl DynamicFee {
     /// # Returns
     /// A fee rate as a u64, where 10000 represents 1%
     fn calculate_dynamic_fee(
+        amount: u128,
+        swap_source_amount: u128,
         block_timestamp: u64,
-        observation_state: &ObservationState,
+        observation_state: &ObservationBuffer,
         fee_type: FeeType,
         base_fees: u64,
         pool_state: &PoolState,
         is_invoked_by_signed_segmenter: bool,
     ) -> Result<u64> {
-        match fee_type {
+        let fee_rate = match fee_type {
             FeeType::Volatility => Self::calculate_volatile_fee(
                 block_timestamp,
                 observation_state,
                 base_fees,
                 pool_state,
                 is_invoked_by_signed_segmenter,
-            ),
+            )?,
+            FeeType::Static => base_fees,
+            FeeType::Imbalance => Self::calculate_imbalance_fee(
+                amount,
+                swap_source_amount,
+                base_fees,
+                pool_state,
+                is_invoked_by_signed_segmenter,
+            )?,
+        };
+        Self::apply_launch_fee(block_timestamp, fee_rate, pool_state)
+    }
+
+    /// Raises the fee rate to the launch fee of the pool while it decays after the open time
+    ///
+    /// # Arguments
+    /// * `block_timestamp` - The current block timestamp
+    /// * `fee_rate` - The fee rate of the fee type of the pool
+    /// * `pool_state` - The current state of the pool
+    ///
+    /// # Returns
+    /// A fee rate as a u64, where 10000 represents 1%
+    fn apply_launch_fee(
+        block_timestamp: u64,
+        fee_rate: u64,
+        pool_state: &PoolState,
+    ) -> Result<u64> {
+        // 1. The launch fee starts at launch_fee_rate at open_time and reaches the normal fee after launch_fee_duration
+        //
+        // 2. Decay of the extra fee (launch_fee_rate - fee_rate):
+        //    - Linear: extra * (duration - elapsed) / duration
+        //    - Exponential: extra * 2^(-LAUNCH_FEE_HALF_LIVES * elapsed / duration)
+        //
+        // 3. Final fee calculation:
+        //    - Add the remaining extra fee to fee_rate
+        //    - Ensure final fee doesn't exceed the max fee of the pool, nor drops below fee_rate
+        let launch_fee_rate = pool_state.launch_fee_rate;
+        let launch_fee_duration = pool_state.launch_fee_duration;
+        let elapsed = block_timestamp.saturating_sub(pool_state.open_time);
+        if launch_fee_rate <= fee_rate || elapsed >= launch_fee_duration {
+            return Ok(fee_rate);
         }
+
+        let extra_fee = u128::from(launch_fee_rate - fee_rate);
+        let remaining_extra_fee = match LaunchFeeDecay::new(pool_state.launch_fee_decay)? {
+            LaunchFeeDecay::Linear => {
+                extra_fee * u128::from(launch_fee_duration - elapsed)
+                    / u128::from(launch_fee_duration)
+            }
+            LaunchFeeDecay::Exponential => {
+                let half_lives_x64 = (u128::from(LAUNCH_FEE_HALF_LIVES * elapsed) << 64)
+                    / u128::from(launch_fee_duration);
+                (U256::from(extra_fee) * U256::from(exp2_neg_x64(half_lives_x64)) / U256::from(Q64))
+                    .as_u128()
+            }
+        };
+        let launch_fee = fee_rate
+            .checked_add(u64::try_from(remaining_extra_fee).map_err(|_| GammaError::MathOverflow)?)
+            .ok_or(GammaError::MathOverflow)?;
+        #[cfg(feature = "enable-log")]
+        msg!("elapsed: {}, launch_fee: {}", elapsed, launch_fee);
+
+        let max_fee = if pool_state.max_trade_fee_rate == 0 {
+            DEFAULT_MAX_FEE
+        } else {
+            pool_state.max_trade_fee_rate
+        };
+
+        Ok(std::cmp::max(fee_rate, std::cmp::min(launch_fee, max_fee)))
+    }
+
+    /// Calculates a dynamic fee based on the price impact of the trade
+    ///
+    /// # Arguments
+    /// * `amount` - The input amount
+    /// * `swap_source_amount` - The amount of the input token in the vault before the trade
+    /// * `base_fees` - The base fee rate
+    ///
+    /// # Returns
+    /// A fee rate as a u64, where 10000 represents 1%
+    fn calculate_imbalance_fee(
+        amount: u128,
+        swap_source_amount: u128,
+        base_fees: u64,
+        pool_state: &PoolState,
+        is_invoked_by_signed_segmenter: bool,
+    ) -> Result<u64> {
+        // 1. Price impact of the trade against the vault reserves:
+        //    - impact = amount / (swap_source_amount + amount), scaled by FEE_RATE_DENOMINATOR_VALUE
+        //
+        // 2. Final fee calculation:
+        //    - Add IMBALANCE_FACTOR / FEE_RATE_DENOMINATOR_VALUE of the impact to base_fees
+        //    - Ensure final fee doesn't exceed the max fee of the pool
+        let total_amount = swap_source_amount
+            .checked_add(amount)
+            .ok_or(GammaError::MathOverflow)?;
+        if total_amount == 0 {
+            return Ok(base_fees);
+        }
+        let price_impact = amount
+            .checked_mul(u128::from(FEE_RATE_DENOMINATOR_VALUE))
+            .ok_or(GammaError::MathOverflow)?
+            / total_amount;
+        let imbalance_component = u64::try_from(
+            price_impact * u128::from(IMBALANCE_FACTOR) / u128::from(FEE_RATE_DENOMINATOR_VALUE),
+        )
+        .map_err(|_| GammaError::MathOverflow)?;
+        #[cfg(feature = "enable-log")]
+        msg!(
+            "price_impact: {}, imbalance_component: {}",
+            price_impact,
+            imbalance_component
+        );
+
+        let dynamic_fee = base_fees
+            .checked_add(imbalance_component)
+            .ok_or(GammaError::MathOverflow)?;
+
+        let max_fee = if pool_state.max_trade_fee_rate == 0 {
+            DEFAULT_MAX_FEE
+        } else {
+            pool_state.max_trade_fee_rate
+        };
+
+        if is_invoked_by_signed_segmenter {
+            return Ok(std::cmp::max(
+                base_fees,
+                std::cmp::min(dynamic_fee, max_fee).saturating_sub(ONE_BASIS_POINT),
+            ));
+        }
+
+        Ok(std::cmp::min(dynamic_fee, max_fee))
     }
 
     /// Calculates a dynamic fee based on price volatility
@@ -109,7 +286,7 @@ impl DynamicFee {
     /// A fee rate as a u64, where 10000 represents 1%
     fn calculate_volatile_fee(
         block_timestamp: u64,
-        observation_state: &ObservationState,
+        observation_state: &ObservationBuffer,
         base_fees: u64,
         pool_state: &PoolState,
         is_invoked_by_signed_segmenter: bool,
@@ -117,6 +294,7 @@ impl DynamicFee {
         // 1. Price volatility calculation:
         //    - Get min, max and TWAP (Time-Weighted Average Price) over the volatility window
         //    - Volatility = |ln(max_price) - ln(min_price)| / |ln(twap_price)|
+        //    - The base of the logarithms cancels out, so fixed point log2 is used to stay deterministic
         //
         // 2. Volatility component calculation:
         //    - Scale volatility by FEE_RATE_DENOMINATOR_VALUE (1_000_000)
@@ -136,33 +314,6 @@ impl DynamicFee {
             return Ok(base_fees);
         }
 
-        // Compute logarithms
-        let log_max_price = (max_price as f64).ln();
-        let log_min_price = (min_price as f64).ln();
-        let log_twap_price = (twap_price as f64).ln();
-        #[cfg(feature = "enable-log")]
-        msg!(
-            "log_max_price: {},log_min_price={},log_twap_price={}  ",
-            log_max_price,
-            log_min_price,
-            log_twap_price
-        );
-
-        // Compute volatility numerator and denominator
-        let volatility_numerator = (log_max_price - log_min_price).abs();
-        let volatility_denominator = log_twap_price.abs();
-
-        // Check if volatility_denominator is zero to avoid division by zero
-        if volatility_denominator.is_zero() {
-            return Ok(base_fees);
-        }
-
-        // Compute volatility: volatility = volatility_numerator / volatility_denominator
-        // Dividing f64 with f64. We want to know the decimals so we keep the
-        let volatility = volatility_numerator / volatility_denominator;
-        #[cfg(feature = "enable-log")]
-        msg!("volatility: {} ", volatility);
-
         #[cfg(feature = "enable-log")]
         msg!(
             "is_invoked_by_signed_segmenter: {}",
@@ -176,19 +327,20 @@ impl DynamicFee {
         };
 
         // Calculate volatility component
-        let volatility_component_calculated = (volatility_factor as f64 * volatility)
-            .to_u64()
-            .ok_or(GammaError::MathOverflow)?;
+        #[cfg(not(feature = "f64-volatility-fee"))]
+        let volatility_component_calculated =
+            Self::volatility_component(min_price, max_price, twap_price, volatility_factor)?;
+        #[cfg(feature = "f64-volatility-fee")]
+        let volatility_component_calculated =
+            Self::f64_volatility_component(min_price, max_price, twap_price, volatility_factor);
         #[cfg(feature = "enable-log")]
         msg!(
             "volatility_component_calculated: {} ",
             volatility_component_calculated
         );
 
-        // Calculate final dynamic fee
-        let dynamic_fee = base_fees
-            .checked_add(volatility_component_calculated)
-            .ok_or(GammaError::MathOverflow)?;
+        // Calculate final dynamic fee, extreme volatility saturates to the max fee below
+        let dynamic_fee = base_fees.saturating_add(volatility_component_calculated);
 
         let max_fee = if pool_state.max_trade_fee_rate == 0 {
             DEFAULT_MAX_FEE
@@ -209,6 +361,58 @@ impl DynamicFee {
         Ok(std::cmp::min(dynamic_fee, max_fee))
     }
 
+    /// volatility_factor * |log2(max_price) - log2(min_price)| / |log2(twap_price)|, rounded down.
+    /// Saturates at `u64::MAX`, the fee is capped by the max trade fee rate anyway.
+    ///
+    /// Computed with Q64.64 logarithms, so every validator gets the same result.
+    #[cfg_attr(feature = "f64-volatility-fee", allow(dead_code))]
+    fn volatility_component(
+        min_price: u128,
+        max_price: u128,
+        twap_price: u128,
+        volatility_factor: u64,
+    ) -> Result<u64> {
+        let log_max_price = log2_x64(max_price).ok_or(GammaError::MathError)?;
+        let log_min_price = log2_x64(min_price).ok_or(GammaError::MathError)?;
+        let log_twap_price = log2_x64(twap_price).ok_or(GammaError::MathError)?;
+        #[cfg(feature = "enable-log")]
+        msg!(
+            "log_max_price: {},log_min_price={},log_twap_price={}  ",
+            log_max_price,
+            log_min_price,
+            log_twap_price
+        );
+
+        // Compute volatility numerator and denominator, prices are at least 1 so the logarithms are positive
+        let volatility_numerator = log_max_price.abs_diff(log_min_price);
+        let volatility_denominator = log_twap_price;
+
+        // Check if volatility_denominator is zero to avoid division by zero
+        if volatility_denominator == 0 {
+            return Ok(0);
+        }
+
+        let volatility_component = U256::from(volatility_factor)
+            .checked_mul(U256::from(volatility_numerator))
+            .and_then(|v| v.checked_div(U256::from(volatility_denominator)))
+            .ok_or(GammaError::MathOverflow)?;
+        Ok(u64::try_from(volatility_component).unwrap_or(u64::MAX))
+    }
+
+    /// The floating point volatility component computed on chain before the switch to fixed point,
+    /// kept as the reference of the tests and of the compute unit comparison
+    #[cfg(any(test, feature = "f64-volatility-fee"))]
+    fn f64_volatility_component(
+        min_price: u128,
+        max_price: u128,
+        twap_price: u128,
+        volatility_factor: u64,
+    ) -> u64 {
+        let volatility = ((max_price as f64).ln() - (min_price as f64).ln()).abs()
+            / (twap_price as f64).ln().abs();
+        (volatility_factor as f64 * volatility) as u64
+    }
+
     /// Gets the price range within a specified time window and computes TWAP
     ///
     /// # Arguments
@@ -219,117 +423,83 @@ impl DynamicFee {
     /// # Returns
     /// A tuple of (min_price, max_price, twap_price) observed within the window
     fn get_price_range(
-        observation_state: &ObservationState,
+        observation_state: &ObservationBuffer,
         current_time: u64,
         window: u64,
     ) -> Result<(u128, u128, u128)> {
         let mut min_price = u128::MAX;
         let mut max_price = 0u128;
-        // Filter and sort observations:
-        // 1. Remove invalid observations (zero timestamps or prices)
-        // 2. Keep only observations within our time window
-        // 3. Sort from newest to oldest
-        let mut descending_order_observations = observation_state
-            .observations
-            .iter()
-            .enumerate()
-            .filter(|(_, observation)| {
-                observation.block_timestamp != 0
-                    && observation.cumulative_token_0_price_x32 != 0
-                    && observation.cumulative_token_1_price_x32 != 0
-                    && current_time.saturating_sub(observation.block_timestamp) <= window
-            })
-            .map(|(index, observation)| ObservationWithIndex {
-                index: index as u16,
-                observation: *observation,
-            })
-            .collect::<Vec<_>>();
-
-        // Sort observations by timestamp (newest first)
-        descending_order_observations.sort_by(|a, b| {
-            { b.observation.block_timestamp }.cmp(&{ a.observation.block_timestamp })
-        });
+        // Remove invalid observations (zero prices) and keep only observations within our time window
+        let is_in_window = |observation: &Observation| {
+            observation.cumulative_token_0_price_x32 != 0
+                && observation.cumulative_token_1_price_x32 != 0
+                && current_time.saturating_sub(observation.block_timestamp) <= window
+        };
+
+        // Walk back from the newest observation, the observations in the window are the newest ones.
+        // Every interval ending in the window counts for the min/max spot prices, also the one starting before it.
+        let mut newest_obs: Option<Observation> = None;
+        let mut oldest_obs: Option<Observation> = None;
+        let mut window_observations = 0;
+        for obs in observation_state.newest_first() {
+            if let Some(next_obs) = oldest_obs {
+                let time_delta =
+                    next_obs.block_timestamp.saturating_sub(obs.block_timestamp) as u128;
+                if time_delta != 0 {
+                    // Calculate spot price for this interval
+                    let price = next_obs
+                        .cumulative_token_0_price_x32
+                        .wrapping_sub(obs.cumulative_token_0_price_x32)
+                        .checked_div(time_delta)
+                        .ok_or(GammaError::MathOverflow)?;
+
+                    // Update min and max prices
+                    min_price = min_price.min(price);
+                    max_price = max_price.max(price);
+                }
+            }
+            if !is_in_window(&obs) {
+                break;
+            }
+            newest_obs.get_or_insert(obs);
+            oldest_obs = Some(obs);
+            window_observations += 1;
+        }
 
         // Need at least 2 observations to calculate prices
-        if descending_order_observations.len() < 2 {
+        if window_observations < 2 {
             // Not enough data points to compute TWAP
             return Ok((0, 0, 0));
         }
 
         // For TWAP: use first and last observations within our window
-        // Get newest and oldest observations in our filtered set
-        let newest_obs = descending_order_observations.first().unwrap();
-        let oldest_obs = descending_order_observations.last().unwrap();
+        let newest_obs = newest_obs.unwrap();
+        let oldest_obs = oldest_obs.unwrap();
 
         // Calculate time delta using the correct start time
         let total_time_delta = newest_obs
-            .observation
             .block_timestamp
-            .saturating_sub(oldest_obs.observation.block_timestamp)
-            as u128;
+            .saturating_sub(oldest_obs.block_timestamp) as u128;
 
         if total_time_delta == 0 {
             return Ok((0, 0, 0));
         }
 
-        // Calculate TWAP using real observations only
+        // Calculate TWAP using real observations only, the cumulative prices wrap around on overflow
         let twap_price = newest_obs
-            .observation
             .cumulative_token_0_price_x32
-            .checked_sub(oldest_obs.observation.cumulative_token_0_price_x32)
-            .ok_or(GammaError::MathOverflow)?
+            .wrapping_sub(oldest_obs.cumulative_token_0_price_x32)
             .checked_div(total_time_delta)
             .ok_or(GammaError::MathOverflow)?;
 
-        // Iterate to find min/max spot prices
-        for observation_with_index in descending_order_observations {
-            let last_observation_index = if observation_with_index.index == 0 {
-                OBSERVATION_NUM - 1
-            } else {
-                observation_with_index.index as usize - 1
-            };
-
-            // if last observation is not valid, skip this observation
-            if observation_state.observations[last_observation_index].block_timestamp == 0 {
-                continue;
-            }
-
-            if observation_state.observations[last_observation_index].block_timestamp
-                > observation_with_index.observation.block_timestamp
-            {
-                // Break if current observation is older than the last observation.
-                break;
-            }
-
-            let obs = observation_state.observations[last_observation_index];
-            let next_obs = observation_with_index.observation;
-
-            let time_delta = next_obs.block_timestamp.saturating_sub(obs.block_timestamp) as u128;
-
-            if time_delta == 0 {
-                continue;
-            }
-
-            // Calculate spot price for this interval
-            let price = next_obs
-                .cumulative_token_0_price_x32
-                .checked_sub(obs.cumulative_token_0_price_x32)
-                .ok_or(GammaError::MathOverflow)?
-                .checked_div(time_delta)
-                .ok_or(GammaError::MathOverflow)?;
-
-            // Update min and max prices
-            min_price = min_price.min(price);
-            max_price = max_price.max(price);
-        }
-
         Ok((min_price, max_price, twap_price))
     }
 
     /// Calculates the pre-fee amount given a post-fee amount
     ///
     /// # Arguments
-    /// * `post_fee_amount` - The amount after fees have been deducted
+    /// * `post_fee_amount` - The amount after fees have been deducted, also used as the trade size of `FeeType::Imbalance`
+    /// * `swap_source_amount` - The amount of the input token in the vault before the trade
     /// * `pool_state` - The current state of the pool
     /// * `observation_state` - Historical price observations
     /// * `vault_0` - Amount of token 0 in the vault
@@ -341,7 +511,8 @@ impl DynamicFee {
     pub fn calculate_pre_fee_amount(
         block_timestamp: u64,
         post_fee_amount: u128,
-        observation_state: &ObservationState,
+        swap_source_amount: u128,
+        observation_state: &ObservationBuffer,
         fee_type: FeeType,
         base_fees: u64,
         pool_state: &PoolState,
@@ -363,6 +534,8 @@ impl DynamicFee {
         // x = (y * D + (D - r) - 1) / (D - r)
 
         let dynamic_fee_rate = Self::calculate_dynamic_fee(
+            post_fee_amount,
+            swap_source_amount,
             block_timestamp,
             observation_state,
             fee_type,
@@ -392,3 +565,363 @@ impl DynamicFee {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::states::{ObservationBufferMut, ObservationState, OBSERVATION_NUM};
+
+    const BASE_FEES: u64 = 2500;
+
+    fn fee_rate(
+        fee_type: FeeType,
+        amount: u128,
+        swap_source_amount: u128,
+        pool_state: &PoolState,
+        is_invoked_by_signed_segmenter: bool,
+    ) -> u64 {
+        let (_, dynamic_fee_rate) = DynamicFee::dynamic_fee(
+            amount,
+            swap_source_amount,
+            1_000,
+            &ObservationBuffer::new(&ObservationState::default(), &[]),
+            fee_type,
+            BASE_FEES,
+            pool_state,
+            is_invoked_by_signed_segmenter,
+        )
+        .unwrap();
+        dynamic_fee_rate
+    }
+
+    #[test]
+    fn fee_type_from_u8() {
+        assert_eq!(FeeType::new(0).unwrap(), FeeType::Volatility);
+        assert_eq!(FeeType::new(1).unwrap(), FeeType::Static);
+        assert_eq!(FeeType::new(2).unwrap(), FeeType::Imbalance);
+        assert!(FeeType::new(3).is_err());
+    }
+
+    #[test]
+    fn volatility_fee_without_observations_is_base_fee() {
+        let pool_state = PoolState::default();
+        assert_eq!(
+            fee_rate(
+                FeeType::Volatility,
+                1_000_000,
+                1_000_000,
+                &pool_state,
+                false
+            ),
+            BASE_FEES
+        );
+    }
+
+    #[test]
+    fn static_fee_uses_only_trade_fee_rate() {
+        let pool_state = PoolState::default();
+        for amount in [1, 1_000, 1_000_000_000] {
+            assert_eq!(
+                fee_rate(FeeType::Static, amount, 1_000_000, &pool_state, false),
+                BASE_FEES
+            );
+            assert_eq!(
+                fee_rate(FeeType::Static, amount, 1_000_000, &pool_state, true),
+                BASE_FEES
+            );
+        }
+
+        let (fee, _) = DynamicFee::dynamic_fee(
+            10_000,
+            1_000_000,
+            1_000,
+            &ObservationBuffer::new(&ObservationState::default(), &[]),
+            FeeType::Static,
+            BASE_FEES,
+            &pool_state,
+            false,
+        )
+        .unwrap();
+        assert_eq!(fee, 25);
+    }
+
+    #[test]
+    fn imbalance_fee_grows_with_price_impact() {
+        let pool_state = PoolState::default();
+        // No price impact
+        assert_eq!(
+            fee_rate(FeeType::Imbalance, 1, 1_000_000_000, &pool_state, false),
+            BASE_FEES
+        );
+        // 1% price impact adds 0.1%
+        assert_eq!(
+            fee_rate(
+                FeeType::Imbalance,
+                1_000_000,
+                99_000_000,
+                &pool_state,
+                false
+            ),
+            BASE_FEES + 1_000
+        );
+        // 10% price impact adds 1%
+        assert_eq!(
+            fee_rate(
+                FeeType::Imbalance,
+                10_000_000,
+                90_000_000,
+                &pool_state,
+                false
+            ),
+            BASE_FEES + 10_000
+        );
+
+        let mut previous_fee_rate = 0;
+        for amount in [1, 10, 1_000, 100_000, 10_000_000, 1_000_000_000] {
+            let dynamic_fee_rate =
+                fee_rate(FeeType::Imbalance, amount, 100_000_000, &pool_state, false);
+            assert!(dynamic_fee_rate >= previous_fee_rate);
+            previous_fee_rate = dynamic_fee_rate;
+        }
+    }
+
+    #[test]
+    fn imbalance_fee_is_capped_by_max_trade_fee_rate() {
+        let mut pool_state = PoolState::default();
+        assert_eq!(
+            fee_rate(FeeType::Imbalance, u64::MAX as u128, 1, &pool_state, false),
+            DEFAULT_MAX_FEE
+        );
+
+        pool_state.max_trade_fee_rate = 5_000;
+        assert_eq!(
+            fee_rate(
+                FeeType::Imbalance,
+                10_000_000,
+                90_000_000,
+                &pool_state,
+                false
+            ),
+            5_000
+        );
+    }
+
+    #[test]
+    fn imbalance_fee_discount_for_signed_segmenter() {
+        let pool_state = PoolState::default();
+        assert_eq!(
+            fee_rate(FeeType::Imbalance, 1_000_000, 99_000_000, &pool_state, true),
+            BASE_FEES + 1_000 - ONE_BASIS_POINT
+        );
+        // Never below the base fee
+        assert_eq!(
+            fee_rate(FeeType::Imbalance, 1, 1_000_000_000, &pool_state, true),
+            BASE_FEES
+        );
+    }
+
+    #[test]
+    fn pre_fee_amount_covers_fee_for_every_fee_type() {
+        let pool_state = PoolState::default();
+        let post_fee_amount = 1_000_000;
+        for fee_type in [FeeType::Volatility, FeeType::Static, FeeType::Imbalance] {
+            let (pre_fee_amount, dynamic_fee_rate) = DynamicFee::calculate_pre_fee_amount(
+                1_000,
+                post_fee_amount,
+                99_000_000,
+                &ObservationBuffer::new(&ObservationState::default(), &[]),
+                fee_type,
+                BASE_FEES,
+                &pool_state,
+                false,
+            )
+            .unwrap();
+            let fee = ceil_div(
+                pre_fee_amount,
+                u128::from(dynamic_fee_rate),
+                u128::from(FEE_RATE_DENOMINATOR_VALUE),
+            )
+            .unwrap();
+            assert!(pre_fee_amount - fee >= post_fee_amount);
+            assert!(pre_fee_amount - fee <= post_fee_amount + 1);
+        }
+    }
+
+    fn launch_fee_rate(pool_state: &PoolState, block_timestamp: u64) -> u64 {
+        DynamicFee::calculate_dynamic_fee(
+            1_000,
+            1_000_000_000,
+            block_timestamp,
+            &ObservationBuffer::new(&ObservationState::default(), &[]),
+            FeeType::Static,
+            BASE_FEES,
+            pool_state,
+            false,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn launch_fee_decays_linearly_to_normal_fee() {
+        let mut pool_state = PoolState::default();
+        pool_state.open_time = 1_000;
+        pool_state
+            .set_launch_fee(BASE_FEES + 40_000, 100, LaunchFeeDecay::Linear as u8)
+            .unwrap();
+
+        assert_eq!(launch_fee_rate(&pool_state, 500), BASE_FEES + 40_000);
+        assert_eq!(launch_fee_rate(&pool_state, 1_000), BASE_FEES + 40_000);
+        assert_eq!(launch_fee_rate(&pool_state, 1_025), BASE_FEES + 30_000);
+        assert_eq!(launch_fee_rate(&pool_state, 1_050), BASE_FEES + 20_000);
+        assert_eq!(launch_fee_rate(&pool_state, 1_099), BASE_FEES + 400);
+        assert_eq!(launch_fee_rate(&pool_state, 1_100), BASE_FEES);
+        assert_eq!(launch_fee_rate(&pool_state, 10_000), BASE_FEES);
+    }
+
+    #[test]
+    fn launch_fee_decays_exponentially_to_normal_fee() {
+        let mut pool_state = PoolState::default();
+        pool_state.open_time = 1_000;
+        pool_state
+            .set_launch_fee(BASE_FEES + 40_960, 100, LaunchFeeDecay::Exponential as u8)
+            .unwrap();
+
+        assert_eq!(launch_fee_rate(&pool_state, 1_000), BASE_FEES + 40_960);
+        // Halves every tenth of the duration
+        let mut previous_fee_rate = BASE_FEES + 40_960;
+        for half_lives in 1..LAUNCH_FEE_HALF_LIVES {
+            let dynamic_fee_rate = launch_fee_rate(&pool_state, 1_000 + half_lives * 10);
+            let expected = BASE_FEES + (40_960 >> half_lives);
+            assert!(dynamic_fee_rate.abs_diff(expected) <= 1);
+            assert!(dynamic_fee_rate < previous_fee_rate);
+            previous_fee_rate = dynamic_fee_rate;
+        }
+        assert_eq!(launch_fee_rate(&pool_state, 1_100), BASE_FEES);
+    }
+
+    #[test]
+    fn launch_fee_is_capped_by_max_trade_fee_rate() {
+        let mut pool_state = PoolState::default();
+        pool_state.open_time = 1_000;
+        pool_state.max_trade_fee_rate = 20_000;
+        pool_state
+            .set_launch_fee(500_000, 100, LaunchFeeDecay::Linear as u8)
+            .unwrap();
+        assert_eq!(launch_fee_rate(&pool_state, 1_000), 20_000);
+
+        // Never lowers the normal fee
+        pool_state.max_trade_fee_rate = 1_000;
+        assert_eq!(launch_fee_rate(&pool_state, 1_000), BASE_FEES);
+    }
+
+    #[test]
+    fn launch_fee_is_validated() {
+        let mut pool_state = PoolState::default();
+        assert!(pool_state.set_launch_fee(0, 0, 0).is_ok());
+        assert!(pool_state
+            .set_launch_fee(FEE_RATE_DENOMINATOR_VALUE, 100, 0)
+            .is_err());
+        assert!(pool_state
+            .set_launch_fee(10_000, MAX_LAUNCH_FEE_DURATION + 1, 0)
+            .is_err());
+        assert!(pool_state.set_launch_fee(10_000, 100, 2).is_err());
+    }
+
+    #[test]
+    fn volatility_component_matches_known_values() {
+        // log2(2^40) - log2(2^32) = 8, log2(2^32) = 32
+        assert_eq!(
+            DynamicFee::volatility_component(1 << 32, 1 << 40, 1 << 32, 300_000).unwrap(),
+            75_000
+        );
+        assert_eq!(
+            DynamicFee::volatility_component(1 << 32, 1 << 32, 1 << 32, 300_000).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn volatility_component_saturates() {
+        // log2(u128::MAX) - log2(2) is about 127, log2(2) = 1
+        assert_eq!(
+            DynamicFee::volatility_component(2, u128::MAX, 2, u64::MAX).unwrap(),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn price_range_across_ring_buffer_wrap() {
+        let mut observation_state = ObservationState::default();
+        observation_state.observation_cardinality_next = OBSERVATION_NUM as u16 + 20;
+        let mut extra_observations = [Observation::default(); 20];
+        let mut observations =
+            ObservationBufferMut::new(&mut observation_state, &mut extra_observations);
+
+        let price = 3u128 << 32;
+        let mut block_timestamp = 1_000;
+        observations.update(block_timestamp, price, price).unwrap();
+        // Start close to the overflow of the cumulative prices
+        observations.state.observations[0].cumulative_token_0_price_x32 = u128::MAX - price;
+        observations.state.observations[0].cumulative_token_1_price_x32 = u128::MAX - price;
+        // Wrap around the grown buffer a couple of times, cycling through three prices
+        for i in 0..300 {
+            block_timestamp += 60;
+            let spot_price = price * (1 + i % 3);
+            observations
+                .update(block_timestamp, spot_price, price)
+                .unwrap();
+        }
+        assert_eq!(
+            { observations.state.observation_cardinality },
+            OBSERVATION_NUM as u16 + 20
+        );
+        assert_eq!({ observations.state.observation_index }, 300 % 120);
+        assert_eq!(
+            observations.as_buffer().newest_first().count(),
+            OBSERVATION_NUM + 20
+        );
+
+        // 60 intervals in the hour, 20 at each of the three prices
+        let (min_price, max_price, twap_price) =
+            DynamicFee::get_price_range(&observations.as_buffer(), block_timestamp, 3600).unwrap();
+        assert_eq!(min_price, price);
+        assert_eq!(max_price, 3 * price);
+        assert_eq!(twap_price, 2 * price);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn volatility_component_is_close_to_f64(
+            min_price in 1u128..=u128::from(u64::MAX),
+            spread in 0u128..=u128::from(u32::MAX),
+            twap_price in 2u128..=u128::from(u64::MAX),
+            volatility_factor in 0u64..=1_000_000,
+        ) {
+            let max_price = min_price + spread;
+            let fixed =
+                DynamicFee::volatility_component(min_price, max_price, twap_price, volatility_factor)
+                    .unwrap();
+            let float = DynamicFee::f64_volatility_component(min_price, max_price, twap_price, volatility_factor);
+            proptest::prop_assert!(fixed.abs_diff(float) <= 1, "fixed {} float {}", fixed, float);
+        }
+
+        #[test]
+        fn volatility_component_is_monotone_in_max_price(
+            min_price in 1u128..=u128::from(u64::MAX),
+            spread in 0u128..=u128::from(u32::MAX),
+            twap_price in 2u128..=u128::from(u64::MAX),
+        ) {
+            let lower =
+                DynamicFee::volatility_component(min_price, min_price + spread, twap_price, 300_000)
+                    .unwrap();
+            let higher = DynamicFee::volatility_component(
+                min_price,
+                min_price + spread + 1,
+                twap_price,
+                300_000,
+            )
+            .unwrap();
+            proptest::prop_assert!(lower <= higher);
+        }
+    }
+}
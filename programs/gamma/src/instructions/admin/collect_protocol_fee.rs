@@ -82,8 +82,18 @@ pub fn collect_protocol_fee(
     {
         let mut pool_state = ctx.accounts.pool_state.load_mut()?;
 
-        amount_0 = amount_0_requested.min(pool_state.protocol_fees_token_0);
-        amount_1 = amount_1_requested.min(pool_state.protocol_fees_token_1);
+        // The fee share of the partners stays in the pool until they claim it
+        let (partner_fees_token_0, partner_fees_token_1) = pool_state.partner_fees();
+        amount_0 = amount_0_requested.min(
+            pool_state
+                .protocol_fees_token_0
+                .saturating_sub(partner_fees_token_0),
+        );
+        amount_1 = amount_1_requested.min(
+            pool_state
+                .protocol_fees_token_1
+                .saturating_sub(partner_fees_token_1),
+        );
 
         pool_state.protocol_fees_token_0 = pool_state
             .protocol_fees_token_0
@@ -2,12 +2,16 @@ pub mod collect_fund_fee;
 pub mod collect_protocol_fee;
 pub mod create_config;
 pub mod create_referral_project;
+pub mod register_partner;
 pub mod update_config;
+pub mod update_partner_authority;
 pub mod update_pool;
 
 pub use collect_fund_fee::*;
 pub use collect_protocol_fee::*;
 pub use create_config::*;
 pub use create_referral_project::*;
+pub use register_partner::*;
 pub use update_config::*;
+pub use update_partner_authority::*;
 pub use update_pool::*;
@@ -0,0 +1,52 @@
+use crate::{
+    error::GammaError,
+    fees::FEE_RATE_DENOMINATOR_VALUE,
+    states::{Partner, PARTNER_SEED},
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(partner_id: u64)]
+pub struct RegisterPartner<'info> {
+    /// Only the admin can register partners
+    #[account(
+        mut,
+        address = crate::admin::id() @ GammaError::InvalidOwner
+    )]
+    pub owner: Signer<'info>,
+
+    /// Registry account of the partner
+    #[account(
+        init,
+        seeds = [
+            PARTNER_SEED.as_bytes(),
+            &partner_id.to_le_bytes(),
+        ],
+        bump,
+        payer = owner,
+        space = Partner::LEN
+    )]
+    pub partner: Account<'info, Partner>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn register_partner(
+    ctx: Context<RegisterPartner>,
+    partner_id: u64,
+    fee_share_rate: u64,
+    authority: Pubkey,
+) -> Result<()> {
+    require_gte!(
+        FEE_RATE_DENOMINATOR_VALUE,
+        fee_share_rate,
+        GammaError::InvalidFee
+    );
+
+    let partner = &mut ctx.accounts.partner;
+    partner.partner_id = partner_id;
+    partner.fee_share_rate = fee_share_rate;
+    partner.bump = ctx.bumps.partner;
+    partner.authority = authority;
+    Ok(())
+}
@@ -47,6 +47,7 @@ pub fn update_amm_config(ctx: Context<UpdateAmmConfig>, param: u16, value: u64)
             };
             set_new_secondary_admin(amm_config, new_secondary_admin)?;
         }
+        9 => amm_config.flash_loan_fee_rate = value,
         _ => return err!(GammaError::InvalidInput),
     }
 
@@ -0,0 +1,31 @@
+use crate::{
+    error::GammaError,
+    states::{Partner, PARTNER_SEED},
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct UpdatePartnerAuthority<'info> {
+    /// Only the admin or the current authority of the partner can rotate the authority
+    #[account(constraint = (owner.key() == partner.authority || owner.key() == crate::admin::id()) @ GammaError::InvalidOwner)]
+    pub owner: Signer<'info>,
+
+    /// Registry account of the partner
+    #[account(
+        mut,
+        seeds = [
+            PARTNER_SEED.as_bytes(),
+            &partner.partner_id.to_le_bytes(),
+        ],
+        bump = partner.bump,
+    )]
+    pub partner: Account<'info, Partner>,
+}
+
+pub fn update_partner_authority(
+    ctx: Context<UpdatePartnerAuthority>,
+    authority: Pubkey,
+) -> Result<()> {
+    ctx.accounts.partner.authority = authority;
+    Ok(())
+}
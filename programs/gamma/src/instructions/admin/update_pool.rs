@@ -1,5 +1,6 @@
-use crate::fees::MAX_SHARED_WITH_KAMINO_RATE;
-use crate::states::AmmConfig;
+use crate::curve::{CurveType, MAX_AMP, MAX_AMP_CHANGE, MIN_AMP, MIN_RAMP_DURATION};
+use crate::fees::{FeeType, MAX_SHARED_WITH_KAMINO_RATE};
+use crate::states::{AmmConfig, ObservationBufferMut, MAX_OBSERVATION_UPDATE_DURATION};
 use crate::{error::GammaError, fees::FEE_RATE_DENOMINATOR_VALUE, states::PoolState};
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::clock;
@@ -38,6 +39,10 @@ pub fn update_pool(ctx: Context<UpdatePool>, param: u32, value: u64) -> Result<(
         3 => update_max_shared_token0(ctx, value),
         4 => update_max_shared_token1(ctx, value),
         5 => update_open_time(ctx),
+        6 => ramp_amp(ctx, value),
+        7 => update_fee_type(ctx, value),
+        8 => update_launch_fee(ctx, value),
+        9 => update_observation_update_duration(ctx, value),
         _ => Err(GammaError::InvalidInput.into()),
     }
 }
@@ -49,6 +54,44 @@ fn update_open_time(ctx: Context<UpdatePool>) -> Result<()> {
     Ok(())
 }
 
+/// Starts moving the StableSwap amplification coefficient from its current value to a target.
+/// The low 32 bits of `value` are the target amplification and the high 32 bits the ramp duration in seconds.
+fn ramp_amp(ctx: Context<UpdatePool>, value: u64) -> Result<()> {
+    let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+    require!(
+        pool_state.curve_type()? == CurveType::StableSwap,
+        GammaError::InvalidCurveType
+    );
+    let target_amp = value & u64::from(u32::MAX);
+    let ramp_duration = value >> 32;
+    if !(MIN_AMP..=MAX_AMP).contains(&target_amp) {
+        return err!(GammaError::InvalidAmp);
+    }
+    require_gte!(ramp_duration, MIN_RAMP_DURATION, GammaError::InvalidInput);
+
+    let block_timestamp = clock::Clock::get()?.unix_timestamp as u64;
+    let current_amp = pool_state.current_amp(block_timestamp);
+    if target_amp > current_amp {
+        require_gte!(
+            current_amp * MAX_AMP_CHANGE,
+            target_amp,
+            GammaError::InvalidAmp
+        );
+    } else {
+        require_gte!(
+            target_amp * MAX_AMP_CHANGE,
+            current_amp,
+            GammaError::InvalidAmp
+        );
+    }
+
+    pool_state.initial_amp = current_amp as u32;
+    pool_state.target_amp = target_amp as u32;
+    pool_state.amp_ramp_start_time = block_timestamp;
+    pool_state.amp_ramp_stop_time = block_timestamp + ramp_duration;
+    Ok(())
+}
+
 fn update_max_trade_fee_rate(ctx: Context<UpdatePool>, max_trade_fee_rate: u64) -> Result<()> {
     let mut pool_state = ctx.accounts.pool_state.load_mut()?;
     pool_state.max_trade_fee_rate = max_trade_fee_rate;
@@ -78,6 +121,51 @@ fn update_volatility_factor(ctx: Context<UpdatePool>, volatility_factor: u64) ->
     Ok(())
 }
 
+fn update_fee_type(ctx: Context<UpdatePool>, fee_type: u64) -> Result<()> {
+    let fee_type = u8::try_from(fee_type).map_err(|_| GammaError::InvalidFee)?;
+    FeeType::new(fee_type)?;
+    let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+    pool_state.fee_type = fee_type;
+    Ok(())
+}
+
+/// Sets the launch fee schedule, which starts at the open time of the pool.
+/// The low 32 bits of `value` are the launch fee rate, the next 24 bits the decay duration in seconds
+/// and the high 8 bits the `LaunchFeeDecay`.
+fn update_launch_fee(ctx: Context<UpdatePool>, value: u64) -> Result<()> {
+    let launch_fee_rate = value & u64::from(u32::MAX);
+    let launch_fee_duration = (value >> 32) & 0xff_ffff;
+    let launch_fee_decay = (value >> 56) as u8;
+    let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+    pool_state.set_launch_fee(launch_fee_rate, launch_fee_duration, launch_fee_decay)
+}
+
+/// Sets the minimum number of seconds between two observations of the pool.
+/// The observation account of the pool is the first remaining account.
+fn update_observation_update_duration(
+    ctx: Context<UpdatePool>,
+    observation_update_duration: u64,
+) -> Result<()> {
+    require!(
+        (1..=MAX_OBSERVATION_UPDATE_DURATION).contains(&observation_update_duration),
+        GammaError::InvalidInput
+    );
+    let observation_info = match ctx.remaining_accounts.iter().next() {
+        Some(account) => account,
+        None => return err!(GammaError::InvalidInput),
+    };
+    require_keys_eq!(
+        observation_info.key(),
+        ctx.accounts.pool_state.load()?.observation_key,
+        GammaError::InvalidInput
+    );
+    require!(observation_info.is_writable, ErrorCode::AccountNotMutable);
+    let mut observation_data = observation_info.try_borrow_mut_data()?;
+    let observation_state = ObservationBufferMut::from_account_data(&mut observation_data)?;
+    observation_state.state.observation_update_duration = observation_update_duration;
+    Ok(())
+}
+
 fn update_pool_status(ctx: Context<UpdatePool>, status: u8) -> Result<()> {
     require_gte!(255, status);
     let mut pool_state = ctx.accounts.pool_state.load_mut()?;
@@ -13,6 +13,7 @@ pub struct CalculateRewards<'info> {
     pub pool_state: AccountLoader<'info, PoolState>,
 
     #[account(
+        mut,
         seeds = [
             crate::REWARD_INFO_SEED.as_bytes(),
             pool_state.key().as_ref(),
@@ -57,11 +58,15 @@ pub fn calculate_rewards(ctx: Context<CalculateRewards>) -> Result<()> {
         return Ok(());
     }
 
+    let reward_info = &mut ctx.accounts.reward_info;
+    reward_info.update_reward_per_lp(pool_state.lp_supply_in_positions()?)?;
+
     let user_reward_info = &mut ctx.accounts.user_reward_info;
     user_reward_info.calculate_claimable_rewards(
-        ctx.accounts.user_pool_liquidity.lp_tokens_owned as u64,
-        pool_state.lp_supply as u64,
-        &ctx.accounts.reward_info,
+        ctx.accounts
+            .user_pool_liquidity
+            .lp_tokens_earning(reward_info)?,
+        reward_info,
     )?;
 
     Ok(())
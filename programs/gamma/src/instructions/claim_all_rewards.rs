@@ -0,0 +1,142 @@
+use crate::{
+    error::GammaError,
+    states::{PoolState, RewardInfo, RewardsClaimedEvent, UserPoolLiquidity, UserRewardInfo},
+    utils::transfer_from_pool_vault_to_user,
+    REWARD_VAULT_SEED, USER_REWARD_INFO_SEED,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_interface::{Mint, Token2022, TokenAccount},
+};
+
+/// Accounts of each claim: `[reward_info, user_reward_info, reward_vault, user_token_account, reward_mint, pool_state, user_pool_liquidity]`
+pub const CLAIM_ACCOUNTS_LEN: usize = 7;
+
+#[derive(Accounts)]
+pub struct ClaimAllRewards<'info> {
+    pub user: Signer<'info>,
+
+    /// CHECK: pool vault authority
+    #[account(
+        seeds = [
+            crate::AUTH_SEED.as_bytes(),
+        ],
+        bump,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    /// token Program
+    pub token_program: Program<'info, Token>,
+
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+}
+
+pub fn claim_all_rewards<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, ClaimAllRewards<'info>>,
+) -> Result<()> {
+    let remaining_accounts = ctx.remaining_accounts;
+    require!(
+        !remaining_accounts.is_empty() && remaining_accounts.len() % CLAIM_ACCOUNTS_LEN == 0,
+        GammaError::InvalidInput
+    );
+
+    let user = ctx.accounts.user.key();
+    for accounts in remaining_accounts.chunks(CLAIM_ACCOUNTS_LEN) {
+        let pool_state_loader = AccountLoader::<PoolState>::try_from(&accounts[5])?;
+        let pool_id = pool_state_loader.key();
+        let mut reward_info = Account::<RewardInfo>::try_from(&accounts[0])?;
+        require_keys_eq!(reward_info.pool, pool_id, GammaError::InvalidInput);
+        require!(accounts[0].is_writable, GammaError::InvalidInput);
+        let reward_info_key = reward_info.key();
+
+        let user_pool_liquidity = Account::<UserPoolLiquidity>::try_from(&accounts[6])?;
+        require_keys_eq!(user_pool_liquidity.user, user, GammaError::InvalidOwner);
+        require_keys_eq!(
+            user_pool_liquidity.pool_state,
+            pool_id,
+            GammaError::InvalidInput
+        );
+
+        let (user_reward_info_key, _) = Pubkey::find_program_address(
+            &[
+                USER_REWARD_INFO_SEED.as_bytes(),
+                reward_info_key.as_ref(),
+                user.as_ref(),
+            ],
+            &crate::id(),
+        );
+        require_keys_eq!(
+            accounts[1].key(),
+            user_reward_info_key,
+            GammaError::InvalidInput
+        );
+        let mut user_reward_info = Account::<UserRewardInfo>::try_from(&accounts[1])?;
+
+        let (reward_vault_key, _) = Pubkey::find_program_address(
+            &[REWARD_VAULT_SEED.as_bytes(), reward_info_key.as_ref()],
+            &crate::id(),
+        );
+        require_keys_eq!(
+            accounts[2].key(),
+            reward_vault_key,
+            GammaError::InvalidInput
+        );
+
+        let reward_mint = InterfaceAccount::<Mint>::try_from(&accounts[4])?;
+        require_keys_eq!(
+            reward_mint.key(),
+            reward_info.mint,
+            GammaError::InvalidInput
+        );
+        let user_token_account = InterfaceAccount::<TokenAccount>::try_from(&accounts[3])?;
+        require_keys_eq!(user_token_account.owner, user, GammaError::InvalidOwner);
+        require_keys_eq!(
+            user_token_account.mint,
+            reward_info.mint,
+            GammaError::InvalidInput
+        );
+
+        reward_info.update_reward_per_lp(pool_state_loader.load()?.lp_supply_in_positions()?)?;
+        user_reward_info.calculate_claimable_rewards(
+            user_pool_liquidity.lp_tokens_earning(&reward_info)?,
+            &reward_info,
+        )?;
+
+        let total_claimable_rewards = user_reward_info.get_total_claimable_rewards();
+        transfer_from_pool_vault_to_user(
+            ctx.accounts.authority.to_account_info(),
+            accounts[2].clone(),
+            accounts[3].clone(),
+            accounts[4].clone(),
+            if accounts[4].owner == ctx.accounts.token_program.key {
+                ctx.accounts.token_program.to_account_info()
+            } else {
+                ctx.accounts.token_program_2022.to_account_info()
+            },
+            total_claimable_rewards,
+            reward_mint.decimals,
+            &[&[crate::AUTH_SEED.as_bytes(), &[ctx.bumps.authority]]],
+        )?;
+        user_reward_info.total_claimed = user_reward_info
+            .total_claimed
+            .checked_add(total_claimable_rewards)
+            .ok_or(GammaError::MathOverflow)?;
+
+        reward_info.exit(&crate::id())?;
+        user_reward_info.exit(&crate::id())?;
+
+        if total_claimable_rewards > 0 {
+            emit!(RewardsClaimedEvent {
+                pool_id,
+                reward_info: reward_info_key,
+                user,
+                mint: reward_info.mint,
+                amount: total_claimable_rewards,
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use crate::{
+    error::GammaError,
+    states::{
+        Partner, PartnerFeesClaimedEvent, PartnerInfo, PoolState, PARTNER_INFO_SEED, PARTNER_SEED,
+    },
+    utils::transfer_from_pool_vault_to_user,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_interface::{Mint, Token2022, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct ClaimPartnerFees<'info> {
+    /// Authority of the partner
+    pub partner_authority: Signer<'info>,
+
+    /// CHECK: pool vault authority
+    #[account(
+        seeds = [
+            crate::AUTH_SEED.as_bytes(),
+        ],
+        bump,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    /// Pool state stores accumulated protocol fee amount
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// Registry account of the partner
+    #[account(
+        seeds = [
+            PARTNER_SEED.as_bytes(),
+            &partner.partner_id.to_le_bytes(),
+        ],
+        bump = partner.bump,
+        constraint = partner.authority == partner_authority.key() @ GammaError::InvalidOwner,
+    )]
+    pub partner: Account<'info, Partner>,
+
+    /// Ledger of the partner in the pool
+    #[account(
+        mut,
+        seeds = [
+            PARTNER_INFO_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &partner.partner_id.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub partner_info: Box<Account<'info, PartnerInfo>>,
+
+    /// The address that holds pool tokens for token_0
+    #[account(
+        mut,
+        constraint = token_0_vault.key() == pool_state.load()?.token_0_vault
+    )]
+    pub token_0_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_1
+    #[account(
+        mut,
+        constraint = token_1_vault.key() == pool_state.load()?.token_1_vault
+    )]
+    pub token_1_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The mint of token_0 vault
+    #[account(
+        address = token_0_vault.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of token_1 vault
+    #[account(
+        address = token_1_vault.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The address that receives the token_0 fee share
+    #[account(
+        mut,
+        token::mint = vault_0_mint,
+    )]
+    pub recipient_token_0_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that receives the token_1 fee share
+    #[account(
+        mut,
+        token::mint = vault_1_mint,
+    )]
+    pub recipient_token_1_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The SPL program to perform token transfers
+    pub token_program: Program<'info, Token>,
+
+    /// The SPL program 2022 to perform token transfers
+    pub token_program_2022: Program<'info, Token2022>,
+}
+
+pub fn claim_partner_fees(ctx: Context<ClaimPartnerFees>) -> Result<()> {
+    let pool_id = ctx.accounts.pool_state.key();
+    let amount_0: u64;
+    let amount_1: u64;
+    let auth_bump: u8;
+    {
+        let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+        let partner_info = &mut ctx.accounts.partner_info;
+        partner_info.update_fees(&pool_state)?;
+
+        // Ledgers migrated after the protocol fees were collected may not be paid in full,
+        // the rest can be claimed later
+        let (claimable_0, claimable_1) = partner_info.claimable_fees()?;
+        amount_0 = claimable_0.min(pool_state.protocol_fees_token_0);
+        amount_1 = claimable_1.min(pool_state.protocol_fees_token_1);
+
+        partner_info.fees_claimed_token_0 = partner_info
+            .fees_claimed_token_0
+            .checked_add(amount_0)
+            .ok_or(GammaError::MathOverflow)?;
+        partner_info.fees_claimed_token_1 = partner_info
+            .fees_claimed_token_1
+            .checked_add(amount_1)
+            .ok_or(GammaError::MathOverflow)?;
+        pool_state.protocol_fees_token_0 = pool_state
+            .protocol_fees_token_0
+            .checked_sub(amount_0)
+            .ok_or(GammaError::MathOverflow)?;
+        pool_state.protocol_fees_token_1 = pool_state
+            .protocol_fees_token_1
+            .checked_sub(amount_1)
+            .ok_or(GammaError::MathOverflow)?;
+        pool_state.release_partner_fees(amount_0, amount_1);
+
+        auth_bump = pool_state.auth_bump;
+        pool_state.recent_epoch = Clock::get()?.epoch;
+    }
+
+    transfer_from_pool_vault_to_user(
+        ctx.accounts.authority.to_account_info(),
+        ctx.accounts.token_0_vault.to_account_info(),
+        ctx.accounts.recipient_token_0_account.to_account_info(),
+        ctx.accounts.vault_0_mint.to_account_info(),
+        if ctx.accounts.vault_0_mint.to_account_info().owner == ctx.accounts.token_program.key {
+            ctx.accounts.token_program.to_account_info()
+        } else {
+            ctx.accounts.token_program_2022.to_account_info()
+        },
+        amount_0,
+        ctx.accounts.vault_0_mint.decimals,
+        &[&[crate::AUTH_SEED.as_bytes(), &[auth_bump]]],
+    )?;
+
+    transfer_from_pool_vault_to_user(
+        ctx.accounts.authority.to_account_info(),
+        ctx.accounts.token_1_vault.to_account_info(),
+        ctx.accounts.recipient_token_1_account.to_account_info(),
+        ctx.accounts.vault_1_mint.to_account_info(),
+        if ctx.accounts.vault_1_mint.to_account_info().owner == ctx.accounts.token_program.key {
+            ctx.accounts.token_program.to_account_info()
+        } else {
+            ctx.accounts.token_program_2022.to_account_info()
+        },
+        amount_1,
+        ctx.accounts.vault_1_mint.decimals,
+        &[&[crate::AUTH_SEED.as_bytes(), &[auth_bump]]],
+    )?;
+
+    emit!(PartnerFeesClaimedEvent {
+        pool_id,
+        partner_id: ctx.accounts.partner.partner_id,
+        amount_0,
+        amount_1,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use crate::{
+    error::GammaError,
+    states::{PoolState, RewardInfo},
+    utils::transfer_from_pool_vault_to_user,
+    REWARD_VAULT_SEED,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_interface::{Mint, Token2022, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct CloseRewards<'info> {
+    pub reward_provider: Signer<'info>,
+
+    /// CHECK: pool vault authority
+    #[account(
+        seeds = [
+            crate::AUTH_SEED.as_bytes(),
+        ],
+        bump,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            crate::REWARD_INFO_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            reward_info.start_at.to_le_bytes().as_ref(),
+            reward_info.mint.as_ref(),
+        ],
+        bump,
+        constraint = reward_info.rewarded_by == reward_provider.key() @ GammaError::InvalidOwner,
+    )]
+    pub reward_info: Account<'info, RewardInfo>,
+
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = reward_provider,
+    )]
+    pub reward_providers_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            REWARD_VAULT_SEED.as_bytes(),
+            reward_info.key().as_ref(),
+        ],
+        bump,
+        token::mint = reward_mint,
+        token::authority = authority,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        address = reward_info.mint
+    )]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// token Program
+    pub token_program: Program<'info, Token>,
+
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+}
+
+pub fn close_rewards(ctx: Context<CloseRewards>) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+    let reward_info = &mut ctx.accounts.reward_info;
+    reward_info.update_reward_per_lp(pool_state.lp_supply_in_positions()?)?;
+    let undisbursed_rewards = reward_info.close()?;
+    // The reward stops growing, so lp changes do not have to settle it anymore
+    if reward_info.close_index == 0 {
+        pool_state.reward_count = pool_state
+            .reward_count
+            .checked_sub(1)
+            .ok_or(GammaError::MathOverflow)?;
+        reward_info.mark_closed(pool_state)?;
+    }
+
+    #[cfg(feature = "enable-log")]
+    msg!(
+        "closing reward {}, returning {} undisbursed rewards",
+        reward_info.key(),
+        undisbursed_rewards
+    );
+
+    transfer_from_pool_vault_to_user(
+        ctx.accounts.authority.to_account_info(),
+        ctx.accounts.reward_vault.to_account_info(),
+        ctx.accounts
+            .reward_providers_token_account
+            .to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        if ctx.accounts.reward_mint.to_account_info().owner == ctx.accounts.token_program.key {
+            ctx.accounts.token_program.to_account_info()
+        } else {
+            ctx.accounts.token_program_2022.to_account_info()
+        },
+        undisbursed_rewards,
+        ctx.accounts.reward_mint.decimals,
+        &[&[crate::AUTH_SEED.as_bytes(), &[pool_state.auth_bump]]],
+    )?;
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use crate::{
     error::GammaError,
-    states::{PoolState, RewardInfo},
+    states::{PoolState, RewardInfo, MAX_REWARDS_PER_POOL},
     utils::transfer_from_user_to_pool_vault,
     REWARD_VAULT_SEED,
 };
@@ -25,7 +25,7 @@ pub struct CreateRewards<'info> {
     )]
     pub authority: UncheckedAccount<'info>,
 
-    #[account()]
+    #[account(mut)]
     pub pool_state: AccountLoader<'info, PoolState>,
 
     #[account(
@@ -90,6 +90,16 @@ pub fn create_rewards(
         return err!(GammaError::InvalidRewardTime);
     }
 
+    {
+        let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+        require_gt!(
+            MAX_REWARDS_PER_POOL,
+            pool_state.reward_count,
+            GammaError::MaxRewardsReached
+        );
+        pool_state.reward_count += 1;
+    }
+
     transfer_from_user_to_pool_vault(
         ctx.accounts.reward_provider.to_account_info(),
         ctx.accounts
@@ -119,5 +129,11 @@ pub fn create_rewards(
 
     reward_info.pool = ctx.accounts.pool_state.key();
 
+    reward_info.reward_per_lp_x64 = 0;
+    reward_info.last_updated_at = start_time;
+    reward_info.total_disbursed = 0;
+    reward_info.schedule_start_at = start_time;
+    reward_info.schedule_amount = amount_in_vault;
+
     Ok(())
 }
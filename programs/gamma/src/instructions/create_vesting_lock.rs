@@ -0,0 +1,162 @@
+use crate::{
+    error::GammaError,
+    states::{
+        PartnerInfo, PoolState, UserPoolLiquidity, VestingLock, USER_POOL_LIQUIDITY_SEED,
+        VESTING_LOCK_SEED,
+    },
+    utils::settle_user_rewards,
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct CreateVestingLock<'info> {
+    /// Owner of the liquidity being vested, also pays for the beneficiary accounts
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    /// Pool state the liquidity belongs to
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            funder.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub funder_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    /// Ledger of the partner the funder's position is linked with, required when the position
+    /// has a partner
+    #[account(mut)]
+    pub funder_partner_info: Option<Box<Account<'info, PartnerInfo>>>,
+
+    /// CHECK: the wallet the liquidity vests to, only used as a seed
+    #[account(constraint = beneficiary.key() != funder.key() @ GammaError::InvalidInput)]
+    pub beneficiary: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            beneficiary.key().as_ref(),
+        ],
+        bump,
+        payer = funder,
+        space = UserPoolLiquidity::LEN,
+    )]
+    pub beneficiary_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    /// Ledger of the partner the beneficiary's position is linked with, required when it has a
+    /// partner other than the one of the funder's position
+    #[account(mut)]
+    pub beneficiary_partner_info: Option<Box<Account<'info, PartnerInfo>>>,
+
+    /// Vesting lock of the funder attached to the beneficiary's position, keyed by the funder so
+    /// that nobody else can hold up the locks of a position
+    #[account(
+        init_if_needed,
+        seeds = [
+            VESTING_LOCK_SEED.as_bytes(),
+            beneficiary_pool_liquidity.key().as_ref(),
+            funder.key().as_ref(),
+        ],
+        bump,
+        payer = funder,
+        space = VestingLock::LEN,
+    )]
+    pub vesting_lock: Box<Account<'info, VestingLock>>,
+
+    /// To create the beneficiary accounts
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves `lp_token_amount` from the funder's position into the vesting lp tokens of the
+/// beneficiary's position.
+///
+/// Remaining accounts are read in groups of three for every open reward of the pool:
+/// `[reward_info, funder_user_reward_info, beneficiary_user_reward_info]`, like `transfer_lp`.
+pub fn create_vesting_lock<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, CreateVestingLock<'info>>,
+    lp_token_amount: u64,
+    cliff_seconds: u64,
+    duration: u64,
+) -> Result<()> {
+    require_gt!(lp_token_amount, 0, GammaError::InvalidLpTokenAmount);
+    require_gte!(
+        ctx.accounts.funder_pool_liquidity.lp_tokens_owned,
+        u128::from(lp_token_amount),
+        GammaError::InvalidLpTokenAmount
+    );
+
+    let pool_id = ctx.accounts.pool_state.key();
+
+    let beneficiary_pool_liquidity = &mut ctx.accounts.beneficiary_pool_liquidity;
+    if beneficiary_pool_liquidity.user == Pubkey::default() {
+        beneficiary_pool_liquidity.initialize(ctx.accounts.beneficiary.key(), pool_id, None);
+    }
+
+    settle_user_rewards(
+        ctx.remaining_accounts,
+        pool_id,
+        &ctx.accounts.pool_state.load()?,
+        &mut [
+            (
+                ctx.accounts.funder.key(),
+                &mut **ctx.accounts.funder_pool_liquidity,
+            ),
+            (
+                ctx.accounts.beneficiary.key(),
+                &mut ***beneficiary_pool_liquidity,
+            ),
+        ],
+        &ctx.accounts.funder.to_account_info(),
+        Some(&ctx.accounts.system_program.to_account_info()),
+    )?;
+
+    let vesting_lock = &mut ctx.accounts.vesting_lock;
+    if vesting_lock.beneficiary == Pubkey::default() {
+        vesting_lock.pool_state = pool_id;
+        vesting_lock.user_pool_liquidity = beneficiary_pool_liquidity.key();
+        vesting_lock.beneficiary = ctx.accounts.beneficiary.key();
+        vesting_lock.bump = ctx.bumps.vesting_lock;
+    }
+    vesting_lock.start(
+        ctx.accounts.funder.key(),
+        lp_token_amount,
+        cliff_seconds,
+        duration,
+    )?;
+
+    let funder_pool_liquidity = &mut ctx.accounts.funder_pool_liquidity;
+    funder_pool_liquidity.lp_tokens_owned = funder_pool_liquidity
+        .lp_tokens_owned
+        .checked_sub(u128::from(lp_token_amount))
+        .ok_or(GammaError::MathOverflow)?;
+    beneficiary_pool_liquidity.lp_tokens_vesting = beneficiary_pool_liquidity
+        .lp_tokens_vesting
+        .checked_add(lp_token_amount)
+        .ok_or(GammaError::MathOverflow)?;
+
+    if funder_pool_liquidity.partner != beneficiary_pool_liquidity.partner {
+        let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+        pool_state.sub_lp_token_linked_with_partner(
+            &mut ctx.accounts.funder_partner_info,
+            pool_id,
+            funder_pool_liquidity.partner,
+            lp_token_amount,
+        )?;
+        pool_state.add_lp_token_linked_with_partner(
+            &mut ctx.accounts.beneficiary_partner_info,
+            pool_id,
+            beneficiary_pool_liquidity.partner,
+            lp_token_amount,
+        )?;
+    }
+
+    Ok(())
+}
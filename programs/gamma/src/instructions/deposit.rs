@@ -2,10 +2,10 @@ use crate::{
     curve::{CurveCalculator, RoundDirection},
     error::GammaError,
     states::{
-        LpChangeEvent, PartnerType, PoolState, PoolStatusBitIndex, UserPoolLiquidity,
+        LpChangeEvent, PartnerInfo, PoolState, PoolStatusBitIndex, UserPoolLiquidity,
         USER_POOL_LIQUIDITY_SEED,
     },
-    utils::{get_transfer_inverse_fee, transfer_from_user_to_pool_vault},
+    utils::{get_transfer_inverse_fee, settle_user_rewards, transfer_from_user_to_pool_vault},
 };
 use anchor_lang::prelude::*;
 use anchor_spl::{
@@ -15,7 +15,7 @@ use anchor_spl::{
 
 #[derive(Accounts)]
 pub struct Deposit<'info> {
-    /// Owner of the liquidity provided
+    /// Owner of the liquidity provided, pays for the missing user reward infos when writable
     pub owner: Signer<'info>,
 
     /// CHECK: pool vault authority
@@ -88,24 +88,37 @@ pub struct Deposit<'info> {
         address = token_1_vault.mint
     )]
     pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// To create the missing user reward infos of the owner, can be left out by pools without rewards.
+    /// The accounts past vault_1_mint are optional and appended, so the clients built before them
+    /// keep working, see the `allow-missing-optionals` feature of anchor-lang
+    pub system_program: Option<Program<'info, System>>,
+
+    /// Ledger of the partner the position is linked with, required when the position has a partner
+    #[account(mut)]
+    pub partner_info: Option<Box<Account<'info, PartnerInfo>>>,
 }
 
-pub fn deposit(
-    ctx: Context<Deposit>,
+pub fn deposit<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, Deposit<'info>>,
     lp_token_amount: u64,
     maximum_token_0_amount: u64,
     maximum_token_1_amount: u64,
 ) -> Result<()> {
     deposit_to_gamma_pool(
         ctx.accounts,
+        ctx.remaining_accounts,
         lp_token_amount,
         maximum_token_0_amount,
         maximum_token_1_amount,
     )
 }
 
-pub fn deposit_to_gamma_pool(
-    accounts: &mut Deposit,
+/// Deposits into the pool, `reward_accounts` are `[reward_info, owner_user_reward_info]` for each
+/// reward of the pool, the missing user reward infos are created and paid for by the owner.
+pub fn deposit_to_gamma_pool<'info>(
+    accounts: &mut Deposit<'info>,
+    reward_accounts: &'info [AccountInfo<'info>],
     lp_token_amount: u64,
     maximum_token_0_amount: u64,
     maximum_token_1_amount: u64,
@@ -115,6 +128,19 @@ pub fn deposit_to_gamma_pool(
     if !pool_state.get_status_by_bit(PoolStatusBitIndex::Deposit) {
         return err!(GammaError::NotApproved);
     }
+    require!(!pool_state.flash_loan_open(), GammaError::FlashLoanOpen);
+    let system_program = accounts
+        .system_program
+        .as_ref()
+        .map(|system_program| system_program.to_account_info());
+    settle_user_rewards(
+        reward_accounts,
+        pool_id,
+        pool_state,
+        &mut [(accounts.owner.key(), &mut *accounts.user_pool_liquidity)],
+        &accounts.owner.to_account_info(),
+        system_program.as_ref(),
+    )?;
     let (total_token_0_amount, total_token_1_amount) = pool_state.vault_amount_without_fee()?;
     let results = CurveCalculator::lp_tokens_to_trading_tokens(
         u128::from(lp_token_amount),
@@ -236,18 +262,25 @@ pub fn deposit_to_gamma_pool(
         .ok_or(GammaError::MathOverflow)?;
     pool_state.recent_epoch = Clock::get()?.epoch;
 
-    if let Some(user_pool_liquidity_partner) = user_pool_liquidity.partner {
-        let mut pool_state_partners = pool_state.partners;
-        let partner: Option<&mut crate::states::PartnerInfo> = pool_state_partners
-            .iter_mut()
-            .find(|p| PartnerType::new(p.partner_id) == user_pool_liquidity_partner);
-        if let Some(partner) = partner {
-            partner.lp_token_linked_with_partner = partner
-                .lp_token_linked_with_partner
-                .checked_add(lp_token_amount)
-                .ok_or(GammaError::MathOverflow)?;
-        }
-        pool_state.partners = pool_state_partners;
-    }
+    pool_state.add_lp_token_linked_with_partner(
+        &mut accounts.partner_info,
+        pool_id,
+        user_pool_liquidity.partner,
+        lp_token_amount,
+    )?;
     Ok(())
 }
+
+/// Splits the remaining accounts of a migration into the accounts of the source program and the
+/// `[reward_info, owner_user_reward_info]` of each open reward of the pool, which come last
+pub fn split_reward_accounts<'c, 'info>(
+    remaining_accounts: &'c [AccountInfo<'info>],
+    pool_state: &AccountLoader<'info, PoolState>,
+) -> Result<(&'c [AccountInfo<'info>], &'c [AccountInfo<'info>])> {
+    let reward_accounts_len = 2 * usize::from(pool_state.load()?.reward_count);
+    let source_accounts_len = remaining_accounts
+        .len()
+        .checked_sub(reward_accounts_len)
+        .ok_or(GammaError::IncompleteRewardInfos)?;
+    Ok(remaining_accounts.split_at(source_accounts_len))
+}
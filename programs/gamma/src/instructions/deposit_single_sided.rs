@@ -0,0 +1,425 @@
+use crate::{
+    curve::{CurveCalculator, RoundDirection, TradeDirection},
+    error::GammaError,
+    states::{
+        oracle, AmmConfig, LpChangeEvent, ObservationBufferMut, ObservationState, PartnerInfo,
+        PoolState, PoolStatusBitIndex, SwapEvent, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED,
+    },
+    utils::{
+        get_transfer_fee, get_transfer_inverse_fee, settle_user_rewards,
+        transfer_from_pool_vault_to_user, transfer_from_user_to_pool_vault,
+    },
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_interface::{Mint, Token2022, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct DepositSingleSided<'info> {
+    /// Owner of the liquidity provided
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: pool vault authority
+    #[account(
+        seeds = [
+            crate::AUTH_SEED.as_bytes(),
+        ],
+        bump,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    /// The factory state to read protocol fees
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Box<Account<'info, AmmConfig>>,
+
+    /// Pool state the owner is depositing into
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            owner.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub user_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    /// Ledger of the partner the position is linked with, required when the position has a partner
+    #[account(mut)]
+    pub partner_info: Option<Box<Account<'info, PartnerInfo>>>,
+
+    /// The owner's token account for token_0, pays token_0 or receives the leftover of it
+    #[account(
+        mut,
+        token::mint = token_0_vault.mint,
+        token::authority = owner
+    )]
+    pub token_0_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The owner's token account for token_1, pays token_1 or receives the leftover of it
+    #[account(
+        mut,
+        token::mint = token_1_vault.mint,
+        token::authority = owner
+    )]
+    pub token_1_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_0
+    #[account(
+        mut,
+        constraint = token_0_vault.key() == pool_state.load()?.token_0_vault
+    )]
+    pub token_0_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_1
+    #[account(
+        mut,
+        constraint = token_1_vault.key() == pool_state.load()?.token_1_vault
+    )]
+    pub token_1_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// token Program
+    pub token_program: Program<'info, Token>,
+
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+
+    /// The mint of token_0 vault
+    #[account(
+        address = token_0_vault.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of token_1 vault
+    #[account(
+        address = token_1_vault.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The program account for the most recent oracle observation
+    #[account(mut, address = pool_state.load()?.observation_key)]
+    pub observation_state: AccountLoader<'info, ObservationState>,
+
+    /// To create the missing user reward infos of the owner
+    pub system_program: Program<'info, System>,
+}
+
+/// Remaining accounts are `[reward_info, owner_user_reward_info]` for each open reward of the pool.
+pub fn deposit_single_sided<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, DepositSingleSided<'info>>,
+    input_mint: Pubkey,
+    amount_in: u64,
+    min_lp_out: u64,
+) -> Result<()> {
+    let block_timestamp = Clock::get()?.unix_timestamp as u64;
+    let pool_id = ctx.accounts.pool_state.key();
+    let accounts = ctx.accounts;
+    let pool_state = &mut accounts.pool_state.load_mut()?;
+    if !pool_state.get_status_by_bit(PoolStatusBitIndex::Deposit)
+        || !pool_state.get_status_by_bit(PoolStatusBitIndex::Swap)
+        || block_timestamp < pool_state.open_time
+    {
+        return err!(GammaError::NotApproved);
+    }
+    require!(!pool_state.flash_loan_open(), GammaError::FlashLoanOpen);
+    settle_user_rewards(
+        ctx.remaining_accounts,
+        pool_id,
+        pool_state,
+        &mut [(accounts.owner.key(), &mut **accounts.user_pool_liquidity)],
+        &accounts.owner.to_account_info(),
+        Some(&accounts.system_program.to_account_info()),
+    )?;
+
+    let trade_direction = if input_mint == accounts.vault_0_mint.key() {
+        TradeDirection::ZeroForOne
+    } else if input_mint == accounts.vault_1_mint.key() {
+        TradeDirection::OneForZero
+    } else {
+        return err!(GammaError::InvalidInput);
+    };
+    let (
+        input_token_account,
+        other_token_account,
+        input_vault,
+        other_vault,
+        input_mint,
+        other_mint,
+    ) = match trade_direction {
+        TradeDirection::ZeroForOne => (
+            &accounts.token_0_account,
+            &accounts.token_1_account,
+            &accounts.token_0_vault,
+            &accounts.token_1_vault,
+            &accounts.vault_0_mint,
+            &accounts.vault_1_mint,
+        ),
+        TradeDirection::OneForZero => (
+            &accounts.token_1_account,
+            &accounts.token_0_account,
+            &accounts.token_1_vault,
+            &accounts.token_0_vault,
+            &accounts.vault_1_mint,
+            &accounts.vault_0_mint,
+        ),
+    };
+    let input_token_program = if input_mint.to_account_info().owner == accounts.token_program.key {
+        accounts.token_program.to_account_info()
+    } else {
+        accounts.token_program_2022.to_account_info()
+    };
+    let other_token_program = if other_mint.to_account_info().owner == accounts.token_program.key {
+        accounts.token_program.to_account_info()
+    } else {
+        accounts.token_program_2022.to_account_info()
+    };
+
+    let (token_0_price_x32_before_swap, token_1_price_x32_before_swap) =
+        pool_state.token_price_x32(block_timestamp)?;
+
+    let transfer_fee = get_transfer_fee(&input_mint.to_account_info(), amount_in)?;
+    let actual_amount_in = amount_in.saturating_sub(transfer_fee);
+    require_gt!(actual_amount_in, 0);
+
+    let (total_token_0_amount, total_token_1_amount) = pool_state.vault_amount_without_fee()?;
+    let (total_input_token_amount, total_other_token_amount) = match trade_direction {
+        TradeDirection::ZeroForOne => (total_token_0_amount, total_token_1_amount),
+        TradeDirection::OneForZero => (total_token_1_amount, total_token_0_amount),
+    };
+
+    let mut observation_data = ObservationState::try_borrow_mut_data(&accounts.observation_state)?;
+    let mut observation_state = ObservationBufferMut::from_account_data(&mut observation_data)?;
+
+    // 1. Swap the part of the input that has to be converted into the other token
+    let constant_before = CurveCalculator::invariant(
+        u128::from(total_input_token_amount),
+        u128::from(total_other_token_amount),
+        trade_direction,
+        pool_state,
+        block_timestamp,
+    )?;
+    let result = CurveCalculator::swap_for_single_sided_deposit(
+        u128::from(actual_amount_in),
+        u128::from(total_input_token_amount),
+        u128::from(total_other_token_amount),
+        trade_direction,
+        &accounts.amm_config,
+        pool_state,
+        block_timestamp,
+        &observation_state.as_buffer(),
+    )?;
+    let swap_amount_in =
+        u64::try_from(result.source_amount_swapped).map_err(|_| GammaError::MathOverflow)?;
+    let swap_amount_out =
+        u64::try_from(result.destination_amount_swapped).map_err(|_| GammaError::MathOverflow)?;
+    require_gt!(swap_amount_out, 0, GammaError::ZeroTradingTokens);
+    let constant_after = CurveCalculator::invariant(
+        result
+            .new_swap_source_amount
+            .checked_sub(result.dynamic_fee)
+            .ok_or(GammaError::MathOverflow)?,
+        result.new_swap_destination_amount,
+        trade_direction,
+        pool_state,
+        block_timestamp,
+    )?;
+    require_gte!(constant_after, constant_before);
+
+    emit!(SwapEvent {
+        pool_id,
+        input_vault_before: total_input_token_amount,
+        output_vault_before: total_other_token_amount,
+        input_amount: swap_amount_in,
+        output_amount: swap_amount_out,
+        input_mint: input_mint.key(),
+        output_mint: other_mint.key(),
+        input_transfer_fee: 0,
+        output_transfer_fee: 0,
+        base_input: true,
+        dynamic_fee: result.dynamic_fee
+    });
+
+    pool_state.record_swap(
+        trade_direction,
+        swap_amount_in,
+        swap_amount_out,
+        u64::try_from(result.dynamic_fee).map_err(|_| GammaError::MathOverflow)?,
+        u64::try_from(result.protocol_fee).map_err(|_| GammaError::MathOverflow)?,
+        u64::try_from(result.fund_fee).map_err(|_| GammaError::MathOverflow)?,
+        result.dynamic_fee_rate,
+    )?;
+
+    // 2. Deposit the rest of the input together with the swap output at the new pool ratio
+    let (total_token_0_amount, total_token_1_amount) = pool_state.vault_amount_without_fee()?;
+    let (total_input_token_amount, total_other_token_amount) = match trade_direction {
+        TradeDirection::ZeroForOne => (total_token_0_amount, total_token_1_amount),
+        TradeDirection::OneForZero => (total_token_1_amount, total_token_0_amount),
+    };
+    let input_amount_left = actual_amount_in
+        .checked_sub(swap_amount_in)
+        .ok_or(GammaError::MathOverflow)?;
+    // Both lp amounts are rounded down, so the deposit amounts rounded up fit into what is available
+    let lp_token_amount = std::cmp::min(
+        CurveCalculator::token_0_to_lp_tokens(
+            u128::from(input_amount_left),
+            u128::from(total_input_token_amount),
+            u128::from(pool_state.lp_supply),
+        )
+        .ok_or(GammaError::MathOverflow)?,
+        CurveCalculator::token_1_to_lp_tokens(
+            u128::from(swap_amount_out),
+            u128::from(total_other_token_amount),
+            u128::from(pool_state.lp_supply),
+        )
+        .ok_or(GammaError::MathOverflow)?,
+    );
+    require_gt!(lp_token_amount, 0, GammaError::InvalidLpTokenAmount);
+    let results = CurveCalculator::lp_tokens_to_trading_tokens(
+        lp_token_amount,
+        u128::from(pool_state.lp_supply),
+        u128::from(total_token_0_amount),
+        u128::from(total_token_1_amount),
+        RoundDirection::Ceiling,
+    )
+    .ok_or(GammaError::ZeroTradingTokens)?;
+    let (input_deposit_amount, other_deposit_amount) = match trade_direction {
+        TradeDirection::ZeroForOne => (results.token_0_amount, results.token_1_amount),
+        TradeDirection::OneForZero => (results.token_1_amount, results.token_0_amount),
+    };
+    require_gte!(
+        u128::from(input_amount_left),
+        input_deposit_amount,
+        GammaError::MathOverflow
+    );
+    require_gte!(
+        u128::from(swap_amount_out),
+        other_deposit_amount,
+        GammaError::MathOverflow
+    );
+    let input_deposit_amount =
+        u64::try_from(input_deposit_amount).map_err(|_| GammaError::MathOverflow)?;
+    let other_deposit_amount =
+        u64::try_from(other_deposit_amount).map_err(|_| GammaError::MathOverflow)?;
+    let lp_token_amount = u64::try_from(lp_token_amount).map_err(|_| GammaError::MathOverflow)?;
+    require_gte!(lp_token_amount, min_lp_out, GammaError::ExceededSlippage);
+
+    // Only take what is actually used from the owner, the leftover of the swap output is sent back
+    let input_amount_used = swap_amount_in
+        .checked_add(input_deposit_amount)
+        .ok_or(GammaError::MathOverflow)?;
+    let input_transfer_fee =
+        get_transfer_inverse_fee(&input_mint.to_account_info(), input_amount_used)?;
+    let input_transfer_amount = input_amount_used
+        .checked_add(input_transfer_fee)
+        .ok_or(GammaError::MathOverflow)?;
+    require_gte!(
+        amount_in,
+        input_transfer_amount,
+        GammaError::ExceededSlippage
+    );
+    // The part of the swap output that did not fit the pool ratio goes back to the owner
+    let other_amount_left = swap_amount_out
+        .checked_sub(other_deposit_amount)
+        .ok_or(GammaError::MathOverflow)?;
+
+    let (token_0_amount, token_1_amount, token_0_transfer_fee, token_1_transfer_fee) =
+        match trade_direction {
+            TradeDirection::ZeroForOne => (
+                input_deposit_amount,
+                other_deposit_amount,
+                input_transfer_fee,
+                0,
+            ),
+            TradeDirection::OneForZero => (
+                other_deposit_amount,
+                input_deposit_amount,
+                0,
+                input_transfer_fee,
+            ),
+        };
+    emit!(LpChangeEvent {
+        pool_id,
+        lp_amount_before: pool_state.lp_supply,
+        token_0_vault_before: total_token_0_amount,
+        token_1_vault_before: total_token_1_amount,
+        token_0_amount,
+        token_1_amount,
+        token_0_transfer_fee,
+        token_1_transfer_fee,
+        change_type: 0
+    });
+
+    transfer_from_user_to_pool_vault(
+        accounts.owner.to_account_info(),
+        input_token_account.to_account_info(),
+        input_vault.to_account_info(),
+        input_mint.to_account_info(),
+        input_token_program,
+        input_transfer_amount,
+        input_mint.decimals,
+    )?;
+    transfer_from_pool_vault_to_user(
+        accounts.authority.to_account_info(),
+        other_vault.to_account_info(),
+        other_token_account.to_account_info(),
+        other_mint.to_account_info(),
+        other_token_program,
+        other_amount_left,
+        other_mint.decimals,
+        &[&[crate::AUTH_SEED.as_bytes(), &[pool_state.auth_bump]]],
+    )?;
+
+    pool_state.token_0_vault_amount = pool_state
+        .token_0_vault_amount
+        .checked_add(token_0_amount)
+        .ok_or(GammaError::MathOverflow)?;
+    pool_state.token_1_vault_amount = pool_state
+        .token_1_vault_amount
+        .checked_add(token_1_amount)
+        .ok_or(GammaError::MathOverflow)?;
+    pool_state.lp_supply = pool_state
+        .lp_supply
+        .checked_add(lp_token_amount)
+        .ok_or(GammaError::MathOverflow)?;
+
+    let user_pool_liquidity = &mut accounts.user_pool_liquidity;
+    match trade_direction {
+        TradeDirection::ZeroForOne => {
+            user_pool_liquidity.token_0_deposited = user_pool_liquidity
+                .token_0_deposited
+                .checked_add(u128::from(input_amount_used))
+                .ok_or(GammaError::MathOverflow)?;
+        }
+        TradeDirection::OneForZero => {
+            user_pool_liquidity.token_1_deposited = user_pool_liquidity
+                .token_1_deposited
+                .checked_add(u128::from(input_amount_used))
+                .ok_or(GammaError::MathOverflow)?;
+        }
+    }
+    user_pool_liquidity.lp_tokens_owned = user_pool_liquidity
+        .lp_tokens_owned
+        .checked_add(u128::from(lp_token_amount))
+        .ok_or(GammaError::MathOverflow)?;
+    pool_state.add_lp_token_linked_with_partner(
+        &mut accounts.partner_info,
+        pool_id,
+        user_pool_liquidity.partner,
+        lp_token_amount,
+    )?;
+
+    observation_state.update(
+        oracle::block_timestamp()?,
+        token_0_price_x32_before_swap,
+        token_1_price_x32_before_swap,
+    )?;
+    pool_state.recent_epoch = Clock::get()?.epoch;
+
+    Ok(())
+}
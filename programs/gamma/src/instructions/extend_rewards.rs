@@ -0,0 +1,40 @@
+use crate::{
+    error::GammaError,
+    states::{PoolState, RewardInfo},
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct ExtendRewards<'info> {
+    pub reward_provider: Signer<'info>,
+
+    #[account()]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            crate::REWARD_INFO_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            reward_info.start_at.to_le_bytes().as_ref(),
+            reward_info.mint.as_ref(),
+        ],
+        bump,
+        constraint = reward_info.rewarded_by == reward_provider.key() @ GammaError::InvalidOwner,
+    )]
+    pub reward_info: Account<'info, RewardInfo>,
+}
+
+pub fn extend_rewards(ctx: Context<ExtendRewards>, end_time: u64) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp as u64;
+    let reward_info = &mut ctx.accounts.reward_info;
+    require_eq!(reward_info.close_index, 0, GammaError::RewardClosed);
+    if end_time <= current_time || end_time <= reward_info.end_rewards_at {
+        return err!(GammaError::InvalidRewardTime);
+    }
+
+    reward_info.update_reward_per_lp(ctx.accounts.pool_state.load()?.lp_supply_in_positions()?)?;
+    reward_info.reschedule(0, end_time)?;
+
+    Ok(())
+}
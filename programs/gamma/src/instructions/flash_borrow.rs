@@ -0,0 +1,134 @@
+use crate::{
+    error::GammaError,
+    states::{PoolState, PoolStatusBitIndex},
+    utils::transfer_from_pool_vault_to_user,
+};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked, ID as INSTRUCTION_SYSVAR_ID,
+};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Index of the pool state in the accounts of `flash_repay`
+const FLASH_REPAY_POOL_STATE_INDEX: usize = 1;
+
+#[derive(Accounts)]
+pub struct FlashBorrow<'info> {
+    /// The borrower, anyone can borrow as long as the loan is repaid in the same transaction
+    pub borrower: Signer<'info>,
+
+    /// CHECK: pool vault authority
+    #[account(
+        seeds = [
+            crate::AUTH_SEED.as_bytes(),
+        ],
+        bump,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    /// The program account of the pool lending its vault
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The vault lending the tokens, token_0 or token_1 vault of the pool
+    #[account(
+        mut,
+        constraint = token_vault.key() == pool_state.load()?.token_0_vault || token_vault.key() == pool_state.load()?.token_1_vault
+    )]
+    pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The mint of the vault
+    #[account(
+        address = token_vault.mint
+    )]
+    pub vault_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The token account receiving the borrowed tokens
+    #[account(
+        mut,
+        token::mint = vault_mint,
+    )]
+    pub borrower_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = token_program.key() == *vault_mint.to_account_info().owner
+    )]
+    pub token_program: Interface<'info, TokenInterface>,
+
+    #[account(address = INSTRUCTION_SYSVAR_ID)]
+    /// CHECK: The native instructions sysvar
+    pub instruction_sysvar_account: UncheckedAccount<'info>,
+}
+
+pub fn flash_borrow(ctx: Context<FlashBorrow>, amount: u64) -> Result<()> {
+    require_gt!(amount, 0, GammaError::InvalidInput);
+    let pool_id = ctx.accounts.pool_state.key();
+    check_flash_repay(&ctx.accounts.instruction_sysvar_account, pool_id)?;
+
+    let auth_bump: u8;
+    {
+        let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+        // The loan lends the liquidity of the pool, so it follows the swap status of the pool
+        let block_timestamp = Clock::get()?.unix_timestamp as u64;
+        if !pool_state.get_status_by_bit(PoolStatusBitIndex::Swap)
+            || block_timestamp < pool_state.open_time
+        {
+            return err!(GammaError::NotApproved);
+        }
+        require!(!pool_state.flash_loan_open(), GammaError::FlashLoanOpen);
+
+        let (token_index, vault_amount) =
+            if ctx.accounts.token_vault.key() == pool_state.token_0_vault {
+                (0, pool_state.token_0_vault_amount)
+            } else {
+                (1, pool_state.token_1_vault_amount)
+            };
+        // Only the liquidity of the lp can be lent, not the fees owed to the protocol and fund
+        require_gte!(vault_amount, amount, GammaError::InvalidInput);
+
+        pool_state.flash_loan_token_index = token_index;
+        pool_state.flash_loan_amount = amount;
+        auth_bump = pool_state.auth_bump;
+    }
+
+    transfer_from_pool_vault_to_user(
+        ctx.accounts.authority.to_account_info(),
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.borrower_token_account.to_account_info(),
+        ctx.accounts.vault_mint.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        amount,
+        ctx.accounts.vault_mint.decimals,
+        &[&[crate::AUTH_SEED.as_bytes(), &[auth_bump]]],
+    )
+}
+
+/// Requires a later top level instruction of the transaction to be a `flash_repay` of the pool.
+/// The borrow itself must be a top level instruction, so that it can not be hidden in a cpi.
+fn check_flash_repay(instruction_sysvar: &AccountInfo, pool_id: Pubkey) -> Result<()> {
+    let current_index = load_current_index_checked(instruction_sysvar)? as usize;
+    let current_instruction = load_instruction_at_checked(current_index, instruction_sysvar)?;
+    require_keys_eq!(
+        current_instruction.program_id,
+        crate::id(),
+        GammaError::FlashLoanNotRepaid
+    );
+
+    let mut index = current_index + 1;
+    // Loading past the last instruction fails, which ends the search
+    while let Ok(instruction) = load_instruction_at_checked(index, instruction_sysvar) {
+        if instruction.program_id == crate::id()
+            && instruction
+                .data
+                .starts_with(&crate::instruction::FlashRepay::DISCRIMINATOR)
+            && instruction
+                .accounts
+                .get(FLASH_REPAY_POOL_STATE_INDEX)
+                .is_some_and(|account| account.pubkey == pool_id)
+        {
+            return Ok(());
+        }
+        index += 1;
+    }
+    err!(GammaError::FlashLoanNotRepaid)
+}
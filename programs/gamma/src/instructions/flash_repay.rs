@@ -0,0 +1,159 @@
+use crate::{
+    curve::TradeDirection,
+    error::GammaError,
+    fees::{ceil_div, StaticFee, FEE_RATE_DENOMINATOR_VALUE},
+    states::{AmmConfig, FlashLoanEvent, PoolState},
+    utils::{get_transfer_inverse_fee, transfer_from_user_to_pool_vault},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct FlashRepay<'info> {
+    /// Pays back the loan and its fee, does not have to be the borrower
+    pub payer: Signer<'info>,
+
+    /// The program account of the pool the loan is repaid to, `flash_borrow` looks it up at
+    /// this position
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// Amm config of the pool, stores the flash loan fee rate
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Box<Account<'info, AmmConfig>>,
+
+    /// The vault the tokens were lent from
+    #[account(mut)]
+    pub token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The mint of the vault
+    #[account(
+        address = token_vault.mint
+    )]
+    pub vault_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The token account paying back the loan
+    #[account(
+        mut,
+        token::mint = vault_mint,
+    )]
+    pub payer_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = token_program.key() == *vault_mint.to_account_info().owner
+    )]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+pub fn flash_repay(ctx: Context<FlashRepay>) -> Result<()> {
+    let pool_id = ctx.accounts.pool_state.key();
+    let amount: u64;
+    let fee: u64;
+    let protocol_fee: u64;
+    let fund_fee: u64;
+    {
+        let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+        require!(pool_state.flash_loan_open(), GammaError::InvalidInput);
+
+        let (token_vault, trade_direction) = if pool_state.flash_loan_token_index == 0 {
+            (pool_state.token_0_vault, TradeDirection::ZeroForOne)
+        } else {
+            (pool_state.token_1_vault, TradeDirection::OneForZero)
+        };
+        require_keys_eq!(
+            ctx.accounts.token_vault.key(),
+            token_vault,
+            GammaError::InvalidVault
+        );
+
+        amount = pool_state.flash_loan_amount;
+        fee = u64::try_from(
+            ceil_div(
+                u128::from(amount),
+                u128::from(ctx.accounts.amm_config.flash_loan_fee_rate),
+                u128::from(FEE_RATE_DENOMINATOR_VALUE),
+            )
+            .ok_or(GammaError::MathOverflow)?,
+        )
+        .map_err(|_| GammaError::MathOverflow)?;
+        protocol_fee = u64::try_from(
+            StaticFee::protocol_fee(u128::from(fee), ctx.accounts.amm_config.protocol_fee_rate)
+                .ok_or(GammaError::MathOverflow)?,
+        )
+        .map_err(|_| GammaError::MathOverflow)?;
+        fund_fee = u64::try_from(
+            StaticFee::fund_fee(u128::from(fee), ctx.accounts.amm_config.fund_fee_rate)
+                .ok_or(GammaError::MathOverflow)?,
+        )
+        .map_err(|_| GammaError::MathOverflow)?;
+
+        // The rest of the fee stays in the vault for the lps, like the trade fees of a swap
+        let lp_fee = fee
+            .checked_sub(protocol_fee)
+            .ok_or(GammaError::MathOverflow)?
+            .checked_sub(fund_fee)
+            .ok_or(GammaError::MathOverflow)?;
+        pool_state.update_protocol_fees_per_lp(protocol_fee, trade_direction)?;
+        match trade_direction {
+            TradeDirection::ZeroForOne => {
+                pool_state.protocol_fees_token_0 = pool_state
+                    .protocol_fees_token_0
+                    .checked_add(protocol_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+                pool_state.fund_fees_token_0 = pool_state
+                    .fund_fees_token_0
+                    .checked_add(fund_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+                pool_state.token_0_vault_amount = pool_state
+                    .token_0_vault_amount
+                    .checked_add(lp_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+            }
+            TradeDirection::OneForZero => {
+                pool_state.protocol_fees_token_1 = pool_state
+                    .protocol_fees_token_1
+                    .checked_add(protocol_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+                pool_state.fund_fees_token_1 = pool_state
+                    .fund_fees_token_1
+                    .checked_add(fund_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+                pool_state.token_1_vault_amount = pool_state
+                    .token_1_vault_amount
+                    .checked_add(lp_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+            }
+        }
+
+        pool_state.flash_loan_amount = 0;
+        pool_state.flash_loan_token_index = 0;
+        pool_state.recent_epoch = Clock::get()?.epoch;
+    }
+
+    // The vault must receive the amount and the fee after the transfer fees
+    let repay_amount = amount.checked_add(fee).ok_or(GammaError::MathOverflow)?;
+    let transfer_fee =
+        get_transfer_inverse_fee(&ctx.accounts.vault_mint.to_account_info(), repay_amount)?;
+    transfer_from_user_to_pool_vault(
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.payer_token_account.to_account_info(),
+        ctx.accounts.token_vault.to_account_info(),
+        ctx.accounts.vault_mint.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        repay_amount
+            .checked_add(transfer_fee)
+            .ok_or(GammaError::MathOverflow)?,
+        ctx.accounts.vault_mint.decimals,
+    )?;
+
+    emit!(FlashLoanEvent {
+        pool_id,
+        mint: ctx.accounts.vault_mint.key(),
+        amount,
+        fee,
+        protocol_fee,
+        fund_fee,
+    });
+
+    Ok(())
+}
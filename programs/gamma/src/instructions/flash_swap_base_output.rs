@@ -0,0 +1,257 @@
+use super::swap_base_input::Swap;
+use crate::curve::{calculator::CurveCalculator, TradeDirection};
+use crate::error::GammaError;
+use crate::states::{
+    oracle, ObservationBufferMut, ObservationState, PoolStatusBitIndex, SwapEvent,
+};
+use crate::utils::token::*;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::invoke;
+
+#[derive(Accounts)]
+pub struct FlashSwap<'info> {
+    /// The accounts of a swap, `payer` pays the input after the callback
+    pub swap: Swap<'info>,
+
+    /// CHECK: the program called back once the output is sent, it can not be this program
+    #[account(
+        executable,
+        constraint = callback_program.key() != crate::id() @ GammaError::InvalidInput,
+    )]
+    pub callback_program: UncheckedAccount<'info>,
+}
+
+/// Swaps base output like `swap_base_output`, but the output is sent before the input is paid.
+///
+/// The callback program is invoked with the remaining accounts and `callback_data` once the
+/// output is in the output token account, after it returns the input is transferred from the
+/// input token account of the payer. The pool is locked like during a flash loan until then,
+/// so the callback can not trade against it.
+pub fn flash_swap_base_output<'c, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, FlashSwap<'info>>,
+    max_amount_in: u64,
+    amount_out_less_fee: u64,
+    callback_data: Vec<u8>,
+) -> Result<()> {
+    let accounts = &mut ctx.accounts.swap;
+    let block_timestamp = solana_program::clock::Clock::get()?.unix_timestamp as u64;
+    let pool_id = accounts.pool_state.key();
+
+    let out_transfer_fee = get_transfer_inverse_fee(
+        &accounts.output_token_mint.to_account_info(),
+        amount_out_less_fee,
+    )?;
+    let actual_amount_out = amount_out_less_fee
+        .checked_add(out_transfer_fee)
+        .ok_or(GammaError::MathOverflow)?;
+
+    let trade_direction: TradeDirection;
+    let total_input_token_amount: u64;
+    let total_output_token_amount: u64;
+    let constant_before: u128;
+    let token_0_price_x64_before_swap: u128;
+    let token_1_price_x64_before_swap: u128;
+    let input_transfer_amount: u64;
+    let input_transfer_fee: u64;
+    let source_amount_swapped: u64;
+    let dynamic_fee: u128;
+    let protocol_fee: u64;
+    let fund_fee: u64;
+    let dynamic_fee_rate: u64;
+    let auth_bump: u8;
+    {
+        let pool_state = &mut accounts.pool_state.load_mut()?;
+        if !pool_state.get_status_by_bit(PoolStatusBitIndex::Swap)
+            || block_timestamp < pool_state.open_time
+        {
+            return err!(GammaError::NotApproved);
+        }
+        require!(!pool_state.flash_loan_open(), GammaError::FlashLoanOpen);
+
+        (token_0_price_x64_before_swap, token_1_price_x64_before_swap) =
+            pool_state.token_price_x32(block_timestamp)?;
+
+        let (total_token_0_amount, total_token_1_amount) = pool_state.vault_amount_without_fee()?;
+        (
+            trade_direction,
+            total_input_token_amount,
+            total_output_token_amount,
+        ) = if accounts.input_vault.key() == pool_state.token_0_vault
+            && accounts.output_vault.key() == pool_state.token_1_vault
+        {
+            (
+                TradeDirection::ZeroForOne,
+                total_token_0_amount,
+                total_token_1_amount,
+            )
+        } else if accounts.input_vault.key() == pool_state.token_1_vault
+            && accounts.output_vault.key() == pool_state.token_0_vault
+        {
+            (
+                TradeDirection::OneForZero,
+                total_token_1_amount,
+                total_token_0_amount,
+            )
+        } else {
+            return err!(GammaError::InvalidVault);
+        };
+        constant_before = CurveCalculator::invariant(
+            u128::from(total_input_token_amount),
+            u128::from(total_output_token_amount),
+            trade_direction,
+            pool_state,
+            block_timestamp,
+        )?;
+
+        let mut observation_data =
+            ObservationState::try_borrow_mut_data(&accounts.observation_state)?;
+        let observation_state = ObservationBufferMut::from_account_data(&mut observation_data)?;
+        let result = match CurveCalculator::swap_base_output(
+            u128::from(actual_amount_out),
+            u128::from(total_input_token_amount),
+            u128::from(total_output_token_amount),
+            trade_direction,
+            &accounts.amm_config,
+            pool_state,
+            block_timestamp,
+            &observation_state.as_buffer(),
+            false,
+        ) {
+            Ok(value) => value,
+            Err(_) => return err!(GammaError::ZeroTradingTokens),
+        };
+
+        source_amount_swapped =
+            u64::try_from(result.source_amount_swapped).or(err!(GammaError::MathOverflow))?;
+        require_gt!(source_amount_swapped, 0);
+        let destination_amount_swapped =
+            u64::try_from(result.destination_amount_swapped).or(err!(GammaError::MathOverflow))?;
+        require_eq!(destination_amount_swapped, actual_amount_out);
+
+        input_transfer_fee = get_transfer_inverse_fee(
+            &accounts.input_token_mint.to_account_info(),
+            source_amount_swapped,
+        )?;
+        input_transfer_amount = source_amount_swapped
+            .checked_add(input_transfer_fee)
+            .ok_or(GammaError::MathOverflow)?;
+        require_gte!(
+            max_amount_in,
+            input_transfer_amount,
+            GammaError::ExceededSlippage
+        );
+
+        dynamic_fee = result.dynamic_fee;
+        protocol_fee = u64::try_from(result.protocol_fee).or(err!(GammaError::MathOverflow))?;
+        fund_fee = u64::try_from(result.fund_fee).or(err!(GammaError::MathOverflow))?;
+        dynamic_fee_rate = result.dynamic_fee_rate;
+
+        // Lock the pool while the output is out, like a flash loan of the output token
+        pool_state.flash_loan_token_index = match trade_direction {
+            TradeDirection::ZeroForOne => 1,
+            TradeDirection::OneForZero => 0,
+        };
+        pool_state.flash_loan_amount = actual_amount_out;
+        auth_bump = pool_state.auth_bump;
+    }
+
+    transfer_from_pool_vault_to_user(
+        accounts.authority.to_account_info(),
+        accounts.output_vault.to_account_info(),
+        accounts.output_token_account.to_account_info(),
+        accounts.output_token_mint.to_account_info(),
+        accounts.output_token_program.to_account_info(),
+        actual_amount_out,
+        accounts.output_token_mint.decimals,
+        &[&[crate::AUTH_SEED.as_bytes(), &[auth_bump]]],
+    )?;
+
+    let callback_instruction = Instruction {
+        program_id: ctx.accounts.callback_program.key(),
+        accounts: ctx
+            .remaining_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: callback_data,
+    };
+    let mut callback_account_infos = ctx.remaining_accounts.to_vec();
+    callback_account_infos.push(ctx.accounts.callback_program.to_account_info());
+    invoke(&callback_instruction, &callback_account_infos)?;
+
+    let accounts = &mut ctx.accounts.swap;
+    accounts.input_token_account.reload()?;
+    require_gte!(
+        accounts.input_token_account.amount,
+        input_transfer_amount,
+        GammaError::FlashSwapNotPaid
+    );
+    transfer_from_user_to_pool_vault(
+        accounts.payer.to_account_info(),
+        accounts.input_token_account.to_account_info(),
+        accounts.input_vault.to_account_info(),
+        accounts.input_token_mint.to_account_info(),
+        accounts.input_token_program.to_account_info(),
+        input_transfer_amount,
+        accounts.input_token_mint.decimals,
+    )?;
+
+    let pool_state = &mut accounts.pool_state.load_mut()?;
+    pool_state.flash_loan_amount = 0;
+    pool_state.flash_loan_token_index = 0;
+    pool_state.record_swap(
+        trade_direction,
+        source_amount_swapped,
+        actual_amount_out,
+        u64::try_from(dynamic_fee).map_err(|_| GammaError::MathOverflow)?,
+        protocol_fee,
+        fund_fee,
+        dynamic_fee_rate,
+    )?;
+
+    // The invariant of the booked vault amounts, with the lp share of the fee in the pool
+    let (total_token_0_amount, total_token_1_amount) = pool_state.vault_amount_without_fee()?;
+    let (total_input_token_amount_after, total_output_token_amount_after) = match trade_direction {
+        TradeDirection::ZeroForOne => (total_token_0_amount, total_token_1_amount),
+        TradeDirection::OneForZero => (total_token_1_amount, total_token_0_amount),
+    };
+    let constant_after = CurveCalculator::invariant(
+        u128::from(total_input_token_amount_after),
+        u128::from(total_output_token_amount_after),
+        trade_direction,
+        pool_state,
+        block_timestamp,
+    )?;
+    require_gte!(constant_after, constant_before);
+
+    emit!(SwapEvent {
+        pool_id,
+        input_vault_before: total_input_token_amount,
+        output_vault_before: total_output_token_amount,
+        input_amount: source_amount_swapped,
+        output_amount: actual_amount_out,
+        input_mint: accounts.input_vault.mint,
+        output_mint: accounts.output_vault.mint,
+        input_transfer_fee,
+        output_transfer_fee: out_transfer_fee,
+        base_input: false,
+        dynamic_fee,
+    });
+
+    let mut observation_data = ObservationState::try_borrow_mut_data(&accounts.observation_state)?;
+    let mut observation_state = ObservationBufferMut::from_account_data(&mut observation_data)?;
+    observation_state.update(
+        oracle::block_timestamp()?,
+        token_0_price_x64_before_swap,
+        token_1_price_x64_before_swap,
+    )?;
+    pool_state.recent_epoch = Clock::get()?.epoch;
+
+    Ok(())
+}
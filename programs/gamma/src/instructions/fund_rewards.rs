@@ -0,0 +1,107 @@
+use crate::{
+    error::GammaError,
+    states::{PoolState, RewardInfo},
+    utils::transfer_from_user_to_pool_vault,
+    REWARD_VAULT_SEED,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_interface::{Mint, Token2022, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct FundRewards<'info> {
+    pub reward_provider: Signer<'info>,
+
+    #[account()]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            crate::REWARD_INFO_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            reward_info.start_at.to_le_bytes().as_ref(),
+            reward_info.mint.as_ref(),
+        ],
+        bump,
+        constraint = reward_info.rewarded_by == reward_provider.key() @ GammaError::InvalidOwner,
+    )]
+    pub reward_info: Account<'info, RewardInfo>,
+
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = reward_provider,
+    )]
+    pub reward_providers_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            REWARD_VAULT_SEED.as_bytes(),
+            reward_info.key().as_ref(),
+        ],
+        bump,
+        token::mint = reward_mint,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        address = reward_info.mint
+    )]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// token Program
+    pub token_program: Program<'info, Token>,
+
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+}
+
+pub fn fund_rewards(ctx: Context<FundRewards>, reward_amount: u64) -> Result<()> {
+    require_eq!(
+        ctx.accounts.reward_info.close_index,
+        0,
+        GammaError::RewardClosed
+    );
+    let current_time = Clock::get()?.unix_timestamp as u64;
+    if current_time >= ctx.accounts.reward_info.end_rewards_at {
+        return err!(GammaError::InvalidRewardTime);
+    }
+
+    let amount_in_vault_before = ctx.accounts.reward_vault.amount;
+    transfer_from_user_to_pool_vault(
+        ctx.accounts.reward_provider.to_account_info(),
+        ctx.accounts
+            .reward_providers_token_account
+            .to_account_info(),
+        ctx.accounts.reward_vault.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        if ctx.accounts.reward_mint.to_account_info().owner == ctx.accounts.token_program.key {
+            ctx.accounts.token_program.to_account_info()
+        } else {
+            ctx.accounts.token_program_2022.to_account_info()
+        },
+        reward_amount,
+        ctx.accounts.reward_mint.decimals,
+    )?;
+    ctx.accounts.reward_vault.reload()?;
+
+    // Transfer fees are not rewarded
+    let amount_received = ctx
+        .accounts
+        .reward_vault
+        .amount
+        .checked_sub(amount_in_vault_before)
+        .ok_or(GammaError::MathOverflow)?;
+
+    let lp_supply = ctx.accounts.pool_state.load()?.lp_supply_in_positions()?;
+    let reward_info = &mut ctx.accounts.reward_info;
+    reward_info.update_reward_per_lp(lp_supply)?;
+    let end_rewards_at = reward_info.end_rewards_at;
+    reward_info.reschedule(amount_received, end_rewards_at)?;
+
+    Ok(())
+}
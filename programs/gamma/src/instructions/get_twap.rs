@@ -0,0 +1,36 @@
+use crate::states::{oracle, ObservationBuffer, ObservationState, PoolState, Twap};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct GetTwap<'info> {
+    /// The pool to query
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The oracle observations of the pool
+    #[account(address = pool_state.load()?.observation_key)]
+    pub observation_state: AccountLoader<'info, ObservationState>,
+}
+
+pub fn get_twap(ctx: Context<GetTwap>, window_seconds: u64) -> Result<Twap> {
+    let pool_state = ctx.accounts.pool_state.load()?;
+    let observation_info: &AccountInfo = ctx.accounts.observation_state.as_ref();
+    let observation_data = observation_info.try_borrow_data()?;
+    let observation_state = ObservationBuffer::from_account_data(&observation_data)?;
+    let block_timestamp = oracle::block_timestamp()?;
+    let (token_0_price_x32, token_1_price_x32) = pool_state.token_price_x32(block_timestamp)?;
+
+    let twap = observation_state.twap(
+        block_timestamp,
+        window_seconds,
+        token_0_price_x32,
+        token_1_price_x32,
+    )?;
+    #[cfg(feature = "enable-log")]
+    msg!(
+        "twap over {} seconds, token_0_twap_price_x32: {}, token_1_twap_price_x32: {}",
+        window_seconds,
+        twap.token_0_twap_price_x32,
+        twap.token_1_twap_price_x32
+    );
+    Ok(twap)
+}
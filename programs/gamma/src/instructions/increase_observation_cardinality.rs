@@ -0,0 +1,84 @@
+use crate::error::GammaError;
+use crate::states::{ObservationState, PoolState, MAX_OBSERVATION_CARDINALITY};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use anchor_lang::system_program;
+
+#[derive(Accounts)]
+pub struct IncreaseObservationCardinality<'info> {
+    /// Pays the rent of the grown observation account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The oracle observations of the pool
+    #[account(
+        mut,
+        address = pool_state.load()?.observation_key
+    )]
+    pub observation_state: AccountLoader<'info, ObservationState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn increase_observation_cardinality(
+    ctx: Context<IncreaseObservationCardinality>,
+    observation_cardinality_next: u16,
+) -> Result<()> {
+    let observation_info = ctx.accounts.observation_state.to_account_info();
+    let current_observation_cardinality_next = ctx
+        .accounts
+        .observation_state
+        .load()?
+        .observation_cardinality_next();
+    require_gt!(
+        observation_cardinality_next,
+        current_observation_cardinality_next,
+        GammaError::InvalidInput
+    );
+    // Every swap reads the observations of the volatility window, the ring buffer is bounded to keep the
+    // account size in check
+    require_gte!(
+        MAX_OBSERVATION_CARDINALITY,
+        observation_cardinality_next,
+        GammaError::InvalidInput
+    );
+
+    // The account can only grow by MAX_PERMITTED_DATA_INCREASE bytes per instruction
+    let new_len = ObservationState::len_with_cardinality(observation_cardinality_next);
+    require_gte!(
+        observation_info.data_len() + MAX_PERMITTED_DATA_INCREASE,
+        new_len,
+        GammaError::InvalidInput
+    );
+
+    let rent_lamports = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(observation_info.lamports());
+    if rent_lamports > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: observation_info.clone(),
+                },
+            ),
+            rent_lamports,
+        )?;
+    }
+    observation_info.realloc(new_len, true)?;
+
+    // The new observations are used once the observation index reaches the end of the current ones
+    let mut observation_state = ctx.accounts.observation_state.load_mut()?;
+    observation_state.observation_cardinality = observation_state.observation_cardinality();
+    observation_state.observation_cardinality_next = observation_cardinality_next;
+    #[cfg(feature = "enable-log")]
+    msg!(
+        "observation_cardinality_next: {} -> {}",
+        current_observation_cardinality_next,
+        observation_cardinality_next
+    );
+    Ok(())
+}
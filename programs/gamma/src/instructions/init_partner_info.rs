@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+
+use crate::error::GammaError;
+use crate::states::{Partner, PartnerInfo, PoolState, PARTNER_INFO_SEED, PARTNER_SEED};
+
+#[derive(Accounts)]
+pub struct InitPartnerInfo<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// Registry account of the partner
+    #[account(
+        seeds = [
+            PARTNER_SEED.as_bytes(),
+            &partner.partner_id.to_le_bytes(),
+        ],
+        bump = partner.bump,
+    )]
+    pub partner: Account<'info, Partner>,
+
+    #[account(
+        init,
+        seeds = [
+            PARTNER_INFO_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &partner.partner_id.to_le_bytes(),
+        ],
+        bump,
+        payer = payer,
+        space = PartnerInfo::LEN,
+    )]
+    pub partner_info: Box<Account<'info, PartnerInfo>>,
+
+    /// To create a new program account
+    pub system_program: Program<'info, System>,
+}
+
+pub fn init_partner_info(ctx: Context<InitPartnerInfo>) -> Result<()> {
+    let pool_state = ctx.accounts.pool_state.load()?;
+    require!(!pool_state.is_legacy_layout(), GammaError::PoolNotMigrated);
+    ctx.accounts.partner_info.initialize(
+        ctx.accounts.pool_state.key(),
+        &ctx.accounts.partner,
+        &pool_state,
+    );
+    Ok(())
+}
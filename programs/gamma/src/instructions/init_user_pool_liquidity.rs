@@ -1,6 +1,9 @@
 use anchor_lang::prelude::*;
 
-use crate::states::{PartnerType, PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED};
+use crate::error::GammaError;
+use crate::states::{
+    PartnerInfo, PoolState, UserPoolLiquidity, LEGACY_PARTNER_ID, USER_POOL_LIQUIDITY_SEED,
+};
 
 #[derive(Accounts)]
 pub struct InitUserPoolLiquidity<'info> {
@@ -34,7 +37,7 @@ pub fn init_user_pool_liquidity(
 
     let partner = match partner {
         Some(partner_value) => match partner_value.as_str() {
-            "AssetDash" => Some(PartnerType::AssetDash),
+            "AssetDash" => Some(LEGACY_PARTNER_ID),
             _ => None,
         },
         None => None,
@@ -47,3 +50,60 @@ pub fn init_user_pool_liquidity(
     );
     Ok(())
 }
+
+#[derive(Accounts)]
+pub struct InitUserPoolLiquidityV2<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        init,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            user.key().as_ref(),
+        ],
+        bump,
+        payer = user,
+        space = UserPoolLiquidity::LEN,
+    )]
+    pub user_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    /// Ledger of the partner in the pool, required when the position is linked with a partner
+    #[account(
+        constraint = partner_info.pool_state == pool_state.key() @ GammaError::PartnerNotFound,
+    )]
+    pub partner_info: Option<Box<Account<'info, PartnerInfo>>>,
+
+    /// To create a new program account
+    pub system_program: Program<'info, System>,
+}
+
+pub fn init_user_pool_liquidity_v2(
+    ctx: Context<InitUserPoolLiquidityV2>,
+    partner: Option<u64>,
+) -> Result<()> {
+    if let Some(partner_id) = partner {
+        let partner_info = ctx
+            .accounts
+            .partner_info
+            .as_ref()
+            .ok_or(GammaError::PartnerNotFound)?;
+        require_eq!(
+            partner_info.partner_id,
+            partner_id,
+            GammaError::PartnerNotFound
+        );
+    }
+
+    let user_pool_liquidity = &mut ctx.accounts.user_pool_liquidity;
+
+    user_pool_liquidity.initialize(
+        ctx.accounts.user.key(),
+        ctx.accounts.pool_state.key(),
+        partner,
+    );
+    Ok(())
+}
@@ -1,11 +1,12 @@
 use std::ops::Deref;
 
 use crate::{
-    curve::CurveCalculator,
+    curve::{CurveCalculator, CurveType, MAX_AMP, MAX_WEIGHT, MIN_AMP, MIN_WEIGHT},
     error::GammaError,
     states::{
-        AmmConfig, ObservationState, PoolState, UserPoolLiquidity, OBSERVATION_SEED, POOL_SEED,
-        POOL_VAULT_SEED, USER_POOL_LIQUIDITY_SEED,
+        AmmConfig, ObservationState, PoolState, UserPoolLiquidity, OBSERVATION_NUM,
+        OBSERVATION_SEED, OBSERVATION_UPDATE_DURATION_DEFAULT, POOL_SEED, POOL_VAULT_SEED,
+        USER_POOL_LIQUIDITY_SEED,
     },
     utils::{create_token_account, is_supported_mint, transfer_from_user_to_pool_vault, U128},
     LOCK_LP_AMOUNT,
@@ -164,6 +165,12 @@ pub fn initialize(
     mut open_time: u64,
     max_trade_fee_rate: u64,
     volatility_factor: u64,
+    curve_type: u8,
+    amp: u64,
+    token_0_weight: u32,
+    launch_fee_rate: u64,
+    launch_fee_duration: u64,
+    launch_fee_decay: u8,
 ) -> Result<()> {
     if !(is_supported_mint(&ctx.accounts.token_0_mint)?
         && is_supported_mint(&ctx.accounts.token_1_mint)?)
@@ -171,6 +178,32 @@ pub fn initialize(
         return err!(GammaError::NotSupportMint);
     }
 
+    let curve_type = CurveType::new(curve_type)?;
+    let amp = match curve_type {
+        CurveType::ConstantProduct | CurveType::Weighted => 0,
+        CurveType::StableSwap => {
+            if !(MIN_AMP..=MAX_AMP).contains(&amp) {
+                return err!(GammaError::InvalidAmp);
+            }
+            // The invariant compares raw amounts, so pegged tokens must use the same decimals
+            require_eq!(
+                ctx.accounts.token_0_mint.decimals,
+                ctx.accounts.token_1_mint.decimals,
+                GammaError::InvalidCurveType
+            );
+            amp as u32
+        }
+    };
+    let token_0_weight = match curve_type {
+        CurveType::Weighted => {
+            if !(MIN_WEIGHT..=MAX_WEIGHT).contains(&token_0_weight) {
+                return err!(GammaError::InvalidWeight);
+            }
+            token_0_weight
+        }
+        _ => 0,
+    };
+
     if ctx.accounts.amm_config.disable_create_pool {
         return err!(GammaError::NotApproved);
     }
@@ -214,6 +247,9 @@ pub fn initialize(
 
     let mut observation_state = ctx.accounts.observation_state.load_init()?;
     observation_state.pool_id = ctx.accounts.pool_state.key();
+    observation_state.observation_update_duration = OBSERVATION_UPDATE_DURATION_DEFAULT;
+    observation_state.observation_cardinality = OBSERVATION_NUM as u16;
+    observation_state.observation_cardinality_next = OBSERVATION_NUM as u16;
 
     let pool_state = &mut ctx.accounts.pool_state.load_init()?;
 
@@ -313,6 +349,12 @@ pub fn initialize(
         &ctx.accounts.token_0_mint,
         &ctx.accounts.token_1_mint,
         ctx.accounts.observation_state.key(),
+        curve_type,
+        amp,
+        token_0_weight,
+        launch_fee_rate,
+        launch_fee_duration,
+        launch_fee_decay,
     )?;
 
     let user_pool_liquidity = &mut ctx.accounts.user_pool_liquidity;
@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::error::GammaError;
+use crate::states::{
+    LiquidityLock, PoolState, UserPoolLiquidity, LIQUIDITY_LOCK_SEED, USER_POOL_LIQUIDITY_SEED,
+};
+
+#[derive(Accounts)]
+pub struct LockLiquidity<'info> {
+    /// Owner of the liquidity being locked
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            owner.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub user_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    /// Lock of the position, created on the first lock
+    #[account(
+        init_if_needed,
+        seeds = [
+            LIQUIDITY_LOCK_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            owner.key().as_ref(),
+        ],
+        bump,
+        payer = owner,
+        space = LiquidityLock::LEN,
+    )]
+    pub liquidity_lock: Box<Account<'info, LiquidityLock>>,
+
+    /// To create a new program account
+    pub system_program: Program<'info, System>,
+}
+
+pub fn lock_liquidity(
+    ctx: Context<LockLiquidity>,
+    lp_token_amount: u64,
+    unlock_timestamp: u64,
+) -> Result<()> {
+    require_gt!(lp_token_amount, 0, GammaError::InvalidLpTokenAmount);
+
+    let user_pool_liquidity = &mut ctx.accounts.user_pool_liquidity;
+    require_gte!(
+        user_pool_liquidity.lp_tokens_owned,
+        u128::from(lp_token_amount),
+        GammaError::InvalidLpTokenAmount
+    );
+
+    let liquidity_lock = &mut ctx.accounts.liquidity_lock;
+    if liquidity_lock.owner == Pubkey::default() {
+        liquidity_lock.owner = ctx.accounts.owner.key();
+        liquidity_lock.pool_state = ctx.accounts.pool_state.key();
+        liquidity_lock.bump = ctx.bumps.liquidity_lock;
+    }
+    liquidity_lock.lock(lp_token_amount, unlock_timestamp)?;
+
+    // The locked lp tokens keep earning rewards, so the rewards of the position do not change
+    user_pool_liquidity.lp_tokens_owned = user_pool_liquidity
+        .lp_tokens_owned
+        .checked_sub(u128::from(lp_token_amount))
+        .ok_or(GammaError::MathOverflow)?;
+    user_pool_liquidity.lp_tokens_locked = user_pool_liquidity
+        .lp_tokens_locked
+        .checked_add(lp_token_amount)
+        .ok_or(GammaError::MathOverflow)?;
+
+    Ok(())
+}
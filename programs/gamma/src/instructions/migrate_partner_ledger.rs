@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{
+    Partner, PartnerInfo, PoolState, LEGACY_PARTNER_ID, PARTNER_INFO_SEED, PARTNER_SEED,
+};
+
+#[derive(Accounts)]
+pub struct MigratePartnerLedger<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Pool created before the partner ledgers
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// Registry account of the legacy partner, it sets the fee share of the migrated slot
+    #[account(
+        seeds = [
+            PARTNER_SEED.as_bytes(),
+            &LEGACY_PARTNER_ID.to_le_bytes(),
+        ],
+        bump = partner.bump,
+    )]
+    pub partner: Account<'info, Partner>,
+
+    /// Ledger of the legacy partner in the pool, seeded from the partner slot of the pool
+    #[account(
+        init,
+        seeds = [
+            PARTNER_INFO_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &LEGACY_PARTNER_ID.to_le_bytes(),
+        ],
+        bump,
+        payer = payer,
+        space = PartnerInfo::LEN,
+    )]
+    pub partner_info: Box<Account<'info, PartnerInfo>>,
+
+    /// To create a new program account
+    pub system_program: Program<'info, System>,
+}
+
+pub fn migrate_partner_ledger(ctx: Context<MigratePartnerLedger>) -> Result<()> {
+    let pool_id = ctx.accounts.pool_state.key();
+    let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+    let partner_info = &mut ctx.accounts.partner_info;
+    partner_info.initialize(pool_id, &ctx.accounts.partner, pool_state);
+    pool_state.migrate_partner_slot(partner_info)
+}
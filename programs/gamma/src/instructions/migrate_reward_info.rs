@@ -0,0 +1,72 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::GammaError,
+    states::{LegacyRewardInfo, PoolState, RewardInfo, MAX_REWARDS_PER_POOL},
+    utils::realloc_account,
+};
+
+#[derive(Accounts)]
+pub struct MigrateRewardInfo<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// CHECK: Reward of the pool in the legacy layout, checked in the handler
+    #[account(
+        mut,
+        owner = crate::id(),
+    )]
+    pub reward_info: UncheckedAccount<'info>,
+
+    /// To pay for the extended account
+    pub system_program: Program<'info, System>,
+}
+
+pub fn migrate_reward_info(ctx: Context<MigrateRewardInfo>) -> Result<()> {
+    let reward_info = ctx.accounts.reward_info.to_account_info();
+    require_eq!(
+        reward_info.data_len(),
+        RewardInfo::LEGACY_LEN,
+        GammaError::RewardAlreadyMigrated
+    );
+    let legacy = {
+        let data = reward_info.try_borrow_data()?;
+        require!(
+            data[..8] == RewardInfo::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        LegacyRewardInfo::deserialize(&mut &data[8..])?
+    };
+    require_keys_eq!(
+        legacy.pool,
+        ctx.accounts.pool_state.key(),
+        GammaError::InvalidInput
+    );
+
+    let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+    let mut migrated = RewardInfo::from_legacy(legacy, pool_state.lp_supply_in_positions()?)?;
+    // The lp changes of the pool did not settle legacy rewards, they must from now on until the
+    // reward ends. An ended reward is closed right away so that it takes no slot of the pool
+    if migrated.end_rewards_at > migrated.migrated_at {
+        require_gt!(
+            MAX_REWARDS_PER_POOL,
+            pool_state.reward_count,
+            GammaError::MaxRewardsReached
+        );
+        pool_state.reward_count += 1;
+    } else {
+        migrated.mark_closed(pool_state)?;
+    }
+
+    realloc_account(
+        &reward_info,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + std::mem::size_of::<RewardInfo>(),
+    )?;
+    let mut account_data = reward_info.try_borrow_mut_data()?;
+    migrated.try_serialize(&mut &mut account_data[..])
+}
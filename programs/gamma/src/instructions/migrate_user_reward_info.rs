@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::{states::RewardInfo, utils::extend_legacy_user_reward_info, USER_REWARD_INFO_SEED};
+
+#[derive(Accounts)]
+pub struct MigrateUserRewardInfo<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Owner of the user reward info
+    pub user: UncheckedAccount<'info>,
+
+    /// The reward, migrated first with `migrate_reward_info`
+    pub reward_info: Account<'info, RewardInfo>,
+
+    /// CHECK: User reward in the legacy layout, checked in the handler
+    #[account(
+        mut,
+        owner = crate::id(),
+        seeds = [
+            USER_REWARD_INFO_SEED.as_bytes(),
+            reward_info.key().as_ref(),
+            user.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub user_reward_info: UncheckedAccount<'info>,
+
+    /// To pay for the extended account
+    pub system_program: Program<'info, System>,
+}
+
+pub fn migrate_user_reward_info(ctx: Context<MigrateUserRewardInfo>) -> Result<()> {
+    extend_legacy_user_reward_info(
+        &ctx.accounts.user_reward_info.to_account_info(),
+        &ctx.accounts.reward_info,
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )
+}
@@ -1,27 +1,79 @@
 pub mod admin;
 pub mod calculate_rewards;
+pub mod claim_all_rewards;
+pub mod claim_partner_fees;
 pub mod claim_rewards;
+pub mod close_rewards;
 pub mod create_rewards;
+pub mod create_vesting_lock;
 pub mod deposit;
+pub mod deposit_single_sided;
+pub mod extend_rewards;
+pub mod flash_borrow;
+pub mod flash_repay;
+pub mod flash_swap_base_output;
+pub mod fund_rewards;
+pub mod get_twap;
+pub mod increase_observation_cardinality;
+pub mod init_partner_info;
 pub mod init_user_pool_liquidity;
 pub mod initialize;
+pub mod lock_liquidity;
+pub mod migrate_partner_ledger;
+pub mod migrate_reward_info;
+pub mod migrate_user_reward_info;
 // pub mod migrate_orca_to_gamma;
 // pub mod migrate_raydium_to_gamma;
 pub mod rebalance;
+pub mod release_vested_lp;
 pub mod swap_base_input;
+pub mod swap_base_input_v2;
 pub mod swap_base_output;
+pub mod swap_base_output_v2;
+pub mod swap_route;
+pub mod transfer_lp;
+pub mod unlock_liquidity;
+pub mod unwrap_lp;
 pub mod withdraw;
+pub mod withdraw_single_sided;
+pub mod wrap_lp;
 
 pub use admin::*;
 pub use deposit::*;
+pub use deposit_single_sided::*;
+pub use get_twap::*;
+pub use increase_observation_cardinality::*;
+pub use init_partner_info::*;
 pub use init_user_pool_liquidity::*;
 pub use initialize::*;
+pub use lock_liquidity::*;
+pub use migrate_partner_ledger::*;
+pub use migrate_reward_info::*;
+pub use migrate_user_reward_info::*;
 // pub use migrate_orca_to_gamma::*;
 // pub use migrate_raydium_to_gamma::*;
 pub use calculate_rewards::*;
+pub use claim_all_rewards::*;
+pub use claim_partner_fees::*;
 pub use claim_rewards::*;
+pub use close_rewards::*;
 pub use create_rewards::*;
+pub use create_vesting_lock::*;
+pub use extend_rewards::*;
+pub use flash_borrow::*;
+pub use flash_repay::*;
+pub use flash_swap_base_output::*;
+pub use fund_rewards::*;
 pub use rebalance::*;
+pub use release_vested_lp::*;
 pub use swap_base_input::*;
+pub use swap_base_input_v2::*;
 pub use swap_base_output::*;
+pub use swap_base_output_v2::*;
+pub use swap_route::*;
+pub use transfer_lp::*;
+pub use unlock_liquidity::*;
+pub use unwrap_lp::*;
 pub use withdraw::*;
+pub use withdraw_single_sided::*;
+pub use wrap_lp::*;
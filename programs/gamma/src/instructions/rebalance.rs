@@ -244,6 +244,8 @@ fn get_deposit_withdraw_amounts<'c, 'info>(
     gamma_pool_destination_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
 ) -> Result<DepositWithdrawAmountResult> {
     let pool_state = pool_state.load()?;
+    // The vault amounts are rebuilt from the token accounts, which are short of a pending loan
+    require!(!pool_state.flash_loan_open(), GammaError::FlashLoanOpen);
     let is_token_0 = token_vault.key() == pool_state.token_0_vault;
 
     let collateral_amount = gamma_pool_destination_collateral.amount;
@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::error::GammaError;
+use crate::states::{
+    PoolState, UserPoolLiquidity, VestingLock, USER_POOL_LIQUIDITY_SEED, VESTING_LOCK_SEED,
+};
+
+#[derive(Accounts)]
+pub struct ReleaseVestedLp<'info> {
+    /// Beneficiary of the vesting lock
+    pub beneficiary: Signer<'info>,
+
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            beneficiary.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub user_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    #[account(
+        mut,
+        seeds = [
+            VESTING_LOCK_SEED.as_bytes(),
+            user_pool_liquidity.key().as_ref(),
+            vesting_lock.funder.as_ref(),
+        ],
+        bump = vesting_lock.bump,
+    )]
+    pub vesting_lock: Box<Account<'info, VestingLock>>,
+}
+
+pub fn release_vested_lp(ctx: Context<ReleaseVestedLp>) -> Result<()> {
+    let lp_tokens_released = ctx.accounts.vesting_lock.release()?;
+
+    // The released lp tokens keep earning rewards, so the rewards of the position do not change
+    let user_pool_liquidity = &mut ctx.accounts.user_pool_liquidity;
+    user_pool_liquidity.lp_tokens_vesting = user_pool_liquidity
+        .lp_tokens_vesting
+        .checked_sub(lp_tokens_released)
+        .ok_or(GammaError::MathOverflow)?;
+    user_pool_liquidity.lp_tokens_owned = user_pool_liquidity
+        .lp_tokens_owned
+        .checked_add(u128::from(lp_tokens_released))
+        .ok_or(GammaError::MathOverflow)?;
+
+    Ok(())
+}
@@ -4,6 +4,7 @@ use crate::error::GammaError;
 use crate::external::dflow_segmenter::is_invoked_by_segmenter;
 use crate::states::oracle;
 use crate::states::AmmConfig;
+use crate::states::ObservationBufferMut;
 use crate::states::ObservationState;
 use crate::states::PoolState;
 use crate::states::PoolStatusBitIndex;
@@ -134,16 +135,17 @@ pub fn swap_base_input<'c, 'info>(
     {
         return err!(GammaError::NotApproved);
     }
+    require!(!pool_state.flash_loan_open(), GammaError::FlashLoanOpen);
 
     let (token_0_price_x64_before_swap, token_1_price_x64_before_swap) =
         if ctx.accounts.input_vault.key() == pool_state.token_0_vault
             && ctx.accounts.output_vault.key() == pool_state.token_1_vault
         {
-            pool_state.token_price_x32()?
+            pool_state.token_price_x32(block_timestamp)?
         } else if ctx.accounts.input_vault.key() == pool_state.token_1_vault
             && ctx.accounts.output_vault.key() == pool_state.token_0_vault
         {
-            pool_state.token_price_x32()?
+            pool_state.token_price_x32(block_timestamp)?
         } else {
             return err!(GammaError::InvalidVault);
         };
@@ -181,11 +183,17 @@ pub fn swap_base_input<'c, 'info>(
         } else {
             return err!(GammaError::InvalidVault);
         };
-    let constant_before = u128::from(total_input_token_amount)
-        .checked_mul(u128::from(total_output_token_amount))
-        .ok_or(GammaError::MathOverflow)?;
+    let constant_before = CurveCalculator::invariant(
+        u128::from(total_input_token_amount),
+        u128::from(total_output_token_amount),
+        trade_direction,
+        &pool_state,
+        block_timestamp,
+    )?;
 
-    let mut observation_state = ctx.accounts.observation_state.load_mut()?;
+    let mut observation_data =
+        ObservationState::try_borrow_mut_data(&ctx.accounts.observation_state)?;
+    let mut observation_state = ObservationBufferMut::from_account_data(&mut observation_data)?;
 
     let mut is_invoked_by_signed_segmenter = false;
 
@@ -205,24 +213,27 @@ pub fn swap_base_input<'c, 'info>(
         u128::from(actual_amount_in),
         u128::from(total_input_token_amount),
         u128::from(total_output_token_amount),
+        trade_direction,
         &ctx.accounts.amm_config,
         &pool_state,
         block_timestamp,
-        &observation_state,
+        &observation_state.as_buffer(),
         is_invoked_by_signed_segmenter,
     ) {
         Ok(value) => value,
         Err(_) => return err!(GammaError::ZeroTradingTokens),
     };
 
-    let constant_after = u128::from(
+    let constant_after = CurveCalculator::invariant(
         result
             .new_swap_source_amount
             .checked_sub(result.dynamic_fee)
             .ok_or(GammaError::MathOverflow)?,
-    )
-    .checked_mul(u128::from(result.new_swap_destination_amount))
-    .ok_or(GammaError::MathOverflow)?;
+        result.new_swap_destination_amount,
+        trade_direction,
+        &pool_state,
+        block_timestamp,
+    )?;
     // #[cfg(feature = "enable-log")]
     msg!(
         "actual_amount_in:{} source_amount_swapped:{}, destination_amount_swapped:{}, dynamic_fee: {}, constant_before:{},constant_after:{}",
@@ -297,106 +308,15 @@ pub fn swap_base_input<'c, 'info>(
             transfer_referral_amount = Some(referral_amount)
         }
     }
-    // Save fees metric for the pool partners.
-    let mut partners = pool_state.partners;
-    for partner in partners.iter_mut() {
-        // we multiply by 100000 to keep decimals.
-        let decimal_number = 100000;
-        let tvl_share = partner
-            .lp_token_linked_with_partner
-            .checked_mul(decimal_number)
-            .ok_or(GammaError::MathOverflow)?
-            .checked_div(pool_state.lp_supply)
-            .ok_or(GammaError::MathOverflow)?;
-
-        let partner_fee = protocol_fee
-            .checked_mul(tvl_share)
-            .ok_or(GammaError::MathOverflow)?
-            .checked_div(decimal_number)
-            .ok_or(GammaError::MathOverflow)?;
-
-        match trade_direction {
-            TradeDirection::ZeroForOne => {
-                partner.cumulative_fee_total_times_tvl_share_token_0 = partner
-                    .cumulative_fee_total_times_tvl_share_token_0
-                    .checked_add(partner_fee)
-                    .ok_or(GammaError::MathOverflow)?;
-            }
-            TradeDirection::OneForZero => {
-                partner.cumulative_fee_total_times_tvl_share_token_1 = partner
-                    .cumulative_fee_total_times_tvl_share_token_1
-                    .checked_add(partner_fee)
-                    .ok_or(GammaError::MathOverflow)?;
-            }
-        }
-    }
-    pool_state.partners = partners;
-
-    match trade_direction {
-        TradeDirection::ZeroForOne => {
-            pool_state.protocol_fees_token_0 = pool_state
-                .protocol_fees_token_0
-                .checked_add(protocol_fee)
-                .ok_or(GammaError::MathOverflow)?;
-            pool_state.fund_fees_token_0 = pool_state
-                .fund_fees_token_0
-                .checked_add(fund_fee)
-                .ok_or(GammaError::MathOverflow)?;
-            pool_state.cumulative_trade_fees_token_0 = pool_state
-                .cumulative_trade_fees_token_0
-                .checked_add((dynamic_fee) as u128)
-                .ok_or(GammaError::MathOverflow)?;
-            pool_state.cumulative_volume_token_0 = pool_state
-                .cumulative_volume_token_0
-                .checked_add(actual_amount_in as u128)
-                .ok_or(GammaError::MathOverflow)?;
-
-            pool_state.token_0_vault_amount = pool_state
-                .token_0_vault_amount
-                .checked_add(actual_amount_in)
-                .ok_or(GammaError::MathOverflow)?
-                .checked_sub(fund_fee)
-                .ok_or(GammaError::MathOverflow)?
-                .checked_sub(protocol_fee)
-                .ok_or(GammaError::MathOverflow)?;
-            pool_state.token_1_vault_amount = pool_state
-                .token_1_vault_amount
-                .checked_sub(output_transfer_amount)
-                .ok_or(GammaError::MathOverflow)?;
-        }
-        TradeDirection::OneForZero => {
-            pool_state.protocol_fees_token_1 = pool_state
-                .protocol_fees_token_1
-                .checked_add(protocol_fee)
-                .ok_or(GammaError::MathOverflow)?;
-            pool_state.fund_fees_token_1 = pool_state
-                .fund_fees_token_1
-                .checked_add(fund_fee)
-                .ok_or(GammaError::MathOverflow)?;
-            pool_state.cumulative_trade_fees_token_1 = pool_state
-                .cumulative_trade_fees_token_1
-                .checked_add((dynamic_fee) as u128)
-                .ok_or(GammaError::MathOverflow)?;
-            pool_state.cumulative_volume_token_1 = pool_state
-                .cumulative_volume_token_1
-                .checked_add(actual_amount_in as u128)
-                .ok_or(GammaError::MathOverflow)?;
-
-            pool_state.token_1_vault_amount = pool_state
-                .token_1_vault_amount
-                .checked_add(actual_amount_in)
-                .ok_or(GammaError::MathOverflow)?
-                .checked_sub(fund_fee)
-                .ok_or(GammaError::MathOverflow)?
-                .checked_sub(protocol_fee)
-                .ok_or(GammaError::MathOverflow)?;
-            pool_state.token_0_vault_amount = pool_state
-                .token_0_vault_amount
-                .checked_sub(output_transfer_amount)
-                .ok_or(GammaError::MathOverflow)?;
-        }
-    };
-    pool_state.latest_dynamic_fee_rate = result.dynamic_fee_rate;
+    pool_state.record_swap(
+        trade_direction,
+        actual_amount_in,
+        output_transfer_amount,
+        dynamic_fee,
+        protocol_fee,
+        fund_fee,
+        result.dynamic_fee_rate,
+    )?;
 
     emit!(SwapEvent {
         pool_id,
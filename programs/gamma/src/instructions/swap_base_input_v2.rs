@@ -0,0 +1,105 @@
+use super::swap_base_input::{swap_base_input, Swap};
+use crate::curve::{calculator::CurveCalculator, TradeDirection};
+use crate::error::GammaError;
+use crate::states::PoolState;
+use crate::utils::token::*;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program;
+
+/// The trade direction and the input and output vault amounts of a swap
+pub(crate) fn swap_vault_amounts(
+    accounts: &Swap,
+    pool_state: &PoolState,
+) -> Result<(TradeDirection, u64, u64)> {
+    let (total_token_0_amount, total_token_1_amount) = pool_state.vault_amount_without_fee()?;
+    if accounts.input_vault.key() == pool_state.token_0_vault
+        && accounts.output_vault.key() == pool_state.token_1_vault
+    {
+        Ok((
+            TradeDirection::ZeroForOne,
+            total_token_0_amount,
+            total_token_1_amount,
+        ))
+    } else if accounts.input_vault.key() == pool_state.token_1_vault
+        && accounts.output_vault.key() == pool_state.token_0_vault
+    {
+        Ok((
+            TradeDirection::OneForZero,
+            total_token_1_amount,
+            total_token_0_amount,
+        ))
+    } else {
+        err!(GammaError::InvalidVault)
+    }
+}
+
+/// Checks the deadline and returns the most input token, before transfer fees, the swap can
+/// take until the price of the output token in input token reaches `max_price_x32`
+pub(crate) fn price_limited_amount_in(
+    accounts: &Swap,
+    max_amount_in: u64,
+    max_price_x32: u128,
+    deadline: u64,
+) -> Result<u64> {
+    let block_timestamp = solana_program::clock::Clock::get()?.unix_timestamp as u64;
+    require_gte!(deadline, block_timestamp, GammaError::DeadlineExceeded);
+
+    let pool_state = accounts.pool_state.load()?;
+    let (trade_direction, total_input_token_amount, total_output_token_amount) =
+        swap_vault_amounts(accounts, &pool_state)?;
+
+    let transfer_fee =
+        get_transfer_fee(&accounts.input_token_mint.to_account_info(), max_amount_in)?;
+    let max_actual_amount_in = max_amount_in.saturating_sub(transfer_fee);
+    let actual_amount_in = CurveCalculator::max_source_amount_within_price(
+        u128::from(max_actual_amount_in),
+        u128::from(total_input_token_amount),
+        u128::from(total_output_token_amount),
+        trade_direction,
+        max_price_x32,
+        &pool_state,
+        block_timestamp,
+    )?;
+    let actual_amount_in = u64::try_from(actual_amount_in).or(err!(GammaError::MathOverflow))?;
+    require_gt!(actual_amount_in, 0, GammaError::PriceLimitReached);
+    if actual_amount_in == max_actual_amount_in {
+        return Ok(max_amount_in);
+    }
+
+    let transfer_fee = get_transfer_inverse_fee(
+        &accounts.input_token_mint.to_account_info(),
+        actual_amount_in,
+    )?;
+    Ok(actual_amount_in
+        .checked_add(transfer_fee)
+        .ok_or(GammaError::MathOverflow)?
+        .min(max_amount_in))
+}
+
+/// Scales a slippage bound given for the whole swap down to the filled part of it
+pub(crate) fn pro_rata_amount(amount: u64, filled: u64, total: u64) -> Result<u64> {
+    let scaled = u128::from(amount)
+        .checked_mul(u128::from(filled))
+        .ok_or(GammaError::MathOverflow)?
+        .checked_div(u128::from(total))
+        .ok_or(GammaError::MathOverflow)?;
+    u64::try_from(scaled).or(err!(GammaError::MathOverflow))
+}
+
+/// Swaps base input like `swap_base_input`, until the price of the output token in input token
+/// reaches `max_price_x32`.
+///
+/// When the limit is reached first only part of `amount_in` is swapped, and `minimum_amount_out`
+/// is scaled down to the filled part. The `SwapEvent` reports the amounts actually filled.
+pub fn swap_base_input_v2<'c, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, Swap<'info>>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+    max_price_x32: u128,
+    deadline: u64,
+) -> Result<()> {
+    let filled_amount_in =
+        price_limited_amount_in(&ctx.accounts, amount_in, max_price_x32, deadline)?;
+    let minimum_amount_out = pro_rata_amount(minimum_amount_out, filled_amount_in, amount_in)?;
+    swap_base_input(ctx, filled_amount_in, minimum_amount_out)
+}
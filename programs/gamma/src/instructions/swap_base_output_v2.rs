@@ -0,0 +1,77 @@
+use super::swap_base_input::{swap_base_input, Swap};
+use super::swap_base_input_v2::{price_limited_amount_in, pro_rata_amount, swap_vault_amounts};
+use super::swap_base_output::swap_base_output;
+use crate::curve::calculator::CurveCalculator;
+use crate::error::GammaError;
+use crate::states::ObservationBuffer;
+use crate::utils::token::*;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program;
+
+/// The input token, with transfer fees, `swap_base_output` takes to send `amount_out_less_fee`.
+/// Outputs the pool can not pay need more than any input.
+fn required_amount_in(accounts: &Swap, amount_out_less_fee: u64) -> Result<u64> {
+    let block_timestamp = solana_program::clock::Clock::get()?.unix_timestamp as u64;
+    let pool_state = accounts.pool_state.load()?;
+    let (trade_direction, total_input_token_amount, total_output_token_amount) =
+        swap_vault_amounts(accounts, &pool_state)?;
+
+    let out_transfer_fee = get_transfer_inverse_fee(
+        &accounts.output_token_mint.to_account_info(),
+        amount_out_less_fee,
+    )?;
+    let actual_amount_out = amount_out_less_fee
+        .checked_add(out_transfer_fee)
+        .ok_or(GammaError::MathOverflow)?;
+
+    let observation_info: &AccountInfo = accounts.observation_state.as_ref();
+    let observation_data = observation_info.try_borrow_data()?;
+    let observation_state = ObservationBuffer::from_account_data(&observation_data)?;
+    let result = match CurveCalculator::swap_base_output(
+        u128::from(actual_amount_out),
+        u128::from(total_input_token_amount),
+        u128::from(total_output_token_amount),
+        trade_direction,
+        &accounts.amm_config,
+        &pool_state,
+        block_timestamp,
+        &observation_state,
+        false,
+    ) {
+        Ok(value) => value,
+        Err(_) => return Ok(u64::MAX),
+    };
+    let source_amount_swapped = match u64::try_from(result.source_amount_swapped) {
+        Ok(value) => value,
+        Err(_) => return Ok(u64::MAX),
+    };
+    let transfer_fee = get_transfer_inverse_fee(
+        &accounts.input_token_mint.to_account_info(),
+        source_amount_swapped,
+    )?;
+    Ok(source_amount_swapped.saturating_add(transfer_fee))
+}
+
+/// Swaps base output like `swap_base_output`, until the price of the output token in input token
+/// reaches `max_price_x32`.
+///
+/// When the limit is reached before `amount_out` is filled, the input up to the limit is swapped
+/// as an exact input instead, receiving at least the share of `amount_out` it pays for at the
+/// `max_amount_in` rate. The `SwapEvent` reports the amounts actually filled.
+pub fn swap_base_output_v2<'c, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, Swap<'info>>,
+    max_amount_in: u64,
+    amount_out_less_fee: u64,
+    max_price_x32: u128,
+    deadline: u64,
+) -> Result<()> {
+    let limited_amount_in =
+        price_limited_amount_in(&ctx.accounts, max_amount_in, max_price_x32, deadline)?;
+    if required_amount_in(&ctx.accounts, amount_out_less_fee)? <= limited_amount_in {
+        return swap_base_output(ctx, limited_amount_in, amount_out_less_fee);
+    }
+
+    let minimum_amount_out =
+        pro_rata_amount(amount_out_less_fee, limited_amount_in, max_amount_in)?;
+    swap_base_input(ctx, limited_amount_in, minimum_amount_out)
+}
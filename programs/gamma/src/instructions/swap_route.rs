@@ -0,0 +1,330 @@
+use crate::curve::calculator::CurveCalculator;
+use crate::curve::TradeDirection;
+use crate::error::GammaError;
+use crate::states::oracle;
+use crate::states::AmmConfig;
+use crate::states::ObservationBufferMut;
+use crate::states::ObservationState;
+use crate::states::PoolState;
+use crate::states::PoolStatusBitIndex;
+use crate::states::SwapEvent;
+use crate::utils::token::*;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{Mint, Token2022, TokenAccount};
+
+/// Number of remaining accounts for every hop of the route
+pub const SWAP_ROUTE_HOP_ACCOUNTS_LEN: usize = 6;
+
+#[derive(Accounts)]
+pub struct SwapRoute<'info> {
+    /// The user performing the swap
+    pub payer: Signer<'info>,
+
+    /// CHECK: pool vault authority
+    #[account(
+        seeds = [
+            crate::AUTH_SEED.as_bytes(),
+        ],
+        bump,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    /// The user token account for the input token of the first hop
+    #[account(mut)]
+    pub input_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The user token account for the output token of the last hop
+    #[account(mut)]
+    pub output_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The mint of the input token of the first hop
+    #[account(
+        address = input_token_account.mint
+    )]
+    pub input_token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// token Program
+    pub token_program: Program<'info, Token>,
+
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+}
+
+/// Accounts of one hop, read from the remaining accounts in this order
+struct SwapRouteHop<'info> {
+    amm_config: Box<Account<'info, AmmConfig>>,
+    pool_state: AccountLoader<'info, PoolState>,
+    observation_state: AccountLoader<'info, ObservationState>,
+    input_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    output_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    output_token_mint: Box<InterfaceAccount<'info, Mint>>,
+}
+
+impl<'info> SwapRouteHop<'info> {
+    fn new(accounts: &'info [AccountInfo<'info>]) -> Result<Self> {
+        let hop = Self {
+            amm_config: Box::new(Account::try_from(&accounts[0])?),
+            pool_state: AccountLoader::try_from(&accounts[1])?,
+            observation_state: AccountLoader::try_from(&accounts[2])?,
+            input_vault: Box::new(InterfaceAccount::try_from(&accounts[3])?),
+            output_vault: Box::new(InterfaceAccount::try_from(&accounts[4])?),
+            output_token_mint: Box::new(InterfaceAccount::try_from(&accounts[5])?),
+        };
+        {
+            let pool_state = hop.pool_state.load()?;
+            require_keys_eq!(hop.amm_config.key(), pool_state.amm_config);
+            require_keys_eq!(hop.observation_state.key(), pool_state.observation_key);
+        }
+        require_keys_eq!(hop.output_token_mint.key(), hop.output_vault.mint);
+        Ok(hop)
+    }
+
+    fn token_program(&self, swap_route: &SwapRoute<'info>) -> AccountInfo<'info> {
+        if self.output_token_mint.to_account_info().owner == swap_route.token_program.key {
+            swap_route.token_program.to_account_info()
+        } else {
+            swap_route.token_program_2022.to_account_info()
+        }
+    }
+}
+
+/// Swaps `amount_in` through an ordered list of pools, the output of every hop is the input of the next.
+///
+/// Remaining accounts are `[amm_config, pool_state, observation_state, input_vault, output_vault, output_token_mint]`
+/// for every hop. Intermediate amounts move directly from the output vault of a hop to the input vault of the next,
+/// so only the final amount is checked against `minimum_amount_out`.
+pub fn swap_route<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, SwapRoute<'info>>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Result<()> {
+    let remaining_accounts = ctx.remaining_accounts;
+    require!(
+        !remaining_accounts.is_empty()
+            && remaining_accounts.len() % SWAP_ROUTE_HOP_ACCOUNTS_LEN == 0,
+        GammaError::InvalidInput
+    );
+    let hops = remaining_accounts
+        .chunks(SWAP_ROUTE_HOP_ACCOUNTS_LEN)
+        .map(SwapRouteHop::new)
+        .collect::<Result<Vec<_>>>()?;
+    let block_timestamp = solana_program::clock::Clock::get()?.unix_timestamp as u64;
+
+    let first_hop = &hops[0];
+    require_keys_eq!(
+        first_hop.input_vault.mint,
+        ctx.accounts.input_token_mint.key(),
+        GammaError::InvalidVault
+    );
+    require_keys_eq!(
+        hops[hops.len() - 1].output_token_mint.key(),
+        ctx.accounts.output_token_account.mint,
+        GammaError::InvalidVault
+    );
+    transfer_from_user_to_pool_vault(
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.input_token_account.to_account_info(),
+        first_hop.input_vault.to_account_info(),
+        ctx.accounts.input_token_mint.to_account_info(),
+        if ctx.accounts.input_token_mint.to_account_info().owner == ctx.accounts.token_program.key {
+            ctx.accounts.token_program.to_account_info()
+        } else {
+            ctx.accounts.token_program_2022.to_account_info()
+        },
+        amount_in,
+        ctx.accounts.input_token_mint.decimals,
+    )?;
+
+    let mut input_token_mint = ctx.accounts.input_token_mint.to_account_info();
+    let mut hop_amount_in = amount_in;
+    for (index, hop) in hops.iter().enumerate() {
+        require_keys_eq!(
+            hop.input_vault.mint,
+            input_token_mint.key(),
+            GammaError::InvalidVault
+        );
+        let amount_out = swap_route_hop(hop, &input_token_mint, hop_amount_in, block_timestamp)?;
+
+        // Intermediate amounts go straight into the input vault of the next hop
+        let destination = match hops.get(index + 1) {
+            Some(next_hop) => next_hop.input_vault.to_account_info(),
+            None => ctx.accounts.output_token_account.to_account_info(),
+        };
+        let auth_bump = hop.pool_state.load()?.auth_bump;
+        transfer_from_pool_vault_to_user(
+            ctx.accounts.authority.to_account_info(),
+            hop.output_vault.to_account_info(),
+            destination,
+            hop.output_token_mint.to_account_info(),
+            hop.token_program(ctx.accounts),
+            amount_out,
+            hop.output_token_mint.decimals,
+            &[&[crate::AUTH_SEED.as_bytes(), &[auth_bump]]],
+        )?;
+
+        // The transfer fee of this transfer is taken by the next hop as its input transfer fee
+        input_token_mint = hop.output_token_mint.to_account_info();
+        hop_amount_in = amount_out;
+    }
+
+    let amount_received = hop_amount_in
+        .checked_sub(get_transfer_fee(&input_token_mint, hop_amount_in)?)
+        .ok_or(GammaError::MathOverflow)?;
+    require_gt!(amount_received, 0);
+    require_gte!(
+        amount_received,
+        minimum_amount_out,
+        GammaError::ExceededSlippage
+    );
+
+    Ok(())
+}
+
+/// Swaps `amount_in`, already transferred into the input vault, in the pool of `hop` and books it
+/// the same way `swap_base_input` does. Returns the amount to transfer out of the output vault.
+fn swap_route_hop(
+    hop: &SwapRouteHop,
+    input_token_mint: &AccountInfo,
+    amount_in: u64,
+    block_timestamp: u64,
+) -> Result<u64> {
+    let pool_id = hop.pool_state.key();
+    let pool_state = &mut hop.pool_state.load_mut()?;
+    if !pool_state.get_status_by_bit(PoolStatusBitIndex::Swap)
+        || block_timestamp < pool_state.open_time
+    {
+        return err!(GammaError::NotApproved);
+    }
+    require!(!pool_state.flash_loan_open(), GammaError::FlashLoanOpen);
+
+    let (token_0_price_x64_before_swap, token_1_price_x64_before_swap) =
+        pool_state.token_price_x32(block_timestamp)?;
+
+    let input_transfer_fee = get_transfer_fee(input_token_mint, amount_in)?;
+    // Take transfer fees into account for actual amount transferred in
+    let actual_amount_in = amount_in.saturating_sub(input_transfer_fee);
+    require_gt!(actual_amount_in, 0);
+
+    let (trade_direction, total_input_token_amount, total_output_token_amount) =
+        if hop.input_vault.key() == pool_state.token_0_vault
+            && hop.output_vault.key() == pool_state.token_1_vault
+        {
+            let (total_input_token_amount, total_output_token_amount) =
+                pool_state.vault_amount_without_fee()?;
+
+            (
+                TradeDirection::ZeroForOne,
+                total_input_token_amount,
+                total_output_token_amount,
+            )
+        } else if hop.input_vault.key() == pool_state.token_1_vault
+            && hop.output_vault.key() == pool_state.token_0_vault
+        {
+            let (total_output_token_amount, total_input_token_amount) =
+                pool_state.vault_amount_without_fee()?;
+
+            (
+                TradeDirection::OneForZero,
+                total_input_token_amount,
+                total_output_token_amount,
+            )
+        } else {
+            return err!(GammaError::InvalidVault);
+        };
+    let constant_before = CurveCalculator::invariant(
+        u128::from(total_input_token_amount),
+        u128::from(total_output_token_amount),
+        trade_direction,
+        pool_state,
+        block_timestamp,
+    )?;
+
+    let mut observation_data = ObservationState::try_borrow_mut_data(&hop.observation_state)?;
+    let mut observation_state = ObservationBufferMut::from_account_data(&mut observation_data)?;
+
+    let result = match CurveCalculator::swap_base_input(
+        u128::from(actual_amount_in),
+        u128::from(total_input_token_amount),
+        u128::from(total_output_token_amount),
+        trade_direction,
+        &hop.amm_config,
+        pool_state,
+        block_timestamp,
+        &observation_state.as_buffer(),
+        false,
+    ) {
+        Ok(value) => value,
+        Err(_) => return err!(GammaError::ZeroTradingTokens),
+    };
+
+    let constant_after = CurveCalculator::invariant(
+        result
+            .new_swap_source_amount
+            .checked_sub(result.dynamic_fee)
+            .ok_or(GammaError::MathOverflow)?,
+        result.new_swap_destination_amount,
+        trade_direction,
+        pool_state,
+        block_timestamp,
+    )?;
+    #[cfg(feature = "enable-log")]
+    msg!(
+        "actual_amount_in:{} source_amount_swapped:{}, destination_amount_swapped:{}, dynamic_fee: {}, constant_before:{},constant_after:{}",
+        actual_amount_in,
+        result.source_amount_swapped,
+        result.destination_amount_swapped,
+        result.dynamic_fee,
+        constant_before,
+        constant_after
+    );
+    let source_amount_swapped =
+        u64::try_from(result.source_amount_swapped).map_err(|_| GammaError::MathOverflow)?;
+    require_eq!(source_amount_swapped, actual_amount_in);
+    let amount_out =
+        u64::try_from(result.destination_amount_swapped).map_err(|_| GammaError::MathOverflow)?;
+    let output_transfer_fee =
+        get_transfer_fee(&hop.output_token_mint.to_account_info(), amount_out)?;
+    require_gt!(
+        amount_out
+            .checked_sub(output_transfer_fee)
+            .ok_or(GammaError::MathOverflow)?,
+        0
+    );
+
+    pool_state.record_swap(
+        trade_direction,
+        actual_amount_in,
+        amount_out,
+        u64::try_from(result.dynamic_fee).map_err(|_| GammaError::MathOverflow)?,
+        u64::try_from(result.protocol_fee).map_err(|_| GammaError::MathOverflow)?,
+        u64::try_from(result.fund_fee).map_err(|_| GammaError::MathOverflow)?,
+        result.dynamic_fee_rate,
+    )?;
+
+    emit!(SwapEvent {
+        pool_id,
+        input_vault_before: total_input_token_amount,
+        output_vault_before: total_output_token_amount,
+        input_amount: source_amount_swapped,
+        output_amount: amount_out,
+        input_mint: hop.input_vault.mint,
+        output_mint: hop.output_vault.mint,
+        input_transfer_fee,
+        output_transfer_fee,
+        base_input: true,
+        dynamic_fee: result.dynamic_fee
+    });
+    require_gte!(constant_after, constant_before);
+
+    observation_state.update(
+        oracle::block_timestamp()?,
+        token_0_price_x64_before_swap,
+        token_1_price_x64_before_swap,
+    )?;
+
+    pool_state.recent_epoch = Clock::get()?.epoch;
+
+    Ok(amount_out)
+}
@@ -0,0 +1,131 @@
+use crate::{
+    error::GammaError,
+    states::{PartnerInfo, PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
+    utils::settle_user_rewards,
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct TransferLp<'info> {
+    /// Owner of the liquidity being transferred, also pays for the destination accounts
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Pool state the liquidity belongs to
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            owner.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub user_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    /// Ledger of the partner the position is linked with, required when the position has a partner
+    #[account(mut)]
+    pub partner_info: Option<Box<Account<'info, PartnerInfo>>>,
+
+    /// CHECK: the wallet receiving the liquidity, only used as a seed
+    #[account(constraint = destination.key() != owner.key() @ GammaError::InvalidInput)]
+    pub destination: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            destination.key().as_ref(),
+        ],
+        bump,
+        payer = owner,
+        space = UserPoolLiquidity::LEN,
+    )]
+    pub destination_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    /// Ledger of the partner the destination position is linked with, required when it has a
+    /// partner other than the one of the owner's position
+    #[account(mut)]
+    pub destination_partner_info: Option<Box<Account<'info, PartnerInfo>>>,
+
+    /// To create the destination accounts
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves `lp_token_amount` from the owner's position to the destination's position.
+///
+/// Remaining accounts are read in groups of three for every open reward of the pool:
+/// `[reward_info, owner_user_reward_info, destination_user_reward_info]`.
+/// Both user reward infos are brought up to date with the balances before the transfer,
+/// and created if they do not exist yet, so neither side gains or loses accrued rewards.
+pub fn transfer_lp<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, TransferLp<'info>>,
+    lp_token_amount: u64,
+) -> Result<()> {
+    require_gt!(lp_token_amount, 0, GammaError::InvalidLpTokenAmount);
+    let lp_token_amount = u128::from(lp_token_amount);
+    require_gte!(
+        ctx.accounts.user_pool_liquidity.lp_tokens_owned,
+        lp_token_amount,
+        GammaError::InvalidLpTokenAmount
+    );
+
+    let pool_id = ctx.accounts.pool_state.key();
+
+    let destination_pool_liquidity = &mut ctx.accounts.destination_pool_liquidity;
+    if destination_pool_liquidity.user == Pubkey::default() {
+        destination_pool_liquidity.initialize(ctx.accounts.destination.key(), pool_id, None);
+    }
+
+    settle_user_rewards(
+        ctx.remaining_accounts,
+        pool_id,
+        &ctx.accounts.pool_state.load()?,
+        &mut [
+            (
+                ctx.accounts.owner.key(),
+                &mut **ctx.accounts.user_pool_liquidity,
+            ),
+            (
+                ctx.accounts.destination.key(),
+                &mut ***destination_pool_liquidity,
+            ),
+        ],
+        &ctx.accounts.owner.to_account_info(),
+        Some(&ctx.accounts.system_program.to_account_info()),
+    )?;
+
+    let user_pool_liquidity = &mut ctx.accounts.user_pool_liquidity;
+    user_pool_liquidity.lp_tokens_owned = user_pool_liquidity
+        .lp_tokens_owned
+        .checked_sub(lp_token_amount)
+        .ok_or(GammaError::MathOverflow)?;
+    destination_pool_liquidity.lp_tokens_owned = destination_pool_liquidity
+        .lp_tokens_owned
+        .checked_add(lp_token_amount)
+        .ok_or(GammaError::MathOverflow)?;
+
+    if user_pool_liquidity.partner != destination_pool_liquidity.partner {
+        let lp_token_amount =
+            u64::try_from(lp_token_amount).map_err(|_| GammaError::MathOverflow)?;
+        let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+        pool_state.sub_lp_token_linked_with_partner(
+            &mut ctx.accounts.partner_info,
+            pool_id,
+            user_pool_liquidity.partner,
+            lp_token_amount,
+        )?;
+        pool_state.add_lp_token_linked_with_partner(
+            &mut ctx.accounts.destination_partner_info,
+            pool_id,
+            destination_pool_liquidity.partner,
+            lp_token_amount,
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+
+use crate::error::GammaError;
+use crate::states::{
+    LiquidityLock, PoolState, UserPoolLiquidity, LIQUIDITY_LOCK_SEED, USER_POOL_LIQUIDITY_SEED,
+};
+
+#[derive(Accounts)]
+pub struct UnlockLiquidity<'info> {
+    /// Owner of the locked liquidity
+    pub owner: Signer<'info>,
+
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            owner.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub user_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_LOCK_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            owner.key().as_ref(),
+        ],
+        bump = liquidity_lock.bump,
+    )]
+    pub liquidity_lock: Box<Account<'info, LiquidityLock>>,
+}
+
+pub fn unlock_liquidity(ctx: Context<UnlockLiquidity>) -> Result<()> {
+    let lp_tokens_unlocked = ctx.accounts.liquidity_lock.unlock()?;
+
+    let user_pool_liquidity = &mut ctx.accounts.user_pool_liquidity;
+    user_pool_liquidity.lp_tokens_locked = user_pool_liquidity
+        .lp_tokens_locked
+        .checked_sub(lp_tokens_unlocked)
+        .ok_or(GammaError::MathOverflow)?;
+    user_pool_liquidity.lp_tokens_owned = user_pool_liquidity
+        .lp_tokens_owned
+        .checked_add(u128::from(lp_tokens_unlocked))
+        .ok_or(GammaError::MathOverflow)?;
+
+    Ok(())
+}
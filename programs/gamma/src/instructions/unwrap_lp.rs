@@ -0,0 +1,115 @@
+use crate::{
+    error::GammaError,
+    states::{
+        PartnerInfo, PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED, WRAPPED_LP_MINT_SEED,
+    },
+    utils::{settle_user_rewards, token_burn},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct UnwrapLp<'info> {
+    /// Holder of the wrapped lp tokens, receives the liquidity in their position
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Pool state the liquidity belongs to
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The holder's position, created if they never held liquidity in the pool
+    #[account(
+        init_if_needed,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            owner.key().as_ref(),
+        ],
+        bump,
+        payer = owner,
+        space = UserPoolLiquidity::LEN,
+    )]
+    pub user_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    /// Ledger of the partner the position is linked with, required when the position has a partner
+    #[account(mut)]
+    pub partner_info: Option<Box<Account<'info, PartnerInfo>>>,
+
+    /// Receipt mint of the pool
+    #[account(
+        mut,
+        seeds = [
+            WRAPPED_LP_MINT_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+        ],
+        bump,
+        mint::token_program = token_program,
+    )]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The holder's token account to burn the wrapped lp tokens from
+    #[account(
+        mut,
+        token::mint = lp_mint,
+        token::authority = owner,
+    )]
+    pub owner_lp_token: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Program to burn lp tokens
+    pub token_program: Interface<'info, TokenInterface>,
+    /// To create the position account
+    pub system_program: Program<'info, System>,
+}
+
+/// Burns `lp_token_amount` receipt tokens and credits them back to the holder's position.
+///
+/// Remaining accounts are `[reward_info, owner_user_reward_info]` for each open reward of the pool.
+pub fn unwrap_lp<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, UnwrapLp<'info>>,
+    lp_token_amount: u64,
+) -> Result<()> {
+    require_gt!(lp_token_amount, 0, GammaError::InvalidLpTokenAmount);
+
+    let pool_id = ctx.accounts.pool_state.key();
+
+    let user_pool_liquidity = &mut ctx.accounts.user_pool_liquidity;
+    if user_pool_liquidity.user == Pubkey::default() {
+        user_pool_liquidity.initialize(ctx.accounts.owner.key(), pool_id, None);
+    }
+
+    settle_user_rewards(
+        ctx.remaining_accounts,
+        pool_id,
+        &ctx.accounts.pool_state.load()?,
+        &mut [(ctx.accounts.owner.key(), &mut ***user_pool_liquidity)],
+        &ctx.accounts.owner.to_account_info(),
+        Some(&ctx.accounts.system_program.to_account_info()),
+    )?;
+
+    token_burn(
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.lp_mint.to_account_info(),
+        ctx.accounts.owner_lp_token.to_account_info(),
+        lp_token_amount,
+        &[],
+    )?;
+
+    user_pool_liquidity.lp_tokens_owned = user_pool_liquidity
+        .lp_tokens_owned
+        .checked_add(u128::from(lp_token_amount))
+        .ok_or(GammaError::MathOverflow)?;
+
+    let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+    pool_state.lp_tokens_wrapped = pool_state
+        .lp_tokens_wrapped
+        .checked_sub(lp_token_amount)
+        .ok_or(GammaError::MathOverflow)?;
+    pool_state.add_lp_token_linked_with_partner(
+        &mut ctx.accounts.partner_info,
+        pool_id,
+        user_pool_liquidity.partner,
+        lp_token_amount,
+    )
+}
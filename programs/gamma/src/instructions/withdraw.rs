@@ -4,14 +4,14 @@ use anchor_spl::token_interface::{Mint, Token2022, TokenAccount};
 
 use crate::curve::{CurveCalculator, RoundDirection};
 use crate::states::{
-    LpChangeEvent, PartnerType, PoolStatusBitIndex, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED,
+    LpChangeEvent, PartnerInfo, PoolStatusBitIndex, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED,
 };
-use crate::utils::{get_transfer_fee, transfer_from_pool_vault_to_user};
+use crate::utils::{get_transfer_fee, settle_user_rewards, transfer_from_pool_vault_to_user};
 use crate::{error::GammaError, states::PoolState};
 
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
-    /// Owner of the liquidity provided
+    /// Owner of the liquidity provided, pays for the missing user reward infos when writable
     pub owner: Signer<'info>,
 
     /// CHECK: pool vault authority
@@ -93,10 +93,18 @@ pub struct Withdraw<'info> {
         address = spl_memo::id()
     )]
     pub memo_program: UncheckedAccount<'info>,
+
+    /// To create the missing user reward infos of the owner, can be left out by pools without rewards
+    pub system_program: Option<Program<'info, System>>,
+
+    /// Ledger of the partner the position is linked with, required when the position has a partner
+    #[account(mut)]
+    pub partner_info: Option<Box<Account<'info, PartnerInfo>>>,
 }
 
-pub fn withdraw(
-    ctx: Context<Withdraw>,
+/// Remaining accounts are `[reward_info, owner_user_reward_info]` for each open reward of the pool.
+pub fn withdraw<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, Withdraw<'info>>,
     lp_token_amount: u64,
     minimum_token_0_amount: u64,
     minimum_token_1_amount: u64,
@@ -107,6 +115,29 @@ pub fn withdraw(
     if !pool_state.get_status_by_bit(PoolStatusBitIndex::Withdraw) {
         return err!(GammaError::NotApproved);
     }
+    require!(!pool_state.flash_loan_open(), GammaError::FlashLoanOpen);
+    // Locked lp tokens are not part of the owned ones, so they can not be withdrawn
+    require_gte!(
+        ctx.accounts.user_pool_liquidity.lp_tokens_owned,
+        u128::from(lp_token_amount),
+        GammaError::InvalidLpTokenAmount
+    );
+    let system_program = ctx
+        .accounts
+        .system_program
+        .as_ref()
+        .map(|system_program| system_program.to_account_info());
+    settle_user_rewards(
+        ctx.remaining_accounts,
+        pool_id,
+        pool_state,
+        &mut [(
+            ctx.accounts.owner.key(),
+            &mut *ctx.accounts.user_pool_liquidity,
+        )],
+        &ctx.accounts.owner.to_account_info(),
+        system_program.as_ref(),
+    )?;
     let (total_token_0_amount, total_token_1_amount) = pool_state.vault_amount_without_fee()?;
     let results = CurveCalculator::lp_tokens_to_trading_tokens(
         u128::from(lp_token_amount),
@@ -196,19 +227,12 @@ pub fn withdraw(
         .checked_add(u128::from(receive_token_1_amount))
         .ok_or(GammaError::MathOverflow)?;
 
-    if let Some(user_pool_liquidity_partner) = user_pool_liquidity.partner {
-        let mut pool_state_partners = pool_state.partners;
-        let partner: Option<&mut crate::states::PartnerInfo> = pool_state_partners
-            .iter_mut()
-            .find(|p| PartnerType::new(p.partner_id) == user_pool_liquidity_partner);
-        if let Some(partner) = partner {
-            partner.lp_token_linked_with_partner = partner
-                .lp_token_linked_with_partner
-                .checked_sub(lp_token_amount)
-                .ok_or(GammaError::MathOverflow)?;
-        }
-        pool_state.partners = pool_state_partners;
-    }
+    pool_state.sub_lp_token_linked_with_partner(
+        &mut ctx.accounts.partner_info,
+        pool_id,
+        user_pool_liquidity.partner,
+        lp_token_amount,
+    )?;
 
     transfer_from_pool_vault_to_user(
         ctx.accounts.authority.to_account_info(),
@@ -0,0 +1,348 @@
+use crate::{
+    curve::{CurveCalculator, RoundDirection, TradeDirection},
+    error::GammaError,
+    states::{
+        oracle, AmmConfig, LpChangeEvent, ObservationBufferMut, ObservationState, PartnerInfo,
+        PoolState, PoolStatusBitIndex, SwapEvent, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED,
+    },
+    utils::{get_transfer_fee, settle_user_rewards, transfer_from_pool_vault_to_user},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_interface::{Mint, Token2022, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct WithdrawSingleSided<'info> {
+    /// Owner of the liquidity provided
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: pool vault authority
+    #[account(
+        seeds = [
+            crate::AUTH_SEED.as_bytes(),
+        ],
+        bump,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    /// The factory state to read protocol fees
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Box<Account<'info, AmmConfig>>,
+
+    /// Pool state account
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// User pool liquidity account
+    #[account(
+        mut,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            owner.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub user_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    /// Ledger of the partner the position is linked with, required when the position has a partner
+    #[account(mut)]
+    pub partner_info: Option<Box<Account<'info, PartnerInfo>>>,
+
+    /// The owner's token account for token_0, only written to when token_0 is the output
+    #[account(
+        mut,
+        token::mint = token_0_vault.mint,
+        token::authority = owner
+    )]
+    pub token_0_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The owner's token account for token_1, only written to when token_1 is the output
+    #[account(
+        mut,
+        token::mint = token_1_vault.mint,
+        token::authority = owner
+    )]
+    pub token_1_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_0
+    #[account(
+        mut,
+        constraint = token_0_vault.key() == pool_state.load()?.token_0_vault
+    )]
+    pub token_0_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_1
+    #[account(
+        mut,
+        constraint = token_1_vault.key() == pool_state.load()?.token_1_vault
+    )]
+    pub token_1_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// token Program
+    pub token_program: Program<'info, Token>,
+
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+
+    /// The mint of token_0 vault
+    #[account(
+        address = token_0_vault.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of token_1 vault
+    #[account(
+        address = token_1_vault.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The program account for the most recent oracle observation
+    #[account(mut, address = pool_state.load()?.observation_key)]
+    pub observation_state: AccountLoader<'info, ObservationState>,
+
+    /// To create the missing user reward infos of the owner
+    pub system_program: Program<'info, System>,
+}
+
+/// Remaining accounts are `[reward_info, owner_user_reward_info]` for each open reward of the pool.
+pub fn withdraw_single_sided<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, WithdrawSingleSided<'info>>,
+    lp_token_amount: u64,
+    output_mint: Pubkey,
+    minimum_amount_out: u64,
+) -> Result<()> {
+    let block_timestamp = Clock::get()?.unix_timestamp as u64;
+    let pool_id = ctx.accounts.pool_state.key();
+    let accounts = ctx.accounts;
+    let pool_state = &mut accounts.pool_state.load_mut()?;
+    if !pool_state.get_status_by_bit(PoolStatusBitIndex::Withdraw)
+        || !pool_state.get_status_by_bit(PoolStatusBitIndex::Swap)
+        || block_timestamp < pool_state.open_time
+    {
+        return err!(GammaError::NotApproved);
+    }
+    require!(!pool_state.flash_loan_open(), GammaError::FlashLoanOpen);
+    // Locked lp tokens are not part of the owned ones, so they can not be withdrawn
+    require_gte!(
+        accounts.user_pool_liquidity.lp_tokens_owned,
+        u128::from(lp_token_amount),
+        GammaError::InvalidLpTokenAmount
+    );
+    settle_user_rewards(
+        ctx.remaining_accounts,
+        pool_id,
+        pool_state,
+        &mut [(accounts.owner.key(), &mut **accounts.user_pool_liquidity)],
+        &accounts.owner.to_account_info(),
+        Some(&accounts.system_program.to_account_info()),
+    )?;
+    require_gt!(lp_token_amount, 0, GammaError::InvalidLpTokenAmount);
+
+    // The token that is not wanted is swapped into the output token
+    let trade_direction = if output_mint == accounts.vault_1_mint.key() {
+        TradeDirection::ZeroForOne
+    } else if output_mint == accounts.vault_0_mint.key() {
+        TradeDirection::OneForZero
+    } else {
+        return err!(GammaError::InvalidInput);
+    };
+    let (input_mint, output_mint, output_token_account, output_vault) = match trade_direction {
+        TradeDirection::ZeroForOne => (
+            &accounts.vault_0_mint,
+            &accounts.vault_1_mint,
+            &accounts.token_1_account,
+            &accounts.token_1_vault,
+        ),
+        TradeDirection::OneForZero => (
+            &accounts.vault_1_mint,
+            &accounts.vault_0_mint,
+            &accounts.token_0_account,
+            &accounts.token_0_vault,
+        ),
+    };
+    let output_token_program = if output_mint.to_account_info().owner == accounts.token_program.key
+    {
+        accounts.token_program.to_account_info()
+    } else {
+        accounts.token_program_2022.to_account_info()
+    };
+
+    let (token_0_price_x32_before_swap, token_1_price_x32_before_swap) =
+        pool_state.token_price_x32(block_timestamp)?;
+
+    // 1. Burn the lp tokens for both pool tokens
+    let (total_token_0_amount, total_token_1_amount) = pool_state.vault_amount_without_fee()?;
+    let results = CurveCalculator::lp_tokens_to_trading_tokens(
+        u128::from(lp_token_amount),
+        u128::from(pool_state.lp_supply),
+        u128::from(total_token_0_amount),
+        u128::from(total_token_1_amount),
+        RoundDirection::Floor,
+    )
+    .ok_or(GammaError::ZeroTradingTokens)?;
+    let token_0_amount =
+        u64::try_from(results.token_0_amount).map_err(|_| GammaError::MathOverflow)?;
+    let token_0_amount = std::cmp::min(total_token_0_amount, token_0_amount);
+    let token_1_amount =
+        u64::try_from(results.token_1_amount).map_err(|_| GammaError::MathOverflow)?;
+    let token_1_amount = std::cmp::min(total_token_1_amount, token_1_amount);
+
+    emit!(LpChangeEvent {
+        pool_id,
+        lp_amount_before: pool_state.lp_supply,
+        token_0_vault_before: total_token_0_amount,
+        token_1_vault_before: total_token_1_amount,
+        token_0_amount,
+        token_1_amount,
+        token_0_transfer_fee: 0,
+        token_1_transfer_fee: 0,
+        change_type: 1
+    });
+
+    pool_state.lp_supply = pool_state
+        .lp_supply
+        .checked_sub(lp_token_amount)
+        .ok_or(GammaError::MathOverflow)?;
+    pool_state.token_0_vault_amount = pool_state
+        .token_0_vault_amount
+        .checked_sub(token_0_amount)
+        .ok_or(GammaError::MathOverflow)?;
+    pool_state.token_1_vault_amount = pool_state
+        .token_1_vault_amount
+        .checked_sub(token_1_amount)
+        .ok_or(GammaError::MathOverflow)?;
+
+    // 2. Swap the unwanted side against the remaining reserves, the tokens never leave the vault
+    let (swap_amount_in, withdrawn_amount_out) = match trade_direction {
+        TradeDirection::ZeroForOne => (token_0_amount, token_1_amount),
+        TradeDirection::OneForZero => (token_1_amount, token_0_amount),
+    };
+    let (total_token_0_amount, total_token_1_amount) = pool_state.vault_amount_without_fee()?;
+    let (total_input_token_amount, total_output_token_amount) = match trade_direction {
+        TradeDirection::ZeroForOne => (total_token_0_amount, total_token_1_amount),
+        TradeDirection::OneForZero => (total_token_1_amount, total_token_0_amount),
+    };
+
+    let mut observation_data = ObservationState::try_borrow_mut_data(&accounts.observation_state)?;
+    let mut observation_state = ObservationBufferMut::from_account_data(&mut observation_data)?;
+    let (swap_amount_out, output_transfer_fee) = if swap_amount_in > 0 {
+        let result = CurveCalculator::swap_base_input(
+            u128::from(swap_amount_in),
+            u128::from(total_input_token_amount),
+            u128::from(total_output_token_amount),
+            trade_direction,
+            &accounts.amm_config,
+            pool_state,
+            block_timestamp,
+            &observation_state.as_buffer(),
+            false,
+        )?;
+        let swap_amount_out = u64::try_from(result.destination_amount_swapped)
+            .map_err(|_| GammaError::MathOverflow)?;
+        let output_transfer_fee = get_transfer_fee(
+            &output_mint.to_account_info(),
+            withdrawn_amount_out
+                .checked_add(swap_amount_out)
+                .ok_or(GammaError::MathOverflow)?,
+        )?;
+
+        emit!(SwapEvent {
+            pool_id,
+            input_vault_before: total_input_token_amount,
+            output_vault_before: total_output_token_amount,
+            input_amount: swap_amount_in,
+            output_amount: swap_amount_out,
+            input_mint: input_mint.key(),
+            output_mint: output_mint.key(),
+            input_transfer_fee: 0,
+            output_transfer_fee,
+            base_input: true,
+            dynamic_fee: result.dynamic_fee
+        });
+
+        pool_state.record_swap(
+            trade_direction,
+            swap_amount_in,
+            swap_amount_out,
+            u64::try_from(result.dynamic_fee).map_err(|_| GammaError::MathOverflow)?,
+            u64::try_from(result.protocol_fee).map_err(|_| GammaError::MathOverflow)?,
+            u64::try_from(result.fund_fee).map_err(|_| GammaError::MathOverflow)?,
+            result.dynamic_fee_rate,
+        )?;
+        (swap_amount_out, output_transfer_fee)
+    } else {
+        let output_transfer_fee =
+            get_transfer_fee(&output_mint.to_account_info(), withdrawn_amount_out)?;
+        (0, output_transfer_fee)
+    };
+
+    let amount_out = withdrawn_amount_out
+        .checked_add(swap_amount_out)
+        .ok_or(GammaError::MathOverflow)?;
+    let receive_amount = amount_out
+        .checked_sub(output_transfer_fee)
+        .ok_or(GammaError::MathOverflow)?;
+    #[cfg(feature = "enable-log")]
+    msg!(
+        "withdrawn_amount_out:{}, swap_amount_in:{}, swap_amount_out:{}, output_transfer_fee:{}",
+        withdrawn_amount_out,
+        swap_amount_in,
+        swap_amount_out,
+        output_transfer_fee
+    );
+    require_gte!(
+        receive_amount,
+        minimum_amount_out,
+        GammaError::ExceededSlippage
+    );
+
+    let user_pool_liquidity = &mut accounts.user_pool_liquidity;
+    user_pool_liquidity.lp_tokens_owned = user_pool_liquidity
+        .lp_tokens_owned
+        .checked_sub(u128::from(lp_token_amount))
+        .ok_or(GammaError::MathOverflow)?;
+    match trade_direction {
+        TradeDirection::ZeroForOne => {
+            user_pool_liquidity.token_1_withdrawn = user_pool_liquidity
+                .token_1_withdrawn
+                .checked_add(u128::from(receive_amount))
+                .ok_or(GammaError::MathOverflow)?;
+        }
+        TradeDirection::OneForZero => {
+            user_pool_liquidity.token_0_withdrawn = user_pool_liquidity
+                .token_0_withdrawn
+                .checked_add(u128::from(receive_amount))
+                .ok_or(GammaError::MathOverflow)?;
+        }
+    }
+    pool_state.sub_lp_token_linked_with_partner(
+        &mut accounts.partner_info,
+        pool_id,
+        user_pool_liquidity.partner,
+        lp_token_amount,
+    )?;
+
+    transfer_from_pool_vault_to_user(
+        accounts.authority.to_account_info(),
+        output_vault.to_account_info(),
+        output_token_account.to_account_info(),
+        output_mint.to_account_info(),
+        output_token_program,
+        amount_out,
+        output_mint.decimals,
+        &[&[crate::AUTH_SEED.as_bytes(), &[pool_state.auth_bump]]],
+    )?;
+
+    observation_state.update(
+        oracle::block_timestamp()?,
+        token_0_price_x32_before_swap,
+        token_1_price_x32_before_swap,
+    )?;
+    pool_state.recent_epoch = Clock::get()?.epoch;
+
+    Ok(())
+}
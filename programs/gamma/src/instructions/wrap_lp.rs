@@ -0,0 +1,143 @@
+use crate::{
+    error::GammaError,
+    states::{
+        PartnerInfo, PoolState, UserPoolLiquidity, LP_MINT_DECIMALS, USER_POOL_LIQUIDITY_SEED,
+        WRAPPED_LP_MINT_SEED,
+    },
+    utils::{settle_user_rewards, token_mint_to},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct WrapLp<'info> {
+    /// Owner of the liquidity being wrapped
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: pool vault and lp mint authority
+    #[account(
+        seeds = [
+            crate::AUTH_SEED.as_bytes(),
+        ],
+        bump,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    /// Pool state the liquidity belongs to
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            owner.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub user_pool_liquidity: Box<Account<'info, UserPoolLiquidity>>,
+
+    /// Ledger of the partner the position is linked with, required when the position has a partner
+    #[account(mut)]
+    pub partner_info: Option<Box<Account<'info, PartnerInfo>>>,
+
+    /// Receipt mint of the pool, created on the first wrap
+    #[account(
+        init_if_needed,
+        seeds = [
+            WRAPPED_LP_MINT_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+        ],
+        bump,
+        payer = owner,
+        mint::decimals = LP_MINT_DECIMALS,
+        mint::authority = authority,
+        mint::token_program = token_program,
+    )]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The owner's token account receiving the wrapped lp tokens
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = lp_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_lp_token: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Program to create the lp mint and mint lp tokens
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Program to create the owner's lp token account
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// To create new program accounts
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves `lp_token_amount` out of the owner's position into receipt tokens.
+///
+/// Wrapped lp tokens earn neither pool rewards nor partner fees, so the owner's rewards are
+/// settled first and the wrapped lp tokens are left out of `PoolState::lp_supply_in_positions`.
+/// Remaining accounts are `[reward_info, owner_user_reward_info]` for each open reward of the pool.
+pub fn wrap_lp<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, WrapLp<'info>>,
+    lp_token_amount: u64,
+) -> Result<()> {
+    require_gt!(lp_token_amount, 0, GammaError::InvalidLpTokenAmount);
+    require_gte!(
+        ctx.accounts.user_pool_liquidity.lp_tokens_owned,
+        u128::from(lp_token_amount),
+        GammaError::InvalidLpTokenAmount
+    );
+
+    let pool_id = ctx.accounts.pool_state.key();
+    let auth_bump = {
+        let pool_state = ctx.accounts.pool_state.load()?;
+        require!(!pool_state.is_legacy_layout(), GammaError::PoolNotMigrated);
+        pool_state.auth_bump
+    };
+
+    settle_user_rewards(
+        ctx.remaining_accounts,
+        pool_id,
+        &ctx.accounts.pool_state.load()?,
+        &mut [(
+            ctx.accounts.owner.key(),
+            &mut **ctx.accounts.user_pool_liquidity,
+        )],
+        &ctx.accounts.owner.to_account_info(),
+        Some(&ctx.accounts.system_program.to_account_info()),
+    )?;
+
+    let user_pool_liquidity = &mut ctx.accounts.user_pool_liquidity;
+    user_pool_liquidity.lp_tokens_owned = user_pool_liquidity
+        .lp_tokens_owned
+        .checked_sub(u128::from(lp_token_amount))
+        .ok_or(GammaError::MathOverflow)?;
+
+    let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+    pool_state.sub_lp_token_linked_with_partner(
+        &mut ctx.accounts.partner_info,
+        pool_id,
+        user_pool_liquidity.partner,
+        lp_token_amount,
+    )?;
+    pool_state.lp_tokens_wrapped = pool_state
+        .lp_tokens_wrapped
+        .checked_add(lp_token_amount)
+        .ok_or(GammaError::MathOverflow)?;
+
+    token_mint_to(
+        ctx.accounts.authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.lp_mint.to_account_info(),
+        ctx.accounts.owner_lp_token.to_account_info(),
+        lp_token_amount,
+        &[&[crate::AUTH_SEED.as_bytes(), &[auth_bump]]],
+    )
+}
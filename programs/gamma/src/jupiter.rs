@@ -0,0 +1,545 @@
+//! Implementation of the `jupiter_amm_interface::Amm` trait for Gamma pools, for aggregators and routers.
+//! `jupiter_amm_interface::Swap` has no variant for Gamma yet, so `get_swap_and_account_metas` fails and
+//! routers build the swap with `Gamma::swap_instruction`.
+//!
+//! Quotes go through the same `CurveCalculator` as the on chain swap, so they match execution exactly.
+
+use crate::curve::{CurveCalculator, TradeDirection};
+use crate::states::{AmmConfig, ObservationBuffer, PoolState, PoolStatusBitIndex};
+use crate::AUTH_SEED;
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        transfer_fee::{TransferFeeConfig, MAX_FEE_BASIS_POINTS},
+        BaseStateWithExtensions, StateWithExtensions, StateWithExtensionsOwned,
+    },
+    state::Mint,
+};
+use anyhow::{anyhow, Context, Result};
+use jupiter_amm_interface::{
+    try_get_account_data, AccountMap, Amm, AmmContext, KeyedAccount, Quote, QuoteParams,
+    SwapAndAccountMetas, SwapMode, SwapParams,
+};
+use rust_decimal::prelude::FromPrimitive;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Number of accounts of the `Swap` instruction, without the optional remaining accounts
+pub const SWAP_ACCOUNTS_LEN: usize = 13;
+/// Number of optional remaining accounts of a swap: segmenter, registry, referral and referral token account
+pub const SWAP_REMAINING_ACCOUNTS_LEN: usize = 4;
+
+#[derive(Clone)]
+pub struct TokenMints {
+    token0_mint: StateWithExtensionsOwned<Mint>,
+    token1_mint: StateWithExtensionsOwned<Mint>,
+    token0_program: Pubkey,
+    token1_program: Pubkey,
+}
+
+/// A signed segmenter and the dflow registry it is registered in, to get the segmenter fee discount
+#[derive(Clone, Copy, Debug)]
+pub struct Segmenter {
+    pub segmenter: Pubkey,
+    pub registry: Pubkey,
+}
+
+#[derive(Clone)]
+pub struct Gamma {
+    key: Pubkey,
+    pool_state: PoolState,
+    amm_config: Option<AmmConfig>,
+    vault_0_amount: Option<u64>,
+    vault_1_amount: Option<u64>,
+    token_mints_and_token_programs: Option<TokenMints>,
+    epoch: Arc<AtomicU64>,
+    timestamp: Arc<AtomicI64>,
+    /// The observation account data, the observations may extend past the ObservationState struct
+    observation_data: Option<Vec<u8>>,
+    segmenter: Option<Segmenter>,
+    referral_account: Option<Pubkey>,
+}
+
+impl Gamma {
+    fn get_authority(&self) -> Pubkey {
+        Pubkey::create_program_address(
+            &[AUTH_SEED.as_bytes(), &[self.pool_state.auth_bump]],
+            &crate::ID,
+        )
+        .unwrap()
+    }
+
+    /// Quotes and swaps as the given signed segmenter, which pays one basis point less fees
+    pub fn with_segmenter(mut self, segmenter: Segmenter) -> Self {
+        self.segmenter = Some(segmenter);
+        self
+    }
+
+    /// Shares the protocol and fund fees of swaps with the given account of the referral program
+    pub fn with_referral_account(mut self, referral_account: Pubkey) -> Self {
+        self.referral_account = Some(referral_account);
+        self
+    }
+
+    pub fn pool_state(&self) -> &PoolState {
+        &self.pool_state
+    }
+
+    fn trade_direction(&self, input_mint: Pubkey) -> Result<TradeDirection> {
+        if input_mint == self.pool_state.token_0_mint {
+            Ok(TradeDirection::ZeroForOne)
+        } else if input_mint == self.pool_state.token_1_mint {
+            Ok(TradeDirection::OneForZero)
+        } else {
+            Err(anyhow!("Mint {} is not traded by the pool", input_mint))
+        }
+    }
+
+    /// Transfer fee configs of the (input, output) mints of a trade
+    fn transfer_fee_configs(
+        &self,
+        trade_direction: TradeDirection,
+    ) -> Result<(Option<&TransferFeeConfig>, Option<&TransferFeeConfig>)> {
+        let TokenMints {
+            token0_mint: token_mint_0,
+            token1_mint: token_mint_1,
+            ..
+        } = self
+            .token_mints_and_token_programs
+            .as_ref()
+            .ok_or(anyhow!("Missing token mints and token programs"))?;
+
+        let token_mint_0_transfer_fee_config =
+            token_mint_0.get_extension::<TransferFeeConfig>().ok();
+        let token_mint_1_transfer_fee_config =
+            token_mint_1.get_extension::<TransferFeeConfig>().ok();
+
+        Ok(match trade_direction {
+            TradeDirection::ZeroForOne => (
+                token_mint_0_transfer_fee_config,
+                token_mint_1_transfer_fee_config,
+            ),
+            TradeDirection::OneForZero => (
+                token_mint_1_transfer_fee_config,
+                token_mint_0_transfer_fee_config,
+            ),
+        })
+    }
+
+    /// The optional remaining accounts of a swap, the program id stands for a missing account
+    fn swap_remaining_account_metas(&self, input_token_mint: Pubkey) -> Vec<AccountMeta> {
+        let (segmenter, registry) = match self.segmenter {
+            Some(Segmenter {
+                segmenter,
+                registry,
+            }) => (
+                AccountMeta::new_readonly(segmenter, true),
+                AccountMeta::new_readonly(registry, false),
+            ),
+            None => (
+                AccountMeta::new_readonly(crate::ID, false),
+                AccountMeta::new_readonly(crate::ID, false),
+            ),
+        };
+        let (referral_account, referral_token_account) = match self.referral_account {
+            Some(referral_account) => {
+                let referral_token_account = Pubkey::find_program_address(
+                    &[
+                        referral::REFERRAL_ATA_SEED,
+                        referral_account.as_ref(),
+                        input_token_mint.as_ref(),
+                    ],
+                    &referral::ID,
+                )
+                .0;
+                (
+                    AccountMeta::new_readonly(referral_account, false),
+                    AccountMeta::new(referral_token_account, false),
+                )
+            }
+            None => (
+                AccountMeta::new_readonly(crate::ID, false),
+                AccountMeta::new_readonly(crate::ID, false),
+            ),
+        };
+        vec![
+            segmenter,
+            registry,
+            referral_account,
+            referral_token_account,
+        ]
+    }
+
+    /// The `swap_base_input` instruction of a `SwapMode::ExactIn` swap, with `out_amount` as the
+    /// minimum output, or the `swap_base_output` instruction of a `SwapMode::ExactOut` swap, with
+    /// `in_amount` as the maximum input
+    pub fn swap_instruction(&self, swap_params: &SwapParams) -> Result<Instruction> {
+        let data = match swap_params.swap_mode {
+            SwapMode::ExactIn => crate::instruction::SwapBaseInput {
+                amount_in: swap_params.in_amount,
+                minimum_amount_out: swap_params.out_amount,
+            }
+            .data(),
+            SwapMode::ExactOut => crate::instruction::SwapBaseOutput {
+                max_amount_in: swap_params.in_amount,
+                amount_out: swap_params.out_amount,
+            }
+            .data(),
+        };
+        Ok(Instruction {
+            program_id: crate::id(),
+            accounts: self.swap_account_metas(swap_params)?,
+            data,
+        })
+    }
+
+    /// Account metas of a `swap_base_input` or `swap_base_output` instruction, both take the same
+    /// accounts, including the segmenter and referral accounts
+    pub fn swap_account_metas(&self, swap_params: &SwapParams) -> Result<Vec<AccountMeta>> {
+        let TokenMints {
+            token0_program: token_0_token_program,
+            token1_program: token_1_token_program,
+            ..
+        } = self
+            .token_mints_and_token_programs
+            .as_ref()
+            .ok_or(anyhow!("Missing token mints and token programs"))?;
+
+        let (
+            input_token_program,
+            input_vault,
+            input_token_mint,
+            output_token_program,
+            output_vault,
+            output_token_mint,
+        ) = match self.trade_direction(swap_params.source_mint)? {
+            TradeDirection::ZeroForOne => (
+                *token_0_token_program,
+                self.pool_state.token_0_vault,
+                self.pool_state.token_0_mint,
+                *token_1_token_program,
+                self.pool_state.token_1_vault,
+                self.pool_state.token_1_mint,
+            ),
+            TradeDirection::OneForZero => (
+                *token_1_token_program,
+                self.pool_state.token_1_vault,
+                self.pool_state.token_1_mint,
+                *token_0_token_program,
+                self.pool_state.token_0_vault,
+                self.pool_state.token_0_mint,
+            ),
+        };
+
+        let mut account_metas = crate::accounts::Swap {
+            payer: swap_params.token_transfer_authority,
+            authority: self.get_authority(),
+            amm_config: self.pool_state.amm_config,
+            pool_state: self.key,
+            input_token_account: swap_params.source_token_account,
+            output_token_account: swap_params.destination_token_account,
+            input_vault,
+            output_vault,
+            input_token_program,
+            output_token_program,
+            input_token_mint,
+            output_token_mint,
+            observation_state: self.pool_state.observation_key,
+        }
+        .to_account_metas(None);
+        account_metas.extend(self.swap_remaining_account_metas(input_token_mint));
+        Ok(account_metas)
+    }
+}
+
+impl Amm for Gamma {
+    fn from_keyed_account(keyed_account: &KeyedAccount, amm_context: &AmmContext) -> Result<Self> {
+        let pool_state = PoolState::try_deserialize(&mut keyed_account.account.data.as_ref())?;
+
+        Ok(Self {
+            key: keyed_account.key,
+            pool_state,
+            amm_config: None,
+            vault_0_amount: None,
+            vault_1_amount: None,
+            token_mints_and_token_programs: None,
+            epoch: amm_context.clock_ref.epoch.clone(),
+            timestamp: amm_context.clock_ref.unix_timestamp.clone(),
+            observation_data: None,
+            segmenter: None,
+            referral_account: None,
+        })
+    }
+
+    fn label(&self) -> String {
+        "GAMMA".into()
+    }
+
+    fn program_id(&self) -> Pubkey {
+        crate::id()
+    }
+
+    fn key(&self) -> Pubkey {
+        self.key
+    }
+
+    fn get_reserve_mints(&self) -> Vec<Pubkey> {
+        vec![self.pool_state.token_0_mint, self.pool_state.token_1_mint]
+    }
+
+    fn get_accounts_to_update(&self) -> Vec<Pubkey> {
+        vec![
+            self.key,
+            self.pool_state.token_0_vault,
+            self.pool_state.token_1_vault,
+            self.pool_state.amm_config,
+            self.pool_state.observation_key,
+            self.pool_state.token_0_mint,
+            self.pool_state.token_1_mint,
+        ]
+    }
+
+    fn update(&mut self, account_map: &AccountMap) -> Result<()> {
+        let pool_state_data = try_get_account_data(account_map, &self.key)?;
+        self.pool_state = PoolState::try_deserialize(&mut pool_state_data.as_ref())?;
+
+        let token0_mint = try_get_account_data(account_map, &self.pool_state.token_0_mint)
+            .ok()
+            .and_then(|account_data| {
+                StateWithExtensionsOwned::<Mint>::unpack(account_data.to_vec()).ok()
+            })
+            .context("Token 0 mint not found")?;
+
+        let token1_mint = try_get_account_data(account_map, &self.pool_state.token_1_mint)
+            .ok()
+            .and_then(|account_data| {
+                StateWithExtensionsOwned::<Mint>::unpack(account_data.to_vec()).ok()
+            })
+            .context("Token 1 mint not found")?;
+
+        self.token_mints_and_token_programs = Some(TokenMints {
+            token0_mint,
+            token1_mint,
+            token0_program: self.pool_state.token_0_program,
+            token1_program: self.pool_state.token_1_program,
+        });
+
+        let amm_config_data = try_get_account_data(account_map, &self.pool_state.amm_config)?;
+        self.amm_config = Some(AmmConfig::try_deserialize(&mut amm_config_data.as_ref())?);
+
+        let get_unfrozen_token_amount = |token_vault| {
+            try_get_account_data(account_map, token_vault)
+                .ok()
+                .and_then(|account_data| {
+                    StateWithExtensions::<spl_token_2022::state::Account>::unpack(account_data).ok()
+                })
+                .and_then(|token_account| {
+                    if token_account.base.is_frozen() {
+                        None
+                    } else {
+                        Some(token_account.base.amount)
+                    }
+                })
+        };
+
+        let observation_data = try_get_account_data(account_map, &self.pool_state.observation_key)?;
+        ObservationBuffer::from_account_data(observation_data)?;
+        self.observation_data = Some(observation_data.to_vec());
+
+        self.vault_0_amount = get_unfrozen_token_amount(&self.pool_state.token_0_vault);
+        self.vault_1_amount = get_unfrozen_token_amount(&self.pool_state.token_1_vault);
+
+        Ok(())
+    }
+
+    fn quote(&self, quote_params: &QuoteParams) -> Result<Quote> {
+        let block_timestamp = self.timestamp.load(Ordering::Relaxed) as u64;
+        if !self.pool_state.get_status_by_bit(PoolStatusBitIndex::Swap)
+            || block_timestamp < self.pool_state.open_time
+        {
+            return Err(anyhow!("Pool is not trading"));
+        }
+        // Frozen vaults can not send or receive tokens
+        if self.vault_0_amount.is_none() || self.vault_1_amount.is_none() {
+            return Err(anyhow!("Pool vaults are frozen"));
+        }
+
+        let amm_config = self.amm_config.as_ref().context("Missing AmmConfig")?;
+        let observation_data = self
+            .observation_data
+            .as_ref()
+            .context("Missing observation state")?;
+        let observation_state = &ObservationBuffer::from_account_data(observation_data)?;
+
+        let trade_direction = self.trade_direction(quote_params.input_mint)?;
+        let (source_mint_transfer_fee_config, destination_mint_transfer_fee_config) =
+            self.transfer_fee_configs(trade_direction)?;
+
+        // Calculate the trade amounts, the vaults may hold less than the pool owns while tokens are lent out
+        let (total_token_0_amount, total_token_1_amount) = (
+            self.pool_state.token_0_vault_amount,
+            self.pool_state.token_1_vault_amount,
+        );
+        let (swap_source_amount, swap_destination_amount) = match trade_direction {
+            TradeDirection::ZeroForOne => (total_token_0_amount, total_token_1_amount),
+            TradeDirection::OneForZero => (total_token_1_amount, total_token_0_amount),
+        };
+        let epoch = self.epoch.load(Ordering::Relaxed);
+
+        let (in_amount, out_amount, source_amount_swapped, result) = match quote_params.swap_mode {
+            SwapMode::ExactIn => {
+                let amount = quote_params.amount;
+                let actual_amount_in =
+                    if let Some(transfer_fee_config) = source_mint_transfer_fee_config {
+                        amount.saturating_sub(
+                            transfer_fee_config
+                                .calculate_epoch_fee(epoch, amount)
+                                .context("Fee calculation failure")?,
+                        )
+                    } else {
+                        amount
+                    };
+                if actual_amount_in == 0 {
+                    return Err(anyhow!("Amount too low"));
+                }
+
+                let result = CurveCalculator::swap_base_input(
+                    actual_amount_in.into(),
+                    swap_source_amount.into(),
+                    swap_destination_amount.into(),
+                    trade_direction,
+                    amm_config,
+                    &self.pool_state,
+                    block_timestamp,
+                    observation_state,
+                    self.segmenter.is_some(),
+                )
+                .context("swap failed")?;
+
+                let amount_out: u64 = result.destination_amount_swapped.try_into()?;
+                let actual_amount_out =
+                    if let Some(transfer_fee_config) = destination_mint_transfer_fee_config {
+                        amount_out.saturating_sub(
+                            transfer_fee_config
+                                .calculate_epoch_fee(epoch, amount_out)
+                                .context("Fee calculation failure")?,
+                        )
+                    } else {
+                        amount_out
+                    };
+                (
+                    actual_amount_in,
+                    actual_amount_out,
+                    actual_amount_in,
+                    result,
+                )
+            }
+            SwapMode::ExactOut => {
+                // Same as `swap_base_output`, the pool sends enough to cover the transfer fee of the output
+                let amount_out_less_fee = quote_params.amount;
+                if amount_out_less_fee == 0 {
+                    return Err(anyhow!("Amount too low"));
+                }
+                let actual_amount_out = amount_out_less_fee
+                    .checked_add(get_transfer_inverse_fee(
+                        destination_mint_transfer_fee_config,
+                        epoch,
+                        amount_out_less_fee,
+                    )?)
+                    .ok_or(anyhow!("Math overflow"))?;
+
+                let result = CurveCalculator::swap_base_output(
+                    actual_amount_out.into(),
+                    swap_source_amount.into(),
+                    swap_destination_amount.into(),
+                    trade_direction,
+                    amm_config,
+                    &self.pool_state,
+                    block_timestamp,
+                    observation_state,
+                    self.segmenter.is_some(),
+                )
+                .context("swap failed")?;
+
+                // The user sends enough to cover the transfer fee of the input
+                let source_amount_swapped: u64 = result.source_amount_swapped.try_into()?;
+                if source_amount_swapped == 0 {
+                    return Err(anyhow!("Amount too low"));
+                }
+                let input_transfer_amount = source_amount_swapped
+                    .checked_add(get_transfer_inverse_fee(
+                        source_mint_transfer_fee_config,
+                        epoch,
+                        source_amount_swapped,
+                    )?)
+                    .ok_or(anyhow!("Math overflow"))?;
+                (
+                    input_transfer_amount,
+                    amount_out_less_fee,
+                    source_amount_swapped,
+                    result,
+                )
+            }
+        };
+
+        Ok(Quote {
+            in_amount,
+            out_amount,
+            fee_mint: quote_params.input_mint,
+            fee_amount: result.dynamic_fee as u64,
+            // our understanding is this is the fee percentage of the input amount
+            fee_pct: rust_decimal::Decimal::from_u128(result.dynamic_fee)
+                .ok_or(anyhow!("Math overflow"))?
+                .checked_div(
+                    rust_decimal::Decimal::from_u64(source_amount_swapped)
+                        .ok_or(anyhow!("Math overflow"))?,
+                )
+                .context("Failed to divide")?,
+            ..Default::default()
+        })
+    }
+
+    fn supports_exact_out(&self) -> bool {
+        true
+    }
+
+    fn get_accounts_len(&self) -> usize {
+        SWAP_ACCOUNTS_LEN + SWAP_REMAINING_ACCOUNTS_LEN
+    }
+
+    fn get_swap_and_account_metas(&self, swap_params: &SwapParams) -> Result<SwapAndAccountMetas> {
+        // TODO: Return the account metas once jupiter_amm_interface::Swap has a variant for Gamma
+        self.swap_account_metas(swap_params)?;
+        Err(anyhow!(
+            "jupiter_amm_interface::Swap has no variant for Gamma, build the swap with Gamma::swap_instruction"
+        ))
+    }
+
+    fn clone_amm(&self) -> Box<dyn Amm + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+/// The transfer fee to add to `post_fee_amount` so that the recipient gets `post_fee_amount`,
+/// the same as `crate::utils::get_transfer_inverse_fee` on chain
+fn get_transfer_inverse_fee(
+    transfer_fee_config: Option<&TransferFeeConfig>,
+    epoch: u64,
+    post_fee_amount: u64,
+) -> Result<u64> {
+    let transfer_fee_config = match transfer_fee_config {
+        Some(transfer_fee_config) => transfer_fee_config,
+        None => return Ok(0),
+    };
+    let transfer_fee = transfer_fee_config.get_epoch_fee(epoch);
+    if u16::from(transfer_fee.transfer_fee_basis_points) == MAX_FEE_BASIS_POINTS {
+        Ok(u64::from(transfer_fee.maximum_fee))
+    } else {
+        transfer_fee_config
+            .calculate_inverse_epoch_fee(epoch, post_fee_amount)
+            .context("Fee calculation failure")
+    }
+}
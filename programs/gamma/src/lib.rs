@@ -3,6 +3,8 @@ pub mod error;
 mod external;
 pub mod fees;
 pub mod instructions;
+#[cfg(all(feature = "jupiter", not(target_os = "solana")))]
+pub mod jupiter;
 pub mod migration;
 pub mod states;
 pub mod utils;
@@ -114,7 +116,8 @@ pub mod gamma {
     /// * `fund_fee_rate`- The new fund fee rate of amm config, be set when `param` is 2
     /// * `new_owner`- The config's new owner, be set when `param` is 3
     /// * `new_fund_owner`- The config's new fund owner, be set when `param` is 4
-    /// * `param`- The vaule can be 0 | 1 | 2 | 3 | 4, otherwise will report a error
+    /// * `flash_loan_fee_rate`- The new flash loan fee rate of amm config, be set when `param` is 9
+    /// * `param`- The vaule can be 0 | 1 | 2 | 3 | 4 | 5 | 6 | 7 | 8 | 9, otherwise will report a error
     ///
     pub fn update_amm_config(ctx: Context<UpdateAmmConfig>, param: u16, value: u64) -> Result<()> {
         instructions::update_amm_config(ctx, param, value)
@@ -126,13 +129,19 @@ pub mod gamma {
     ///
     /// * `ctx`- The context of accounts
     /// * `param`- The param of pool status
-    /// * `status` - The value
+    /// * `status` - The value, for param 6 (ramp amplification) the low 32 bits are the target
+    ///   amplification coefficient and the high 32 bits the ramp duration in seconds, for param 7
+    ///   the fee type: 0 for volatility, 1 for static and 2 for imbalance, for param 8 (launch fee)
+    ///   the low 32 bits are the launch fee rate, the next 24 bits the decay duration in seconds and
+    ///   the high 8 bits the decay: 0 for linear and 1 for exponential, for param 9 the minimum
+    ///   number of seconds between two observations, with the observation account of the pool as
+    ///   remaining account
     ///
     pub fn update_pool(ctx: Context<UpdatePool>, param: u32, value: u64) -> Result<()> {
         instructions::update_pool(ctx, param, value)
     }
 
-    /// Collect the protocol fee accrued to the pool
+    /// Collect the protocol fee accrued to the pool, except the fee share kept for the partners
     ///
     /// # Arguments
     ///
@@ -164,7 +173,73 @@ pub mod gamma {
         instructions::collect_fund_fee(ctx, amount_0_requested, amount_1_requested)
     }
 
-    /// Creates a pool for the given token pair and the initial price
+    /// Registers a wallet partner whose customers' liquidity is tracked in every pool
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `partner_id` - The id of the partner, linked with the positions of its customers
+    /// * `fee_share_rate` - Share of the protocol fees earned by the linked liquidity that goes to the partner
+    /// * `authority` - Signs the claims of the fee share of the partner
+    ///
+    pub fn register_partner(
+        ctx: Context<RegisterPartner>,
+        partner_id: u64,
+        fee_share_rate: u64,
+        authority: Pubkey,
+    ) -> Result<()> {
+        instructions::register_partner(ctx, partner_id, fee_share_rate, authority)
+    }
+
+    /// Rotates the authority of a partner, signed by its current authority or the admin
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `authority` - Signs the claims of the fee share of the partner from now on
+    ///
+    pub fn update_partner_authority(
+        ctx: Context<UpdatePartnerAuthority>,
+        authority: Pubkey,
+    ) -> Result<()> {
+        instructions::update_partner_authority(ctx, authority)
+    }
+
+    /// Creates the ledger of a registered partner in a pool, required before positions linked with
+    /// the partner can change their liquidity
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    ///
+    pub fn init_partner_info(ctx: Context<InitPartnerInfo>) -> Result<()> {
+        instructions::init_partner_info(ctx)
+    }
+
+    /// Moves the partner slot of a pool created before the partner ledgers to the ledger of the
+    /// legacy partner, freeing the protocol fees per lp of the pool. The legacy partner must be
+    /// registered. Permissionless
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    ///
+    pub fn migrate_partner_ledger(ctx: Context<MigratePartnerLedger>) -> Result<()> {
+        instructions::migrate_partner_ledger(ctx)
+    }
+
+    /// Pays out the share of the protocol fees earned by the liquidity linked with a partner.
+    /// The share is kept out of `collect_protocol_fee` until it is claimed
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts, signed by the authority of the partner
+    ///
+    pub fn claim_partner_fees(ctx: Context<ClaimPartnerFees>) -> Result<()> {
+        instructions::claim_partner_fees(ctx)
+    }
+
+    /// Creates a constant product pool for the given token pair and the initial price
     ///
     /// # Arguments
     ///
@@ -190,6 +265,60 @@ pub mod gamma {
             open_time,
             max_trade_fee_rate,
             volatility_factor,
+            curve::CurveType::ConstantProduct as u8,
+            0,
+            0,
+            0,
+            0,
+            0,
+        )
+    }
+
+    /// Creates a pool for the given token pair and the initial price, with the curve and the
+    /// launch fee of the pool
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `init_amount_0` - the initial amount_0 to deposit
+    /// * `init_amount_1` - the initial amount_1 to deposit
+    /// * `open_time` - the timestamp allowed for swap
+    /// * `max_trade_fee_rate` - The maximum trade fee that can be charged on swaps
+    /// * `volatility_factor` - The volatility factor of the pool to determine the trade fee
+    /// * `curve_type` - The invariant of the pool, 0 for constant product, 1 for StableSwap and 2 for weighted
+    /// * `amp` - The amplification coefficient of a StableSwap pool, ignored for other curves
+    /// * `token_0_weight` - The weight of token_0 out of 100 in a weighted pool, token_1 gets the rest, ignored for other curves
+    /// * `launch_fee_rate` - The trade fee charged at open time, decaying to the normal fee, 0 to disable
+    /// * `launch_fee_duration` - The seconds after open time it takes the launch fee to decay
+    /// * `launch_fee_decay` - 0 to decay the launch fee linearly, 1 to decay it exponentially
+    ///
+    pub fn initialize_v2(
+        ctx: Context<Initialize>,
+        init_amount_0: u64,
+        init_amount_1: u64,
+        open_time: u64,
+        max_trade_fee_rate: u64,
+        volatility_factor: u64,
+        curve_type: u8,
+        amp: u64,
+        token_0_weight: u32,
+        launch_fee_rate: u64,
+        launch_fee_duration: u64,
+        launch_fee_decay: u8,
+    ) -> Result<()> {
+        instructions::initialize(
+            ctx,
+            init_amount_0,
+            init_amount_1,
+            open_time,
+            max_trade_fee_rate,
+            volatility_factor,
+            curve_type,
+            amp,
+            token_0_weight,
+            launch_fee_rate,
+            launch_fee_duration,
+            launch_fee_decay,
         )
     }
 
@@ -200,17 +329,31 @@ pub mod gamma {
         instructions::init_user_pool_liquidity(ctx, partner)
     }
 
+    /// Creates the liquidity position of a user in a pool
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts, the partner ledger of the pool is required when linking a partner
+    /// * `partner` - Id of the registered partner the position is linked with
+    ///
+    pub fn init_user_pool_liquidity_v2(
+        ctx: Context<InitUserPoolLiquidityV2>,
+        partner: Option<u64>,
+    ) -> Result<()> {
+        instructions::init_user_pool_liquidity_v2(ctx, partner)
+    }
+
     /// Creates a pool for the given token pair and the initial price
     ///
     /// # Arguments
     ///
-    /// * `ctx`- The context of accounts
+    /// * `ctx`- The context of accounts, remaining accounts are `[reward_info, owner_user_reward_info]` for each open reward of the pool and each reward closed since the last lp change of the positions, the missing user reward infos are created
     /// * `lp_token_amount` - Pool token amount to transfer. token_a and token_b amount are set by the current exchange rate and size of the pool
     /// * `maximum_token_0_amount` -  Maximum token 0 amount to deposit, prevents excessive slippage
     /// * `maximum_token_1_amount` - Maximum token 1 amount to deposit, prevents excessive slippage
     ///
-    pub fn deposit(
-        ctx: Context<Deposit>,
+    pub fn deposit<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Deposit<'info>>,
         lp_token_amount: u64,
         maximum_token_0_amount: u64,
         maximum_token_1_amount: u64,
@@ -223,17 +366,36 @@ pub mod gamma {
         )
     }
 
+    /// Deposit liquidity with only one of the pool tokens, part of it is swapped at the pool
+    /// price and the rest is deposited together with the swap output
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts, remaining accounts are `[reward_info, owner_user_reward_info]` for each open reward of the pool and each reward closed since the last lp change of the positions, the missing user reward infos are created
+    /// * `input_mint` - Mint of the token provided, must be token_0 or token_1 of the pool
+    /// * `amount_in` - Amount of the input token to deposit
+    /// * `min_lp_out` - Minimum amount of lp tokens to receive, prevents excessive slippage
+    ///
+    pub fn deposit_single_sided<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, DepositSingleSided<'info>>,
+        input_mint: Pubkey,
+        amount_in: u64,
+        min_lp_out: u64,
+    ) -> Result<()> {
+        instructions::deposit_single_sided(ctx, input_mint, amount_in, min_lp_out)
+    }
+
     /// Withdraw lp for token0 ande token1
     ///
     /// # Arguments
     ///
-    /// * `ctx`- The context of accounts
+    /// * `ctx`- The context of accounts, remaining accounts are `[reward_info, owner_user_reward_info]` for each open reward of the pool and each reward closed since the last lp change of the positions, the missing user reward infos are created
     /// * `lp_token_amount` - Amount of pool tokens to burn. User receives an output of token a and b based on the percentage of the pool tokens that are returned.
     /// * `minimum_token_0_amount` -  Minimum amount of token 0 to receive, prevents excessive slippage
     /// * `minimum_token_1_amount` -  Minimum amount of token 1 to receive, prevents excessive slippage
     ///
-    pub fn withdraw(
-        ctx: Context<Withdraw>,
+    pub fn withdraw<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Withdraw<'info>>,
         lp_token_amount: u64,
         minimum_token_0_amount: u64,
         minimum_token_1_amount: u64,
@@ -246,6 +408,125 @@ pub mod gamma {
         )
     }
 
+    /// Withdraw lp into a single token, the share of the other token is swapped through the pool
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts, remaining accounts are `[reward_info, owner_user_reward_info]` for each open reward of the pool and each reward closed since the last lp change of the positions, the missing user reward infos are created
+    /// * `lp_token_amount` - Amount of pool tokens to burn
+    /// * `output_mint` - Mint of the token to receive, must be token_0 or token_1 of the pool
+    /// * `minimum_amount_out` - Minimum amount of the output token to receive, prevents excessive slippage
+    ///
+    pub fn withdraw_single_sided<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, WithdrawSingleSided<'info>>,
+        lp_token_amount: u64,
+        output_mint: Pubkey,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        instructions::withdraw_single_sided(ctx, lp_token_amount, output_mint, minimum_amount_out)
+    }
+
+    /// Transfer lp tokens from the owner's position to another wallet's position
+    /// Settles the rewards of both positions before moving the liquidity
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts, remaining accounts are `[reward_info, owner_user_reward_info, destination_user_reward_info]` for each open reward of the pool and each reward closed since the last lp change of the positions
+    /// * `lp_token_amount` - Amount of lp tokens to transfer
+    ///
+    pub fn transfer_lp<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, TransferLp<'info>>,
+        lp_token_amount: u64,
+    ) -> Result<()> {
+        instructions::transfer_lp(ctx, lp_token_amount)
+    }
+
+    /// Wrap lp tokens of the owner's position into the pool's spl receipt token
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts, remaining accounts are `[reward_info, owner_user_reward_info]` for each open reward of the pool and each reward closed since the last lp change of the positions
+    /// * `lp_token_amount` - Amount of lp tokens to wrap, minted 1:1 as receipt tokens
+    ///
+    pub fn wrap_lp<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, WrapLp<'info>>,
+        lp_token_amount: u64,
+    ) -> Result<()> {
+        instructions::wrap_lp(ctx, lp_token_amount)
+    }
+
+    /// Unwrap the pool's spl receipt token back into lp tokens of the owner's position
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts, remaining accounts are `[reward_info, owner_user_reward_info]` for each open reward of the pool and each reward closed since the last lp change of the positions
+    /// * `lp_token_amount` - Amount of receipt tokens to burn, credited 1:1 as lp tokens
+    ///
+    pub fn unwrap_lp<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, UnwrapLp<'info>>,
+        lp_token_amount: u64,
+    ) -> Result<()> {
+        instructions::unwrap_lp(ctx, lp_token_amount)
+    }
+
+    /// Lock lp tokens of the owner's position until `unlock_timestamp`
+    /// Locked lp tokens can not be withdrawn or transferred but keep earning fees and rewards,
+    /// locking again adds to the lock and can only push the unlock time back
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `lp_token_amount` - Amount of lp tokens to lock
+    /// * `unlock_timestamp` - Time the lp tokens can be unlocked, `PERMANENT_LOCK` to never unlock them
+    ///
+    pub fn lock_liquidity(
+        ctx: Context<LockLiquidity>,
+        lp_token_amount: u64,
+        unlock_timestamp: u64,
+    ) -> Result<()> {
+        instructions::lock_liquidity(ctx, lp_token_amount, unlock_timestamp)
+    }
+
+    /// Unlock all the lp tokens of the owner's lock once the unlock time is reached
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    ///
+    pub fn unlock_liquidity(ctx: Context<UnlockLiquidity>) -> Result<()> {
+        instructions::unlock_liquidity(ctx)
+    }
+
+    /// Vest lp tokens of the funder's position to the position of a beneficiary
+    /// The vesting lp tokens earn fees and rewards for the beneficiary, they vest linearly over
+    /// `duration` seconds and none can be released before the cliff
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts, remaining accounts are `[reward_info, funder_user_reward_info, beneficiary_user_reward_info]` for each open reward of the pool and each reward closed since the last lp change of the positions
+    /// * `lp_token_amount` - Amount of lp tokens to vest
+    /// * `cliff_seconds` - Seconds from now before the first lp tokens can be released
+    /// * `duration` - Seconds from now until all the lp tokens are vested
+    ///
+    pub fn create_vesting_lock<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, CreateVestingLock<'info>>,
+        lp_token_amount: u64,
+        cliff_seconds: u64,
+        duration: u64,
+    ) -> Result<()> {
+        instructions::create_vesting_lock(ctx, lp_token_amount, cliff_seconds, duration)
+    }
+
+    /// Release the vested lp tokens of one of the beneficiary's vesting locks into their position
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    ///
+    pub fn release_vested_lp(ctx: Context<ReleaseVestedLp>) -> Result<()> {
+        instructions::release_vested_lp(ctx)
+    }
+
     /// Swap the tokens in the pool base input amount
     ///
     /// # Arguments
@@ -278,9 +559,116 @@ pub mod gamma {
         instructions::swap_base_output(ctx, max_amount_in, amount_out)
     }
 
+    /// Swap the tokens in the pool base input amount, until the pool price reaches a limit
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `amount_in` -  input amount to transfer, only part of it is swapped if the limit is reached
+    /// * `minimum_amount_out` -  Minimum amount of output token for the whole `amount_in`
+    /// * `max_price_x32` - Highest price of the output token in input token the pool may reach
+    /// * `deadline` - Unix timestamp after which the swap fails
+    ///
+    pub fn swap_base_input_v2<'c, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Swap<'info>>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        max_price_x32: u128,
+        deadline: u64,
+    ) -> Result<()> {
+        instructions::swap_base_input_v2(
+            ctx,
+            amount_in,
+            minimum_amount_out,
+            max_price_x32,
+            deadline,
+        )
+    }
+
+    /// Swap the tokens in the pool base output amount, until the pool price reaches a limit
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `max_amount_in` -  input amount prevents excessive slippage
+    /// * `amount_out` -  amount of output token, only part of it is filled if the limit is reached
+    /// * `max_price_x32` - Highest price of the output token in input token the pool may reach
+    /// * `deadline` - Unix timestamp after which the swap fails
+    ///
+    pub fn swap_base_output_v2<'c, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, Swap<'info>>,
+        max_amount_in: u64,
+        amount_out: u64,
+        max_price_x32: u128,
+        deadline: u64,
+    ) -> Result<()> {
+        instructions::swap_base_output_v2(ctx, max_amount_in, amount_out, max_price_x32, deadline)
+    }
+
+    /// Swap the tokens in the pool base output amount, paying the input after a callback
+    /// The output is sent first, then `callback_program` is invoked with the remaining accounts
+    /// and `callback_data`, and the input is transferred from the payer once it returns
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts, remaining accounts are passed to the callback
+    /// * `max_amount_in` -  input amount prevents excessive slippage
+    /// * `amount_out` -  amount of output token
+    /// * `callback_data` - instruction data of the callback
+    ///
+    pub fn flash_swap_base_output<'c, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, FlashSwap<'info>>,
+        max_amount_in: u64,
+        amount_out: u64,
+        callback_data: Vec<u8>,
+    ) -> Result<()> {
+        instructions::flash_swap_base_output(ctx, max_amount_in, amount_out, callback_data)
+    }
+
+    /// Borrow tokens from a vault of the pool, the loan must be repaid by a `flash_repay`
+    /// later in the same transaction. Swaps, deposits and withdrawals of the pool are blocked
+    /// until then
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `amount` - Amount of tokens to borrow from the vault
+    ///
+    pub fn flash_borrow(ctx: Context<FlashBorrow>, amount: u64) -> Result<()> {
+        instructions::flash_borrow(ctx, amount)
+    }
+
+    /// Repay the open flash loan of the pool with the flash loan fee of the amm config
+    /// The fee is split with the protocol and fund fee rates, the rest goes to the lps
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    ///
+    pub fn flash_repay(ctx: Context<FlashRepay>) -> Result<()> {
+        instructions::flash_repay(ctx)
+    }
+
+    /// Swap the tokens through several pools base input amount, the output of every pool is the input of the next
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts, remaining accounts are `[amm_config, pool_state, observation_state, input_vault, output_vault, output_token_mint]` for each pool of the route
+    /// * `amount_in` -  input amount to transfer into the first pool
+    /// * `minimum_amount_out` -  Minimum amount of output token from the last pool, prevents excessive slippage
+    ///
+    pub fn swap_route<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, SwapRoute<'info>>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        instructions::swap_route(ctx, amount_in, minimum_amount_out)
+    }
+
     /// Create rewards for the pool
     /// Initializes a new reward info account and a reward vault account
     /// Transfers the rewards to the reward vault
+    /// A pool can have up to `MAX_REWARDS_PER_POOL` rewards, which all lp changes must settle
     ///
     /// # Arguments
     ///
@@ -298,6 +686,43 @@ pub mod gamma {
         instructions::create_rewards(ctx, start_time, end_time, reward_amount)
     }
 
+    /// Add rewards to a running reward
+    /// The rewards not emitted yet and the added rewards are emitted evenly until the end of the reward
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `reward_amount` - The amount of rewards to add
+    ///
+    pub fn fund_rewards(ctx: Context<FundRewards>, reward_amount: u64) -> Result<()> {
+        instructions::fund_rewards(ctx, reward_amount)
+    }
+
+    /// Extend the end time of a reward, also restarts a reward that already ended
+    /// The rewards not emitted yet are emitted evenly until the new end time
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `end_time` - The new end time of the reward
+    ///
+    pub fn extend_rewards(ctx: Context<ExtendRewards>, end_time: u64) -> Result<()> {
+        instructions::extend_rewards(ctx, end_time)
+    }
+
+    /// Close a reward, ending it now if it is still running, and free its slot in the pool
+    /// Returns the rewards that will not be disbursed to the reward provider, including the rewards
+    /// accrued while no lp tokens were eligible. Disbursed rewards can still be claimed, by the
+    /// positions whose lp tokens did not change since the close
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn close_rewards(ctx: Context<CloseRewards>) -> Result<()> {
+        instructions::close_rewards(ctx)
+    }
+
     /// Claim rewards for the user
     /// Transfers the amount of tokens calculated for the user to their reward token account
     ///
@@ -309,7 +734,21 @@ pub mod gamma {
         instructions::claim_rewards(ctx)
     }
 
+    /// Calculate and claim many rewards of the user at once, across pools
+    /// Emits a `RewardsClaimedEvent` for each reward with claimable tokens
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts, remaining accounts are `[reward_info, user_reward_info, reward_vault, user_token_account, reward_mint, pool_state, user_pool_liquidity]` for each reward to claim
+    ///
+    pub fn claim_all_rewards<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, ClaimAllRewards<'info>>,
+    ) -> Result<()> {
+        instructions::claim_all_rewards(ctx)
+    }
+
     /// Calculate rewards for the user
+    /// Brings the reward per lp of the reward up to date and checkpoints the rewards of the user
     ///
     /// * `ctx` - The context of accounts
     ///
@@ -317,11 +756,64 @@ pub mod gamma {
         instructions::calculate_rewards(ctx)
     }
 
+    /// Migrate a reward created before the rewards were accrued per lp token. Permissionless
+    /// The rewards emitted so far are disbursed to the current lp tokens and a reward still running
+    /// takes a slot of the pool, so lp changes settle it from now on
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn migrate_reward_info(ctx: Context<MigrateRewardInfo>) -> Result<()> {
+        instructions::migrate_reward_info(ctx)
+    }
+
+    /// Migrate a user reward created before the rewards were accrued per lp token. Permissionless
+    /// The reward must be migrated first. Lp changes migrate the user rewards they settle on their own
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn migrate_user_reward_info(ctx: Context<MigrateUserRewardInfo>) -> Result<()> {
+        instructions::migrate_user_reward_info(ctx)
+    }
+
+    /// Query the time weighted average prices of the pool
+    /// Returns both token TWAPs and the range of the average prices between the observations of the
+    /// window as return data
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `window_seconds` - The length of the window ending at the current block timestamp
+    ///
+    pub fn get_twap(ctx: Context<GetTwap>, window_seconds: u64) -> Result<states::Twap> {
+        instructions::get_twap(ctx, window_seconds)
+    }
+
+    /// Grow the observation account of the pool to hold more observations
+    /// The payer pays the rent of the grown account, the new observations are used once the
+    /// observation index wraps around
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `observation_cardinality_next` - The number of observations to hold, at most
+    ///   MAX_OBSERVATION_CARDINALITY, the account grows by at most 256 observations per instruction
+    ///
+    pub fn increase_observation_cardinality(
+        ctx: Context<IncreaseObservationCardinality>,
+        observation_cardinality_next: u16,
+    ) -> Result<()> {
+        instructions::increase_observation_cardinality(ctx, observation_cardinality_next)
+    }
+
     /********************* Migration Instructions *********************/
 
     /// Migrate from Meteora Dlmm to Gamma
 
-    pub fn migrate_meteora_dlmm_to_gamma<'a, 'b, 'c, 'info>(
+    pub fn migrate_meteora_dlmm_to_gamma<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, MeteoraDlmmToGamma<'info>>,
         bin_liquidity_reduction: Vec<crate::external::dlmm::lb_clmm::types::BinLiquidityReduction>,
         maximum_token_0_amount: u64,
@@ -337,7 +829,7 @@ pub mod gamma {
 
     /// Migrate from Orca Whirlpool to Gamma for token 2022
 
-    pub fn migrate_orca_whirlpool_to_gamma_v2<'a, 'b, 'c, 'info>(
+    pub fn migrate_orca_whirlpool_to_gamma_v2<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, OrcaWhirlpoolToGammaV2<'info>>,
         liquidity_amount: u128,
         token_min_a: u64,
@@ -361,7 +853,7 @@ pub mod gamma {
 
     /// Migrate from Orca Whirlpool to Gamma for simple spl tokens
 
-    pub fn migrate_orca_whirlpool_to_gamma<'a, 'b, 'c, 'info>(
+    pub fn migrate_orca_whirlpool_to_gamma<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, OrcaWhirlpoolToGamma<'info>>,
         liquidity_amount: u128,
         token_min_a: u64,
@@ -381,7 +873,7 @@ pub mod gamma {
 
     /// Migrate from Raydium Clmm to Gamma
 
-    pub fn migrate_raydium_clmm_to_gamma<'a, 'b, 'c, 'info>(
+    pub fn migrate_raydium_clmm_to_gamma<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, RaydiumClmmToGamma<'info>>,
         liquidity: u128,
         amount_0_min: u64,
@@ -401,7 +893,7 @@ pub mod gamma {
 
     /// Migrate from Raydium Clmm to Gamma for token 2022
 
-    pub fn migrate_raydium_clmm_to_gamma_v2<'a, 'b, 'c, 'info>(
+    pub fn migrate_raydium_clmm_to_gamma_v2<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, RaydiumClmmToGammaV2<'info>>,
         liquidity: u128,
         amount_0_min: u64,
@@ -421,7 +913,7 @@ pub mod gamma {
 
     /// Migrate from Raydium Cpmm Swap to Gamma
 
-    pub fn migrate_raydium_cp_swap_to_gamma<'a, 'b, 'c, 'info>(
+    pub fn migrate_raydium_cp_swap_to_gamma<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, RaydiumCpSwapToGamma<'info>>,
         lp_token_amount_withdraw: u64,
         minimum_token_0_amount: u64,
@@ -2,7 +2,7 @@ use crate::external::dlmm::lb_clmm::types::BinLiquidityReduction;
 use crate::{
     calculate_gamma_lp_tokens,
     instructions::deposit::{deposit_to_gamma_pool, Deposit},
-    states::{MigrationEvent, PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
+    states::{MigrationEvent, PartnerInfo, PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
 };
 use anchor_lang::prelude::*;
 use anchor_spl::{
@@ -136,10 +136,17 @@ pub struct MeteoraDlmmToGamma<'info> {
         address = gamma_token_1_vault.mint
     )]
     pub gamma_vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// To create the missing user reward infos of the owner, can be left out by pools without rewards
+    pub system_program: Option<Program<'info, System>>,
+
+    /// Ledger of the partner the position is linked with, required when the position has a partner
+    #[account(mut)]
+    pub gamma_partner_info: Option<Box<Account<'info, PartnerInfo>>>,
 }
 
-pub fn meteora_dlmm_to_gamma(
-    ctx: Context<MeteoraDlmmToGamma>,
+pub fn meteora_dlmm_to_gamma<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, MeteoraDlmmToGamma<'info>>,
     bin_liquidity_reduction: Vec<BinLiquidityReduction>,
     maximum_token_0_amount: u64,
     maximum_token_1_amount: u64,
@@ -198,6 +205,7 @@ pub fn meteora_dlmm_to_gamma(
         authority: ctx.accounts.gamma_authority.clone(),
         pool_state: ctx.accounts.gamma_pool_state.clone(),
         user_pool_liquidity: ctx.accounts.gamma_user_pool_liquidity.clone(),
+        partner_info: ctx.accounts.gamma_partner_info.clone(),
         token_0_account: ctx.accounts.gamma_token_0_account.clone(),
         token_1_account: ctx.accounts.gamma_token_1_account.clone(),
         token_0_vault: ctx.accounts.gamma_token_0_vault.clone(),
@@ -206,10 +214,12 @@ pub fn meteora_dlmm_to_gamma(
         token_program_2022: ctx.accounts.token_program_2022.clone(),
         vault_0_mint: ctx.accounts.gamma_vault_0_mint.clone(),
         vault_1_mint: ctx.accounts.gamma_vault_1_mint.clone(),
+        system_program: ctx.accounts.system_program.clone(),
     };
 
     deposit_to_gamma_pool(
         &mut deposit_accounts,
+        ctx.remaining_accounts,
         gamma_lp_tokens as u64,
         maximum_token_0_amount,
         maximum_token_1_amount,
@@ -6,7 +6,7 @@ pub use meteora::*;
 pub use orca::*;
 pub use raydium::*;
 
-use crate::{curve::ConstantProductCurve, error::GammaError, states::PoolState};
+use crate::{curve::CurveCalculator, error::GammaError, states::PoolState};
 use anchor_lang::prelude::*;
 
 pub fn calculate_gamma_lp_tokens(
@@ -16,14 +16,14 @@ pub fn calculate_gamma_lp_tokens(
 ) -> Result<u128> {
     let (total_token_0_amount, total_token_1_amount) = pool_state.vault_amount_without_fee()?;
 
-    let gamma_lp_tokens_0 = ConstantProductCurve::token_0_to_lp_tokens(
+    let gamma_lp_tokens_0 = CurveCalculator::token_0_to_lp_tokens(
         u128::from(token_0_amount_withdrawn),
         u128::from(total_token_0_amount),
         u128::from(pool_state.lp_supply),
     )
     .ok_or(GammaError::InvalidLpTokenAmount)?;
 
-    let gamma_lp_tokens_1 = ConstantProductCurve::token_1_to_lp_tokens(
+    let gamma_lp_tokens_1 = CurveCalculator::token_1_to_lp_tokens(
         u128::from(token_1_amount_withdrawn),
         u128::from(total_token_1_amount),
         u128::from(pool_state.lp_supply),
@@ -1,7 +1,7 @@
 use crate::{
     calculate_gamma_lp_tokens,
     instructions::deposit::{deposit_to_gamma_pool, Deposit},
-    states::{MigrationEvent, PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
+    states::{MigrationEvent, PartnerInfo, PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
 };
 use anchor_lang::prelude::*;
 use anchor_spl::{
@@ -137,13 +137,20 @@ pub struct OrcaWhirlpoolToGamma<'info> {
         address = gamma_token_1_vault.mint
     )]
     pub gamma_vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// To create the missing user reward infos of the owner, can be left out by pools without rewards
+    pub system_program: Option<Program<'info, System>>,
+
+    /// Ledger of the partner the position is linked with, required when the position has a partner
+    #[account(mut)]
+    pub gamma_partner_info: Option<Box<Account<'info, PartnerInfo>>>,
     // remaining accounts
     // - accounts for transfer hook program of token_mint_a
     // - accounts for transfer hook program of token_mint_b
 }
 
-pub fn orca_whirlpool_to_gamma<'info>(
-    ctx: Context<'_, '_, '_, 'info, OrcaWhirlpoolToGamma<'info>>,
+pub fn orca_whirlpool_to_gamma<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, OrcaWhirlpoolToGamma<'info>>,
     liquidity_amount: u128,
     token_min_a: u64,
     token_min_b: u64,
@@ -202,6 +209,7 @@ pub fn orca_whirlpool_to_gamma<'info>(
         authority: ctx.accounts.gamma_authority.clone(),
         pool_state: ctx.accounts.gamma_pool_state.clone(),
         user_pool_liquidity: ctx.accounts.gamma_user_pool_liquidity.clone(),
+        partner_info: ctx.accounts.gamma_partner_info.clone(),
         token_0_account: ctx.accounts.gamma_token_0_account.clone(),
         token_1_account: ctx.accounts.gamma_token_1_account.clone(),
         token_0_vault: ctx.accounts.gamma_token_0_vault.clone(),
@@ -210,10 +218,12 @@ pub fn orca_whirlpool_to_gamma<'info>(
         token_program_2022: ctx.accounts.token_program_2022.clone(),
         vault_0_mint: ctx.accounts.gamma_vault_0_mint.clone(),
         vault_1_mint: ctx.accounts.gamma_vault_1_mint.clone(),
+        system_program: ctx.accounts.system_program.clone(),
     };
 
     deposit_to_gamma_pool(
         &mut deposit_accounts,
+        ctx.remaining_accounts,
         gamma_lp_tokens as u64,
         maximum_token_0_amount,
         maximum_token_1_amount,
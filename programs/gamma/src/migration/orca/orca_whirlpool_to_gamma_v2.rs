@@ -1,8 +1,8 @@
 use crate::external::whirlpool::whirlpool::types::RemainingAccountsInfo;
 use crate::{
     calculate_gamma_lp_tokens,
-    instructions::deposit::{deposit_to_gamma_pool, Deposit},
-    states::{MigrationEvent, PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
+    instructions::deposit::{deposit_to_gamma_pool, split_reward_accounts, Deposit},
+    states::{MigrationEvent, PartnerInfo, PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
 };
 use anchor_lang::prelude::*;
 use anchor_spl::{
@@ -138,13 +138,21 @@ pub struct OrcaWhirlpoolToGammaV2<'info> {
         address = gamma_token_1_vault.mint
     )]
     pub gamma_vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// To create the missing user reward infos of the owner, can be left out by pools without rewards
+    pub system_program: Option<Program<'info, System>>,
+
+    /// Ledger of the partner the position is linked with, required when the position has a partner
+    #[account(mut)]
+    pub gamma_partner_info: Option<Box<Account<'info, PartnerInfo>>>,
     // remaining accounts
     // - accounts for transfer hook program of token_mint_a
     // - accounts for transfer hook program of token_mint_b
+    // - reward_info and owner user_reward_info of each open reward of the gamma pool
 }
 
-pub fn orca_whirlpool_to_gamma_v2<'info>(
-    ctx: Context<'_, '_, '_, 'info, OrcaWhirlpoolToGammaV2<'info>>,
+pub fn orca_whirlpool_to_gamma_v2<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, OrcaWhirlpoolToGammaV2<'info>>,
     liquidity_amount: u128,
     token_min_a: u64,
     token_min_b: u64,
@@ -152,6 +160,9 @@ pub fn orca_whirlpool_to_gamma_v2<'info>(
     maximum_token_0_amount: u64,
     maximum_token_1_amount: u64,
 ) -> Result<()> {
+    // The reward accounts of the gamma pool come after the accounts of the source program
+    let (remaining_accounts, reward_accounts) =
+        split_reward_accounts(ctx.remaining_accounts, &ctx.accounts.gamma_pool_state)?;
     let user_token0_balance_before = ctx.accounts.gamma_token_0_account.amount;
     let user_token1_balance_before = ctx.accounts.gamma_token_1_account.amount;
     // Withdraw from Orca Whirlpool
@@ -177,7 +188,7 @@ pub fn orca_whirlpool_to_gamma_v2<'info>(
     };
 
     let cpi_ctx = CpiContext::new(ctx.accounts.whirlpool_program.to_account_info(), accounts)
-        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        .with_remaining_accounts(remaining_accounts.to_vec());
     crate::external::whirlpool::whirlpool::cpi::decrease_liquidity_v2(
         cpi_ctx,
         liquidity_amount,
@@ -209,6 +220,7 @@ pub fn orca_whirlpool_to_gamma_v2<'info>(
         authority: ctx.accounts.gamma_authority.clone(),
         pool_state: ctx.accounts.gamma_pool_state.clone(),
         user_pool_liquidity: ctx.accounts.gamma_user_pool_liquidity.clone(),
+        partner_info: ctx.accounts.gamma_partner_info.clone(),
         token_0_account: ctx.accounts.gamma_token_0_account.clone(),
         token_1_account: ctx.accounts.gamma_token_1_account.clone(),
         token_0_vault: ctx.accounts.gamma_token_0_vault.clone(),
@@ -217,10 +229,12 @@ pub fn orca_whirlpool_to_gamma_v2<'info>(
         token_program_2022: ctx.accounts.token_program_2022.clone(),
         vault_0_mint: ctx.accounts.gamma_vault_0_mint.clone(),
         vault_1_mint: ctx.accounts.gamma_vault_1_mint.clone(),
+        system_program: ctx.accounts.system_program.clone(),
     };
 
     deposit_to_gamma_pool(
         &mut deposit_accounts,
+        reward_accounts,
         gamma_lp_tokens as u64,
         maximum_token_0_amount,
         maximum_token_1_amount,
@@ -1,7 +1,7 @@
 use crate::{
     calculate_gamma_lp_tokens,
-    instructions::deposit::{deposit_to_gamma_pool, Deposit},
-    states::{MigrationEvent, PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
+    instructions::deposit::{deposit_to_gamma_pool, split_reward_accounts, Deposit},
+    states::{MigrationEvent, PartnerInfo, PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
 };
 use anchor_lang::prelude::*;
 use anchor_spl::{
@@ -135,6 +135,13 @@ pub struct RaydiumClmmToGammaV2<'info> {
 
     /// Token program 2022
     pub token_program_2022: Program<'info, Token2022>,
+
+    /// To create the missing user reward infos of the owner, can be left out by pools without rewards
+    pub system_program: Option<Program<'info, System>>,
+
+    /// Ledger of the partner the position is linked with, required when the position has a partner
+    #[account(mut)]
+    pub gamma_partner_info: Option<Box<Account<'info, PartnerInfo>>>,
     // remaining account
     // #[account(
     //     seeds = [
@@ -145,9 +152,10 @@ pub struct RaydiumClmmToGammaV2<'info> {
     // )]
     // pub tick_array_bitmap: AccountLoader<'info, TickArrayBitmapExtension>,
     // pub tick_array_bitmap: UncheckedAccount<'info>,
+    // followed by the reward_info and owner user_reward_info of each open reward of the gamma pool
 }
 
-pub fn raydium_clmm_to_gamma_v2<'a, 'b, 'c, 'info>(
+pub fn raydium_clmm_to_gamma_v2<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, RaydiumClmmToGammaV2<'info>>,
     liquidity: u128,
     amount_0_min: u64,
@@ -155,6 +163,9 @@ pub fn raydium_clmm_to_gamma_v2<'a, 'b, 'c, 'info>(
     maximum_token_0_amount: u64,
     maximum_token_1_amount: u64,
 ) -> Result<()> {
+    // The reward accounts of the gamma pool come after the accounts of the source program
+    let (remaining_accounts, reward_accounts) =
+        split_reward_accounts(ctx.remaining_accounts, &ctx.accounts.gamma_pool_state)?;
     let user_token0_balance_before = ctx.accounts.gamma_token_0_account.amount;
     let user_token1_balance_before = ctx.accounts.gamma_token_1_account.amount;
 
@@ -187,7 +198,7 @@ pub fn raydium_clmm_to_gamma_v2<'a, 'b, 'c, 'info>(
         ctx.accounts.raydium_clmm_program.to_account_info(),
         cpi_accounts,
     )
-    .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+    .with_remaining_accounts(remaining_accounts.to_vec());
     crate::external::raydium_clmm::amm_v3::cpi::decrease_liquidity_v2(
         cpi_context,
         liquidity,
@@ -218,6 +229,7 @@ pub fn raydium_clmm_to_gamma_v2<'a, 'b, 'c, 'info>(
         authority: ctx.accounts.gamma_authority.clone(),
         pool_state: ctx.accounts.gamma_pool_state.clone(),
         user_pool_liquidity: ctx.accounts.gamma_user_pool_liquidity.clone(),
+        partner_info: ctx.accounts.gamma_partner_info.clone(),
         token_0_account: ctx.accounts.gamma_token_0_account.clone(),
         token_1_account: ctx.accounts.gamma_token_1_account.clone(),
         token_0_vault: ctx.accounts.gamma_token_0_vault.clone(),
@@ -226,10 +238,12 @@ pub fn raydium_clmm_to_gamma_v2<'a, 'b, 'c, 'info>(
         token_program_2022: ctx.accounts.token_program_2022.clone(),
         vault_0_mint: ctx.accounts.gamma_vault_0_mint.clone(),
         vault_1_mint: ctx.accounts.gamma_vault_1_mint.clone(),
+        system_program: ctx.accounts.system_program.clone(),
     };
 
     deposit_to_gamma_pool(
         &mut deposit_accounts,
+        reward_accounts,
         gamma_lp_tokens as u64,
         maximum_token_0_amount,
         maximum_token_1_amount,
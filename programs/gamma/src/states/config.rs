@@ -32,8 +32,10 @@ pub struct AmmConfig {
     pub max_open_time: u64,
     // This account is not a multisig and is allowed to update certain config values on pools
     pub secondary_admin: Pubkey,
+    /// The flash loan fee, denominated in hundredths of bip (10^-6)
+    pub flash_loan_fee_rate: u64,
     /// padding
-    pub padding: [u64; 7],
+    pub padding: [u64; 6],
 }
 
 impl AmmConfig {
@@ -49,6 +51,7 @@ pub fn validate_config_rates(amm_config: &AmmConfig) -> Result<()> {
         FEE_RATE_DENOMINATOR_VALUE,
         amm_config.fund_fee_rate + amm_config.protocol_fee_rate
     );
+    require_gt!(FEE_RATE_DENOMINATOR_VALUE, amm_config.flash_loan_fee_rate);
 
     Ok(())
 }
@@ -63,3 +63,47 @@ pub struct MigrationEvent {
     pub token_1_amount_withdrawn: u64,
     pub lp_tokens_migrated: u128,
 }
+
+/// Emitted for each reward claimed by `claim_all_rewards`
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+#[derive(Clone, Debug)]
+pub struct RewardsClaimedEvent {
+    #[index]
+    pub pool_id: Pubkey,
+    pub reward_info: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    /// amount sent from the reward vault, before transfer fees
+    pub amount: u64,
+}
+
+/// Emitted when a partner claims its share of the protocol fees of a pool
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+#[derive(Clone, Debug)]
+pub struct PartnerFeesClaimedEvent {
+    #[index]
+    pub pool_id: Pubkey,
+    pub partner_id: u64,
+    /// token_0 amount sent from the pool vault, before transfer fees
+    pub amount_0: u64,
+    /// token_1 amount sent from the pool vault, before transfer fees
+    pub amount_1: u64,
+}
+
+/// Emitted when a flash loan is repaid
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+#[derive(Clone, Debug)]
+pub struct FlashLoanEvent {
+    #[index]
+    pub pool_id: Pubkey,
+    pub mint: Pubkey,
+    /// amount lent from the pool vault, before transfer fees
+    pub amount: u64,
+    /// fee paid on top of the amount, including the protocol and fund fees
+    pub fee: u64,
+    pub protocol_fee: u64,
+    pub fund_fee: u64,
+}
@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+
+use crate::error::GammaError;
+
+pub const LIQUIDITY_LOCK_SEED: &str = "liquidity-lock";
+
+/// `unlock_timestamp` of a lock that never unlocks
+pub const PERMANENT_LOCK: u64 = u64::MAX;
+
+/// Lp tokens of a position that can not be withdrawn before `unlock_timestamp`.
+/// The locked lp tokens stay in the pool, so they keep earning trading fees, and they keep
+/// earning rewards for the owner of the position through `UserPoolLiquidity.lp_tokens_locked`.
+#[account]
+#[derive(Default, Debug)]
+pub struct LiquidityLock {
+    pub owner: Pubkey,
+    pub pool_state: Pubkey,
+    pub lp_tokens_locked: u64,
+    /// Time the lp tokens can be unlocked, `PERMANENT_LOCK` if they can never be
+    pub unlock_timestamp: u64,
+    pub bump: u8,
+    pub padding: [u8; 32],
+}
+
+impl LiquidityLock {
+    pub const LEN: usize = 8 + 32 * 2 + 8 * 2 + 1 + 32;
+
+    /// Adds `lp_token_amount` to the lock, the unlock time can only be pushed back
+    pub fn lock(&mut self, lp_token_amount: u64, unlock_timestamp: u64) -> Result<()> {
+        let time_now = Clock::get()?.unix_timestamp as u64;
+        require_gt!(unlock_timestamp, time_now, GammaError::InvalidUnlockTime);
+        require_gte!(
+            unlock_timestamp,
+            self.unlock_timestamp,
+            GammaError::InvalidUnlockTime
+        );
+
+        self.lp_tokens_locked = self
+            .lp_tokens_locked
+            .checked_add(lp_token_amount)
+            .ok_or(GammaError::MathOverflow)?;
+        self.unlock_timestamp = unlock_timestamp;
+        Ok(())
+    }
+
+    /// Releases all the lp tokens of the lock once the unlock time is reached
+    pub fn unlock(&mut self) -> Result<u64> {
+        let time_now = Clock::get()?.unix_timestamp as u64;
+        if self.unlock_timestamp == PERMANENT_LOCK || time_now < self.unlock_timestamp {
+            return err!(GammaError::LiquidityLocked);
+        }
+
+        let lp_tokens_unlocked = self.lp_tokens_locked;
+        self.lp_tokens_locked = 0;
+        Ok(lp_tokens_unlocked)
+    }
+}
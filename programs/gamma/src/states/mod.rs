@@ -1,15 +1,21 @@
 pub mod config;
 pub mod events;
+pub mod liquidity_lock;
 pub mod oracle;
+pub mod partner;
 pub mod pool;
 pub mod reward_info;
 pub mod user_pool_liquidity;
 pub mod user_reward_info;
+pub mod vesting_lock;
 
 pub use config::*;
 pub use events::*;
+pub use liquidity_lock::*;
 pub use oracle::*;
+pub use partner::*;
 pub use pool::*;
 pub use reward_info::*;
 pub use user_pool_liquidity::*;
 pub use user_reward_info::*;
+pub use vesting_lock::*;
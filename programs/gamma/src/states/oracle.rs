@@ -2,13 +2,18 @@ use crate::error::GammaError;
 /// Oracle provides price data useful for a wide variety of system designs
 ///
 use anchor_lang::prelude::*;
+use std::cell::RefMut;
 /// Seed to derive account address and signature
 pub const OBSERVATION_SEED: &str = "observation";
 // Number of ObservationState element
 pub const OBSERVATION_NUM: usize = 100;
+/// The maximum number of observations `increase_observation_cardinality` can grow the account to
+pub const MAX_OBSERVATION_CARDINALITY: u16 = 1000;
 
 /// The duration of observation update in seconds
 pub const OBSERVATION_UPDATE_DURATION_DEFAULT: u64 = 15;
+/// The maximum duration of observation update in seconds
+pub const MAX_OBSERVATION_UPDATE_DURATION: u64 = 600;
 
 /// The element of observations in ObservationState
 #[zero_copy(unsafe)]
@@ -35,10 +40,16 @@ pub struct ObservationState {
     /// The most recently updated index of the observations array
     pub observation_index: u16,
     pub pool_id: Pubkey,
-    /// observation array
+    /// observation array, the observations past OBSERVATION_NUM follow the struct in the account data
     pub observations: [Observation; OBSERVATION_NUM],
+    /// The minimum number of seconds between two observations, 0 for OBSERVATION_UPDATE_DURATION_DEFAULT
+    pub observation_update_duration: u64,
+    /// The number of observations in use, 0 for OBSERVATION_NUM
+    pub observation_cardinality: u16,
+    /// The number of observations the account has room for, in use once observation_index wraps around
+    pub observation_cardinality_next: u16,
     /// padding
-    pub padding: [u64; 4],
+    pub padding: [u8; 20],
 }
 
 impl Default for ObservationState {
@@ -49,69 +60,403 @@ impl Default for ObservationState {
             observation_index: 0,
             pool_id: Pubkey::default(),
             observations: [Observation::default(); OBSERVATION_NUM],
-            padding: [0u64; 4],
+            observation_update_duration: OBSERVATION_UPDATE_DURATION_DEFAULT,
+            observation_cardinality: OBSERVATION_NUM as u16,
+            observation_cardinality_next: OBSERVATION_NUM as u16,
+            padding: [0u8; 20],
         }
     }
 }
 
 impl ObservationState {
-    pub const LEN: usize = 8 + 1 + 2 + 32 + (OBSERVATION_NUM * Observation::LEN) + 4 * 8;
+    pub const LEN: usize = 8 + 1 + 2 + 32 + (OBSERVATION_NUM * Observation::LEN) + 8 + 2 + 2 + 20;
 
-    // Writes an oracle observation to the account, returning the next observation_index.
-    /// Writable at most once per 15 seconds. Index represents the most recently written element.
-    /// If the index is at the end of the allowable array length (100 - 1), the next index will turn to 0.
+    /// The size of an account holding `observation_cardinality` observations
+    pub fn len_with_cardinality(observation_cardinality: u16) -> usize {
+        Self::LEN
+            + (observation_cardinality as usize).saturating_sub(OBSERVATION_NUM) * Observation::LEN
+    }
+
+    pub fn observation_update_duration(&self) -> u64 {
+        if self.observation_update_duration == 0 {
+            OBSERVATION_UPDATE_DURATION_DEFAULT
+        } else {
+            self.observation_update_duration
+        }
+    }
+
+    pub fn observation_cardinality(&self) -> u16 {
+        if self.observation_cardinality == 0 {
+            OBSERVATION_NUM as u16
+        } else {
+            self.observation_cardinality
+        }
+    }
+
+    pub fn observation_cardinality_next(&self) -> u16 {
+        std::cmp::max(
+            self.observation_cardinality_next,
+            self.observation_cardinality(),
+        )
+    }
+
+    /// Borrows the data of a writable ObservationState account, to be read with `ObservationBufferMut::from_account_data`
+    pub fn try_borrow_mut_data<'a, 'info>(
+        account: &'a AccountLoader<'info, ObservationState>,
+    ) -> Result<RefMut<'a, &'info mut [u8]>> {
+        let account_info: &AccountInfo<'info> = account.as_ref();
+        require!(account_info.is_writable, ErrorCode::AccountNotMutable);
+        Ok(account_info.try_borrow_mut_data()?)
+    }
+}
+
+/// Time weighted average prices over a window and the range of the average prices between its
+/// observations, returned by `get_twap`.
+///
+/// The min and max prices are taken over the averages of each interval between two consecutive
+/// observations, not over spot prices, so moves reverted before the next observation do not show.
+/// The oldest interval is the one around the start of the window and counts whole, even the part
+/// before the window starts.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Twap {
+    /// Start of the window
+    pub start_time: u64,
+    /// End of the window, the block timestamp of the query
+    pub end_time: u64,
+    /// Time weighted average price of token_0 in token_1, Q32.32
+    pub token_0_twap_price_x32: u128,
+    /// Time weighted average price of token_1 in token_0, Q32.32
+    pub token_1_twap_price_x32: u128,
+    /// Lowest average price of token_0 over an interval between two observations of the window, Q32.32
+    pub token_0_min_price_x32: u128,
+    /// Highest average price of token_0 over an interval between two observations of the window, Q32.32
+    pub token_0_max_price_x32: u128,
+    /// Lowest average price of token_1 over an interval between two observations of the window, Q32.32
+    pub token_1_min_price_x32: u128,
+    /// Highest average price of token_1 over an interval between two observations of the window, Q32.32
+    pub token_1_max_price_x32: u128,
+}
+
+/// Average prices of token_0 and token_1 between two observations
+fn average_prices_x32(from: &Observation, to: &Observation) -> (u128, u128) {
+    let delta_time = u128::from(to.block_timestamp - from.block_timestamp);
+    (
+        to.cumulative_token_0_price_x32
+            .wrapping_sub(from.cumulative_token_0_price_x32)
+            / delta_time,
+        to.cumulative_token_1_price_x32
+            .wrapping_sub(from.cumulative_token_1_price_x32)
+            / delta_time,
+    )
+}
+
+/// The observations of an ObservationState account, including the ones appended to the account data
+/// by `increase_observation_cardinality`
+#[derive(Clone, Copy)]
+pub struct ObservationBuffer<'a> {
+    pub state: &'a ObservationState,
+    /// The observations from index OBSERVATION_NUM on
+    pub extra_observations: &'a [Observation],
+}
+
+/// The mutable counterpart of `ObservationBuffer`, used to write observations
+pub struct ObservationBufferMut<'a> {
+    pub state: &'a mut ObservationState,
+    /// The observations from index OBSERVATION_NUM on
+    pub extra_observations: &'a mut [Observation],
+}
+
+/// Splits the account data past the discriminator into the ObservationState and the extra observations
+fn split_account_data(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    require_gte!(
+        data.len(),
+        ObservationState::LEN,
+        ErrorCode::AccountDidNotDeserialize
+    );
+    require!(
+        data[..8] == ObservationState::DISCRIMINATOR,
+        ErrorCode::AccountDiscriminatorMismatch
+    );
+    Ok(data[8..].split_at(ObservationState::LEN - 8))
+}
+
+impl<'a> ObservationBuffer<'a> {
+    pub fn new(state: &'a ObservationState, extra_observations: &'a [Observation]) -> Self {
+        Self {
+            state,
+            extra_observations,
+        }
+    }
+
+    /// Reads the observations from the data of an ObservationState account
+    pub fn from_account_data(data: &'a [u8]) -> Result<Self> {
+        let (state_data, extra_data) = split_account_data(data)?;
+        let state: &ObservationState = bytemuck::from_bytes(state_data);
+        let extra_len = (state.observation_cardinality_next() as usize)
+            .saturating_sub(OBSERVATION_NUM)
+            * Observation::LEN;
+        require_gte!(
+            extra_data.len(),
+            extra_len,
+            ErrorCode::AccountDidNotDeserialize
+        );
+        Ok(Self::new(
+            state,
+            bytemuck::cast_slice(&extra_data[..extra_len]),
+        ))
+    }
+
+    /// Returns the observation at `index` of the ring buffer
+    pub fn get(&self, index: usize) -> Observation {
+        if index < OBSERVATION_NUM {
+            self.state.observations[index]
+        } else {
+            self.extra_observations[index - OBSERVATION_NUM]
+        }
+    }
+
+    /// Returns the written observations from the newest to the oldest, the elements never written end the walk.
+    ///
+    /// Callers stop at the start of their window, so the cost does not grow with the size of the ring buffer.
+    pub fn newest_first(&self) -> impl Iterator<Item = Observation> + '_ {
+        let observation_cardinality = self.state.observation_cardinality() as usize;
+        let observation_index = self.state.observation_index as usize;
+        (0..observation_cardinality)
+            .map(move |offset| {
+                self.get(
+                    (observation_index + observation_cardinality - offset)
+                        % observation_cardinality,
+                )
+            })
+            .take_while(|observation| observation.block_timestamp != 0)
+    }
+
+    /// Computes the time weighted average prices over the `window` seconds before `block_timestamp`.
+    ///
+    /// The prices since the newest observation are accumulated at the current spot prices of the pool,
+    /// and the start of the window is interpolated between the two observations around it.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_timestamp` - The end of the window
+    /// * `window` - The length of the window in seconds
+    /// * `token_0_price_x32` - The current spot price of token_0
+    /// * `token_1_price_x32` - The current spot price of token_1
+    ///
+    pub fn twap(
+        &self,
+        block_timestamp: u64,
+        window: u64,
+        token_0_price_x32: u128,
+        token_1_price_x32: u128,
+    ) -> Result<Twap> {
+        require_gt!(window, 0, GammaError::InvalidInput);
+        let start_time = block_timestamp
+            .checked_sub(window)
+            .ok_or(GammaError::InvalidInput)?;
+
+        let mut observations = self.newest_first().peekable();
+        let newest_observation = *observations
+            .peek()
+            .ok_or(GammaError::ObservationCoverageTooShort)?;
+        let newest_block_timestamp = newest_observation.block_timestamp;
+        let end_observation = if newest_block_timestamp < block_timestamp {
+            let delta_time = u128::from(block_timestamp - newest_block_timestamp);
+            Observation {
+                block_timestamp,
+                cumulative_token_0_price_x32: newest_observation
+                    .cumulative_token_0_price_x32
+                    .wrapping_add(
+                        token_0_price_x32
+                            .checked_mul(delta_time)
+                            .ok_or(GammaError::MathOverflow)?,
+                    ),
+                cumulative_token_1_price_x32: newest_observation
+                    .cumulative_token_1_price_x32
+                    .wrapping_add(
+                        token_1_price_x32
+                            .checked_mul(delta_time)
+                            .ok_or(GammaError::MathOverflow)?,
+                    ),
+            }
+        } else {
+            observations.next();
+            newest_observation
+        };
+
+        // Walk back from the end of the window to the newest observation at or before its start
+        let mut token_0_min_price_x32 = u128::MAX;
+        let mut token_0_max_price_x32 = 0;
+        let mut token_1_min_price_x32 = u128::MAX;
+        let mut token_1_max_price_x32 = 0;
+        let mut after_start = end_observation;
+        let mut before_start = None;
+        for observation in observations {
+            let (token_0_price_x32, token_1_price_x32) =
+                average_prices_x32(&observation, &after_start);
+            token_0_min_price_x32 = token_0_min_price_x32.min(token_0_price_x32);
+            token_0_max_price_x32 = token_0_max_price_x32.max(token_0_price_x32);
+            token_1_min_price_x32 = token_1_min_price_x32.min(token_1_price_x32);
+            token_1_max_price_x32 = token_1_max_price_x32.max(token_1_price_x32);
+            if observation.block_timestamp <= start_time {
+                before_start = Some(observation);
+                break;
+            }
+            after_start = observation;
+        }
+        let before_start = before_start.ok_or(GammaError::ObservationCoverageTooShort)?;
+
+        // Interpolate the cumulative prices at the start of the window
+        let (token_0_price_x32, token_1_price_x32) =
+            average_prices_x32(&before_start, &after_start);
+        let delta_time = u128::from(start_time - before_start.block_timestamp);
+        let start_cumulative_token_0_price_x32 = before_start
+            .cumulative_token_0_price_x32
+            .wrapping_add(token_0_price_x32 * delta_time);
+        let start_cumulative_token_1_price_x32 = before_start
+            .cumulative_token_1_price_x32
+            .wrapping_add(token_1_price_x32 * delta_time);
+
+        Ok(Twap {
+            start_time,
+            end_time: block_timestamp,
+            token_0_twap_price_x32: end_observation
+                .cumulative_token_0_price_x32
+                .wrapping_sub(start_cumulative_token_0_price_x32)
+                / u128::from(window),
+            token_1_twap_price_x32: end_observation
+                .cumulative_token_1_price_x32
+                .wrapping_sub(start_cumulative_token_1_price_x32)
+                / u128::from(window),
+            token_0_min_price_x32,
+            token_0_max_price_x32,
+            token_1_min_price_x32,
+            token_1_max_price_x32,
+        })
+    }
+}
+
+impl<'a> ObservationBufferMut<'a> {
+    pub fn new(state: &'a mut ObservationState, extra_observations: &'a mut [Observation]) -> Self {
+        Self {
+            state,
+            extra_observations,
+        }
+    }
+
+    /// Reads the observations from the data of an ObservationState account for writing
+    pub fn from_account_data(data: &'a mut [u8]) -> Result<Self> {
+        split_account_data(data)?;
+        let (state_data, extra_data) = data[8..].split_at_mut(ObservationState::LEN - 8);
+        let state: &mut ObservationState = bytemuck::from_bytes_mut(state_data);
+        let extra_len = (state.observation_cardinality_next() as usize)
+            .saturating_sub(OBSERVATION_NUM)
+            * Observation::LEN;
+        require_gte!(
+            extra_data.len(),
+            extra_len,
+            ErrorCode::AccountDidNotDeserialize
+        );
+        Ok(Self::new(
+            state,
+            bytemuck::cast_slice_mut(&mut extra_data[..extra_len]),
+        ))
+    }
+
+    /// Returns a read only view of the observations
+    pub fn as_buffer(&self) -> ObservationBuffer<'_> {
+        ObservationBuffer::new(self.state, self.extra_observations)
+    }
+
+    fn get_mut(&mut self, index: usize) -> &mut Observation {
+        if index < OBSERVATION_NUM {
+            &mut self.state.observations[index]
+        } else {
+            &mut self.extra_observations[index - OBSERVATION_NUM]
+        }
+    }
+
+    /// Accumulates the price of the pool since the newest observation into the oracle.
+    /// Called by every instruction moving the price with the price from before the instruction, which is the
+    /// price the pool closed the previous second at, i.e. the post-swap price of the last trade of that second.
+    /// Trades within the same second are accumulated once the second has closed, so only the end price of
+    /// every second is recorded and a sandwich within a block leaves no trace.
+    ///
+    /// The newest observation keeps accumulating until it spans observation_update_duration seconds, or until
+    /// the pool has not been updated for observation_update_duration seconds, then the next observation is written.
+    /// Index represents the most recently written element.
+    /// If the index is at the end of the observations in use, the cardinality grows to observation_cardinality_next
+    /// and the next index is the first added element, otherwise the next index will turn to 0.
     ///
     /// # Arguments
     ///
     /// * `self` - The ObservationState account to write in
     /// * `block_timestamp` - The current timestamp of to update
-    /// * `token_0_price_x32` - The token_0_price_x32 at the time of the new observation
-    /// * `token_1_price_x32` - The token_1_price_x32 at the time of the new observation
+    /// * `token_0_price_x32` - The token_0_price_x32 since the newest observation
+    /// * `token_1_price_x32` - The token_1_price_x32 since the newest observation
     ///
-
     pub fn update(
         &mut self,
         block_timestamp: u64,
         token_0_price_x32: u128,
         token_1_price_x32: u128,
     ) -> Result<()> {
-        let observation_index = self.observation_index;
-        if !self.initialized {
-            self.initialized = true;
-            self.observations[observation_index as usize].block_timestamp = block_timestamp;
-            self.observations[observation_index as usize].cumulative_token_0_price_x32 = 0;
-            self.observations[observation_index as usize].cumulative_token_1_price_x32 = 0;
-            Ok(())
-        } else {
-            let last_observation = self.observations[observation_index as usize];
-            let delta_time = block_timestamp.saturating_sub(last_observation.block_timestamp);
-            if delta_time < OBSERVATION_UPDATE_DURATION_DEFAULT {
-                return Ok(());
-            }
-            let delta_token_0_price_x32 = token_0_price_x32
-                .checked_mul(delta_time.into())
-                .ok_or(GammaError::MathOverflow)?;
-            let delta_token_1_price_x32 = token_1_price_x32
-                .checked_mul(delta_time.into())
-                .ok_or(GammaError::MathOverflow)?;
-            let next_observation_index = if observation_index as usize == OBSERVATION_NUM - 1 {
-                0
-            } else {
-                observation_index + 1
-            };
-            self.observations[next_observation_index as usize].block_timestamp = block_timestamp;
-            // cumulative_token_price_x32 only occupies the first 64 bits, and the remaining 64 bits are used to store overflow data
-            self.observations[next_observation_index as usize].cumulative_token_0_price_x32 =
-                last_observation
-                    .cumulative_token_0_price_x32
-                    .wrapping_add(delta_token_0_price_x32);
-            self.observations[next_observation_index as usize].cumulative_token_1_price_x32 =
-                last_observation
-                    .cumulative_token_1_price_x32
-                    .wrapping_add(delta_token_1_price_x32);
-            self.observation_index = next_observation_index;
-            Ok(())
+        let observation_index = self.state.observation_index as usize;
+        if !self.state.initialized {
+            self.state.initialized = true;
+            let observation = self.get_mut(observation_index);
+            observation.block_timestamp = block_timestamp;
+            observation.cumulative_token_0_price_x32 = 0;
+            observation.cumulative_token_1_price_x32 = 0;
+            return Ok(());
+        }
+
+        let last_observation = self.as_buffer().get(observation_index);
+        let delta_time = block_timestamp.saturating_sub(last_observation.block_timestamp);
+        if delta_time == 0 {
+            return Ok(());
         }
+        let delta_token_0_price_x32 = token_0_price_x32
+            .checked_mul(delta_time.into())
+            .ok_or(GammaError::MathOverflow)?;
+        let delta_token_1_price_x32 = token_1_price_x32
+            .checked_mul(delta_time.into())
+            .ok_or(GammaError::MathOverflow)?;
+
+        let mut observation_cardinality = self.state.observation_cardinality();
+        let observation_update_duration = self.state.observation_update_duration();
+        let previous_observation = self.as_buffer().get(
+            (observation_index + observation_cardinality as usize - 1)
+                % observation_cardinality as usize,
+        );
+        let is_newest_observation_complete = previous_observation.block_timestamp == 0
+            || last_observation.block_timestamp - previous_observation.block_timestamp
+                >= observation_update_duration
+            || delta_time >= observation_update_duration;
+
+        let next_observation_index = if is_newest_observation_complete {
+            let observation_cardinality_next = self.state.observation_cardinality_next();
+            if observation_index == observation_cardinality as usize - 1
+                && observation_cardinality_next > observation_cardinality
+            {
+                observation_cardinality = observation_cardinality_next;
+                self.state.observation_cardinality = observation_cardinality;
+            }
+            (observation_index + 1) % observation_cardinality as usize
+        } else {
+            observation_index
+        };
+        let next_observation = self.get_mut(next_observation_index);
+        next_observation.block_timestamp = block_timestamp;
+        // cumulative_token_price_x32 only occupies the first 64 bits, and the remaining 64 bits are used to store overflow data
+        next_observation.cumulative_token_0_price_x32 = last_observation
+            .cumulative_token_0_price_x32
+            .wrapping_add(delta_token_0_price_x32);
+        next_observation.cumulative_token_1_price_x32 = last_observation
+            .cumulative_token_1_price_x32
+            .wrapping_add(delta_token_1_price_x32);
+        self.state.observation_index = next_observation_index as u16;
+        Ok(())
     }
 }
 
@@ -0,0 +1,177 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::GammaError, fees::FEE_RATE_DENOMINATOR_VALUE, utils::U256};
+
+use super::PoolState;
+
+pub const PARTNER_SEED: &str = "partner";
+pub const PARTNER_INFO_SEED: &str = "partner-info";
+
+/// A wallet partner registered by the admin.
+/// The liquidity of its customers is tracked in every pool by a `PartnerInfo` ledger.
+#[account]
+#[derive(Default, Debug)]
+pub struct Partner {
+    pub partner_id: u64,
+    /// Share of the protocol fees earned by the liquidity linked with the partner that goes to the partner,
+    /// out of `FEE_RATE_DENOMINATOR_VALUE`
+    pub fee_share_rate: u64,
+    pub bump: u8,
+    /// Signs the claims of the fee share of the partner
+    pub authority: Pubkey,
+    pub padding: [u8; 32],
+}
+
+impl Partner {
+    pub const LEN: usize = 8 + 8 * 2 + 1 + 32 + 32;
+}
+
+/// The liquidity of a pool linked with a partner and the protocol fees it earned
+#[account]
+#[derive(Default, Debug)]
+pub struct PartnerInfo {
+    pub pool_state: Pubkey,
+    pub partner_id: u64,
+    // This stores the LP tokens that are linked with the partner, i.e owned by customers of the partner.
+    pub lp_token_linked_with_partner: u64,
+
+    // This keeps track of tvl_share * fee_we_earned_with_swap_token0
+    pub cumulative_fee_total_times_tvl_share_token_0: u64,
+
+    // This keeps track of tvl_share * fee_we_earned_with_swap_token1
+    pub cumulative_fee_total_times_tvl_share_token_1: u64,
+
+    /// `PoolState.protocol_fees_per_lp_x64_token_0` when the cumulative fees were last brought up to date
+    pub protocol_fees_per_lp_paid_x64_token_0: u128,
+    /// `PoolState.protocol_fees_per_lp_x64_token_1` when the cumulative fees were last brought up to date
+    pub protocol_fees_per_lp_paid_x64_token_1: u128,
+
+    /// Fee share of token_0 already paid out to the partner
+    pub fees_claimed_token_0: u64,
+    /// Fee share of token_1 already paid out to the partner
+    pub fees_claimed_token_1: u64,
+    /// `Partner.fee_share_rate` of the partner
+    pub fee_share_rate: u64,
+    pub padding: [u8; 40],
+}
+
+impl PartnerInfo {
+    pub const LEN: usize = 8 + 32 + 8 * 7 + 16 * 2 + 40;
+
+    pub fn initialize(&mut self, pool_id: Pubkey, partner: &Partner, pool_state: &PoolState) {
+        self.pool_state = pool_id;
+        self.partner_id = partner.partner_id;
+        self.fee_share_rate = partner.fee_share_rate;
+        self.lp_token_linked_with_partner = 0;
+        self.cumulative_fee_total_times_tvl_share_token_0 = 0;
+        self.cumulative_fee_total_times_tvl_share_token_1 = 0;
+        self.protocol_fees_per_lp_paid_x64_token_0 = pool_state.protocol_fees_per_lp_x64_token_0;
+        self.protocol_fees_per_lp_paid_x64_token_1 = pool_state.protocol_fees_per_lp_x64_token_1;
+        self.fees_claimed_token_0 = 0;
+        self.fees_claimed_token_1 = 0;
+        self.padding = [0u8; 40];
+    }
+
+    /// The linked lp tokens weighted by the fee share rate, rounded up.
+    /// Their sum over all the ledgers of a pool is `PoolState.partner_fee_share_lp`.
+    pub fn fee_share_lp(&self) -> Result<u64> {
+        let fee_share_lp = u128::from(self.lp_token_linked_with_partner)
+            .checked_mul(u128::from(self.fee_share_rate))
+            .ok_or(GammaError::MathOverflow)?
+            .checked_add(u128::from(FEE_RATE_DENOMINATOR_VALUE - 1))
+            .ok_or(GammaError::MathOverflow)?
+            .checked_div(u128::from(FEE_RATE_DENOMINATOR_VALUE))
+            .ok_or(GammaError::MathOverflow)?;
+        u64::try_from(fee_share_lp).map_err(|_| GammaError::MathOverflow.into())
+    }
+
+    /// The protocol fees of token_0 and token_1 earned by the linked liquidity, including the fees
+    /// accrued since the last update
+    pub fn fees_earned(&self, pool_state: &PoolState) -> Result<(u64, u64)> {
+        let fees_token_0 = self
+            .cumulative_fee_total_times_tvl_share_token_0
+            .checked_add(self.fees_since(
+                pool_state.protocol_fees_per_lp_x64_token_0,
+                self.protocol_fees_per_lp_paid_x64_token_0,
+            )?)
+            .ok_or(GammaError::MathOverflow)?;
+        let fees_token_1 = self
+            .cumulative_fee_total_times_tvl_share_token_1
+            .checked_add(self.fees_since(
+                pool_state.protocol_fees_per_lp_x64_token_1,
+                self.protocol_fees_per_lp_paid_x64_token_1,
+            )?)
+            .ok_or(GammaError::MathOverflow)?;
+        Ok((fees_token_0, fees_token_1))
+    }
+
+    /// Accrues the protocol fees earned by the linked liquidity since the last update.
+    /// Must be called before `lp_token_linked_with_partner` changes.
+    pub fn update_fees(&mut self, pool_state: &PoolState) -> Result<()> {
+        let (fees_token_0, fees_token_1) = self.fees_earned(pool_state)?;
+        self.cumulative_fee_total_times_tvl_share_token_0 = fees_token_0;
+        self.cumulative_fee_total_times_tvl_share_token_1 = fees_token_1;
+        self.protocol_fees_per_lp_paid_x64_token_0 = pool_state.protocol_fees_per_lp_x64_token_0;
+        self.protocol_fees_per_lp_paid_x64_token_1 = pool_state.protocol_fees_per_lp_x64_token_1;
+        Ok(())
+    }
+
+    /// The fee share of token_0 and token_1 the partner can still claim.
+    /// `update_fees` must be called first.
+    pub fn claimable_fees(&self) -> Result<(u64, u64)> {
+        let claimable = |fees: u64, fees_claimed: u64| -> Result<u64> {
+            let fee_share = u128::from(fees)
+                .checked_mul(u128::from(self.fee_share_rate))
+                .ok_or(GammaError::MathOverflow)?
+                .checked_div(u128::from(FEE_RATE_DENOMINATOR_VALUE))
+                .ok_or(GammaError::MathOverflow)?;
+            Ok((fee_share as u64).saturating_sub(fees_claimed))
+        };
+        Ok((
+            claimable(
+                self.cumulative_fee_total_times_tvl_share_token_0,
+                self.fees_claimed_token_0,
+            )?,
+            claimable(
+                self.cumulative_fee_total_times_tvl_share_token_1,
+                self.fees_claimed_token_1,
+            )?,
+        ))
+    }
+
+    fn fees_since(&self, fees_per_lp_x64: u128, fees_per_lp_paid_x64: u128) -> Result<u64> {
+        // The fees per lp only ever grow, wrapping around like the accumulators themselves
+        let fees_per_lp_x64 = fees_per_lp_x64.wrapping_sub(fees_per_lp_paid_x64);
+        let fees =
+            (U256::from(self.lp_token_linked_with_partner) * U256::from(fees_per_lp_x64)) >> 64;
+        let fees = u128::try_from(fees).map_err(|_| GammaError::MathOverflow)?;
+        u64::try_from(fees).map_err(|_| GammaError::MathOverflow.into())
+    }
+}
+
+/// Looks up the ledger of the partner a position is linked with.
+/// The ledger must be provided when the position has a partner, it is ignored otherwise.
+pub fn find_partner_info<'a>(
+    partner_info: &'a mut Option<Box<Account<'_, PartnerInfo>>>,
+    pool_id: Pubkey,
+    partner_id: Option<u64>,
+) -> Result<Option<&'a mut PartnerInfo>> {
+    let partner_id = match partner_id {
+        Some(partner_id) => partner_id,
+        None => return Ok(None),
+    };
+    let partner_info = partner_info
+        .as_deref_mut()
+        .ok_or(GammaError::PartnerNotFound)?;
+    require_keys_eq!(
+        partner_info.pool_state,
+        pool_id,
+        GammaError::PartnerNotFound
+    );
+    require_eq!(
+        partner_info.partner_id,
+        partner_id,
+        GammaError::PartnerNotFound
+    );
+    Ok(Some(&mut **partner_info))
+}
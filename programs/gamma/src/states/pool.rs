@@ -1,4 +1,7 @@
+use crate::curve::{CurveType, StableSwapCurve, TradeDirection, WEIGHT_DENOMINATOR};
 use crate::error::GammaError;
+use crate::fees::{FeeType, LaunchFeeDecay, FEE_RATE_DENOMINATOR_VALUE, MAX_LAUNCH_FEE_DURATION};
+use crate::states::{find_partner_info, PartnerInfo};
 use anchor_lang::prelude::*;
 use anchor_spl::token_interface::Mint;
 use std::ops::{BitAnd, BitOr, BitXor};
@@ -6,12 +9,23 @@ use std::ops::{BitAnd, BitOr, BitXor};
 // Seed to derive account address and signature
 pub const POOL_SEED: &str = "pool";
 pub const POOL_LP_MINT_SEED: &str = "pool_lp_mint";
+// Seed of the optional receipt mint for wrapped lp tokens
+pub const WRAPPED_LP_MINT_SEED: &str = "wrapped_lp_mint";
+// Decimals of the optional receipt mint for wrapped lp tokens
+pub const LP_MINT_DECIMALS: u8 = 9;
 pub const POOL_VAULT_SEED: &str = "pool_vault";
 // This is for deriving the token account where kamino collateral is deposited
 pub const POOL_KAMINO_DEPOSITS_SEED: &str = "pool_kamino_deposits";
 
 pub const Q32: u128 = (u32::MAX as u128) + 1; // 2^32
 
+/// Layout version of the pools created with the partner ledgers and the wrapped lp counter. Older
+/// pools keep their partner slot where the protocol fees per lp are stored, and may keep their
+/// former lp mint where the wrapped lp tokens are counted, until `migrate_partner_ledger`.
+pub const POOL_LAYOUT_VERSION: u8 = 1;
+/// Partner of the positions linked with "AssetDash", the only partner before the registry
+pub const LEGACY_PARTNER_ID: u64 = 0;
+
 pub enum PoolStatusBitIndex {
     Deposit,
     Withdraw,
@@ -24,37 +38,6 @@ pub enum PoolStatusBitFlag {
     Disable,
 }
 
-#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, AnchorDeserialize, AnchorSerialize)]
-#[repr(u64)]
-pub enum PartnerType {
-    #[default]
-    AssetDash = 0,
-}
-
-impl PartnerType {
-    pub fn new(value: u64) -> Self {
-        match value {
-            0 => PartnerType::AssetDash,
-            _ => PartnerType::AssetDash,
-        }
-    }
-}
-
-#[zero_copy(unsafe)]
-#[repr(packed)]
-#[derive(Default, Debug)]
-pub struct PartnerInfo {
-    pub partner_id: u64,
-    // This stores the LP tokens that are linked with the partner, i.e owned by customers of the partner.
-    pub lp_token_linked_with_partner: u64,
-
-    // This keeps track of tvl_share * fee_we_earned_with_swap_token0
-    pub cumulative_fee_total_times_tvl_share_token_0: u64,
-
-    // This keeps track of tvl_share * fee_we_earned_with_swap_token1
-    pub cumulative_fee_total_times_tvl_share_token_1: u64,
-}
-
 #[account(zero_copy(unsafe))]
 #[repr(packed)]
 #[derive(Default, Debug)]
@@ -71,7 +54,16 @@ pub struct PoolState {
     /// Pool tokens are issued when Token A or Token B are deposited
     /// Pool tokens can be withdrawn back to the original Token A or Token B
     // pub lp_mint: Pubkey,
-    pub _padding1: [u8; 32],
+    /// Lp tokens moved out of the positions into wrapped lp tokens, they are part of `lp_supply`
+    /// but earn neither rewards nor partner fees
+    pub lp_tokens_wrapped: u64,
+    /// Lp tokens linked with partners weighted by the fee share rate of their partner, see
+    /// `PartnerInfo::fee_share_lp`
+    pub partner_fee_share_lp: u64,
+    /// Part of `protocol_fees_token_0` kept in the pool for the partners to claim
+    pub partner_fees_token_0: u64,
+    /// Part of `protocol_fees_token_1` kept in the pool for the partners to claim
+    pub partner_fees_token_1: u64,
     /// Mint info of Token A
     pub token_0_mint: Pubkey,
     /// Mint info of Token B
@@ -140,8 +132,11 @@ pub struct PoolState {
     pub max_shared_token0: u64,
     pub max_shared_token1: u64,
 
-    // This will store the partner information, like how much token0 and token1 they was invested from their platforms.
-    pub partners: [PartnerInfo; 1],
+    /// Protocol fees of token_0 earned per lp token, as a Q64.64 number wrapping around on overflow.
+    /// The `PartnerInfo` ledgers use it to track the fees earned by the liquidity linked with a partner.
+    pub protocol_fees_per_lp_x64_token_0: u128,
+    /// Protocol fees of token_1 earned per lp token, see `protocol_fees_per_lp_x64_token_0`
+    pub protocol_fees_per_lp_x64_token_1: u128,
 
     // Keeps track of the absolute amount we put in kamino, in terms of the token0 or token1.
     // This is important to make sure that when kamino collateral price decreases in rate cases we don't deposit more.
@@ -150,12 +145,44 @@ pub struct PoolState {
     // To keep track of the profit we made from kamino, in terms of the token0 or token1.
     pub withdrawn_kamino_profit_token_0: u64,
     pub withdrawn_kamino_profit_token_1: u64,
-    /// padding
-    pub padding: [u64; 8],
+
+    /// Invariant of the pool, see `CurveType`
+    pub curve_type: u8,
+    /// Fee model of the pool, see `FeeType`
+    pub fee_type: u8,
+    /// How the launch fee decays, see `LaunchFeeDecay`
+    pub launch_fee_decay: u8,
+    /// Number of rewards of the pool, every lp change must settle all of them
+    pub reward_count: u8,
+    /// Vault lent by the open flash loan, 0 for token_0 and 1 for token_1
+    pub flash_loan_token_index: u8,
+    /// Layout of the pool, see `POOL_LAYOUT_VERSION`
+    pub layout_version: u8,
+    /// Number of rewards closed on the pool, see `RewardInfo::close_index`
+    pub rewards_closed: u16,
+    /// StableSwap amplification coefficient at the start of the current ramp
+    pub initial_amp: u32,
+    /// StableSwap amplification coefficient at the end of the current ramp
+    pub target_amp: u32,
+    /// Time the current amplification ramp started
+    pub amp_ramp_start_time: u64,
+    /// Time the current amplification ramp ends, the amplification stays at `target_amp` afterwards
+    pub amp_ramp_stop_time: u64,
+    /// Weight of token_0 in weighted pools, out of `WEIGHT_DENOMINATOR`
+    pub token_0_weight: u32,
+    /// Weight of token_1 in weighted pools, out of `WEIGHT_DENOMINATOR`
+    pub token_1_weight: u32,
+    /// Fee rate charged at `open_time`, decaying to the normal fee over `launch_fee_duration`. Zero disables it
+    pub launch_fee_rate: u64,
+    /// Seconds after `open_time` during which the launch fee applies
+    pub launch_fee_duration: u64,
+    /// Amount lent by the open flash loan, zero when no loan is open. The vault amounts are not
+    /// reduced by the loan, it is repaid within the same transaction
+    pub flash_loan_amount: u64,
 }
 
 impl PoolState {
-    pub const LEN: usize = 8 + 10 * 32 + 5 * 1 + 7 * 8 + 16 * 4 + 23 * 8;
+    pub const LEN: usize = 8 + 9 * 32 + 24 + 5 * 1 + 8 * 8 + 16 * 6 + 19 * 8;
 
     pub fn initialize(
         &mut self,
@@ -173,6 +200,12 @@ impl PoolState {
         token_0_mint: &InterfaceAccount<Mint>,
         token_1_mint: &InterfaceAccount<Mint>,
         observation_key: Pubkey,
+        curve_type: CurveType,
+        amp: u32,
+        token_0_weight: u32,
+        launch_fee_rate: u64,
+        launch_fee_duration: u64,
+        launch_fee_decay: u8,
     ) -> Result<()> {
         self.amm_config = amm_config.key();
         self.pool_creator = pool_creator.key();
@@ -187,6 +220,10 @@ impl PoolState {
         self.mint_0_decimals = token_0_mint.decimals;
         self.mint_1_decimals = token_1_mint.decimals;
         self.lp_supply = lp_supply;
+        self.lp_tokens_wrapped = 0;
+        self.partner_fee_share_lp = 0;
+        self.partner_fees_token_0 = 0;
+        self.partner_fees_token_1 = 0;
         self.protocol_fees_token_0 = 0;
         self.protocol_fees_token_1 = 0;
         self.fund_fees_token_0 = 0;
@@ -211,12 +248,81 @@ impl PoolState {
         self.token_0_amount_in_kamino = 0;
         self.token_1_amount_in_kamino = 0;
 
-        self.partners = [PartnerInfo::default(); 1];
+        self.protocol_fees_per_lp_x64_token_0 = 0;
+        self.protocol_fees_per_lp_x64_token_1 = 0;
+
+        self.curve_type = curve_type as u8;
+        self.fee_type = FeeType::Volatility as u8;
+        self.reward_count = 0;
+        self.flash_loan_token_index = 0;
+        self.layout_version = POOL_LAYOUT_VERSION;
+        self.rewards_closed = 0;
+        self.initial_amp = amp;
+        self.target_amp = amp;
+        self.amp_ramp_start_time = 0;
+        self.amp_ramp_stop_time = 0;
+        self.token_0_weight = token_0_weight;
+        self.token_1_weight = if token_0_weight == 0 {
+            0
+        } else {
+            WEIGHT_DENOMINATOR - token_0_weight
+        };
+        self.set_launch_fee(launch_fee_rate, launch_fee_duration, launch_fee_decay)?;
+        self.flash_loan_amount = 0;
+        Ok(())
+    }
 
-        self.padding = [0u64; 8];
+    /// Sets the launch fee schedule, a zero `launch_fee_rate` disables it
+    pub fn set_launch_fee(
+        &mut self,
+        launch_fee_rate: u64,
+        launch_fee_duration: u64,
+        launch_fee_decay: u8,
+    ) -> Result<()> {
+        LaunchFeeDecay::new(launch_fee_decay)?;
+        require_gt!(
+            FEE_RATE_DENOMINATOR_VALUE,
+            launch_fee_rate,
+            GammaError::InvalidFee
+        );
+        require_gte!(
+            MAX_LAUNCH_FEE_DURATION,
+            launch_fee_duration,
+            GammaError::InvalidFee
+        );
+        self.launch_fee_rate = launch_fee_rate;
+        self.launch_fee_duration = launch_fee_duration;
+        self.launch_fee_decay = launch_fee_decay;
         Ok(())
     }
 
+    pub fn curve_type(&self) -> Result<CurveType> {
+        CurveType::new(self.curve_type)
+    }
+
+    pub fn fee_type(&self) -> Result<FeeType> {
+        FeeType::new(self.fee_type)
+    }
+
+    /// StableSwap amplification coefficient at `block_timestamp`
+    pub fn current_amp(&self, block_timestamp: u64) -> u64 {
+        StableSwapCurve::current_amp(
+            u64::from(self.initial_amp),
+            u64::from(self.target_amp),
+            self.amp_ramp_start_time,
+            self.amp_ramp_stop_time,
+            block_timestamp,
+        )
+    }
+
+    /// Weights of the (input, output) tokens of a trade in a weighted pool
+    pub fn weights(&self, trade_direction: TradeDirection) -> (u32, u32) {
+        match trade_direction {
+            TradeDirection::ZeroForOne => (self.token_0_weight, self.token_1_weight),
+            TradeDirection::OneForZero => (self.token_1_weight, self.token_0_weight),
+        }
+    }
+
     pub fn set_status(&mut self, status: u8) {
         self.status = status
     }
@@ -237,15 +343,416 @@ impl PoolState {
         self.status.bitand(status) == 0
     }
 
+    /// Swaps, deposits and withdrawals are blocked while the vaults are lent out
+    pub fn flash_loan_open(&self) -> bool {
+        self.flash_loan_amount > 0
+    }
+
+    /// Pools created before the partner ledgers still keep their partner slot in place of the
+    /// protocol fees per lp, see `migrate_partner_ledger`
+    pub fn is_legacy_layout(&self) -> bool {
+        self.layout_version < POOL_LAYOUT_VERSION
+    }
+
+    /// The partner slot of a legacy pool: the lp tokens linked with the partner and its cumulative
+    /// protocol fees of token_0 and token_1
+    pub fn legacy_partner_slot(&self) -> (u64, u64, u64) {
+        let slot_0 = self.protocol_fees_per_lp_x64_token_0;
+        let slot_1 = self.protocol_fees_per_lp_x64_token_1;
+        ((slot_0 >> 64) as u64, slot_1 as u64, (slot_1 >> 64) as u64)
+    }
+
+    fn set_legacy_partner_slot(
+        &mut self,
+        lp_token_linked_with_partner: u64,
+        cumulative_fee_token_0: u64,
+        cumulative_fee_token_1: u64,
+    ) {
+        // The partner id stays in the low bits of the first accumulator
+        let partner_id = self.protocol_fees_per_lp_x64_token_0 & u128::from(u64::MAX);
+        self.protocol_fees_per_lp_x64_token_0 =
+            partner_id | (u128::from(lp_token_linked_with_partner) << 64);
+        self.protocol_fees_per_lp_x64_token_1 =
+            u128::from(cumulative_fee_token_0) | (u128::from(cumulative_fee_token_1) << 64);
+    }
+
+    /// Moves the partner slot of a legacy pool to its ledger, frees the protocol fees per lp and
+    /// clears the former lp mint to count the wrapped lp tokens and the partner fees.
+    /// The fee share the partner earned in the slot is kept out of the protocol fees left in the pool.
+    pub fn migrate_partner_slot(&mut self, partner_info: &mut PartnerInfo) -> Result<()> {
+        require!(self.is_legacy_layout(), GammaError::PoolAlreadyMigrated);
+        let (lp_token_linked_with_partner, cumulative_fee_token_0, cumulative_fee_token_1) =
+            self.legacy_partner_slot();
+        self.protocol_fees_per_lp_x64_token_0 = 0;
+        self.protocol_fees_per_lp_x64_token_1 = 0;
+        self.lp_tokens_wrapped = 0;
+        self.partner_fee_share_lp = 0;
+        self.partner_fees_token_0 = 0;
+        self.partner_fees_token_1 = 0;
+        self.layout_version = POOL_LAYOUT_VERSION;
+
+        partner_info.cumulative_fee_total_times_tvl_share_token_0 = cumulative_fee_token_0;
+        partner_info.cumulative_fee_total_times_tvl_share_token_1 = cumulative_fee_token_1;
+        partner_info.protocol_fees_per_lp_paid_x64_token_0 = 0;
+        partner_info.protocol_fees_per_lp_paid_x64_token_1 = 0;
+        self.set_lp_token_linked_with_partner(partner_info, lp_token_linked_with_partner)?;
+
+        let (claimable_0, claimable_1) = partner_info.claimable_fees()?;
+        self.partner_fees_token_0 = claimable_0.min(self.protocol_fees_token_0);
+        self.partner_fees_token_1 = claimable_1.min(self.protocol_fees_token_1);
+        Ok(())
+    }
+
+    /// The protocol fees of token_0 and token_1 kept in the pool for the partners to claim
+    pub fn partner_fees(&self) -> (u64, u64) {
+        if self.is_legacy_layout() {
+            return (0, 0);
+        }
+        (self.partner_fees_token_0, self.partner_fees_token_1)
+    }
+
+    /// Releases the partner fees paid out of the protocol fees. Ledgers migrated after the
+    /// protocol fees were collected may claim more than was kept for them.
+    pub fn release_partner_fees(&mut self, amount_0: u64, amount_1: u64) {
+        self.partner_fees_token_0 = self.partner_fees_token_0.saturating_sub(amount_0);
+        self.partner_fees_token_1 = self.partner_fees_token_1.saturating_sub(amount_1);
+    }
+
+    /// Changes the lp tokens linked with a partner, keeping the weighted lp tokens of the pool
+    /// in step with its ledger
+    fn set_lp_token_linked_with_partner(
+        &mut self,
+        partner_info: &mut PartnerInfo,
+        lp_token_linked_with_partner: u64,
+    ) -> Result<()> {
+        partner_info.update_fees(self)?;
+        let fee_share_lp = partner_info.fee_share_lp()?;
+        partner_info.lp_token_linked_with_partner = lp_token_linked_with_partner;
+        self.partner_fee_share_lp = self
+            .partner_fee_share_lp
+            .checked_sub(fee_share_lp)
+            .ok_or(GammaError::MathOverflow)?
+            .checked_add(partner_info.fee_share_lp()?)
+            .ok_or(GammaError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// The lp tokens held by positions, the wrapped lp tokens earn neither rewards nor partner fees
+    pub fn lp_supply_in_positions(&self) -> Result<u64> {
+        let lp_tokens_wrapped = if self.is_legacy_layout() {
+            0
+        } else {
+            self.lp_tokens_wrapped
+        };
+        self.lp_supply
+            .checked_sub(lp_tokens_wrapped)
+            .ok_or(GammaError::MathOverflow.into())
+    }
+
+    /// Adds lp tokens to the liquidity linked with the partner of a position, if any.
+    /// Legacy pools book it in their partner slot, the ledger is ignored.
+    pub fn add_lp_token_linked_with_partner(
+        &mut self,
+        partner_info: &mut Option<Box<Account<PartnerInfo>>>,
+        pool_id: Pubkey,
+        partner: Option<u64>,
+        lp_token_amount: u64,
+    ) -> Result<()> {
+        if self.is_legacy_layout() {
+            if partner.is_some() {
+                let (lp_token_linked_with_partner, fee_token_0, fee_token_1) =
+                    self.legacy_partner_slot();
+                let lp_token_linked_with_partner = lp_token_linked_with_partner
+                    .checked_add(lp_token_amount)
+                    .ok_or(GammaError::MathOverflow)?;
+                self.set_legacy_partner_slot(
+                    lp_token_linked_with_partner,
+                    fee_token_0,
+                    fee_token_1,
+                );
+            }
+            return Ok(());
+        }
+        if let Some(partner_info) = find_partner_info(partner_info, pool_id, partner)? {
+            let lp_token_linked_with_partner = partner_info
+                .lp_token_linked_with_partner
+                .checked_add(lp_token_amount)
+                .ok_or(GammaError::MathOverflow)?;
+            self.set_lp_token_linked_with_partner(partner_info, lp_token_linked_with_partner)?;
+        }
+        Ok(())
+    }
+
+    /// Removes lp tokens from the liquidity linked with the partner of a position, if any.
+    /// Legacy pools book it in their partner slot, the ledger is ignored.
+    pub fn sub_lp_token_linked_with_partner(
+        &mut self,
+        partner_info: &mut Option<Box<Account<PartnerInfo>>>,
+        pool_id: Pubkey,
+        partner: Option<u64>,
+        lp_token_amount: u64,
+    ) -> Result<()> {
+        if self.is_legacy_layout() {
+            if partner.is_some() {
+                let (lp_token_linked_with_partner, fee_token_0, fee_token_1) =
+                    self.legacy_partner_slot();
+                // Positions linked before the slot tracked them may hold more than it
+                let lp_token_linked_with_partner =
+                    lp_token_linked_with_partner.saturating_sub(lp_token_amount);
+                self.set_legacy_partner_slot(
+                    lp_token_linked_with_partner,
+                    fee_token_0,
+                    fee_token_1,
+                );
+            }
+            return Ok(());
+        }
+        if let Some(partner_info) = find_partner_info(partner_info, pool_id, partner)? {
+            let lp_token_linked_with_partner = partner_info
+                .lp_token_linked_with_partner
+                .checked_sub(lp_token_amount)
+                .ok_or(GammaError::MathOverflow)?;
+            self.set_lp_token_linked_with_partner(partner_info, lp_token_linked_with_partner)?;
+        }
+        Ok(())
+    }
+
+    /// Shares the protocol fee of a swap among all the lp tokens, so that every partner ledger
+    /// can derive the fees earned by its linked liquidity without being passed to the swap.
+    /// Legacy pools accrue the fee share of their partner slot instead.
+    pub fn update_protocol_fees_per_lp(
+        &mut self,
+        protocol_fee: u64,
+        trade_direction: TradeDirection,
+    ) -> Result<()> {
+        if self.is_legacy_layout() {
+            return self.update_legacy_partner_fees(protocol_fee, trade_direction);
+        }
+        let lp_supply_in_positions = u128::from(self.lp_supply_in_positions()?);
+        let protocol_fees_per_lp_x64 = (u128::from(protocol_fee) << 64)
+            .checked_div(lp_supply_in_positions)
+            .ok_or(GammaError::MathOverflow)?;
+        // The fee share of the partners stays in the pool until they claim it, rounded up so that
+        // it covers the fee share every ledger derives from the fees per lp
+        let partner_fee = u128::from(protocol_fee)
+            .checked_mul(u128::from(self.partner_fee_share_lp))
+            .ok_or(GammaError::MathOverflow)?
+            .checked_add(lp_supply_in_positions - 1)
+            .ok_or(GammaError::MathOverflow)?
+            .checked_div(lp_supply_in_positions)
+            .ok_or(GammaError::MathOverflow)?;
+        let partner_fee = u64::try_from(partner_fee)
+            .map_err(|_| GammaError::MathOverflow)?
+            .min(protocol_fee);
+        match trade_direction {
+            TradeDirection::ZeroForOne => {
+                self.protocol_fees_per_lp_x64_token_0 = self
+                    .protocol_fees_per_lp_x64_token_0
+                    .wrapping_add(protocol_fees_per_lp_x64);
+                self.partner_fees_token_0 = self
+                    .partner_fees_token_0
+                    .checked_add(partner_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+            }
+            TradeDirection::OneForZero => {
+                self.protocol_fees_per_lp_x64_token_1 = self
+                    .protocol_fees_per_lp_x64_token_1
+                    .wrapping_add(protocol_fees_per_lp_x64);
+                self.partner_fees_token_1 = self
+                    .partner_fees_token_1
+                    .checked_add(partner_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn update_legacy_partner_fees(
+        &mut self,
+        protocol_fee: u64,
+        trade_direction: TradeDirection,
+    ) -> Result<()> {
+        let (lp_token_linked_with_partner, mut fee_token_0, mut fee_token_1) =
+            self.legacy_partner_slot();
+        // we multiply by 100000 to keep decimals.
+        let decimal_number = 100000;
+        let tvl_share = lp_token_linked_with_partner
+            .checked_mul(decimal_number)
+            .ok_or(GammaError::MathOverflow)?
+            .checked_div(self.lp_supply)
+            .ok_or(GammaError::MathOverflow)?;
+        let partner_fee = protocol_fee
+            .checked_mul(tvl_share)
+            .ok_or(GammaError::MathOverflow)?
+            .checked_div(decimal_number)
+            .ok_or(GammaError::MathOverflow)?;
+        match trade_direction {
+            TradeDirection::ZeroForOne => {
+                fee_token_0 = fee_token_0
+                    .checked_add(partner_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+            }
+            TradeDirection::OneForZero => {
+                fee_token_1 = fee_token_1
+                    .checked_add(partner_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+            }
+        }
+        self.set_legacy_partner_slot(lp_token_linked_with_partner, fee_token_0, fee_token_1);
+        Ok(())
+    }
+
+    /// Books a swap, on its own or inside another pool operation (single sided deposits and
+    /// withdrawals): protocol and fund fees, partner fees per lp, cumulative stats and vault amounts.
+    /// `amount_in` is the amount received by the pool including all fees.
+    pub fn record_swap(
+        &mut self,
+        trade_direction: TradeDirection,
+        amount_in: u64,
+        amount_out: u64,
+        dynamic_fee: u64,
+        protocol_fee: u64,
+        fund_fee: u64,
+        dynamic_fee_rate: u64,
+    ) -> Result<()> {
+        self.update_protocol_fees_per_lp(protocol_fee, trade_direction)?;
+
+        let amount_in_after_fees = amount_in
+            .checked_sub(fund_fee)
+            .ok_or(GammaError::MathOverflow)?
+            .checked_sub(protocol_fee)
+            .ok_or(GammaError::MathOverflow)?;
+        match trade_direction {
+            TradeDirection::ZeroForOne => {
+                self.protocol_fees_token_0 = self
+                    .protocol_fees_token_0
+                    .checked_add(protocol_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+                self.fund_fees_token_0 = self
+                    .fund_fees_token_0
+                    .checked_add(fund_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+                self.cumulative_trade_fees_token_0 = self
+                    .cumulative_trade_fees_token_0
+                    .checked_add(u128::from(dynamic_fee))
+                    .ok_or(GammaError::MathOverflow)?;
+                self.cumulative_volume_token_0 = self
+                    .cumulative_volume_token_0
+                    .checked_add(u128::from(amount_in))
+                    .ok_or(GammaError::MathOverflow)?;
+                self.token_0_vault_amount = self
+                    .token_0_vault_amount
+                    .checked_add(amount_in_after_fees)
+                    .ok_or(GammaError::MathOverflow)?;
+                self.token_1_vault_amount = self
+                    .token_1_vault_amount
+                    .checked_sub(amount_out)
+                    .ok_or(GammaError::MathOverflow)?;
+            }
+            TradeDirection::OneForZero => {
+                self.protocol_fees_token_1 = self
+                    .protocol_fees_token_1
+                    .checked_add(protocol_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+                self.fund_fees_token_1 = self
+                    .fund_fees_token_1
+                    .checked_add(fund_fee)
+                    .ok_or(GammaError::MathOverflow)?;
+                self.cumulative_trade_fees_token_1 = self
+                    .cumulative_trade_fees_token_1
+                    .checked_add(u128::from(dynamic_fee))
+                    .ok_or(GammaError::MathOverflow)?;
+                self.cumulative_volume_token_1 = self
+                    .cumulative_volume_token_1
+                    .checked_add(u128::from(amount_in))
+                    .ok_or(GammaError::MathOverflow)?;
+                self.token_1_vault_amount = self
+                    .token_1_vault_amount
+                    .checked_add(amount_in_after_fees)
+                    .ok_or(GammaError::MathOverflow)?;
+                self.token_0_vault_amount = self
+                    .token_0_vault_amount
+                    .checked_sub(amount_out)
+                    .ok_or(GammaError::MathOverflow)?;
+            }
+        }
+        self.latest_dynamic_fee_rate = dynamic_fee_rate;
+        Ok(())
+    }
+
     pub fn vault_amount_without_fee(&self) -> Result<(u64, u64)> {
         Ok((self.token_0_vault_amount, self.token_1_vault_amount))
     }
 
-    pub fn token_price_x32(&self) -> Result<(u128, u128)> {
+    /// Spot prices of (token_0 in token_1, token_1 in token_0) as Q32.32 numbers at `block_timestamp`
+    pub fn token_price_x32(&self, block_timestamp: u64) -> Result<(u128, u128)> {
         let (token_0_amount, token_1_amount) = self.vault_amount_without_fee()?;
+        if self.curve_type()? == CurveType::StableSwap {
+            // The invariant is symmetric, the price of token_1 is the one of token_0 with the balances swapped
+            let amp = self.current_amp(block_timestamp);
+            return Ok((
+                StableSwapCurve::spot_price_x32(
+                    amp,
+                    u128::from(token_0_amount),
+                    u128::from(token_1_amount),
+                )?,
+                StableSwapCurve::spot_price_x32(
+                    amp,
+                    u128::from(token_1_amount),
+                    u128::from(token_0_amount),
+                )?,
+            ));
+        }
+        if self.curve_type()? == CurveType::Weighted {
+            // The spot price of a weighted pool is (y / w1) / (x / w0)
+            let token_0_value = token_0_amount as u128 * self.token_1_weight as u128;
+            let token_1_value = token_1_amount as u128 * self.token_0_weight as u128;
+            return Ok((
+                token_1_value * Q32 as u128 / token_0_value,
+                token_0_value * Q32 as u128 / token_1_value,
+            ));
+        }
         Ok((
             token_1_amount as u128 * Q32 as u128 / token_0_amount as u128,
             token_0_amount as u128 * Q32 as u128 / token_1_amount as u128,
         ))
     }
+
+    /// The price of the destination token in source token for the given amounts at `block_timestamp`,
+    /// in the same terms as `token_price_x32`. An empty destination is priced at `u128::MAX`.
+    pub fn price_x32(
+        &self,
+        source_amount: u128,
+        destination_amount: u128,
+        trade_direction: TradeDirection,
+        block_timestamp: u64,
+    ) -> Result<u128> {
+        let (source_value, destination_value) = if self.curve_type()? == CurveType::Weighted {
+            let (source_weight, destination_weight) = self.weights(trade_direction);
+            (
+                source_amount
+                    .checked_mul(u128::from(destination_weight))
+                    .ok_or(GammaError::MathOverflow)?,
+                destination_amount
+                    .checked_mul(u128::from(source_weight))
+                    .ok_or(GammaError::MathOverflow)?,
+            )
+        } else {
+            (source_amount, destination_amount)
+        };
+        if destination_value == 0 {
+            return Ok(u128::MAX);
+        }
+        if self.curve_type()? == CurveType::StableSwap {
+            return StableSwapCurve::spot_price_x32(
+                self.current_amp(block_timestamp),
+                destination_amount,
+                source_amount,
+            );
+        }
+        Ok(source_value
+            .checked_mul(Q32)
+            .ok_or(GammaError::MathOverflow)?
+            / destination_value)
+    }
 }
@@ -1,9 +1,13 @@
 use anchor_lang::prelude::*;
 use rust_decimal::Decimal;
 
-use crate::error::GammaError;
+use super::PoolState;
+use crate::{error::GammaError, utils::U256, LOCK_LP_AMOUNT};
 use rust_decimal::prelude::FromPrimitive;
 
+/// Maximum number of rewards a pool can have at the same time, every lp change settles all of them
+pub const MAX_REWARDS_PER_POOL: u8 = 4;
+
 #[account]
 pub struct RewardInfo {
     pub pool: Pubkey,
@@ -12,9 +16,99 @@ pub struct RewardInfo {
     pub mint: Pubkey,
     pub total_to_disburse: u64, // Total rewards to distribute in this unix timestamp.
     pub rewarded_by: Pubkey,    // The reward given by
+    /// Rewards disbursed per eligible lp token since `start_at`, as a Q64.64 number
+    pub reward_per_lp_x64: u128,
+    /// Time `reward_per_lp_x64` was last brought up to date
+    pub last_updated_at: u64,
+    /// Rewards disbursed to eligible lp tokens so far, rewards accrued while no lp token was eligible are left out
+    pub total_disbursed: u64,
+    /// Start of the current emission schedule, moved when the reward is funded or extended
+    pub schedule_start_at: u64,
+    /// Rewards emitted evenly from `schedule_start_at` to `end_rewards_at`
+    pub schedule_amount: u64,
+    /// Time the reward was migrated from the legacy layout, zero for rewards created since
+    pub migrated_at: u64,
+    /// `reward_per_lp_x64` accrued from `start_at` to `migrated_at`, spread evenly over the period
+    pub migrated_reward_per_lp_x64: u128,
+    /// Position of the reward among the rewards closed on the pool starting at one, zero while
+    /// it is open. Lp changes stop settling the reward once it is closed
+    pub close_index: u16,
+}
+
+/// Layout of `RewardInfo` before the rewards were accrued per lp token
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyRewardInfo {
+    pub pool: Pubkey,
+    pub start_at: u64,
+    pub end_rewards_at: u64,
+    pub mint: Pubkey,
+    pub total_to_disburse: u64,
+    pub rewarded_by: Pubkey,
 }
 
 impl RewardInfo {
+    pub const LEGACY_LEN: usize = 8 + 32 * 3 + 8 * 3;
+
+    /// Rebuilds a reward of the legacy layout. The rewards emitted until now are disbursed at once
+    /// to the lp tokens currently eligible, the rest keeps being emitted on the same schedule.
+    ///
+    /// # Arguments
+    ///
+    /// * `legacy` - The reward in the legacy layout
+    /// * `current_lp_supply` - The lp supply held by positions, see `PoolState::lp_supply_in_positions`
+    ///
+    pub fn from_legacy(legacy: LegacyRewardInfo, current_lp_supply: u64) -> Result<Self> {
+        let time_now = Clock::get()?.unix_timestamp as u64;
+        let migrated_at = time_now.clamp(legacy.start_at, legacy.end_rewards_at);
+        let emitted = u128::from(legacy.total_to_disburse)
+            .checked_mul(u128::from(migrated_at - legacy.start_at))
+            .ok_or(GammaError::MathOverflow)?
+            .checked_div(u128::from(legacy.end_rewards_at - legacy.start_at))
+            .ok_or(GammaError::MathOverflow)?;
+
+        // The locked liquidity is not eligible for rewards
+        let eligible_lp_supply = current_lp_supply
+            .checked_sub(LOCK_LP_AMOUNT)
+            .ok_or(GammaError::MathOverflow)?;
+        let (reward_per_lp_x64, total_disbursed) = if eligible_lp_supply > 0 {
+            (
+                (emitted << 64) / u128::from(eligible_lp_supply),
+                emitted as u64,
+            )
+        } else {
+            (0, 0)
+        };
+
+        Ok(Self {
+            pool: legacy.pool,
+            start_at: legacy.start_at,
+            end_rewards_at: legacy.end_rewards_at,
+            mint: legacy.mint,
+            total_to_disburse: legacy.total_to_disburse,
+            rewarded_by: legacy.rewarded_by,
+            reward_per_lp_x64,
+            last_updated_at: migrated_at,
+            total_disbursed,
+            schedule_start_at: legacy.start_at,
+            schedule_amount: legacy.total_to_disburse,
+            migrated_at,
+            migrated_reward_per_lp_x64: reward_per_lp_x64,
+            close_index: 0,
+        })
+    }
+
+    /// `reward_per_lp_x64` at `time` for rewards migrated from the legacy layout, assuming it grew
+    /// evenly from `start_at` to `migrated_at`
+    pub fn migrated_reward_per_lp_at(&self, time: u64) -> Result<u128> {
+        if self.migrated_at <= self.start_at {
+            return Ok(0);
+        }
+        let elapsed = time.clamp(self.start_at, self.migrated_at) - self.start_at;
+        let reward_per_lp_x64 = U256::from(self.migrated_reward_per_lp_x64) * U256::from(elapsed)
+            / U256::from(self.migrated_at - self.start_at);
+        u128::try_from(reward_per_lp_x64).map_err(|_| GammaError::MathOverflow.into())
+    }
+
     pub fn get_time_diff(&self) -> Result<Decimal> {
         let time_diff = self
             .end_rewards_at
@@ -23,4 +117,129 @@ impl RewardInfo {
 
         Decimal::from_u64(time_diff).ok_or(error!(GammaError::MathOverflow))
     }
+
+    /// Disburses the rewards since the last update to the lp tokens eligible for rewards.
+    /// Must be called before the lp supply of the pool or the lp tokens of any user change.
+    ///
+    /// # Arguments
+    ///
+    /// * `current_lp_supply` - The lp supply held by positions since the last update, see
+    ///   `PoolState::lp_supply_in_positions`
+    ///
+    pub fn update_reward_per_lp(&mut self, current_lp_supply: u64) -> Result<()> {
+        let time_now = Clock::get()?.unix_timestamp as u64;
+        let disbursed_from = self.schedule_start_at.max(self.last_updated_at);
+        let disbursed_till = time_now.min(self.end_rewards_at);
+        if disbursed_till <= disbursed_from {
+            return Ok(());
+        }
+
+        let rewards_to_disburse = u128::from(self.schedule_amount)
+            .checked_mul(u128::from(disbursed_till - disbursed_from))
+            .ok_or(GammaError::MathOverflow)?
+            .checked_div(u128::from(self.end_rewards_at - self.schedule_start_at))
+            .ok_or(GammaError::MathOverflow)?;
+
+        // The locked liquidity is not eligible for rewards
+        let eligible_lp_supply = current_lp_supply
+            .checked_sub(LOCK_LP_AMOUNT)
+            .ok_or(GammaError::MathOverflow)?;
+        if eligible_lp_supply > 0 {
+            let eligible_lp_supply = u128::from(eligible_lp_supply);
+            let reward_per_lp_x64 = (rewards_to_disburse << 64)
+                .checked_div(eligible_lp_supply)
+                .ok_or(GammaError::MathOverflow)?;
+            self.reward_per_lp_x64 = self
+                .reward_per_lp_x64
+                .checked_add(reward_per_lp_x64)
+                .ok_or(GammaError::MathOverflow)?;
+
+            // The most the eligible lp tokens can claim for this interval, so that the rewards
+            // returned to the provider never include rewards owed to the lps
+            let disbursed = (U256::from(eligible_lp_supply) * U256::from(reward_per_lp_x64)
+                + U256::from(u64::MAX))
+                >> 64;
+            let disbursed = u128::try_from(disbursed).map_err(|_| GammaError::MathOverflow)?;
+            self.total_disbursed = u64::try_from(
+                u128::from(self.total_disbursed)
+                    .checked_add(disbursed)
+                    .ok_or(GammaError::MathOverflow)?,
+            )
+            .map_err(|_| GammaError::MathOverflow)?;
+        }
+
+        self.last_updated_at = disbursed_till;
+        Ok(())
+    }
+
+    /// Restarts the emission schedule from now with the rewards not emitted yet plus `added_amount`,
+    /// emitted evenly until `end_rewards_at`. Past emissions are left untouched.
+    /// `update_reward_per_lp` must be called first.
+    ///
+    /// # Arguments
+    ///
+    /// * `added_amount` - The rewards added to the vault
+    /// * `end_rewards_at` - The new end time of the reward
+    ///
+    pub fn reschedule(&mut self, added_amount: u64, end_rewards_at: u64) -> Result<()> {
+        let time_now = Clock::get()?.unix_timestamp as u64;
+        let emitted_till = time_now
+            .min(self.end_rewards_at)
+            .max(self.schedule_start_at);
+        let emitted = if self.end_rewards_at > self.schedule_start_at {
+            u128::from(self.schedule_amount)
+                .checked_mul(u128::from(emitted_till - self.schedule_start_at))
+                .ok_or(GammaError::MathOverflow)?
+                .checked_div(u128::from(self.end_rewards_at - self.schedule_start_at))
+                .ok_or(GammaError::MathOverflow)? as u64
+        } else {
+            0
+        };
+
+        self.schedule_amount = (self.schedule_amount - emitted)
+            .checked_add(added_amount)
+            .ok_or(GammaError::MathOverflow)?;
+        self.schedule_start_at = time_now.max(self.start_at);
+        self.end_rewards_at = end_rewards_at;
+        self.total_to_disburse = self
+            .total_to_disburse
+            .checked_add(added_amount)
+            .ok_or(GammaError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Ends the reward now if it is still running and returns the rewards that will not be disbursed,
+    /// including the rewards accrued while no lp token was eligible.
+    /// `update_reward_per_lp` must be called first.
+    pub fn close(&mut self) -> Result<u64> {
+        let time_now = Clock::get()?.unix_timestamp as u64;
+        let undisbursed = self.total_to_disburse.saturating_sub(self.total_disbursed);
+
+        self.end_rewards_at = self.end_rewards_at.min(time_now.max(self.start_at));
+        self.schedule_start_at = self.schedule_start_at.min(self.end_rewards_at);
+        self.schedule_amount = 0;
+        self.total_to_disburse -= undisbursed;
+        Ok(undisbursed)
+    }
+
+    /// Records the reward as the latest closed on `pool_state`
+    pub fn mark_closed(&mut self, pool_state: &mut PoolState) -> Result<()> {
+        pool_state.rewards_closed = pool_state
+            .rewards_closed
+            .checked_add(1)
+            .ok_or(GammaError::MathOverflow)?;
+        self.close_index = pool_state.rewards_closed;
+        Ok(())
+    }
+
+    /// The rewards earned by `lp_amount` lp tokens while the reward per lp grew from `reward_per_lp_paid_x64`
+    pub fn rewards_since(&self, lp_amount: u64, reward_per_lp_paid_x64: u128) -> Result<u64> {
+        let reward_per_lp_x64 = self
+            .reward_per_lp_x64
+            .checked_sub(reward_per_lp_paid_x64)
+            .ok_or(GammaError::MathOverflow)?;
+        let rewards = (U256::from(lp_amount) * U256::from(reward_per_lp_x64)) >> 64;
+        let rewards = u128::try_from(rewards).map_err(|_| GammaError::MathOverflow)?;
+        u64::try_from(rewards).map_err(|_| GammaError::MathOverflow.into())
+    }
 }
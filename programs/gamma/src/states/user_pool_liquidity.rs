@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
 
-use super::PartnerType;
+use super::RewardInfo;
+use crate::error::GammaError;
 
 pub const USER_POOL_LIQUIDITY_SEED: &str = "user-pool-liquidity";
 
@@ -14,14 +15,24 @@ pub struct UserPoolLiquidity {
     pub token_0_withdrawn: u128,
     pub token_1_withdrawn: u128,
     pub lp_tokens_owned: u128,
-    pub partner: Option<PartnerType>,
-    pub padding: [u8; 23],
+    /// Id of the partner the position is linked with, see `Partner`.
+    /// Positions linked with "AssetDash" before the registry read as `LEGACY_PARTNER_ID`
+    pub partner: Option<u64>,
+    /// Lp tokens of the position moved into its `LiquidityLock`, they are not part of
+    /// `lp_tokens_owned` but still earn rewards
+    pub lp_tokens_locked: u64,
+    /// Lp tokens of the position that are not released by its `VestingLock` yet, they are not
+    /// part of `lp_tokens_owned` but still earn rewards
+    pub lp_tokens_vesting: u64,
+    /// `PoolState::rewards_closed` at the last lp change of the position
+    pub rewards_closed_seen: u16,
+    pub padding: [u8; 5],
 }
 
 impl UserPoolLiquidity {
     pub const LEN: usize = 8 + 32 * 2 + 16 * 5 + 32;
 
-    pub fn initialize(&mut self, user: Pubkey, pool_state: Pubkey, partner: Option<PartnerType>) {
+    pub fn initialize(&mut self, user: Pubkey, pool_state: Pubkey, partner: Option<u64>) {
         self.user = user;
         self.pool_state = pool_state;
         self.token_0_deposited = 0;
@@ -30,6 +41,27 @@ impl UserPoolLiquidity {
         self.token_1_withdrawn = 0;
         self.lp_tokens_owned = 0;
         self.partner = partner;
-        self.padding = [0u8; 23];
+        self.lp_tokens_locked = 0;
+        self.lp_tokens_vesting = 0;
+        self.rewards_closed_seen = 0;
+        self.padding = [0u8; 5];
+    }
+
+    /// The lp tokens of the position that earn rewards, the owned, the locked and the vesting ones
+    pub fn lp_tokens_earning_rewards(&self) -> Result<u64> {
+        u64::try_from(self.lp_tokens_owned)
+            .ok()
+            .and_then(|lp_tokens_owned| lp_tokens_owned.checked_add(self.lp_tokens_locked))
+            .and_then(|lp_tokens| lp_tokens.checked_add(self.lp_tokens_vesting))
+            .ok_or(GammaError::MathOverflow.into())
+    }
+
+    /// The lp tokens of the position that earn `reward_info`. The first lp change after a reward is
+    /// closed settles it, the position stops earning it from then on
+    pub fn lp_tokens_earning(&self, reward_info: &RewardInfo) -> Result<u64> {
+        if reward_info.close_index != 0 && self.rewards_closed_seen >= reward_info.close_index {
+            return Ok(0);
+        }
+        self.lp_tokens_earning_rewards()
     }
 }
@@ -0,0 +1,95 @@
+use anchor_lang::prelude::*;
+
+use crate::error::GammaError;
+
+pub const VESTING_LOCK_SEED: &str = "vesting-lock";
+
+/// Lp tokens of a position that vest linearly over `duration` seconds from `start_timestamp`,
+/// nothing can be released before `cliff_timestamp`.
+/// The vesting lp tokens are held by the position of the beneficiary through
+/// `UserPoolLiquidity.lp_tokens_vesting`, so they keep earning trading fees and rewards.
+/// A position has one lock per funder.
+#[account]
+#[derive(Default, Debug)]
+pub struct VestingLock {
+    pub pool_state: Pubkey,
+    /// The position the lock is attached to
+    pub user_pool_liquidity: Pubkey,
+    pub beneficiary: Pubkey,
+    /// The wallet the lp tokens were vested from, seed of the lock
+    pub funder: Pubkey,
+    pub lp_tokens_total: u64,
+    pub lp_tokens_released: u64,
+    pub start_timestamp: u64,
+    pub cliff_timestamp: u64,
+    /// Seconds from `start_timestamp` until all the lp tokens are vested
+    pub duration: u64,
+    pub bump: u8,
+    pub padding: [u8; 32],
+}
+
+impl VestingLock {
+    pub const LEN: usize = 8 + 32 * 4 + 8 * 5 + 1 + 32;
+
+    /// Starts a new schedule, a lock can only be reused once all its lp tokens are released
+    pub fn start(
+        &mut self,
+        funder: Pubkey,
+        lp_token_amount: u64,
+        cliff_seconds: u64,
+        duration: u64,
+    ) -> Result<()> {
+        require_eq!(
+            self.lp_tokens_released,
+            self.lp_tokens_total,
+            GammaError::LiquidityLocked
+        );
+        require_gt!(duration, 0, GammaError::InvalidVestingSchedule);
+        require_gte!(duration, cliff_seconds, GammaError::InvalidVestingSchedule);
+
+        let time_now = Clock::get()?.unix_timestamp as u64;
+        self.funder = funder;
+        self.lp_tokens_total = lp_token_amount;
+        self.lp_tokens_released = 0;
+        self.start_timestamp = time_now;
+        self.cliff_timestamp = time_now
+            .checked_add(cliff_seconds)
+            .ok_or(GammaError::MathOverflow)?;
+        self.duration = duration;
+        Ok(())
+    }
+
+    /// The lp tokens vested at `time_now`, released or not
+    pub fn vested_amount(&self, time_now: u64) -> Result<u64> {
+        if time_now < self.cliff_timestamp {
+            return Ok(0);
+        }
+        let elapsed = time_now.saturating_sub(self.start_timestamp);
+        if elapsed >= self.duration {
+            return Ok(self.lp_tokens_total);
+        }
+
+        let vested = u128::from(self.lp_tokens_total)
+            .checked_mul(u128::from(elapsed))
+            .ok_or(GammaError::MathOverflow)?
+            .checked_div(u128::from(self.duration))
+            .ok_or(GammaError::MathOverflow)?;
+        u64::try_from(vested).map_err(|_| GammaError::MathOverflow.into())
+    }
+
+    /// Releases the lp tokens vested since the last release
+    pub fn release(&mut self) -> Result<u64> {
+        let time_now = Clock::get()?.unix_timestamp as u64;
+        let lp_tokens_releasable = self
+            .vested_amount(time_now)?
+            .checked_sub(self.lp_tokens_released)
+            .ok_or(GammaError::MathOverflow)?;
+        require_gt!(lp_tokens_releasable, 0, GammaError::LiquidityLocked);
+
+        self.lp_tokens_released = self
+            .lp_tokens_released
+            .checked_add(lp_tokens_releasable)
+            .ok_or(GammaError::MathOverflow)?;
+        Ok(lp_tokens_releasable)
+    }
+}
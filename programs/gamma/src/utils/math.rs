@@ -46,3 +46,55 @@ impl CheckedCeilDiv for u128 {
     }
 }
 
+/// 2^64, the scale of the Q64.64 fixed point numbers below
+pub const Q64: u128 = 1 << 64;
+/// ln(2) as a Q64.64 fixed point number, rounded down
+const LN_2_X64: u128 = 0xb17217f7d1cf79ab;
+
+/// Base 2 logarithm of a positive integer as a Q64.64 fixed point number, rounded down.
+/// Non-decreasing in `value`, the error is less than 8 units of 2^-64.
+///
+/// Only uses u128 arithmetic so that it stays deterministic and cheap on chain.
+pub fn log2_x64(value: u128) -> Option<u128> {
+    if value == 0 {
+        return None;
+    }
+    let msb = 127 - value.leading_zeros();
+    let mut result = u128::from(msb) << 64;
+    // Normalize to z in [1, 2) as a Q1.63 number, so that squaring fits into u128
+    let mut z = if msb >= 63 {
+        value >> (msb - 63)
+    } else {
+        value << (63 - msb)
+    };
+    // Every squaring gives the next bit of the fraction
+    for bit in (1..64).rev() {
+        z = (z * z) >> 63;
+        if z >= 1 << 64 {
+            z >>= 1;
+            result |= 1 << bit;
+        }
+    }
+    Some(result)
+}
+
+/// 2^(-exponent) for a Q64.64 fixed point `exponent`, as a Q64.64 fixed point number.
+/// The error is at most 16 units of 2^-64.
+pub fn exp2_neg_x64(exponent: u128) -> u128 {
+    let integer = exponent >> 64;
+    if integer >= 64 {
+        return 0;
+    }
+    let fraction = exponent & (Q64 - 1);
+    // 2^(-f) = 2^(1 - f) / 2, with e^((1 - f) * ln(2)) summed as a taylor series of positive terms
+    let t = (U256::from(Q64 - fraction) * U256::from(LN_2_X64)) >> 64;
+    let mut term = U256::from(Q64);
+    let mut sum = term;
+    let mut n = 1u64;
+    while !term.is_zero() {
+        term = ((term * t) >> 64) / n;
+        sum += term;
+        n += 1;
+    }
+    (sum >> (integer + 1)).as_u128()
+}
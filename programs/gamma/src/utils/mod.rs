@@ -1,7 +1,9 @@
 pub mod math;
+pub mod rewards;
 pub mod swap_referral;
 pub mod token;
 
 pub use math::*;
+pub use rewards::*;
 pub use swap_referral::*;
 pub use token::*;
@@ -0,0 +1,254 @@
+use crate::{
+    error::GammaError,
+    states::{PoolState, RewardInfo, UserPoolLiquidity, UserRewardInfo},
+    USER_REWARD_INFO_SEED,
+};
+use anchor_lang::prelude::*;
+
+/// Brings the reward accruals of `users` up to date before their lp balance changes.
+///
+/// `remaining_accounts` is read in groups of `1 + users.len()` for every reward of the pool:
+/// `[reward_info, user_reward_info for users[0], user_reward_info for users[1], ..]`.
+/// Every open reward of the pool must be passed, so that no lp change escapes the reward accounting.
+/// The rewards closed since the last lp change of a position holding lp tokens must be passed as
+/// well, the lp change makes the position stop earning them, see `UserPoolLiquidity::lp_tokens_earning`.
+/// Missing user reward infos are created and user reward infos of the legacy layout are migrated,
+/// both paid for by `payer`, which then has to be writable, and need the `system_program`.
+pub fn settle_user_rewards<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    pool_id: Pubkey,
+    pool_state: &PoolState,
+    users: &mut [(Pubkey, &mut UserPoolLiquidity)],
+    payer: &AccountInfo<'info>,
+    system_program: Option<&AccountInfo<'info>>,
+) -> Result<()> {
+    let group_len = users.len() + 1;
+    require!(
+        remaining_accounts.len() % group_len == 0,
+        GammaError::IncompleteRewardInfos
+    );
+
+    let mut settled_reward_infos = Vec::with_capacity(remaining_accounts.len() / group_len);
+    let mut open_rewards = 0;
+    let mut settled_close_indexes = Vec::new();
+    for accounts in remaining_accounts.chunks(group_len) {
+        let mut reward_info = Account::<RewardInfo>::try_from(&accounts[0])?;
+        require_keys_eq!(reward_info.pool, pool_id, GammaError::InvalidInput);
+        require!(accounts[0].is_writable, GammaError::InvalidInput);
+        let reward_info_key = reward_info.key();
+        require!(
+            !settled_reward_infos.contains(&reward_info_key),
+            GammaError::IncompleteRewardInfos
+        );
+        settled_reward_infos.push(reward_info_key);
+        if reward_info.close_index == 0 {
+            open_rewards += 1;
+        } else {
+            settled_close_indexes.push(reward_info.close_index);
+        }
+
+        reward_info.update_reward_per_lp(pool_state.lp_supply_in_positions()?)?;
+        reward_info.exit(&crate::id())?;
+
+        for (user_reward_info, (user, user_pool_liquidity)) in
+            accounts[1..].iter().zip(users.iter())
+        {
+            let (expected_key, bump) = Pubkey::find_program_address(
+                &[
+                    USER_REWARD_INFO_SEED.as_bytes(),
+                    reward_info_key.as_ref(),
+                    user.as_ref(),
+                ],
+                &crate::id(),
+            );
+            require_keys_eq!(
+                user_reward_info.key(),
+                expected_key,
+                GammaError::InvalidInput
+            );
+
+            if user_reward_info.owner == &System::id() {
+                let system_program = system_program.ok_or(ErrorCode::AccountNotEnoughKeys)?;
+                create_user_reward_info(
+                    user_reward_info,
+                    payer,
+                    system_program,
+                    &[&[
+                        USER_REWARD_INFO_SEED.as_bytes(),
+                        reward_info_key.as_ref(),
+                        user.as_ref(),
+                        &[bump],
+                    ]],
+                )?;
+            } else if user_reward_info.data_len() == UserRewardInfo::LEGACY_LEN {
+                let system_program = system_program.ok_or(ErrorCode::AccountNotEnoughKeys)?;
+                extend_legacy_user_reward_info(
+                    user_reward_info,
+                    &reward_info,
+                    payer,
+                    system_program,
+                )?;
+            }
+
+            let mut user_reward_info = Account::<UserRewardInfo>::try_from(user_reward_info)?;
+            user_reward_info.calculate_claimable_rewards(
+                user_pool_liquidity.lp_tokens_earning(&reward_info)?,
+                &reward_info,
+            )?;
+            user_reward_info.exit(&crate::id())?;
+        }
+    }
+    require_eq!(
+        open_rewards,
+        pool_state.reward_count,
+        GammaError::IncompleteRewardInfos
+    );
+
+    for (_, user_pool_liquidity) in users.iter_mut() {
+        // Closed rewards left out would be forfeited by the position and stay in their vault
+        if user_pool_liquidity.lp_tokens_earning_rewards()? > 0 {
+            require!(
+                (user_pool_liquidity.rewards_closed_seen + 1..=pool_state.rewards_closed)
+                    .all(|close_index| settled_close_indexes.contains(&close_index)),
+                GammaError::IncompleteRewardInfos
+            );
+        }
+        user_pool_liquidity.rewards_closed_seen = pool_state.rewards_closed;
+    }
+    Ok(())
+}
+
+fn create_user_reward_info<'info>(
+    user_reward_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let space = 8 + std::mem::size_of::<UserRewardInfo>();
+    create_pda_account(user_reward_info, payer, system_program, space, signer_seeds)?;
+
+    let data = UserRewardInfo {
+        total_claimed: 0,
+        total_rewards: 0,
+        rewards_last_calculated_at: 0,
+        reward_per_lp_paid_x64: 0,
+    };
+    let mut account_data = user_reward_info.try_borrow_mut_data()?;
+    data.try_serialize(&mut &mut account_data[..])
+}
+
+/// Creates a program owned pda of `space` bytes the way anchor's `init` does.
+///
+/// Anyone can send lamports to a pda before it is created, which makes `create_account` fail,
+/// so a pre-funded address is topped up to rent exemption, allocated and assigned instead.
+fn create_pda_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let current_lamports = account.lamports();
+    if current_lamports == 0 {
+        return anchor_lang::system_program::create_account(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            )
+            .with_signer(signer_seeds),
+            rent,
+            space as u64,
+            &crate::id(),
+        );
+    }
+
+    let lamports = rent.saturating_sub(current_lamports);
+    if lamports > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            lamports,
+        )?;
+    }
+    anchor_lang::system_program::allocate(
+        CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+        )
+        .with_signer(signer_seeds),
+        space as u64,
+    )?;
+    anchor_lang::system_program::assign(
+        CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+        )
+        .with_signer(signer_seeds),
+        &crate::id(),
+    )
+}
+
+/// Extends a user reward info of the legacy layout, see `UserRewardInfo::migrate`
+pub fn extend_legacy_user_reward_info<'info>(
+    user_reward_info: &AccountInfo<'info>,
+    reward_info: &RewardInfo,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    require_eq!(
+        user_reward_info.data_len(),
+        UserRewardInfo::LEGACY_LEN,
+        GammaError::RewardAlreadyMigrated
+    );
+    realloc_account(
+        user_reward_info,
+        payer,
+        system_program,
+        8 + std::mem::size_of::<UserRewardInfo>(),
+    )?;
+
+    // The legacy fields are a prefix of the new layout and the added ones are zeroed
+    let mut data = UserRewardInfo::try_deserialize(&mut &user_reward_info.try_borrow_data()?[..])?;
+    data.migrate(reward_info)?;
+    let mut account_data = user_reward_info.try_borrow_mut_data()?;
+    data.try_serialize(&mut &mut account_data[..])
+}
+
+/// Resizes a program account to `space` zeroed bytes, `payer` tops up its rent exemption
+pub fn realloc_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+) -> Result<()> {
+    let lamports = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(account.lamports());
+    if lamports > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            lamports,
+        )?;
+    }
+    account.realloc(space, true)?;
+    Ok(())
+}
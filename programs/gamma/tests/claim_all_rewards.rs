@@ -0,0 +1,151 @@
+use anchor_spl::token::TokenAccount;
+use gamma::{error::GammaError, states::UserRewardInfo, REWARD_INFO_SEED, USER_REWARD_INFO_SEED};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn should_claim_rewards_of_many_pools_at_once() {
+    let user = Keypair::new();
+    let other_user = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        other_user.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000, test_env.token_1_mint)
+        .await;
+
+    // One reward in each of two pools
+    let timestamp_now = test_env.timestamp_now().await as u64;
+    let (start_time, end_time) = (timestamp_now + 10, timestamp_now + 1010);
+    let reward_amounts = [1000000000, 300000000];
+    let mut claims = vec![];
+    for (amm_index, reward_amount) in reward_amounts.into_iter().enumerate() {
+        let amm_index = amm_index as u16;
+        test_env
+            .create_config(&admin, amm_index, 100, 20, 5, 0)
+            .await;
+        let pool_id = test_env
+            .initialize_pool(
+                &user,
+                amm_index,
+                1000,
+                2000,
+                0,
+                gamma::create_pool_fee_reveiver::id(),
+            )
+            .await;
+
+        let reward_mint = Keypair::new();
+        test_env
+            .create_token_mint(&reward_mint, &test_env.mint_authority.pubkey(), 9)
+            .await;
+        let reward_provider_token_account = test_env
+            .get_or_create_associated_token_account(
+                reward_provider.pubkey(),
+                reward_mint.pubkey(),
+                &reward_provider,
+            )
+            .await;
+        test_env
+            .mint_base_tokens(
+                reward_provider_token_account,
+                reward_amount,
+                reward_mint.pubkey(),
+            )
+            .await;
+        test_env
+            .create_rewards(
+                &reward_provider,
+                pool_id,
+                start_time,
+                end_time,
+                reward_mint.pubkey(),
+                reward_amount,
+            )
+            .await;
+        let (reward_info_key, _) = Pubkey::find_program_address(
+            &[
+                REWARD_INFO_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                &start_time.to_le_bytes(),
+                reward_mint.pubkey().to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        );
+        test_env
+            .calculate_rewards(&user, pool_id, reward_info_key)
+            .await;
+        claims.push((pool_id, reward_info_key, reward_mint.pubkey()));
+    }
+
+    // Jump to the end of the rewards
+    test_env.jump_seconds(2000).await;
+
+    // The other user has no liquidity in the pools to claim for
+    let result = test_env
+        .claim_all_rewards(&other_user, claims.clone())
+        .await;
+    assert!(result.unwrap().result.is_err());
+    let result = test_env.claim_all_rewards(&user, vec![]).await;
+    assert_error!(result, GammaError::InvalidInput);
+
+    let result = test_env.claim_all_rewards(&user, claims.clone()).await;
+    result.unwrap_transaction();
+    let log_messages = result.unwrap().metadata.unwrap().log_messages;
+    let events = log_messages
+        .iter()
+        .filter(|log| log.starts_with("Program data: "))
+        .count();
+    assert_eq!(events, claims.len());
+
+    for ((_, reward_info_key, reward_mint), reward_amount) in claims.iter().zip(reward_amounts) {
+        let (user_reward_info_key, _) = Pubkey::find_program_address(
+            &[
+                USER_REWARD_INFO_SEED.as_bytes(),
+                reward_info_key.to_bytes().as_ref(),
+                user.pubkey().to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        );
+        let user_reward_info: UserRewardInfo = test_env.fetch_account(user_reward_info_key).await;
+        assert!(user_reward_info.total_rewards >= reward_amount - 1);
+        assert_eq!(
+            user_reward_info.total_claimed,
+            user_reward_info.total_rewards
+        );
+
+        let user_token_account = test_env
+            .get_or_create_associated_token_account(user.pubkey(), *reward_mint, &user)
+            .await;
+        let user_token_account: TokenAccount = test_env.fetch_account(user_token_account).await;
+        assert_eq!(user_token_account.amount, user_reward_info.total_claimed);
+    }
+
+    // Nothing is left to claim, so nothing is emitted
+    test_env.jump_seconds(10).await;
+    let result = test_env.claim_all_rewards(&user, claims.clone()).await;
+    result.unwrap_transaction();
+    let log_messages = result.unwrap().metadata.unwrap().log_messages;
+    assert!(!log_messages
+        .iter()
+        .any(|log| log.starts_with("Program data: ")));
+}
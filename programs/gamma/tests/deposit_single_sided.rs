@@ -0,0 +1,127 @@
+use gamma::{
+    fees::{ceil_div, FeeType, FEE_RATE_DENOMINATOR_VALUE},
+    states::{PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn should_deposit_single_sided_with_either_token() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            10000000,
+            20000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+
+    let user_pool_liquidity_key = Pubkey::find_program_address(
+        &[
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+            user.pubkey().to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    )
+    .0;
+
+    for input_mint in [test_env.token_0_mint, test_env.token_1_mint] {
+        let pool_state_before: PoolState = test_env.fetch_account(pool_id).await;
+        let user_pool_liquidity_before: UserPoolLiquidity =
+            test_env.fetch_account(user_pool_liquidity_key).await;
+
+        test_env
+            .deposit_single_sided(&user, pool_id, amm_index, input_mint, 1000000, 1)
+            .await;
+
+        let pool_state_after: PoolState = test_env.fetch_account(pool_id).await;
+        let user_pool_liquidity_after: UserPoolLiquidity =
+            test_env.fetch_account(user_pool_liquidity_key).await;
+        let lp_minted = pool_state_after.lp_supply - pool_state_before.lp_supply;
+        assert!(lp_minted > 0);
+        assert_eq!(
+            user_pool_liquidity_after.lp_tokens_owned - user_pool_liquidity_before.lp_tokens_owned,
+            lp_minted as u128
+        );
+
+        // The minted lp should be worth close to the whole input, only the swap fee
+        // and rounding on the swapped part are lost.
+        let (vault_0_before, vault_1_before) =
+            pool_state_before.vault_amount_without_fee().unwrap();
+        let input_vault_before = if input_mint == test_env.token_0_mint {
+            vault_0_before
+        } else {
+            vault_1_before
+        };
+        let input_value_of_lp = lp_minted as u128 * 2 * input_vault_before as u128
+            / pool_state_before.lp_supply as u128;
+        assert!(input_value_of_lp <= 1000000);
+        assert!(input_value_of_lp >= 990000);
+    }
+}
+
+#[tokio::test]
+async fn should_size_the_single_sided_swap_with_the_imbalance_fee_it_pays() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env.setup_pool(&user, &admin).await;
+    test_env
+        .update_pool(&admin, pool_id, 0, 7, FeeType::Imbalance as u64)
+        .await;
+    let (token_0_mint, token_1_mint) = (test_env.token_0_mint, test_env.token_1_mint);
+    let token_1_before = test_env.token_balance(&user, token_1_mint).await;
+    let pool_state_before: PoolState = test_env.fetch_account(pool_id).await;
+
+    // The imbalance fee of the whole input is almost twice the one of the part swapped
+    test_env
+        .deposit_single_sided(&user, pool_id, 0, token_0_mint, 500000, 1)
+        .await;
+
+    // The swap is charged at the rate it was sized with
+    let pool_state_after: PoolState = test_env.fetch_account(pool_id).await;
+    let amount_swapped =
+        pool_state_after.cumulative_volume_token_0 - pool_state_before.cumulative_volume_token_0;
+    let fee_charged = pool_state_after.cumulative_trade_fees_token_0
+        - pool_state_before.cumulative_trade_fees_token_0;
+    assert_eq!(
+        fee_charged,
+        ceil_div(
+            amount_swapped,
+            u128::from(pool_state_after.latest_dynamic_fee_rate),
+            u128::from(FEE_RATE_DENOMINATOR_VALUE)
+        )
+        .unwrap()
+    );
+
+    // So the swap output fits the pool ratio, only the rounding of the deposit goes back to the user
+    let token_1_left = test_env.token_balance(&user, token_1_mint).await - token_1_before;
+    assert!(token_1_left <= 10, "{} token_1 left", token_1_left);
+}
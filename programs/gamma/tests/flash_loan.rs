@@ -0,0 +1,149 @@
+use gamma::{curve::TradeDirection, error::GammaError, states::PoolState};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+const FLASH_LOAN_FEE_RATE: u64 = 1000;
+
+async fn setup_pool(test_env: &mut TestEnv, user: &Keypair, admin: &Keypair) -> Pubkey {
+    let pool_id = test_env.setup_pool(user, admin).await;
+    // 10% protocol fee and 5% fund fee
+    test_env.update_amm_config(admin, 0, 1, 100_000).await;
+    test_env.update_amm_config(admin, 0, 2, 50_000).await;
+    test_env
+        .update_amm_config(admin, 0, 9, FLASH_LOAN_FEE_RATE)
+        .await;
+    pool_id
+}
+
+#[tokio::test]
+async fn should_credit_the_flash_loan_fee_to_the_pool() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = setup_pool(&mut test_env, &user, &admin).await;
+    let token_0_mint = test_env.token_0_mint;
+
+    let pool_state_before: PoolState = test_env.fetch_account(pool_id).await;
+    let balance_before = test_env.token_balance(&user, token_0_mint).await;
+
+    let borrow = test_env
+        .flash_borrow_instruction(&user, pool_id, token_0_mint, 100_000)
+        .await;
+    let repay = test_env
+        .flash_repay_instruction(&user, pool_id, 0, token_0_mint)
+        .await;
+    let transaction =
+        get_signed_transaction(&mut test_env.program_test_context, &[borrow, repay], &user).await;
+    test_env
+        .program_test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // 100 of fee, 10 for the protocol, 5 for the fund and 85 for the lps
+    assert_eq!(
+        test_env.token_balance(&user, token_0_mint).await,
+        balance_before - 100
+    );
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq_with_copy!(pool_state.flash_loan_amount, 0);
+    assert_eq_with_copy!(
+        pool_state.token_0_vault_amount,
+        pool_state_before.token_0_vault_amount + 85
+    );
+    assert_eq_with_copy!(
+        pool_state.protocol_fees_token_0,
+        pool_state_before.protocol_fees_token_0 + 10
+    );
+    assert_eq_with_copy!(
+        pool_state.fund_fees_token_0,
+        pool_state_before.fund_fees_token_0 + 5
+    );
+    assert_eq_with_copy!(
+        pool_state.token_1_vault_amount,
+        pool_state_before.token_1_vault_amount
+    );
+}
+
+#[tokio::test]
+async fn should_not_borrow_without_repaying_in_the_same_transaction() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = setup_pool(&mut test_env, &user, &admin).await;
+    let token_1_mint = test_env.token_1_mint;
+
+    let borrow = test_env
+        .flash_borrow_instruction(&user, pool_id, token_1_mint, 100_000)
+        .await;
+    let transaction =
+        get_signed_transaction(&mut test_env.program_test_context, &[borrow], &user).await;
+    let result = test_env
+        .program_test_context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await;
+    assert_error!(result, GammaError::FlashLoanNotRepaid);
+}
+
+#[tokio::test]
+async fn should_block_swaps_while_a_flash_loan_is_open() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = setup_pool(&mut test_env, &user, &admin).await;
+    let token_0_mint = test_env.token_0_mint;
+
+    let borrow = test_env
+        .flash_borrow_instruction(&user, pool_id, token_0_mint, 500_000)
+        .await;
+    let swap = test_env
+        .swap_base_input_instruction(&user, pool_id, 0, 100_000, 1, TradeDirection::OneForZero)
+        .await;
+    let repay = test_env
+        .flash_repay_instruction(&user, pool_id, 0, token_0_mint)
+        .await;
+    let transaction = get_signed_transaction(
+        &mut test_env.program_test_context,
+        &[borrow, swap, repay],
+        &user,
+    )
+    .await;
+    let result = test_env
+        .program_test_context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await;
+    assert_error!(result, GammaError::FlashLoanOpen);
+}
+
+#[tokio::test]
+async fn should_not_borrow_while_swaps_are_disabled() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = setup_pool(&mut test_env, &user, &admin).await;
+    let token_0_mint = test_env.token_0_mint;
+
+    // Disable swaps
+    test_env.update_pool(&admin, pool_id, 0, 0, 1 << 2).await;
+
+    let borrow = test_env
+        .flash_borrow_instruction(&user, pool_id, token_0_mint, 100_000)
+        .await;
+    let repay = test_env
+        .flash_repay_instruction(&user, pool_id, 0, token_0_mint)
+        .await;
+    let transaction =
+        get_signed_transaction(&mut test_env.program_test_context, &[borrow, repay], &user).await;
+    let result = test_env
+        .program_test_context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await;
+    assert_error!(result, GammaError::NotApproved);
+}
@@ -0,0 +1,136 @@
+use gamma::{curve::TradeDirection, error::GammaError, states::PoolState};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+/// Flash swaps token_0 for `amount_out` token_1, the callback is a token transfer of
+/// `callback_amount` token_0 from the venue to the trader standing in for a trade elsewhere
+async fn flash_swap(
+    test_env: &mut TestEnv,
+    trader: &Keypair,
+    venue: &Keypair,
+    pool_id: Pubkey,
+    amount_out: u64,
+    callback_amount: u64,
+) -> ProcessTransactionResult {
+    let token_0_mint = test_env.token_0_mint;
+    let venue_token_account = test_env
+        .get_or_create_associated_token_account(venue.pubkey(), token_0_mint, venue)
+        .await;
+    let trader_token_account = test_env
+        .get_or_create_associated_token_account(trader.pubkey(), token_0_mint, trader)
+        .await;
+    let callback = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &venue_token_account,
+        &trader_token_account,
+        &venue.pubkey(),
+        &[],
+        callback_amount,
+    )
+    .unwrap();
+
+    let instruction = test_env
+        .flash_swap_base_output_instruction(
+            trader,
+            pool_id,
+            0,
+            u64::MAX,
+            amount_out,
+            TradeDirection::ZeroForOne,
+            spl_token::id(),
+            callback.data,
+            callback.accounts,
+        )
+        .await;
+    let transaction = get_signed_transaction_with_different_payer(
+        &mut test_env.program_test_context,
+        &[instruction],
+        venue,
+        trader,
+    )
+    .await;
+    test_env
+        .program_test_context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+}
+
+#[tokio::test]
+async fn should_pay_the_flash_swap_input_with_the_callback_proceeds() {
+    let user = Keypair::new();
+    let trader = Keypair::new();
+    let venue = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        trader.pubkey(),
+        venue.pubkey(),
+        admin.pubkey(),
+    ])
+    .await;
+    let pool_id = test_env.setup_pool(&user, &admin).await;
+    let (token_0_mint, token_1_mint) = (test_env.token_0_mint, test_env.token_1_mint);
+    let venue_token_account = test_env
+        .get_or_create_associated_token_account(venue.pubkey(), token_0_mint, &venue)
+        .await;
+    test_env
+        .mint_base_tokens(venue_token_account, 1_000_000, token_0_mint)
+        .await;
+
+    let pool_state_before: PoolState = test_env.fetch_account(pool_id).await;
+
+    // The trader starts without any token_0 to pay the swap
+    assert_eq!(test_env.token_balance(&trader, token_0_mint).await, 0);
+    flash_swap(&mut test_env, &trader, &venue, pool_id, 10_000, 6_000)
+        .await
+        .unwrap_transaction();
+
+    assert_eq!(test_env.token_balance(&trader, token_1_mint).await, 10_000);
+    let amount_paid = 6_000 - test_env.token_balance(&trader, token_0_mint).await;
+    // The price is about 2 token_1 per token_0
+    assert!(amount_paid > 5_000);
+    assert!(amount_paid < 5_200);
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq_with_copy!(pool_state.flash_loan_amount, 0);
+    assert_eq_with_copy!(
+        pool_state.token_1_vault_amount,
+        pool_state_before.token_1_vault_amount - 10_000
+    );
+    assert_eq_with_copy!(
+        pool_state.token_0_vault_amount,
+        pool_state_before.token_0_vault_amount + amount_paid
+            - (pool_state.protocol_fees_token_0 - pool_state_before.protocol_fees_token_0)
+            - (pool_state.fund_fees_token_0 - pool_state_before.fund_fees_token_0)
+    );
+}
+
+#[tokio::test]
+async fn should_fail_when_the_callback_does_not_cover_the_input() {
+    let user = Keypair::new();
+    let trader = Keypair::new();
+    let venue = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        trader.pubkey(),
+        venue.pubkey(),
+        admin.pubkey(),
+    ])
+    .await;
+    let pool_id = test_env.setup_pool(&user, &admin).await;
+    let token_0_mint = test_env.token_0_mint;
+    let venue_token_account = test_env
+        .get_or_create_associated_token_account(venue.pubkey(), token_0_mint, &venue)
+        .await;
+    test_env
+        .mint_base_tokens(venue_token_account, 1_000_000, token_0_mint)
+        .await;
+
+    let result = flash_swap(&mut test_env, &trader, &venue, pool_id, 10_000, 1_000).await;
+    assert_error!(result, GammaError::FlashSwapNotPaid);
+}
@@ -0,0 +1,106 @@
+use anchor_lang::AnchorDeserialize;
+use gamma::{
+    curve::TradeDirection,
+    error::GammaError,
+    states::{ObservationState, PoolState, Twap, OBSERVATION_SEED},
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn get_twap_should_average_prices_over_window() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env.jump_seconds(1).await;
+
+    // Nothing has been observed before the first swap
+    let result = test_env.get_twap(&user, pool_id, 60).await;
+    assert_error!(result, GammaError::ObservationCoverageTooShort);
+
+    // Every swap lowers the price of token_0 after being observed
+    for _ in 0..3 {
+        test_env
+            .swap_base_input(
+                &user,
+                pool_id,
+                amm_index,
+                500000,
+                1,
+                TradeDirection::ZeroForOne,
+            )
+            .await;
+        test_env.jump_seconds(30).await;
+    }
+
+    let (observation_key, __bump) = Pubkey::find_program_address(
+        &[OBSERVATION_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+        &gamma::ID,
+    );
+    let observation_state: ObservationState = test_env.fetch_account(observation_key).await;
+    let first_observation_time = observation_state.observations[0].block_timestamp;
+    let second_observation_time = observation_state.observations[1].block_timestamp;
+    let now = test_env.timestamp_now().await as u64;
+
+    // The window can not start before the first observation
+    let result = test_env
+        .get_twap(&user, pool_id, now - first_observation_time + 1)
+        .await;
+    assert_error!(result, GammaError::ObservationCoverageTooShort);
+
+    let window = now - second_observation_time;
+    let result = test_env.get_twap(&user, pool_id, window).await;
+    result.unwrap_transaction();
+    let return_data = result.unwrap().metadata.unwrap().return_data.unwrap();
+    let twap = Twap::try_from_slice(&return_data.data).unwrap();
+
+    assert_eq!(twap.start_time, second_observation_time);
+    assert_eq!(twap.end_time, now);
+
+    // The newest part of the window is priced at the current spot price
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let (token_0_price_x32, token_1_price_x32) = pool_state
+        .token_price_x32(test_env.timestamp_now().await as u64)
+        .unwrap();
+    assert_eq!(twap.token_0_min_price_x32, token_0_price_x32);
+    assert_eq!(twap.token_1_max_price_x32, token_1_price_x32);
+
+    assert!(twap.token_0_min_price_x32 < twap.token_0_twap_price_x32);
+    assert!(twap.token_0_twap_price_x32 < twap.token_0_max_price_x32);
+    assert!(twap.token_1_min_price_x32 < twap.token_1_twap_price_x32);
+    assert!(twap.token_1_twap_price_x32 < twap.token_1_max_price_x32);
+    // Token_0 was sold into the pool during the whole window
+    assert!(twap.token_0_max_price_x32 < 1 << 32);
+    assert!(twap.token_1_min_price_x32 > 1 << 32);
+}
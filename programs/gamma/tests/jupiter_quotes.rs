@@ -1,12 +1,11 @@
 use std::collections::HashMap;
 
 use anchor_spl::token::TokenAccount;
-use gamma::{curve::TradeDirection, states::PoolState};
+use gamma::{curve::TradeDirection, jupiter, states::PoolState};
 use solana_program_test::tokio;
-use solana_sdk::{clock::Clock, signature::Keypair, signer::Signer};
+use solana_sdk::{clock::Clock, pubkey::Pubkey, signature::Keypair, signer::Signer};
 mod utils;
 use jupiter_amm_interface::{AccountMap, Amm, AmmContext, ClockRef, KeyedAccount, SwapMode};
-use utils::jupiter;
 
 use utils::*;
 
@@ -331,3 +330,171 @@ async fn jupiter_quotes() {
         test_env.jump_seconds(16).await;
     }
 }
+
+async fn update_jupiter_amm(
+    test_env: &mut TestEnv,
+    amm: &mut jupiter::Gamma,
+    amm_context: &AmmContext,
+    pool_id: Pubkey,
+) {
+    let hasher = ahash::RandomState::new();
+    let mut account_map: AccountMap = HashMap::with_hasher(hasher);
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    for key in [
+        pool_id,
+        pool_state.token_0_mint,
+        pool_state.token_1_mint,
+        pool_state.amm_config,
+        pool_state.observation_key,
+        pool_state.token_0_vault,
+        pool_state.token_1_vault,
+    ] {
+        let account = test_env.get_account_info(key).await.unwrap().unwrap();
+        account_map.insert(key, account);
+    }
+    amm.update(&account_map).unwrap();
+
+    let clock: Clock = test_env
+        .program_test_context
+        .banks_client
+        .get_sysvar()
+        .await
+        .unwrap();
+    amm_context.clock_ref.update(clock);
+}
+
+#[tokio::test]
+async fn jupiter_exact_out_quotes() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+
+    test_env
+        .create_config(&admin, amm_index, 1000, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000000000, test_env.token_1_mint)
+        .await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            20000000000000,
+            10000000000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env.jump_seconds(100).await;
+
+    let keyed_account = KeyedAccount {
+        key: pool_id,
+        account: test_env.get_account_info(pool_id).await.unwrap().unwrap(),
+        params: None,
+    };
+    let clock: Clock = test_env
+        .program_test_context
+        .banks_client
+        .get_sysvar()
+        .await
+        .unwrap();
+    let amm_context = AmmContext {
+        clock_ref: ClockRef::from(clock),
+    };
+    let mut amm = jupiter::Gamma::from_keyed_account(&keyed_account, &amm_context).unwrap();
+    assert!(amm.supports_exact_out());
+
+    for (index, (trade_direction, amount_out)) in [
+        (TradeDirection::ZeroForOne, 100000000000),
+        (TradeDirection::OneForZero, 100000),
+    ]
+    .into_iter()
+    .cycle()
+    .take(20)
+    .enumerate()
+    {
+        let (input_mint, output_mint, input_account, output_account) = match trade_direction {
+            TradeDirection::ZeroForOne => (
+                test_env.token_0_mint,
+                test_env.token_1_mint,
+                user_token_0_account,
+                user_token_1_account,
+            ),
+            TradeDirection::OneForZero => (
+                test_env.token_1_mint,
+                test_env.token_0_mint,
+                user_token_1_account,
+                user_token_0_account,
+            ),
+        };
+        update_jupiter_amm(&mut test_env, &mut amm, &amm_context, pool_id).await;
+        let quote = amm
+            .quote(&jupiter_amm_interface::QuoteParams {
+                amount: amount_out,
+                input_mint,
+                output_mint,
+                swap_mode: SwapMode::ExactOut,
+            })
+            .unwrap();
+        assert_eq!(quote.out_amount, amount_out);
+
+        let pool_state_before: PoolState = test_env.fetch_account(pool_id).await;
+        let input_before: TokenAccount = test_env.fetch_account(input_account).await;
+        let output_before: TokenAccount = test_env.fetch_account(output_account).await;
+
+        // The quoted input is exactly enough for the swap built by the adapter
+        let swap_params = jupiter_amm_interface::SwapParams {
+            swap_mode: SwapMode::ExactOut,
+            in_amount: quote.in_amount,
+            out_amount: quote.out_amount,
+            source_mint: input_mint,
+            destination_mint: output_mint,
+            source_token_account: input_account,
+            destination_token_account: output_account,
+            token_transfer_authority: user.pubkey(),
+            quote_mint_to_referrer: None,
+            jupiter_program_id: &Pubkey::default(),
+            missing_dynamic_accounts_as_default: false,
+        };
+        // Swap has no variant for Gamma, the trait path fails until it does
+        assert!(amm.get_swap_and_account_metas(&swap_params).is_err());
+        let instruction = amm.swap_instruction(&swap_params).unwrap();
+        let transaction =
+            get_signed_transaction(&mut test_env.program_test_context, &[instruction], &user).await;
+        test_env
+            .program_test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+
+        let input_after: TokenAccount = test_env.fetch_account(input_account).await;
+        let output_after: TokenAccount = test_env.fetch_account(output_account).await;
+        assert_eq!(input_before.amount - input_after.amount, quote.in_amount);
+        assert_eq!(output_after.amount - output_before.amount, quote.out_amount);
+
+        let pool_state_after: PoolState = test_env.fetch_account(pool_id).await;
+        let fees_charged = if index % 2 == 0 {
+            pool_state_after.cumulative_trade_fees_token_0
+                - pool_state_before.cumulative_trade_fees_token_0
+        } else {
+            pool_state_after.cumulative_trade_fees_token_1
+                - pool_state_before.cumulative_trade_fees_token_1
+        };
+        assert_eq!(fees_charged as u64, quote.fee_amount);
+
+        test_env.jump_seconds(16).await;
+    }
+}
@@ -0,0 +1,154 @@
+use anchor_spl::token::TokenAccount;
+use gamma::{curve::TradeDirection, fees::LaunchFeeDecay, states::PoolState};
+use solana_program_test::tokio;
+use solana_sdk::{signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn launch_fee_should_decay_to_normal_fee() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env.create_config(&admin, 0, 100, 20, 5, 0).await;
+    test_env.create_config(&admin, 1, 100, 20, 5, 0).await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    let normal_pool = test_env
+        .initialize_pool(
+            &user,
+            0,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    // 5% at open time, decaying over an hour
+    let launch_pool = test_env
+        .initialize_pool_with_launch_fee(
+            &user,
+            1,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+            0,
+            0,
+            0,
+            50000,
+            3600,
+            LaunchFeeDecay::Linear as u8,
+        )
+        .await;
+
+    let launch_pool_state: PoolState = test_env.fetch_account(launch_pool).await;
+    assert_eq!(launch_pool_state.launch_fee_rate, 50000);
+    assert_eq!(launch_pool_state.launch_fee_duration, 3600);
+    assert_eq!(launch_pool_state.launch_fee_decay, 0);
+
+    test_env.jump_seconds(1).await;
+    let mut received = vec![];
+    for (pool_id, amm_index) in [(normal_pool, 0), (launch_pool, 1)] {
+        let user_token_1_before: TokenAccount = test_env.fetch_account(user_token_1_account).await;
+        test_env
+            .swap_base_input(
+                &user,
+                pool_id,
+                amm_index,
+                10000,
+                1,
+                TradeDirection::ZeroForOne,
+            )
+            .await;
+        let user_token_1_after: TokenAccount = test_env.fetch_account(user_token_1_account).await;
+        received.push(user_token_1_after.amount - user_token_1_before.amount);
+    }
+    // Snipers right after the open time pay close to the full launch fee
+    assert!(received[1] < received[0] * 96 / 100);
+    assert!(received[1] > received[0] * 94 / 100);
+
+    // Back to the normal fee once the launch fee has decayed
+    test_env.jump_seconds(3600).await;
+    let mut received = vec![];
+    for (pool_id, amm_index) in [(normal_pool, 0), (launch_pool, 1)] {
+        let user_token_0_before: TokenAccount = test_env.fetch_account(user_token_0_account).await;
+        test_env
+            .swap_base_input(
+                &user,
+                pool_id,
+                amm_index,
+                10000,
+                1,
+                TradeDirection::OneForZero,
+            )
+            .await;
+        let user_token_0_after: TokenAccount = test_env.fetch_account(user_token_0_account).await;
+        received.push(user_token_0_after.amount - user_token_0_before.amount);
+    }
+    assert!(received[1].abs_diff(received[0]) <= received[0] / 1000);
+}
+
+#[tokio::test]
+async fn update_pool_should_set_launch_fee() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+
+    let launch_fee_decay = LaunchFeeDecay::Exponential as u64;
+    test_env
+        .update_pool(
+            &admin,
+            pool_id,
+            amm_index,
+            8,
+            (launch_fee_decay << 56) | (600 << 32) | 30000,
+        )
+        .await;
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq!(pool_state.launch_fee_rate, 30000);
+    assert_eq!(pool_state.launch_fee_duration, 600);
+    assert_eq!(pool_state.launch_fee_decay, 1);
+}
@@ -0,0 +1,187 @@
+use gamma::{
+    error::GammaError,
+    states::{LiquidityLock, UserPoolLiquidity, UserRewardInfo, PERMANENT_LOCK},
+    REWARD_INFO_SEED, USER_REWARD_INFO_SEED,
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn should_not_withdraw_locked_liquidity_before_unlock_time() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env.setup_small_pool(&user, &admin).await;
+
+    let user_pool_liquidity_key = user_pool_liquidity_key(pool_id, user.pubkey());
+    let user_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(user_pool_liquidity_key).await;
+    let lp_owned_before = user_pool_liquidity.lp_tokens_owned as u64;
+
+    let unlock_timestamp = test_env.timestamp_now().await as u64 + 1000;
+    test_env
+        .lock_liquidity(&user, pool_id, 1000, unlock_timestamp)
+        .await
+        .unwrap_transaction();
+
+    let user_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(user_pool_liquidity_key).await;
+    assert_eq!(
+        user_pool_liquidity.lp_tokens_owned as u64,
+        lp_owned_before - 1000
+    );
+    assert_eq!(user_pool_liquidity.lp_tokens_locked, 1000);
+    let liquidity_lock: LiquidityLock = test_env
+        .fetch_account(liquidity_lock_key(pool_id, user.pubkey()))
+        .await;
+    assert_eq!(liquidity_lock.lp_tokens_locked, 1000);
+    assert_eq!(liquidity_lock.unlock_timestamp, unlock_timestamp);
+
+    // The locked lp tokens can not be withdrawn
+    let result = test_env
+        .withdraw_with_rewards(&user, pool_id, 0, lp_owned_before, 0, 0, vec![])
+        .await;
+    assert_error!(result, GammaError::InvalidLpTokenAmount);
+
+    // The unlock time can not be brought forward
+    let result = test_env
+        .lock_liquidity(&user, pool_id, 1, unlock_timestamp - 1)
+        .await;
+    assert_error!(result, GammaError::InvalidUnlockTime);
+
+    let result = test_env.unlock_liquidity(&user, pool_id).await;
+    assert_error!(result, GammaError::LiquidityLocked);
+
+    test_env.jump_seconds(1000).await;
+    test_env
+        .unlock_liquidity(&user, pool_id)
+        .await
+        .unwrap_transaction();
+
+    let user_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(user_pool_liquidity_key).await;
+    assert_eq!(user_pool_liquidity.lp_tokens_owned as u64, lp_owned_before);
+    assert_eq!(user_pool_liquidity.lp_tokens_locked, 0);
+
+    test_env.withdraw(&user, pool_id, 0, 1000, 0, 0).await;
+    let user_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(user_pool_liquidity_key).await;
+    assert_eq!(
+        user_pool_liquidity.lp_tokens_owned as u64,
+        lp_owned_before - 1000
+    );
+}
+
+#[tokio::test]
+async fn should_never_unlock_permanently_locked_liquidity() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env.setup_small_pool(&user, &admin).await;
+
+    test_env
+        .lock_liquidity(&user, pool_id, 1000, PERMANENT_LOCK)
+        .await
+        .unwrap_transaction();
+
+    test_env.jump_days(365 * 100).await;
+    let result = test_env.unlock_liquidity(&user, pool_id).await;
+    assert_error!(result, GammaError::LiquidityLocked);
+
+    // A permanent lock can not be turned into a timed one
+    let unlock_timestamp = test_env.timestamp_now().await as u64 + 1000;
+    let result = test_env
+        .lock_liquidity(&user, pool_id, 1, unlock_timestamp)
+        .await;
+    assert_error!(result, GammaError::InvalidUnlockTime);
+}
+
+#[tokio::test]
+async fn locked_liquidity_should_keep_earning_rewards() {
+    let user = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+    let pool_id = test_env.setup_small_pool(&user, &admin).await;
+
+    // Lock everything the user owns, the locked liquidity is the only one earning rewards
+    let user_pool_liquidity: UserPoolLiquidity = test_env
+        .fetch_account(user_pool_liquidity_key(pool_id, user.pubkey()))
+        .await;
+    test_env
+        .lock_liquidity(
+            &user,
+            pool_id,
+            user_pool_liquidity.lp_tokens_owned as u64,
+            PERMANENT_LOCK,
+        )
+        .await
+        .unwrap_transaction();
+
+    let reward_mint = Keypair::new();
+    test_env
+        .create_token_mint(&reward_mint, &test_env.mint_authority.pubkey(), 9)
+        .await;
+    let reward_provider_token_account = test_env
+        .get_or_create_associated_token_account(
+            reward_provider.pubkey(),
+            reward_mint.pubkey(),
+            &reward_provider,
+        )
+        .await;
+    let reward_amount = 1000000000;
+    test_env
+        .mint_base_tokens(
+            reward_provider_token_account,
+            reward_amount,
+            reward_mint.pubkey(),
+        )
+        .await;
+
+    let start_time = test_env.timestamp_now().await as u64 + 10;
+    let end_time = start_time + 3000;
+    test_env
+        .create_rewards(
+            &reward_provider,
+            pool_id,
+            start_time,
+            end_time,
+            reward_mint.pubkey(),
+            reward_amount,
+        )
+        .await;
+
+    let (reward_info_key, _) = Pubkey::find_program_address(
+        &[
+            REWARD_INFO_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+            &start_time.to_le_bytes(),
+            reward_mint.pubkey().to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    );
+    let (user_reward_info_key, _) = Pubkey::find_program_address(
+        &[
+            USER_REWARD_INFO_SEED.as_bytes(),
+            reward_info_key.to_bytes().as_ref(),
+            user.pubkey().to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    );
+
+    test_env.jump_seconds(500000).await;
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    let user_reward_info: UserRewardInfo = test_env.fetch_account(user_reward_info_key).await;
+    // Some rounding issues cause the last reward to be less than 1
+    assert_eq!(user_reward_info.total_rewards, reward_amount - 1);
+}
@@ -0,0 +1,163 @@
+use gamma::{
+    curve::TradeDirection,
+    error::GammaError,
+    states::{ObservationState, MAX_OBSERVATION_CARDINALITY, OBSERVATION_NUM, OBSERVATION_SEED},
+};
+use solana_program_test::tokio;
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn observations_should_wrap_around_grown_buffer() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    let (observation_key, __bump) = Pubkey::find_program_address(
+        &[OBSERVATION_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+        &gamma::ID,
+    );
+    test_env.jump_seconds(1).await;
+    test_env
+        .swap_base_input(
+            &user,
+            pool_id,
+            amm_index,
+            10000,
+            1,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+
+    let observation_cardinality_next = OBSERVATION_NUM as u16 + 20;
+    let result = test_env
+        .increase_observation_cardinality(&user, pool_id, observation_cardinality_next)
+        .await;
+    result.unwrap_transaction();
+    let observation_account = test_env
+        .get_account_info(observation_key)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        observation_account.data.len(),
+        ObservationState::len_with_cardinality(observation_cardinality_next)
+    );
+    let observation_state: ObservationState = test_env.fetch_account(observation_key).await;
+    assert_eq_with_copy!(
+        observation_state.observation_cardinality,
+        OBSERVATION_NUM as u16
+    );
+    assert_eq_with_copy!(
+        observation_state.observation_cardinality_next,
+        observation_cardinality_next
+    );
+
+    // The cardinality only grows, by at most 256 observations at a time
+    let result = test_env
+        .increase_observation_cardinality(&user, pool_id, observation_cardinality_next)
+        .await;
+    assert_error!(result, GammaError::InvalidInput);
+    let result = test_env
+        .increase_observation_cardinality(&user, pool_id, observation_cardinality_next + 257)
+        .await;
+    assert_error!(result, GammaError::InvalidInput);
+
+    // Observe every swap
+    test_env
+        .update_pool_with_remaining_accounts(
+            &admin,
+            pool_id,
+            amm_index,
+            9,
+            1,
+            vec![AccountMeta::new(observation_key, false)],
+        )
+        .await;
+    let observation_state: ObservationState = test_env.fetch_account(observation_key).await;
+    assert_eq_with_copy!(observation_state.observation_update_duration, 1);
+
+    for i in 0..130 {
+        test_env.jump_seconds(1).await;
+        let trade_direction = if i % 2 == 0 {
+            TradeDirection::OneForZero
+        } else {
+            TradeDirection::ZeroForOne
+        };
+        test_env
+            .swap_base_input(&user, pool_id, amm_index, 10000, 1, trade_direction)
+            .await;
+    }
+
+    let observation_state: ObservationState = test_env.fetch_account(observation_key).await;
+    assert_eq_with_copy!(
+        observation_state.observation_cardinality,
+        observation_cardinality_next
+    );
+    assert_eq_with_copy!(
+        observation_state.observation_index,
+        130 % observation_cardinality_next
+    );
+
+    // The oldest observation is the one after the index, in the grown part of the buffer
+    let now = test_env.timestamp_now().await as u64;
+    let result = test_env.get_twap(&user, pool_id, 200).await;
+    result.unwrap_transaction();
+    let result = test_env.get_twap(&user, pool_id, now).await;
+    assert_error!(result, GammaError::ObservationCoverageTooShort);
+}
+
+#[tokio::test]
+async fn observation_cardinality_should_be_capped() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env.setup_small_pool(&user, &admin).await;
+
+    // Grow in steps of at most 256 observations up to the cap
+    let mut observation_cardinality_next = OBSERVATION_NUM as u16;
+    while observation_cardinality_next < MAX_OBSERVATION_CARDINALITY {
+        observation_cardinality_next = std::cmp::min(
+            observation_cardinality_next + 256,
+            MAX_OBSERVATION_CARDINALITY,
+        );
+        test_env
+            .increase_observation_cardinality(&user, pool_id, observation_cardinality_next)
+            .await
+            .unwrap_transaction();
+    }
+
+    let result = test_env
+        .increase_observation_cardinality(&user, pool_id, MAX_OBSERVATION_CARDINALITY + 1)
+        .await;
+    assert_error!(result, GammaError::InvalidInput);
+}
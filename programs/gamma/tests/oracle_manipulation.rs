@@ -0,0 +1,220 @@
+use anchor_lang::AnchorDeserialize;
+use gamma::{
+    curve::TradeDirection,
+    states::{PoolState, Twap},
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+async fn setup_observed_pool(
+    test_env: &mut TestEnv,
+    admin: &Keypair,
+    users: &[&Keypair],
+    amm_index: u16,
+) -> Pubkey {
+    test_env
+        .create_config(admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    for user in users {
+        let user_token_0_account = test_env
+            .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, user)
+            .await;
+        test_env
+            .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+            .await;
+        let user_token_1_account = test_env
+            .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, user)
+            .await;
+        test_env
+            .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+            .await;
+    }
+
+    let pool_id = test_env
+        .initialize_pool(
+            users[0],
+            amm_index,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+
+    // The first swap initializes the observations
+    test_env.jump_seconds(1).await;
+    test_env
+        .swap_base_input(
+            users[0],
+            pool_id,
+            amm_index,
+            10000,
+            1,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    test_env.jump_seconds(60).await;
+    pool_id
+}
+
+async fn token_0_price_x32(test_env: &mut TestEnv, pool_id: Pubkey) -> u128 {
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    pool_state
+        .token_price_x32(test_env.timestamp_now().await as u64)
+        .unwrap()
+        .0
+}
+
+async fn fetch_twap(test_env: &mut TestEnv, user: &Keypair, pool_id: Pubkey, since: i64) -> Twap {
+    let now = test_env.timestamp_now().await;
+    let result = test_env.get_twap(user, pool_id, (now - since) as u64).await;
+    result.unwrap_transaction();
+    let return_data = result.unwrap().metadata.unwrap().return_data.unwrap();
+    Twap::try_from_slice(&return_data.data).unwrap()
+}
+
+#[tokio::test]
+async fn same_second_sandwich_should_not_move_twap() {
+    let attacker = Keypair::new();
+    let victim = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![attacker.pubkey(), victim.pubkey(), admin.pubkey()]).await;
+    let pool_id =
+        setup_observed_pool(&mut test_env, &admin, &[&attacker, &victim], amm_index).await;
+
+    test_env
+        .swap_base_input(
+            &victim,
+            pool_id,
+            amm_index,
+            10000,
+            1,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    let window_start = test_env.timestamp_now().await;
+    let price_before_x32 = token_0_price_x32(&mut test_env, pool_id).await;
+    test_env.jump_seconds(30).await;
+
+    // Front-run, victim swap and back-run all land in the same second
+    let front_run = test_env
+        .swap_base_input_instruction(
+            &attacker,
+            pool_id,
+            amm_index,
+            5000000,
+            1,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    let victim_swap = test_env
+        .swap_base_input_instruction(
+            &victim,
+            pool_id,
+            amm_index,
+            10000,
+            1,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    let back_run = test_env
+        .swap_base_input_instruction(
+            &attacker,
+            pool_id,
+            amm_index,
+            3330000,
+            1,
+            TradeDirection::OneForZero,
+        )
+        .await;
+    let transaction = get_signed_transaction_with_different_payer(
+        &mut test_env.program_test_context,
+        &[front_run, victim_swap, back_run],
+        &victim,
+        &attacker,
+    )
+    .await;
+    test_env
+        .program_test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    let price_after_x32 = token_0_price_x32(&mut test_env, pool_id).await;
+    assert!(price_after_x32 > price_before_x32 * 9 / 10);
+    assert!(price_after_x32 < price_before_x32 * 11 / 10);
+
+    test_env.jump_seconds(30).await;
+    let twap = fetch_twap(&mut test_env, &victim, pool_id, window_start).await;
+
+    // Only the closing prices of each second are observed
+    assert_eq!(twap.start_time, window_start as u64);
+    assert_eq!(
+        twap.token_0_min_price_x32,
+        price_before_x32.min(price_after_x32)
+    );
+    assert_eq!(
+        twap.token_0_max_price_x32,
+        price_before_x32.max(price_after_x32)
+    );
+}
+
+#[tokio::test]
+async fn multi_second_burst_should_be_observed() {
+    let attacker = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![attacker.pubkey(), admin.pubkey()]).await;
+    let pool_id = setup_observed_pool(&mut test_env, &admin, &[&attacker], amm_index).await;
+
+    test_env
+        .swap_base_input(
+            &attacker,
+            pool_id,
+            amm_index,
+            10000,
+            1,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    let window_start = test_env.timestamp_now().await;
+    let price_before_x32 = token_0_price_x32(&mut test_env, pool_id).await;
+
+    // The manipulated price is held for a few seconds, well within the update duration
+    test_env.jump_seconds(4).await;
+    test_env
+        .swap_base_input(
+            &attacker,
+            pool_id,
+            amm_index,
+            5000000,
+            1,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    let manipulated_price_x32 = token_0_price_x32(&mut test_env, pool_id).await;
+    test_env.jump_seconds(4).await;
+    test_env
+        .swap_base_input(
+            &attacker,
+            pool_id,
+            amm_index,
+            3330000,
+            1,
+            TradeDirection::OneForZero,
+        )
+        .await;
+
+    test_env.jump_seconds(30).await;
+    let twap = fetch_twap(&mut test_env, &attacker, pool_id, window_start).await;
+
+    // The burst is blended into the newest observation instead of being skipped
+    assert!(twap.token_0_min_price_x32 < price_before_x32 * 8 / 10);
+    assert!(twap.token_0_min_price_x32 > manipulated_price_x32);
+    assert!(twap.token_0_twap_price_x32 < price_before_x32);
+}
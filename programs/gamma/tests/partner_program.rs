@@ -1,14 +1,27 @@
 use std::u64;
 
+use anchor_lang::AccountSerialize;
 use gamma::curve::TradeDirection;
-use gamma::states::PartnerType;
-use gamma::states::PoolState;
+use gamma::error::GammaError;
+use gamma::states::{Partner, PartnerInfo, PoolState, UserPoolLiquidity, LEGACY_PARTNER_ID};
+use gamma::AUTH_SEED;
 use solana_program_test::tokio;
-use solana_sdk::{signature::Keypair, signer::Signer};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
 mod utils;
 
 use utils::*;
 
+/// The ledger rounds down the fees of every swap, so it can be a few units off the share of the
+/// total protocol fees
+fn assert_fees_close(actual: u64, expected: u64) {
+    assert!(
+        actual.abs_diff(expected) <= 4,
+        "fees {} should be close to {}",
+        actual,
+        expected
+    );
+}
+
 #[tokio::test]
 async fn should_track_cumulative_rates_correctly() {
     // Setup
@@ -97,27 +110,26 @@ async fn should_track_cumulative_rates_correctly() {
     assert_eq_with_copy!(pool_state.cumulative_trade_fees_token_0, 0);
     assert_eq_with_copy!(pool_state.cumulative_trade_fees_token_1, 0);
 
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_0,
-        0
-    );
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_1,
-        0
-    );
+    let asset_dash_id = 0;
+    test_env
+        .register_partner(&admin, asset_dash_id, 100000, admin.pubkey())
+        .await
+        .unwrap_transaction();
+    let partner_info_key = test_env
+        .init_partner_info(&user, pool_id, asset_dash_id)
+        .await;
 
-    assert_eq_with_copy!(pool_state.partners[0].lp_token_linked_with_partner, 0);
-    assert_eq_with_copy!(
-        PartnerType::new(pool_state.partners[0].partner_id),
-        PartnerType::AssetDash
-    );
-    assert_eq_with_copy!(pool_state.partners[0].partner_id, 0);
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    assert_eq!(partner_info.pool_state, pool_id);
+    assert_eq!(partner_info.partner_id, asset_dash_id);
+    assert_eq!(partner_info.lp_token_linked_with_partner, 0);
+    assert_eq!(partner_info.fees_earned(&pool_state).unwrap(), (0, 0));
 
     test_env
         .init_user_pool_liquidity_with_partner(
             &lp_depositor_asset_dash,
             pool_id,
-            Some("AssetDash".to_string()),
+            Some(asset_dash_id),
         )
         .await;
 
@@ -135,20 +147,11 @@ async fn should_track_cumulative_rates_correctly() {
 
     let pool_state: PoolState = test_env.fetch_account(pool_id).await;
     dbg!(2, pool_state.token_0_vault_amount, pool_state.token_1_vault_amount);
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    let (fees_token_0, fees_token_1) = partner_info.fees_earned(&pool_state).unwrap();
 
-
-    assert_eq_with_copy!(
-        pool_state.partners[0].lp_token_linked_with_partner,
-        lp_deposit_amount
-    );
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_0,
-        0
-    );
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_1,
-        0
-    );
+    assert_eq!(partner_info.lp_token_linked_with_partner, lp_deposit_amount);
+    assert_eq!((fees_token_0, fees_token_1), (0, 0));
 
     let withdraw_amount = 100000000;
     test_env
@@ -164,19 +167,14 @@ async fn should_track_cumulative_rates_correctly() {
 
     let pool_state: PoolState = test_env.fetch_account(pool_id).await;
     dbg!(3, pool_state.token_0_vault_amount, pool_state.token_1_vault_amount);
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    let (fees_token_0, fees_token_1) = partner_info.fees_earned(&pool_state).unwrap();
 
-    assert_eq_with_copy!(
-        pool_state.partners[0].lp_token_linked_with_partner,
+    assert_eq!(
+        partner_info.lp_token_linked_with_partner,
         lp_deposit_amount - withdraw_amount
     );
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_0,
-        0
-    );
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_1,
-        0
-    );
+    assert_eq!((fees_token_0, fees_token_1), (0, 0));
 
     test_env
         .swap_base_input(
@@ -191,19 +189,18 @@ async fn should_track_cumulative_rates_correctly() {
 
     let pool_state: PoolState = test_env.fetch_account(pool_id).await;
     dbg!(4, pool_state.token_0_vault_amount, pool_state.token_1_vault_amount);
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    let (fees_token_0, fees_token_1) = partner_info.fees_earned(&pool_state).unwrap();
 
-    assert_eq_with_copy!(
-        pool_state.partners[0].lp_token_linked_with_partner,
+    assert_eq!(
+        partner_info.lp_token_linked_with_partner,
         lp_deposit_amount - withdraw_amount
     );
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_0,
-        0
-    );
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_1,
-        (pool_state.partners[0].lp_token_linked_with_partner * pool_state.protocol_fees_token_1)
-            / pool_state.lp_supply
+    assert_eq!(fees_token_0, 0);
+    assert_fees_close(
+        fees_token_1,
+        (partner_info.lp_token_linked_with_partner * pool_state.protocol_fees_token_1)
+            / pool_state.lp_supply,
     );
 
     test_env
@@ -219,20 +216,22 @@ async fn should_track_cumulative_rates_correctly() {
 
     let pool_state: PoolState = test_env.fetch_account(pool_id).await;
     dbg!(5, pool_state.token_0_vault_amount, pool_state.token_1_vault_amount);
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    let (fees_token_0, fees_token_1) = partner_info.fees_earned(&pool_state).unwrap();
 
-    assert_eq_with_copy!(
-        pool_state.partners[0].lp_token_linked_with_partner,
+    assert_eq!(
+        partner_info.lp_token_linked_with_partner,
         lp_deposit_amount - withdraw_amount
     );
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_0,
-        (pool_state.partners[0].lp_token_linked_with_partner * pool_state.protocol_fees_token_0)
-            / pool_state.lp_supply
+    assert_fees_close(
+        fees_token_0,
+        (partner_info.lp_token_linked_with_partner * pool_state.protocol_fees_token_0)
+            / pool_state.lp_supply,
     );
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_1,
-        (pool_state.partners[0].lp_token_linked_with_partner * pool_state.protocol_fees_token_1)
-            / pool_state.lp_supply
+    assert_fees_close(
+        fees_token_1,
+        (partner_info.lp_token_linked_with_partner * pool_state.protocol_fees_token_1)
+            / pool_state.lp_supply,
     );
 
     // swap base output
@@ -250,20 +249,22 @@ async fn should_track_cumulative_rates_correctly() {
 
     let pool_state: PoolState = test_env.fetch_account(pool_id).await;
     dbg!(6, pool_state.token_0_vault_amount, pool_state.token_1_vault_amount);
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    let (fees_token_0, fees_token_1) = partner_info.fees_earned(&pool_state).unwrap();
 
-    assert_eq_with_copy!(
-        pool_state.partners[0].lp_token_linked_with_partner,
+    assert_eq!(
+        partner_info.lp_token_linked_with_partner,
         lp_deposit_amount - withdraw_amount
     );
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_0,
-        (pool_state.partners[0].lp_token_linked_with_partner * pool_state.protocol_fees_token_0)
-            / pool_state.lp_supply
+    assert_fees_close(
+        fees_token_0,
+        (partner_info.lp_token_linked_with_partner * pool_state.protocol_fees_token_0)
+            / pool_state.lp_supply,
     );
-    assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_1,
-        (pool_state.partners[0].lp_token_linked_with_partner * pool_state.protocol_fees_token_1)
-            / pool_state.lp_supply
+    assert_fees_close(
+        fees_token_1,
+        (partner_info.lp_token_linked_with_partner * pool_state.protocol_fees_token_1)
+            / pool_state.lp_supply,
     );
 
     test_env
@@ -279,19 +280,756 @@ async fn should_track_cumulative_rates_correctly() {
 
     let pool_state: PoolState = test_env.fetch_account(pool_id).await;
     dbg!(7, pool_state.token_0_vault_amount, pool_state.token_1_vault_amount);
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    let (fees_token_0, fees_token_1) = partner_info.fees_earned(&pool_state).unwrap();
+
+    assert_eq!(
+        partner_info.lp_token_linked_with_partner,
+        lp_deposit_amount - withdraw_amount
+    );
+    assert_fees_close(
+        fees_token_0,
+        (partner_info.lp_token_linked_with_partner * pool_state.protocol_fees_token_0)
+            / pool_state.lp_supply,
+    );
+    assert_fees_close(
+        fees_token_1,
+        (partner_info.lp_token_linked_with_partner * pool_state.protocol_fees_token_1)
+            / pool_state.lp_supply,
+    );
+}
+
+#[tokio::test]
+async fn should_track_every_partner_in_its_own_ledger() {
+    let user = Keypair::new();
+    let first_partner_user = Keypair::new();
+    let second_partner_user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        first_partner_user.pubkey(),
+        second_partner_user.pubkey(),
+        admin.pubkey(),
+    ])
+    .await;
+    test_env
+        .create_config(&admin, amm_index, 3000, 2000, 50, 0)
+        .await;
+
+    for depositor in [&user, &first_partner_user, &second_partner_user] {
+        let token_0_account = test_env
+            .get_or_create_associated_token_account(
+                depositor.pubkey(),
+                test_env.token_0_mint,
+                depositor,
+            )
+            .await;
+        test_env
+            .mint_base_tokens(token_0_account, 100000000000000, test_env.token_0_mint)
+            .await;
+        let token_1_account = test_env
+            .get_or_create_associated_token_account(
+                depositor.pubkey(),
+                test_env.token_1_mint,
+                depositor,
+            )
+            .await;
+        test_env
+            .mint_base_tokens(token_1_account, 100000000000000, test_env.token_1_mint)
+            .await;
+    }
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            200000000,
+            100000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env.jump_seconds(100).await;
+
+    // Only the admin can register partners, with a fee share of at most 100%
+    let (first_partner_id, second_partner_id) = (7, 1000);
+    let result = test_env
+        .register_partner(&user, first_partner_id, 0, user.pubkey())
+        .await;
+    assert_error!(result, GammaError::InvalidOwner);
+    let result = test_env
+        .register_partner(&admin, first_partner_id, 1000001, admin.pubkey())
+        .await;
+    assert_error!(result, GammaError::InvalidFee);
+
+    test_env
+        .register_partner(&admin, first_partner_id, 100000, admin.pubkey())
+        .await
+        .unwrap_transaction();
+    test_env
+        .register_partner(&admin, second_partner_id, 250000, admin.pubkey())
+        .await
+        .unwrap_transaction();
+    let partner: Partner = test_env.fetch_account(partner_key(second_partner_id)).await;
+    assert_eq!(partner.partner_id, second_partner_id);
+    assert_eq!(partner.fee_share_rate, 250000);
+    assert_eq!(partner.authority, admin.pubkey());
+
+    // A position can only be linked with a partner that has a ledger in the pool
+    let result = test_env
+        .try_init_user_pool_liquidity_with_partner(
+            &first_partner_user,
+            pool_id,
+            Some(first_partner_id),
+        )
+        .await;
+    assert!(result.unwrap().result.is_err());
+
+    let first_partner_info_key = test_env
+        .init_partner_info(&user, pool_id, first_partner_id)
+        .await;
+    let second_partner_info_key = test_env
+        .init_partner_info(&user, pool_id, second_partner_id)
+        .await;
+    test_env
+        .init_user_pool_liquidity_with_partner(&first_partner_user, pool_id, Some(first_partner_id))
+        .await;
+    test_env
+        .init_user_pool_liquidity_with_partner(
+            &second_partner_user,
+            pool_id,
+            Some(second_partner_id),
+        )
+        .await;
+
+    // The first partner links three times the liquidity of the second one
+    test_env
+        .deposit(
+            &first_partner_user,
+            pool_id,
+            amm_index,
+            300000000,
+            u64::MAX,
+            u64::MAX,
+        )
+        .await;
+    test_env
+        .deposit(
+            &second_partner_user,
+            pool_id,
+            amm_index,
+            100000000,
+            u64::MAX,
+            u64::MAX,
+        )
+        .await;
+
+    test_env
+        .swap_base_input(
+            &user,
+            pool_id,
+            amm_index,
+            1000000000,
+            0,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    test_env
+        .swap_base_input(
+            &user,
+            pool_id,
+            amm_index,
+            1000000000,
+            0,
+            TradeDirection::OneForZero,
+        )
+        .await;
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let first_partner_info: PartnerInfo = test_env.fetch_account(first_partner_info_key).await;
+    let second_partner_info: PartnerInfo = test_env.fetch_account(second_partner_info_key).await;
+    assert_eq!(first_partner_info.lp_token_linked_with_partner, 300000000);
+    assert_eq!(second_partner_info.lp_token_linked_with_partner, 100000000);
+
+    let (first_fees_token_0, first_fees_token_1) =
+        first_partner_info.fees_earned(&pool_state).unwrap();
+    let (second_fees_token_0, second_fees_token_1) =
+        second_partner_info.fees_earned(&pool_state).unwrap();
+    assert!(second_fees_token_0 > 0 && second_fees_token_1 > 0);
+    assert_fees_close(
+        first_fees_token_0,
+        300000000 * pool_state.protocol_fees_token_0 / pool_state.lp_supply,
+    );
+    assert_fees_close(
+        second_fees_token_0,
+        100000000 * pool_state.protocol_fees_token_0 / pool_state.lp_supply,
+    );
+    assert_fees_close(
+        first_fees_token_1,
+        300000000 * pool_state.protocol_fees_token_1 / pool_state.lp_supply,
+    );
+    assert_fees_close(
+        second_fees_token_1,
+        100000000 * pool_state.protocol_fees_token_1 / pool_state.lp_supply,
+    );
+
+    // Withdrawing settles the fees earned so far before unlinking the liquidity
+    test_env
+        .withdraw(&second_partner_user, pool_id, amm_index, 100000000, 0, 0)
+        .await;
+    let second_partner_info: PartnerInfo = test_env.fetch_account(second_partner_info_key).await;
+    assert_eq!(second_partner_info.lp_token_linked_with_partner, 0);
+    assert_eq!(
+        second_partner_info.cumulative_fee_total_times_tvl_share_token_0,
+        second_fees_token_0
+    );
+    assert_eq!(
+        second_partner_info.cumulative_fee_total_times_tvl_share_token_1,
+        second_fees_token_1
+    );
+
+    // Later swaps only earn fees for the liquidity still linked
+    test_env
+        .swap_base_input(
+            &user,
+            pool_id,
+            amm_index,
+            1000000000,
+            0,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let first_partner_info: PartnerInfo = test_env.fetch_account(first_partner_info_key).await;
+    let second_partner_info: PartnerInfo = test_env.fetch_account(second_partner_info_key).await;
+    assert_eq!(
+        second_partner_info.fees_earned(&pool_state).unwrap(),
+        (second_fees_token_0, second_fees_token_1)
+    );
+    assert!(first_partner_info.fees_earned(&pool_state).unwrap().0 > first_fees_token_0);
+}
+
+#[tokio::test]
+async fn partner_should_claim_its_fee_share() {
+    let user = Keypair::new();
+    let partner_user = Keypair::new();
+    let partner_authority = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        partner_user.pubkey(),
+        partner_authority.pubkey(),
+        admin.pubkey(),
+    ])
+    .await;
+    test_env
+        .create_config(&admin, amm_index, 3000, 200000, 50, 0)
+        .await;
+
+    for depositor in [&user, &partner_user] {
+        let token_0_account = test_env
+            .get_or_create_associated_token_account(
+                depositor.pubkey(),
+                test_env.token_0_mint,
+                depositor,
+            )
+            .await;
+        test_env
+            .mint_base_tokens(token_0_account, 100000000000000, test_env.token_0_mint)
+            .await;
+        let token_1_account = test_env
+            .get_or_create_associated_token_account(
+                depositor.pubkey(),
+                test_env.token_1_mint,
+                depositor,
+            )
+            .await;
+        test_env
+            .mint_base_tokens(token_1_account, 100000000000000, test_env.token_1_mint)
+            .await;
+    }
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            200000000,
+            100000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env.jump_seconds(100).await;
+
+    // The partner gets half of the protocol fees earned by its customers' liquidity
+    let partner_id = 3;
+    test_env
+        .register_partner(&admin, partner_id, 500000, partner_authority.pubkey())
+        .await
+        .unwrap_transaction();
+    let partner_info_key = test_env.init_partner_info(&user, pool_id, partner_id).await;
+    test_env
+        .init_user_pool_liquidity_with_partner(&partner_user, pool_id, Some(partner_id))
+        .await;
+    test_env
+        .deposit(
+            &partner_user,
+            pool_id,
+            amm_index,
+            200000000,
+            u64::MAX,
+            u64::MAX,
+        )
+        .await;
+
+    for trade_direction in [TradeDirection::ZeroForOne, TradeDirection::OneForZero] {
+        test_env
+            .swap_base_input(&user, pool_id, amm_index, 1000000000, 0, trade_direction)
+            .await;
+    }
+
+    // Only the authority of the partner can claim
+    let result = test_env
+        .claim_partner_fees(&user, pool_id, partner_id)
+        .await;
+    assert_error!(result, GammaError::InvalidOwner);
 
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    let (fees_token_0, fees_token_1) = partner_info.fees_earned(&pool_state).unwrap();
+    let (share_token_0, share_token_1) = (fees_token_0 / 2, fees_token_1 / 2);
+    assert!(share_token_0 > 0 && share_token_1 > 0);
+
+    test_env
+        .claim_partner_fees(&partner_authority, pool_id, partner_id)
+        .await
+        .unwrap_transaction();
+    let token_0_mint = test_env.token_0_mint;
+    let token_1_mint = test_env.token_1_mint;
+    assert_eq!(
+        test_env
+            .token_balance(&partner_authority, token_0_mint)
+            .await,
+        share_token_0
+    );
+    assert_eq!(
+        test_env
+            .token_balance(&partner_authority, token_1_mint)
+            .await,
+        share_token_1
+    );
+
+    let claimed_pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    assert_eq!(partner_info.fees_claimed_token_0, share_token_0);
+    assert_eq!(partner_info.fees_claimed_token_1, share_token_1);
+    assert_eq_with_copy!(
+        claimed_pool_state.protocol_fees_token_0,
+        pool_state.protocol_fees_token_0 - share_token_0
+    );
+    assert_eq_with_copy!(
+        claimed_pool_state.protocol_fees_token_1,
+        pool_state.protocol_fees_token_1 - share_token_1
+    );
+
+    // Claiming again pays nothing more
+    test_env
+        .claim_partner_fees(&partner_authority, pool_id, partner_id)
+        .await
+        .unwrap_transaction();
+    assert_eq!(
+        test_env
+            .token_balance(&partner_authority, token_0_mint)
+            .await,
+        share_token_0
+    );
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    assert_eq!(partner_info.fees_claimed_token_0, share_token_0);
+
+    // Collecting the protocol fees leaves the fee share of the partner in the pool
+    test_env
+        .swap_base_input(
+            &user,
+            pool_id,
+            amm_index,
+            1000000000,
+            0,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    let (fees_token_0, fees_token_1) = partner_info.fees_earned(&pool_state).unwrap();
+    let owed_token_0 = fees_token_0 / 2 - share_token_0;
+    let owed_token_1 = fees_token_1 / 2 - share_token_1;
+    assert!(owed_token_0 > 0);
+    test_env
+        .collect_protocol_fee(&admin, pool_id, u64::MAX, u64::MAX)
+        .await
+        .unwrap_transaction();
+    let collected_pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq_with_copy!(
+        collected_pool_state.protocol_fees_token_0,
+        collected_pool_state.partner_fees_token_0
+    );
+    assert_eq_with_copy!(
+        collected_pool_state.protocol_fees_token_1,
+        collected_pool_state.partner_fees_token_1
+    );
+    assert!(collected_pool_state.protocol_fees_token_0 >= owed_token_0);
+    assert!(collected_pool_state.protocol_fees_token_1 >= owed_token_1);
+
+    test_env
+        .claim_partner_fees(&partner_authority, pool_id, partner_id)
+        .await
+        .unwrap_transaction();
+    assert_eq!(
+        test_env
+            .token_balance(&partner_authority, token_0_mint)
+            .await,
+        share_token_0 + owed_token_0
+    );
+    assert_eq!(
+        test_env
+            .token_balance(&partner_authority, token_1_mint)
+            .await,
+        share_token_1 + owed_token_1
+    );
+
+    // Only the rounding of the kept fee share is left for the protocol
+    let claimed_pool_state: PoolState = test_env.fetch_account(pool_id).await;
     assert_eq_with_copy!(
-        pool_state.partners[0].lp_token_linked_with_partner,
+        claimed_pool_state.partner_fees_token_0,
+        collected_pool_state.partner_fees_token_0 - owed_token_0
+    );
+    assert_fees_close(claimed_pool_state.partner_fees_token_0, 0);
+    assert_fees_close(claimed_pool_state.partner_fees_token_1, 0);
+}
+
+#[tokio::test]
+async fn partner_authority_should_be_rotated() {
+    let user = Keypair::new();
+    let partner_authority = Keypair::new();
+    let new_partner_authority = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        partner_authority.pubkey(),
+        new_partner_authority.pubkey(),
+        admin.pubkey(),
+    ])
+    .await;
+
+    let partner_id = 3;
+    test_env
+        .register_partner(&admin, partner_id, 500000, partner_authority.pubkey())
+        .await
+        .unwrap_transaction();
+
+    // Only the admin or the current authority can rotate the authority
+    let result = test_env
+        .update_partner_authority(&user, partner_id, user.pubkey())
+        .await;
+    assert_error!(result, GammaError::InvalidOwner);
+
+    test_env
+        .update_partner_authority(
+            &partner_authority,
+            partner_id,
+            new_partner_authority.pubkey(),
+        )
+        .await
+        .unwrap_transaction();
+    let partner: Partner = test_env.fetch_account(partner_key(partner_id)).await;
+    assert_eq!(partner.authority, new_partner_authority.pubkey());
+
+    // The former authority lost its rights
+    let result = test_env
+        .update_partner_authority(&partner_authority, partner_id, partner_authority.pubkey())
+        .await;
+    assert_error!(result, GammaError::InvalidOwner);
+
+    test_env
+        .update_partner_authority(&admin, partner_id, partner_authority.pubkey())
+        .await
+        .unwrap_transaction();
+    let partner: Partner = test_env.fetch_account(partner_key(partner_id)).await;
+    assert_eq!(partner.authority, partner_authority.pubkey());
+}
+
+#[tokio::test]
+async fn legacy_pool_should_migrate_its_partner_slot() {
+    let user = Keypair::new();
+    let lp_depositor_asset_dash = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        lp_depositor_asset_dash.pubkey(),
+        admin.pubkey(),
+    ])
+    .await;
+
+    test_env
+        .create_config(&admin, amm_index, 3000, 2000, 50, 0)
+        .await;
+    for owner in [&user, &lp_depositor_asset_dash] {
+        for mint in [test_env.token_0_mint, test_env.token_1_mint] {
+            let token_account = test_env
+                .get_or_create_associated_token_account(owner.pubkey(), mint, owner)
+                .await;
+            test_env
+                .mint_base_tokens(token_account, 100000000000000, mint)
+                .await;
+        }
+    }
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            200000000,
+            100000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env.jump_seconds(100).await;
+
+    // A pool and a position created by the clients predating the partner registry
+    test_env.make_legacy_pool(pool_id, (0, 0, 0)).await;
+    test_env
+        .init_user_pool_liquidity_legacy(
+            &lp_depositor_asset_dash,
+            pool_id,
+            Some("AssetDash".to_string()),
+        )
+        .await;
+    let user_pool_liquidity: UserPoolLiquidity = test_env
+        .fetch_account(user_pool_liquidity_key(
+            pool_id,
+            lp_depositor_asset_dash.pubkey(),
+        ))
+        .await;
+    assert_eq!(user_pool_liquidity.partner, Some(LEGACY_PARTNER_ID));
+
+    // Legacy pools keep booking the partner liquidity and fees in their slot
+    let lp_deposit_amount = 200000000;
+    test_env
+        .deposit(
+            &lp_depositor_asset_dash,
+            pool_id,
+            amm_index,
+            lp_deposit_amount,
+            u64::MAX,
+            u64::MAX,
+        )
+        .await;
+    test_env
+        .swap_base_input(
+            &user,
+            pool_id,
+            amm_index,
+            1000000000,
+            0,
+            TradeDirection::OneForZero,
+        )
+        .await;
+    let withdraw_amount = 100000000;
+    test_env
+        .withdraw(
+            &lp_depositor_asset_dash,
+            pool_id,
+            amm_index,
+            withdraw_amount,
+            0,
+            0,
+        )
+        .await;
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert!(pool_state.is_legacy_layout());
+    let (lp_token_linked_with_partner, fee_token_0, fee_token_1) = pool_state.legacy_partner_slot();
+    assert_eq!(
+        lp_token_linked_with_partner,
         lp_deposit_amount - withdraw_amount
     );
+    assert_eq!(fee_token_0, 0);
+    assert!(fee_token_1 > 0);
+
+    // The fee share of the slot is set by the registered legacy partner
+    let result = test_env.migrate_partner_ledger(&user, pool_id).await;
+    assert!(result.unwrap().result.is_err());
+    test_env
+        .register_partner(&admin, LEGACY_PARTNER_ID, 500000, admin.pubkey())
+        .await
+        .unwrap_transaction();
+    let legacy_pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    test_env
+        .migrate_partner_ledger(&user, pool_id)
+        .await
+        .unwrap_transaction();
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert!(!pool_state.is_legacy_layout());
+    assert_eq_with_copy!(pool_state.protocol_fees_per_lp_x64_token_0, 0);
+    assert_eq_with_copy!(pool_state.protocol_fees_per_lp_x64_token_1, 0);
+    let partner_info: PartnerInfo = test_env
+        .fetch_account(partner_info_key(pool_id, LEGACY_PARTNER_ID))
+        .await;
+    assert_eq!(partner_info.pool_state, pool_id);
+    assert_eq!(
+        partner_info.lp_token_linked_with_partner,
+        lp_token_linked_with_partner
+    );
+    assert_eq!(
+        partner_info.fees_earned(&pool_state).unwrap(),
+        (fee_token_0, fee_token_1)
+    );
+    assert_eq!(partner_info.fee_share_rate, 500000);
     assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_0,
-        (pool_state.partners[0].lp_token_linked_with_partner * pool_state.protocol_fees_token_0)
-            / pool_state.lp_supply
+        pool_state.partner_fee_share_lp,
+        (lp_token_linked_with_partner + 1) / 2
     );
+    assert_eq_with_copy!(pool_state.partner_fees_token_0, 0);
     assert_eq_with_copy!(
-        pool_state.partners[0].cumulative_fee_total_times_tvl_share_token_1,
-        (pool_state.partners[0].lp_token_linked_with_partner * pool_state.protocol_fees_token_1)
-            / pool_state.lp_supply
+        pool_state.partner_fees_token_1,
+        (fee_token_1 / 2).min(legacy_pool_state.protocol_fees_token_1)
+    );
+
+    // The ledger takes over from the slot
+    test_env
+        .withdraw(
+            &lp_depositor_asset_dash,
+            pool_id,
+            amm_index,
+            lp_deposit_amount - withdraw_amount,
+            0,
+            0,
+        )
+        .await;
+    let partner_info: PartnerInfo = test_env
+        .fetch_account(partner_info_key(pool_id, LEGACY_PARTNER_ID))
+        .await;
+    assert_eq!(partner_info.lp_token_linked_with_partner, 0);
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq_with_copy!(pool_state.partner_fee_share_lp, 0);
+}
+
+#[tokio::test]
+async fn legacy_partner_slot_should_not_block_withdrawals() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+
+    test_env
+        .create_config(&admin, amm_index, 3000, 2000, 50, 0)
+        .await;
+    for mint in [test_env.token_0_mint, test_env.token_1_mint] {
+        let token_account = test_env
+            .get_or_create_associated_token_account(user.pubkey(), mint, &user)
+            .await;
+        test_env
+            .mint_base_tokens(token_account, 100000000000000, mint)
+            .await;
+    }
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            200000000,
+            100000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env.jump_seconds(100).await;
+    test_env
+        .init_user_pool_liquidity_with_partner(&user, pool_id, None)
+        .await;
+    test_env
+        .deposit(&user, pool_id, amm_index, 200000000, u64::MAX, u64::MAX)
+        .await;
+
+    // The position is linked while the slot tracks less liquidity than it holds
+    test_env.make_legacy_pool(pool_id, (1000, 0, 0)).await;
+    let user_pool_liquidity = user_pool_liquidity_key(pool_id, user.pubkey());
+    let mut account = test_env
+        .program_test_context
+        .banks_client
+        .get_account(user_pool_liquidity)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut position: UserPoolLiquidity = test_env.fetch_account(user_pool_liquidity).await;
+    position.partner = Some(LEGACY_PARTNER_ID);
+    let mut data = Vec::new();
+    position.try_serialize(&mut data).unwrap();
+    account.data[..data.len()].copy_from_slice(&data);
+    test_env
+        .program_test_context
+        .set_account(&user_pool_liquidity, &account.into());
+
+    test_env
+        .withdraw(&user, pool_id, amm_index, 100000000, 0, 0)
+        .await;
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq!(pool_state.legacy_partner_slot(), (0, 0, 0));
+}
+
+#[tokio::test]
+async fn deposit_should_accept_the_accounts_of_existing_clients() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env.setup_small_pool(&user, &admin).await;
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let user_pool_liquidity = user_pool_liquidity_key(pool_id, user.pubkey());
+    let lp_tokens_before: UserPoolLiquidity = test_env.fetch_account(user_pool_liquidity).await;
+
+    // The accounts from before the partner ledgers and the rewards, without the optional ones
+    let accounts = gamma::accounts::Deposit {
+        owner: user.pubkey(),
+        authority: Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id()).0,
+        pool_state: pool_id,
+        user_pool_liquidity,
+        token_0_account: test_env
+            .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+            .await,
+        token_1_account: test_env
+            .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+            .await,
+        token_0_vault: pool_state.token_0_vault,
+        token_1_vault: pool_state.token_1_vault,
+        token_program: spl_token::id(),
+        token_program_2022: spl_token_2022::id(),
+        vault_0_mint: test_env.token_0_mint,
+        vault_1_mint: test_env.token_1_mint,
+        system_program: None,
+        partner_info: None,
+    };
+    let data = gamma::instruction::Deposit {
+        lp_token_amount: 1000,
+        maximum_token_0_amount: u64::MAX,
+        maximum_token_1_amount: u64::MAX,
+    };
+    let mut instruction = get_instruction(data, accounts);
+    instruction.accounts.truncate(12);
+    instruction.accounts[0].is_writable = false;
+    let transaction =
+        get_signed_transaction(&mut test_env.program_test_context, &[instruction], &user).await;
+    test_env
+        .program_test_context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap_transaction();
+
+    let lp_tokens_after: UserPoolLiquidity = test_env.fetch_account(user_pool_liquidity).await;
+    assert_eq!(
+        lp_tokens_after.lp_tokens_owned,
+        lp_tokens_before.lp_tokens_owned + 1000
     );
 }
@@ -0,0 +1,602 @@
+use gamma::{
+    error::GammaError,
+    states::{PoolState, RewardInfo, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction};
+mod utils;
+
+use utils::*;
+
+async fn fund_user(test_env: &mut TestEnv, user: &Keypair, amount: u64) {
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, amount, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, amount, test_env.token_1_mint)
+        .await;
+}
+
+#[tokio::test]
+async fn jit_deposit_should_not_earn_past_rewards() {
+    let user = Keypair::new();
+    let attacker = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        attacker.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+    fund_user(&mut test_env, &user, 100000).await;
+    fund_user(&mut test_env, &attacker, 100000000).await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            1000,
+            2000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env
+        .deposit(&user, pool_id, amm_index, 1, 999999, 99999)
+        .await;
+    test_env.init_user_pool_liquidity(&attacker, pool_id).await;
+
+    let timestamp_now = test_env.timestamp_now().await as u64;
+    let reward_amount = 1000000000;
+    let (reward_info_key, _) = test_env
+        .create_funded_reward(
+            &reward_provider,
+            pool_id,
+            timestamp_now + 10,
+            timestamp_now + 1010,
+            reward_amount,
+        )
+        .await;
+    test_env.jump_seconds(500).await;
+
+    // Lp changes must settle every reward of the pool
+    let result = test_env
+        .deposit_with_rewards(
+            &attacker,
+            pool_id,
+            amm_index,
+            13150,
+            100000000,
+            100000000,
+            vec![],
+        )
+        .await;
+    assert_error!(result, GammaError::IncompleteRewardInfos);
+
+    // The attacker enters and leaves the pool around a reward calculation
+    test_env
+        .calculate_rewards(&attacker, pool_id, reward_info_key)
+        .await;
+    test_env
+        .deposit_with_rewards(
+            &attacker,
+            pool_id,
+            amm_index,
+            13150,
+            100000000,
+            100000000,
+            vec![reward_info_key],
+        )
+        .await
+        .unwrap_transaction();
+    test_env
+        .calculate_rewards(&attacker, pool_id, reward_info_key)
+        .await;
+    let attacker_reward_info = test_env
+        .fetch_user_reward_info(reward_info_key, attacker.pubkey())
+        .await;
+    assert_eq!(attacker_reward_info.total_rewards, 0);
+
+    test_env
+        .withdraw_with_rewards(
+            &attacker,
+            pool_id,
+            amm_index,
+            13150,
+            0,
+            0,
+            vec![reward_info_key],
+        )
+        .await
+        .unwrap_transaction();
+    let attacker_pool_liquidity: UserPoolLiquidity = test_env
+        .fetch_account(
+            Pubkey::find_program_address(
+                &[
+                    USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                    pool_id.to_bytes().as_ref(),
+                    attacker.pubkey().to_bytes().as_ref(),
+                ],
+                &gamma::id(),
+            )
+            .0,
+        )
+        .await;
+    assert_eq!(attacker_pool_liquidity.lp_tokens_owned, 0);
+
+    // Jump to the end of the reward
+    test_env.jump_seconds(1000).await;
+    test_env
+        .calculate_rewards(&attacker, pool_id, reward_info_key)
+        .await;
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+
+    let attacker_reward_info = test_env
+        .fetch_user_reward_info(reward_info_key, attacker.pubkey())
+        .await;
+    assert_eq!(attacker_reward_info.total_rewards, 0);
+    // The only lp over the whole reward gets all of it, apart from rounding
+    let user_reward_info = test_env
+        .fetch_user_reward_info(reward_info_key, user.pubkey())
+        .await;
+    assert!(user_reward_info.total_rewards <= reward_amount);
+    assert!(user_reward_info.total_rewards >= reward_amount - 2);
+}
+
+#[tokio::test]
+async fn concurrent_rewards_should_be_shared_from_deposit_time() {
+    let user = Keypair::new();
+    let late_user = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        late_user.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+    fund_user(&mut test_env, &user, 100000).await;
+    fund_user(&mut test_env, &late_user, 100000000).await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            1000,
+            2000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env
+        .deposit(&user, pool_id, amm_index, 1, 999999, 99999)
+        .await;
+    test_env.init_user_pool_liquidity(&late_user, pool_id).await;
+
+    // Two rewards with different mints and schedules run at the same time
+    let timestamp_now = test_env.timestamp_now().await as u64;
+    let rewards = [
+        (timestamp_now + 10, timestamp_now + 1010, 1000000000),
+        (timestamp_now + 10, timestamp_now + 2010, 300000000),
+    ];
+    let mut reward_info_keys = vec![];
+    for (start_time, end_time, reward_amount) in rewards {
+        reward_info_keys.push(
+            test_env
+                .create_funded_reward(
+                    &reward_provider,
+                    pool_id,
+                    start_time,
+                    end_time,
+                    reward_amount,
+                )
+                .await
+                .0,
+        );
+    }
+    test_env.jump_seconds(500).await;
+
+    for reward_info_key in reward_info_keys.iter() {
+        test_env
+            .calculate_rewards(&late_user, pool_id, *reward_info_key)
+            .await;
+    }
+
+    // Every reward must be passed, exactly once
+    let result = test_env
+        .deposit_with_rewards(
+            &late_user,
+            pool_id,
+            amm_index,
+            1315,
+            100000000,
+            100000000,
+            vec![reward_info_keys[0]],
+        )
+        .await;
+    assert_error!(result, GammaError::IncompleteRewardInfos);
+    let result = test_env
+        .deposit_with_rewards(
+            &late_user,
+            pool_id,
+            amm_index,
+            1315,
+            100000000,
+            100000000,
+            vec![reward_info_keys[0], reward_info_keys[0]],
+        )
+        .await;
+    assert_error!(result, GammaError::IncompleteRewardInfos);
+
+    // The late user owns as many lp tokens as the first one from here on
+    test_env
+        .deposit_with_rewards(
+            &late_user,
+            pool_id,
+            amm_index,
+            1315,
+            100000000,
+            100000000,
+            reward_info_keys.clone(),
+        )
+        .await
+        .unwrap_transaction();
+    let mut deposit_times = vec![];
+    for reward_info_key in reward_info_keys.iter() {
+        let reward_info: RewardInfo = test_env.fetch_account(*reward_info_key).await;
+        deposit_times.push(reward_info.last_updated_at);
+    }
+
+    test_env.jump_seconds(3000).await;
+    for ((reward_info_key, (start_time, end_time, reward_amount)), deposit_time) in
+        reward_info_keys.iter().zip(rewards).zip(deposit_times)
+    {
+        test_env
+            .calculate_rewards(&user, pool_id, *reward_info_key)
+            .await;
+        test_env
+            .calculate_rewards(&late_user, pool_id, *reward_info_key)
+            .await;
+        let user_rewards = test_env
+            .fetch_user_reward_info(*reward_info_key, user.pubkey())
+            .await
+            .total_rewards;
+        let late_user_rewards = test_env
+            .fetch_user_reward_info(*reward_info_key, late_user.pubkey())
+            .await
+            .total_rewards;
+
+        let rewards_before_deposit =
+            reward_amount * (deposit_time - start_time) / (end_time - start_time);
+        let shared_rewards = reward_amount - rewards_before_deposit;
+        assert!(late_user_rewards <= shared_rewards / 2);
+        assert!(late_user_rewards >= shared_rewards / 2 - 2);
+        assert!(user_rewards >= rewards_before_deposit + shared_rewards / 2 - 2);
+        assert!(user_rewards + late_user_rewards <= reward_amount);
+    }
+}
+
+#[tokio::test]
+async fn lp_changes_should_create_missing_user_reward_infos() {
+    let user = Keypair::new();
+    let late_user = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        late_user.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+    fund_user(&mut test_env, &user, 100000).await;
+    fund_user(&mut test_env, &late_user, 100000000).await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            1000,
+            2000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env.init_user_pool_liquidity(&late_user, pool_id).await;
+
+    let timestamp_now = test_env.timestamp_now().await as u64;
+    let reward_amount = 1000000000;
+    let (reward_info_key, _) = test_env
+        .create_funded_reward(
+            &reward_provider,
+            pool_id,
+            timestamp_now + 10,
+            timestamp_now + 1010,
+            reward_amount,
+        )
+        .await;
+    test_env.jump_seconds(500).await;
+
+    // The user reward info of the late user is created by the deposit
+    test_env
+        .deposit_with_rewards(
+            &late_user,
+            pool_id,
+            amm_index,
+            1315,
+            100000000,
+            100000000,
+            vec![reward_info_key],
+        )
+        .await
+        .unwrap_transaction();
+    let reward_info: RewardInfo = test_env.fetch_account(reward_info_key).await;
+    let late_user_reward_info = test_env
+        .fetch_user_reward_info(reward_info_key, late_user.pubkey())
+        .await;
+    assert_eq!(late_user_reward_info.total_rewards, 0);
+    assert_eq!(
+        late_user_reward_info.reward_per_lp_paid_x64,
+        reward_info.reward_per_lp_x64
+    );
+
+    test_env.jump_seconds(1000).await;
+    test_env
+        .calculate_rewards(&late_user, pool_id, reward_info_key)
+        .await;
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    let late_user_rewards = test_env
+        .fetch_user_reward_info(reward_info_key, late_user.pubkey())
+        .await
+        .total_rewards;
+    let user_rewards = test_env
+        .fetch_user_reward_info(reward_info_key, user.pubkey())
+        .await
+        .total_rewards;
+    assert!(late_user_rewards > 0);
+    assert!(late_user_rewards + user_rewards <= reward_amount);
+    assert!(late_user_rewards + user_rewards >= reward_amount - 2);
+}
+
+#[tokio::test]
+async fn prefunded_user_reward_info_should_not_block_lp_changes() {
+    let user = Keypair::new();
+    let late_user = Keypair::new();
+    let attacker = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        late_user.pubkey(),
+        attacker.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+    fund_user(&mut test_env, &user, 100000).await;
+    fund_user(&mut test_env, &late_user, 100000000).await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            1000,
+            2000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env.init_user_pool_liquidity(&late_user, pool_id).await;
+
+    let timestamp_now = test_env.timestamp_now().await as u64;
+    let (reward_info_key, _) = test_env
+        .create_funded_reward(
+            &reward_provider,
+            pool_id,
+            timestamp_now + 10,
+            timestamp_now + 1010,
+            1000000000,
+        )
+        .await;
+
+    // Anyone can send lamports to the user reward info before the program creates it
+    let late_user_reward_info_key = user_reward_info_key(reward_info_key, late_user.pubkey());
+    let transaction = get_signed_transaction(
+        &mut test_env.program_test_context,
+        &[system_instruction::transfer(
+            &attacker.pubkey(),
+            &late_user_reward_info_key,
+            1000000,
+        )],
+        &attacker,
+    )
+    .await;
+    test_env
+        .program_test_context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap_transaction();
+    test_env.jump_seconds(500).await;
+
+    test_env
+        .deposit_with_rewards(
+            &late_user,
+            pool_id,
+            amm_index,
+            1315,
+            100000000,
+            100000000,
+            vec![reward_info_key],
+        )
+        .await
+        .unwrap_transaction();
+    let reward_info: RewardInfo = test_env.fetch_account(reward_info_key).await;
+    let late_user_reward_info = test_env
+        .fetch_user_reward_info(reward_info_key, late_user.pubkey())
+        .await;
+    assert_eq!(late_user_reward_info.total_rewards, 0);
+    assert_eq!(
+        late_user_reward_info.reward_per_lp_paid_x64,
+        reward_info.reward_per_lp_x64
+    );
+}
+
+#[tokio::test]
+async fn legacy_rewards_should_be_migrated() {
+    let user = Keypair::new();
+    let other_user = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        other_user.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+    fund_user(&mut test_env, &user, 100000).await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            1000,
+            2000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env
+        .init_user_pool_liquidity(&other_user, pool_id)
+        .await;
+
+    let timestamp_now = test_env.timestamp_now().await as u64;
+    let start_time = timestamp_now + 10;
+    let reward_amount = 1000000000;
+    let (reward_info_key, _) = test_env
+        .create_funded_reward(
+            &reward_provider,
+            pool_id,
+            start_time,
+            start_time + 1000,
+            reward_amount,
+        )
+        .await;
+
+    // Both users calculated their rewards before the upgrade
+    test_env.jump_seconds(210).await;
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    test_env
+        .calculate_rewards(&other_user, pool_id, reward_info_key)
+        .await;
+    test_env.make_legacy_reward_info(reward_info_key).await;
+    test_env
+        .make_legacy_user_reward_info(reward_info_key, user.pubkey())
+        .await;
+    test_env
+        .make_legacy_user_reward_info(reward_info_key, other_user.pubkey())
+        .await;
+
+    // The user reward can not be migrated before the reward
+    let result = test_env
+        .migrate_user_reward_info(&other_user, other_user.pubkey(), reward_info_key)
+        .await;
+    assert!(result.unwrap().result.is_err());
+
+    test_env.jump_seconds(300).await;
+    test_env
+        .migrate_reward_info(&reward_provider, pool_id, reward_info_key)
+        .await
+        .unwrap_transaction();
+    let result = test_env
+        .migrate_reward_info(&reward_provider, pool_id, reward_info_key)
+        .await;
+    assert_error!(result, GammaError::RewardAlreadyMigrated);
+
+    // The running reward takes a slot of the pool
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq!(pool_state.reward_count, 1);
+    let reward_info: RewardInfo = test_env.fetch_account(reward_info_key).await;
+    assert_eq!(reward_info.migrated_at, reward_info.last_updated_at);
+    let emitted = reward_amount * (reward_info.migrated_at - start_time) / 1000;
+    assert_eq!(reward_info.total_disbursed, emitted);
+
+    // Lp changes migrate the user rewards they settle
+    test_env
+        .deposit_with_rewards(
+            &user,
+            pool_id,
+            amm_index,
+            1,
+            999999,
+            99999,
+            vec![reward_info_key],
+        )
+        .await
+        .unwrap_transaction();
+    let user_reward_info = test_env
+        .fetch_user_reward_info(reward_info_key, user.pubkey())
+        .await;
+    assert!(user_reward_info.total_rewards <= emitted);
+    assert!(user_reward_info.total_rewards >= emitted - 2);
+
+    test_env
+        .migrate_user_reward_info(&other_user, other_user.pubkey(), reward_info_key)
+        .await
+        .unwrap_transaction();
+    let result = test_env
+        .migrate_user_reward_info(&other_user, other_user.pubkey(), reward_info_key)
+        .await;
+    assert_error!(result, GammaError::RewardAlreadyMigrated);
+    let other_user_reward_info = test_env
+        .fetch_user_reward_info(reward_info_key, other_user.pubkey())
+        .await;
+    assert_eq!(other_user_reward_info.total_rewards, 0);
+
+    // The user is the only lp over the whole reward
+    test_env.jump_seconds(1000).await;
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    let user_reward_info = test_env
+        .fetch_user_reward_info(reward_info_key, user.pubkey())
+        .await;
+    assert!(user_reward_info.total_rewards <= reward_amount);
+    assert!(user_reward_info.total_rewards >= reward_amount - 4);
+}
@@ -0,0 +1,418 @@
+use anchor_spl::token::TokenAccount;
+use gamma::{
+    error::GammaError,
+    states::{PoolState, RewardInfo, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED},
+    REWARD_VAULT_SEED,
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn close_rewards_should_return_rewards_accrued_without_lps() {
+    let user = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+    let pool_id = test_env.setup_small_pool(&user, &admin).await;
+
+    let timestamp_now = test_env.timestamp_now().await as u64;
+    let (start_time, end_time, reward_amount) =
+        (timestamp_now + 10, timestamp_now + 1010, 1000000000);
+    let (reward_info_key, reward_mint) = test_env
+        .create_funded_reward(
+            &reward_provider,
+            pool_id,
+            start_time,
+            end_time,
+            reward_amount,
+        )
+        .await;
+
+    // Only the locked liquidity is left in the pool when the reward starts
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    let user_pool_liquidity: UserPoolLiquidity = test_env
+        .fetch_account(
+            Pubkey::find_program_address(
+                &[
+                    USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                    pool_id.to_bytes().as_ref(),
+                    user.pubkey().to_bytes().as_ref(),
+                ],
+                &gamma::id(),
+            )
+            .0,
+        )
+        .await;
+    let lp_tokens_owned = user_pool_liquidity.lp_tokens_owned as u64;
+    test_env
+        .withdraw_with_rewards(
+            &user,
+            pool_id,
+            0,
+            lp_tokens_owned,
+            0,
+            0,
+            vec![reward_info_key],
+        )
+        .await
+        .unwrap_transaction();
+
+    test_env.jump_seconds(500).await;
+    test_env
+        .deposit_with_rewards(
+            &user,
+            pool_id,
+            0,
+            lp_tokens_owned,
+            100000,
+            100000,
+            vec![reward_info_key],
+        )
+        .await
+        .unwrap_transaction();
+    let reward_info: RewardInfo = test_env.fetch_account(reward_info_key).await;
+    let rewards_without_lps =
+        reward_amount * (reward_info.last_updated_at - start_time) / (end_time - start_time);
+
+    // Only the reward provider can close the reward
+    let result = test_env
+        .close_rewards(&user, pool_id, reward_info_key, reward_mint)
+        .await;
+    assert_error!(result, GammaError::InvalidOwner);
+
+    test_env.jump_seconds(1000).await;
+    test_env
+        .close_rewards(&reward_provider, pool_id, reward_info_key, reward_mint)
+        .await
+        .unwrap_transaction();
+    let returned_rewards = test_env.token_balance(&reward_provider, reward_mint).await;
+    // The rewards disbursed to the lp are rounded up
+    assert!(returned_rewards + 1 >= rewards_without_lps);
+    assert!(returned_rewards <= rewards_without_lps + 1);
+
+    // Closing again returns nothing more
+    test_env
+        .close_rewards(&reward_provider, pool_id, reward_info_key, reward_mint)
+        .await
+        .unwrap_transaction();
+    assert_eq!(
+        test_env.token_balance(&reward_provider, reward_mint).await,
+        returned_rewards
+    );
+
+    // The rewards disbursed to the lp can still be claimed
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    let rewards = user_rewards(&mut test_env, reward_info_key, user.pubkey()).await;
+    assert!(rewards + returned_rewards <= reward_amount);
+    assert!(rewards >= reward_amount - rewards_without_lps - 2);
+    test_env
+        .claim_rewards(&user, pool_id, reward_info_key, reward_mint)
+        .await;
+    assert_eq!(test_env.token_balance(&user, reward_mint).await, rewards);
+}
+
+#[tokio::test]
+async fn fund_and_extend_rewards_should_only_change_future_emission() {
+    let user = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+    let pool_id = test_env.setup_small_pool(&user, &admin).await;
+
+    let timestamp_now = test_env.timestamp_now().await as u64;
+    let (start_time, end_time, reward_amount) =
+        (timestamp_now + 10, timestamp_now + 1010, 1000000000);
+    let (reward_info_key, reward_mint) = test_env
+        .create_funded_reward(
+            &reward_provider,
+            pool_id,
+            start_time,
+            end_time,
+            reward_amount,
+        )
+        .await;
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+
+    // Top up the reward half way through
+    test_env.jump_seconds(510).await;
+    test_env
+        .mint_rewards(&reward_provider, reward_mint, reward_amount)
+        .await;
+    let result = test_env
+        .fund_rewards(&user, pool_id, reward_info_key, reward_mint, reward_amount)
+        .await;
+    assert_error!(result, GammaError::InvalidOwner);
+    test_env
+        .fund_rewards(
+            &reward_provider,
+            pool_id,
+            reward_info_key,
+            reward_mint,
+            reward_amount,
+        )
+        .await
+        .unwrap_transaction();
+
+    let reward_info: RewardInfo = test_env.fetch_account(reward_info_key).await;
+    let funded_at = reward_info.last_updated_at;
+    let emitted_before_funding = reward_amount * (funded_at - start_time) / (end_time - start_time);
+    assert_eq!(reward_info.total_to_disburse, 2 * reward_amount);
+    assert_eq!(reward_info.schedule_start_at, funded_at);
+    assert_eq!(
+        reward_info.schedule_amount,
+        2 * reward_amount - emitted_before_funding
+    );
+
+    // The past emission is kept when the reward is extended
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    let rewards_before_extension =
+        user_rewards(&mut test_env, reward_info_key, user.pubkey()).await;
+    assert!(rewards_before_extension >= emitted_before_funding - 1);
+    let result = test_env
+        .extend_rewards(&reward_provider, pool_id, reward_info_key, end_time)
+        .await;
+    assert_error!(result, GammaError::InvalidRewardTime);
+    test_env
+        .extend_rewards(&reward_provider, pool_id, reward_info_key, end_time + 1000)
+        .await
+        .unwrap_transaction();
+    let reward_info: RewardInfo = test_env.fetch_account(reward_info_key).await;
+    assert_eq!(reward_info.end_rewards_at, end_time + 1000);
+    assert_eq!(reward_info.schedule_start_at, reward_info.last_updated_at);
+    assert!(reward_info.schedule_amount <= 2 * reward_amount - emitted_before_funding);
+
+    // The reward can not be funded once it ended
+    test_env.jump_seconds(2000).await;
+    test_env
+        .mint_rewards(&reward_provider, reward_mint, reward_amount)
+        .await;
+    let result = test_env
+        .fund_rewards(
+            &reward_provider,
+            pool_id,
+            reward_info_key,
+            reward_mint,
+            reward_amount,
+        )
+        .await;
+    assert_error!(result, GammaError::InvalidRewardTime);
+
+    // The sole lp gets both the created and the funded rewards
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    let rewards = user_rewards(&mut test_env, reward_info_key, user.pubkey()).await;
+    assert!(rewards <= 2 * reward_amount);
+    assert!(rewards >= 2 * reward_amount - 3);
+
+    let (reward_vault_key, _) = Pubkey::find_program_address(
+        &[
+            REWARD_VAULT_SEED.as_bytes(),
+            reward_info_key.to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    );
+    test_env
+        .close_rewards(&reward_provider, pool_id, reward_info_key, reward_mint)
+        .await
+        .unwrap_transaction();
+    let reward_vault: TokenAccount = test_env.fetch_account(reward_vault_key).await;
+    assert!(reward_vault.amount >= rewards);
+}
+
+#[tokio::test]
+async fn closed_rewards_should_free_their_slot() {
+    let user = Keypair::new();
+    let late_user = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        late_user.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+    let pool_id = test_env.setup_small_pool(&user, &admin).await;
+    for mint in [test_env.token_0_mint, test_env.token_1_mint] {
+        let token_account = test_env
+            .get_or_create_associated_token_account(late_user.pubkey(), mint, &late_user)
+            .await;
+        test_env.mint_base_tokens(token_account, 100000, mint).await;
+    }
+    test_env.init_user_pool_liquidity(&late_user, pool_id).await;
+
+    let start_time = test_env.timestamp_now().await as u64 + 10;
+    let end_time = start_time + 1000;
+    let reward_amount = 1000000000;
+    let (reward_info_key, reward_mint) = test_env
+        .create_funded_reward(
+            &reward_provider,
+            pool_id,
+            start_time,
+            end_time,
+            reward_amount,
+        )
+        .await;
+
+    test_env.jump_seconds(510).await;
+    test_env
+        .close_rewards(&reward_provider, pool_id, reward_info_key, reward_mint)
+        .await
+        .unwrap_transaction();
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq!(pool_state.reward_count, 0);
+    assert_eq!(pool_state.rewards_closed, 1);
+    let reward_info: RewardInfo = test_env.fetch_account(reward_info_key).await;
+    assert_eq!(reward_info.close_index, 1);
+
+    // Closing again does not free another slot
+    test_env
+        .close_rewards(&reward_provider, pool_id, reward_info_key, reward_mint)
+        .await
+        .unwrap_transaction();
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq!(pool_state.reward_count, 0);
+    assert_eq!(pool_state.rewards_closed, 1);
+
+    let result = test_env
+        .extend_rewards(&reward_provider, pool_id, reward_info_key, end_time + 1000)
+        .await;
+    assert_error!(result, GammaError::RewardClosed);
+
+    // Lp changes do not settle the closed reward anymore, so the lp tokens added since do not earn it
+    test_env
+        .deposit_with_rewards(&late_user, pool_id, 0, 1000, 100000, 100000, vec![])
+        .await
+        .unwrap_transaction();
+    test_env
+        .calculate_rewards(&late_user, pool_id, reward_info_key)
+        .await;
+    assert_eq!(
+        user_rewards(&mut test_env, reward_info_key, late_user.pubkey()).await,
+        0
+    );
+
+    // The closed reward can still be settled by the lp change of a position that earned it
+    test_env
+        .deposit_with_rewards(
+            &user,
+            pool_id,
+            0,
+            1000,
+            100000,
+            100000,
+            vec![reward_info_key],
+        )
+        .await
+        .unwrap_transaction();
+    let rewards = user_rewards(&mut test_env, reward_info_key, user.pubkey()).await;
+    assert!(rewards <= reward_info.total_disbursed);
+    assert!(rewards + 1 >= reward_info.total_disbursed);
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    assert_eq!(
+        user_rewards(&mut test_env, reward_info_key, user.pubkey()).await,
+        rewards
+    );
+}
+
+#[tokio::test]
+async fn lp_changes_should_settle_closed_rewards_before_forgetting_them() {
+    let user = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+    let pool_id = test_env.setup_small_pool(&user, &admin).await;
+
+    let start_time = test_env.timestamp_now().await as u64 + 10;
+    let end_time = start_time + 1000;
+    let reward_amount = 1000000000;
+    let (reward_info_key, reward_mint) = test_env
+        .create_funded_reward(
+            &reward_provider,
+            pool_id,
+            start_time,
+            end_time,
+            reward_amount,
+        )
+        .await;
+
+    // The rewards accrued until the close are not checkpointed on the position
+    test_env.jump_seconds(510).await;
+    test_env
+        .close_rewards(&reward_provider, pool_id, reward_info_key, reward_mint)
+        .await
+        .unwrap_transaction();
+    let reward_info: RewardInfo = test_env.fetch_account(reward_info_key).await;
+
+    // An lp change leaving the closed reward out would forfeit them
+    let result = test_env
+        .deposit_with_rewards(&user, pool_id, 0, 1000, 100000, 100000, vec![])
+        .await;
+    assert_error!(result, GammaError::IncompleteRewardInfos);
+
+    test_env
+        .deposit_with_rewards(
+            &user,
+            pool_id,
+            0,
+            1000,
+            100000,
+            100000,
+            vec![reward_info_key],
+        )
+        .await
+        .unwrap_transaction();
+
+    // The lp tokens added after the close do not earn the reward, the settled ones can be claimed
+    test_env.jump_seconds(100).await;
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    let rewards = user_rewards(&mut test_env, reward_info_key, user.pubkey()).await;
+    assert!(rewards <= reward_info.total_disbursed);
+    assert!(rewards + 1 >= reward_info.total_disbursed);
+    test_env
+        .claim_rewards(&user, pool_id, reward_info_key, reward_mint)
+        .await;
+    assert_eq!(test_env.token_balance(&user, reward_mint).await, rewards);
+
+    // Only the rounding of the disbursed rewards is left in the vault
+    let (reward_vault_key, _) = Pubkey::find_program_address(
+        &[
+            REWARD_VAULT_SEED.as_bytes(),
+            reward_info_key.to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    );
+    let reward_vault: TokenAccount = test_env.fetch_account(reward_vault_key).await;
+    assert!(reward_vault.amount <= 1);
+}
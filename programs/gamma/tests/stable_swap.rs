@@ -0,0 +1,228 @@
+use anchor_spl::token::TokenAccount;
+use gamma::{
+    curve::{StableSwapCurve, TradeDirection},
+    states::{PoolState, Q32},
+};
+use solana_program_test::tokio;
+use solana_sdk::{signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn stable_swap_pool_should_give_better_rate_near_peg() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env.create_config(&admin, 0, 100, 20, 5, 0).await;
+    test_env.create_config(&admin, 1, 100, 20, 5, 0).await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    let constant_product_pool = test_env
+        .initialize_pool(
+            &user,
+            0,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    let stable_swap_pool = test_env
+        .initialize_pool_with_curve(
+            &user,
+            1,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+            1,
+            100,
+            0,
+        )
+        .await;
+
+    let stable_swap_pool_state: PoolState = test_env.fetch_account(stable_swap_pool).await;
+    assert_eq!(stable_swap_pool_state.curve_type, 1);
+    assert_eq!(stable_swap_pool_state.initial_amp, 100);
+    assert_eq!(stable_swap_pool_state.target_amp, 100);
+
+    let mut received = vec![];
+    for (pool_id, amm_index) in [(constant_product_pool, 0), (stable_swap_pool, 1)] {
+        let user_token_1_before: TokenAccount = test_env.fetch_account(user_token_1_account).await;
+        test_env
+            .swap_base_input(
+                &user,
+                pool_id,
+                amm_index,
+                1000000,
+                1,
+                TradeDirection::ZeroForOne,
+            )
+            .await;
+        let user_token_1_after: TokenAccount = test_env.fetch_account(user_token_1_account).await;
+        received.push(user_token_1_after.amount - user_token_1_before.amount);
+    }
+
+    // A swap of 10% of the reserves loses about 9% to price impact on the constant product curve,
+    // while the stable swap curve stays close to par
+    assert!(received[0] < 910000);
+    assert!(received[1] > 980000);
+    assert!(received[1] < 1000000);
+}
+
+#[tokio::test]
+async fn stable_swap_amp_should_ramp_linearly() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    let pool_id = test_env
+        .initialize_pool_with_curve(
+            &user,
+            amm_index,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+            1,
+            100,
+            0,
+        )
+        .await;
+
+    // Ramp to 1000 over two days
+    let ramp_duration: u64 = 2 * 86400;
+    test_env
+        .update_pool(&admin, pool_id, amm_index, 6, (ramp_duration << 32) | 1000)
+        .await;
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq!(pool_state.initial_amp, 100);
+    assert_eq!(pool_state.target_amp, 1000);
+    assert_eq!(
+        pool_state.amp_ramp_stop_time - pool_state.amp_ramp_start_time,
+        ramp_duration
+    );
+    let ramp_start_time = pool_state.amp_ramp_start_time;
+    assert_eq!(pool_state.current_amp(ramp_start_time), 100);
+    assert_eq!(pool_state.current_amp(ramp_start_time + 86400), 550);
+    assert_eq!(
+        pool_state.current_amp(ramp_start_time + ramp_duration),
+        1000
+    );
+
+    // Swaps keep working while the ramp is running
+    test_env.jump_days(1).await;
+    test_env
+        .swap_base_input(
+            &user,
+            pool_id,
+            amm_index,
+            1000000,
+            1,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq!(pool_state.cumulative_volume_token_0, 1000000);
+}
+
+#[tokio::test]
+async fn stable_swap_spot_price_should_match_a_small_swap() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    for mint in [test_env.token_0_mint, test_env.token_1_mint] {
+        let token_account = test_env
+            .get_or_create_associated_token_account(user.pubkey(), mint, &user)
+            .await;
+        test_env
+            .mint_base_tokens(token_account, 100000000, mint)
+            .await;
+    }
+
+    // Off peg, the constant product ratio of the vaults is far from the price of the curve
+    let pool_id = test_env
+        .initialize_pool_with_curve(
+            &user,
+            amm_index,
+            30000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+            1,
+            100,
+            0,
+        )
+        .await;
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let amp = pool_state.current_amp(test_env.timestamp_now().await as u64);
+    let (token_0_price_x32, token_1_price_x32) = pool_state
+        .token_price_x32(test_env.timestamp_now().await as u64)
+        .unwrap();
+    assert!(token_0_price_x32 > Q32 * 10000000 / 30000000);
+    assert!(token_0_price_x32 < Q32);
+    assert!(token_1_price_x32 > Q32);
+
+    // The output of a small swap divided by its input is the spot price, up to the rounding margin
+    let amount_in: u128 = 10000;
+    for (swap_source_amount, swap_destination_amount, price_x32) in [
+        (
+            pool_state.token_0_vault_amount,
+            pool_state.token_1_vault_amount,
+            token_0_price_x32,
+        ),
+        (
+            pool_state.token_1_vault_amount,
+            pool_state.token_0_vault_amount,
+            token_1_price_x32,
+        ),
+    ] {
+        let amount_out = StableSwapCurve::swap_base_input_without_fees(
+            amount_in,
+            u128::from(swap_source_amount),
+            u128::from(swap_destination_amount),
+            amp,
+        )
+        .unwrap();
+        let expected_amount_out = amount_in * price_x32 / Q32;
+        assert!(amount_out <= expected_amount_out);
+        assert!(amount_out + 3 >= expected_amount_out);
+    }
+}
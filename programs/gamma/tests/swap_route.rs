@@ -0,0 +1,90 @@
+use anchor_spl::token::TokenAccount;
+use gamma::{curve::TradeDirection, states::PoolState};
+use solana_program_test::tokio;
+use solana_sdk::{signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn should_swap_through_every_pool_of_the_route() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env.create_config(&admin, 0, 100, 20, 5, 0).await;
+    test_env.create_config(&admin, 1, 100, 20, 5, 0).await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    let pool_a = test_env
+        .initialize_pool(
+            &user,
+            0,
+            10000000,
+            20000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    let pool_b = test_env
+        .initialize_pool(
+            &user,
+            1,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+
+    let user_token_0_before: TokenAccount = test_env.fetch_account(user_token_0_account).await;
+    let user_token_1_before: TokenAccount = test_env.fetch_account(user_token_1_account).await;
+
+    // token_0 -> token_1 in pool a, then token_1 -> token_0 in pool b
+    test_env
+        .swap_route(
+            &user,
+            vec![
+                (0, TradeDirection::ZeroForOne),
+                (1, TradeDirection::OneForZero),
+            ],
+            100000,
+            1,
+        )
+        .await;
+
+    let pool_a_state: PoolState = test_env.fetch_account(pool_a).await;
+    let pool_b_state: PoolState = test_env.fetch_account(pool_b).await;
+    assert_eq!(pool_a_state.cumulative_volume_token_0, 100000);
+    assert_eq!(pool_a_state.cumulative_volume_token_1, 0);
+    assert_eq!(pool_b_state.cumulative_volume_token_0, 0);
+    // Everything pool a paid out went into pool b
+    let pool_a_output = 20000000 - pool_a_state.token_1_vault_amount;
+    assert_eq!(
+        pool_b_state.cumulative_volume_token_1,
+        pool_a_output as u128
+    );
+
+    // Intermediate tokens never reach the user
+    let user_token_1_after: TokenAccount = test_env.fetch_account(user_token_1_account).await;
+    assert_eq!(user_token_1_after.amount, user_token_1_before.amount);
+
+    // Pool b prices token_1 at par while pool a prices it at half of token_0,
+    // so the route ends with about twice the input minus fees and price impact
+    let user_token_0_after: TokenAccount = test_env.fetch_account(user_token_0_account).await;
+    let received = user_token_0_after.amount + 100000 - user_token_0_before.amount;
+    assert_eq!(10000000 - pool_b_state.token_0_vault_amount, received);
+    assert!(received > 190000);
+    assert!(received < 200000);
+}
@@ -0,0 +1,314 @@
+use gamma::{
+    curve::TradeDirection,
+    error::GammaError,
+    states::{PoolState, Q32},
+};
+use solana_program_test::tokio;
+use solana_sdk::{instruction::Instruction, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+async fn process(
+    test_env: &mut TestEnv,
+    instruction: Instruction,
+    user: &Keypair,
+) -> ProcessTransactionResult {
+    let transaction =
+        get_signed_transaction(&mut test_env.program_test_context, &[instruction], user).await;
+    test_env
+        .program_test_context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+}
+
+async fn deadline_in_a_minute(test_env: &mut TestEnv) -> u64 {
+    test_env.timestamp_now().await as u64 + 60
+}
+
+#[tokio::test]
+async fn should_fill_swap_base_input_v2_only_until_the_price_limit() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env.setup_pool(&user, &admin).await;
+    let (token_0_mint, token_1_mint) = (test_env.token_0_mint, test_env.token_1_mint);
+    let token_0_before = test_env.token_balance(&user, token_0_mint).await;
+    let token_1_before = test_env.token_balance(&user, token_1_mint).await;
+
+    // Swapping all of it would push token_1 to about 1.1 token_0
+    let max_price_x32 = Q32 * 6 / 10;
+    let deadline = deadline_in_a_minute(&mut test_env).await;
+    let instruction = test_env
+        .swap_base_input_v2_instruction(
+            &user,
+            pool_id,
+            0,
+            500_000,
+            100_000,
+            max_price_x32,
+            deadline,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    process(&mut test_env, instruction, &user)
+        .await
+        .unwrap_transaction();
+
+    // sqrt(0.6 * 1_000_000 * 2_000_000) - 1_000_000 token_0 reach the limit without fees
+    let amount_in = token_0_before - test_env.token_balance(&user, token_0_mint).await;
+    assert_eq!(amount_in, 95_445);
+    let amount_out = test_env.token_balance(&user, token_1_mint).await - token_1_before;
+    assert!(amount_out > 100_000 * amount_in / 500_000);
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let (_, token_1_price_x32) = pool_state
+        .token_price_x32(test_env.timestamp_now().await as u64)
+        .unwrap();
+    assert!(token_1_price_x32 <= max_price_x32);
+    assert!(token_1_price_x32 > Q32 * 59 / 100);
+}
+
+#[tokio::test]
+async fn should_fill_swap_base_input_v2_fully_within_the_price_limit() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env.setup_pool(&user, &admin).await;
+    let token_0_mint = test_env.token_0_mint;
+    let token_0_before = test_env.token_balance(&user, token_0_mint).await;
+
+    let deadline = deadline_in_a_minute(&mut test_env).await;
+    let instruction = test_env
+        .swap_base_input_v2_instruction(
+            &user,
+            pool_id,
+            0,
+            10_000,
+            0,
+            Q32 * 6 / 10,
+            deadline,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    process(&mut test_env, instruction, &user)
+        .await
+        .unwrap_transaction();
+
+    assert_eq!(
+        token_0_before - test_env.token_balance(&user, token_0_mint).await,
+        10_000
+    );
+}
+
+#[tokio::test]
+async fn should_fail_swap_v2_after_the_deadline_or_at_the_price_limit() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env.setup_pool(&user, &admin).await;
+
+    let expired = test_env.timestamp_now().await as u64 - 1;
+    let instruction = test_env
+        .swap_base_input_v2_instruction(
+            &user,
+            pool_id,
+            0,
+            10_000,
+            0,
+            Q32,
+            expired,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    let result = process(&mut test_env, instruction, &user).await;
+    assert_error!(result, GammaError::DeadlineExceeded);
+
+    // token_1 is already priced at 0.5 token_0
+    let deadline = deadline_in_a_minute(&mut test_env).await;
+    let instruction = test_env
+        .swap_base_output_v2_instruction(
+            &user,
+            pool_id,
+            0,
+            u64::MAX,
+            10_000,
+            Q32 * 4 / 10,
+            deadline,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    let result = process(&mut test_env, instruction, &user).await;
+    assert_error!(result, GammaError::PriceLimitReached);
+}
+
+#[tokio::test]
+async fn should_fill_swap_base_output_v2_until_the_price_limit() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env.setup_pool(&user, &admin).await;
+    let token_1_mint = test_env.token_1_mint;
+    let max_price_x32 = Q32 * 6 / 10;
+
+    // Within the limit the exact output is filled
+    let token_1_before = test_env.token_balance(&user, token_1_mint).await;
+    let deadline = deadline_in_a_minute(&mut test_env).await;
+    let instruction = test_env
+        .swap_base_output_v2_instruction(
+            &user,
+            pool_id,
+            0,
+            1_000_000,
+            10_000,
+            max_price_x32,
+            deadline,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    process(&mut test_env, instruction, &user)
+        .await
+        .unwrap_transaction();
+    assert_eq!(
+        test_env.token_balance(&user, token_1_mint).await - token_1_before,
+        10_000
+    );
+
+    // Past the limit only part of the output is filled
+    let token_1_before = test_env.token_balance(&user, token_1_mint).await;
+    let instruction = test_env
+        .swap_base_output_v2_instruction(
+            &user,
+            pool_id,
+            0,
+            1_000_000,
+            400_000,
+            max_price_x32,
+            deadline,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    process(&mut test_env, instruction, &user)
+        .await
+        .unwrap_transaction();
+    let amount_out = test_env.token_balance(&user, token_1_mint).await - token_1_before;
+    assert!(amount_out > 100_000);
+    assert!(amount_out < 400_000);
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let (_, token_1_price_x32) = pool_state
+        .token_price_x32(test_env.timestamp_now().await as u64)
+        .unwrap();
+    assert!(token_1_price_x32 <= max_price_x32);
+    assert!(token_1_price_x32 > Q32 * 59 / 100);
+}
+
+#[tokio::test]
+async fn should_fill_swap_base_input_v2_on_weighted_pools_until_the_price_limit() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    // An 80/20 pool holding 4 times more token_0 than token_1 prices them at par
+    let pool_id = test_env
+        .setup_pool_with_curve(&user, &admin, 10_000_000, 800_000, 200_000, 2, 0, 80)
+        .await;
+    let token_0_mint = test_env.token_0_mint;
+    let token_0_before = test_env.token_balance(&user, token_0_mint).await;
+
+    let max_price_x32 = Q32 * 11 / 10;
+    let deadline = deadline_in_a_minute(&mut test_env).await;
+    let instruction = test_env
+        .swap_base_input_v2_instruction(
+            &user,
+            pool_id,
+            0,
+            500_000,
+            0,
+            max_price_x32,
+            deadline,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    process(&mut test_env, instruction, &user)
+        .await
+        .unwrap_transaction();
+
+    // 800_000 * (1.1^(20 / 100) - 1) token_0 reach the limit without fees
+    let amount_in = token_0_before - test_env.token_balance(&user, token_0_mint).await;
+    assert!(
+        amount_in.abs_diff(15_396) <= 2,
+        "amount in {} should be close to 15396",
+        amount_in
+    );
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let (_, token_1_price_x32) = pool_state
+        .token_price_x32(test_env.timestamp_now().await as u64)
+        .unwrap();
+    assert!(token_1_price_x32 <= max_price_x32);
+    assert!(token_1_price_x32 > Q32 * 109 / 100);
+}
+
+#[tokio::test]
+async fn should_fill_swap_base_input_v2_on_stable_pools_until_the_price_limit() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env
+        .setup_pool_with_curve(&user, &admin, 10_000_000, 1_000_000, 1_000_000, 1, 100, 0)
+        .await;
+    let token_0_mint = test_env.token_0_mint;
+
+    // Without a binding limit the whole input is swapped
+    let token_0_before = test_env.token_balance(&user, token_0_mint).await;
+    let deadline = deadline_in_a_minute(&mut test_env).await;
+    let instruction = test_env
+        .swap_base_input_v2_instruction(
+            &user,
+            pool_id,
+            0,
+            10_000,
+            0,
+            u128::MAX,
+            deadline,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    process(&mut test_env, instruction, &user)
+        .await
+        .unwrap_transaction();
+    assert_eq!(
+        token_0_before - test_env.token_balance(&user, token_0_mint).await,
+        10_000
+    );
+
+    // Near the peg the curve is flat, about 180_000 token_0 move the price of token_1 by 0.2%
+    let max_price_x32 = Q32 * 1002 / 1000;
+    let token_0_before = test_env.token_balance(&user, token_0_mint).await;
+    let instruction = test_env
+        .swap_base_input_v2_instruction(
+            &user,
+            pool_id,
+            0,
+            500_000,
+            0,
+            max_price_x32,
+            deadline,
+            TradeDirection::ZeroForOne,
+        )
+        .await;
+    process(&mut test_env, instruction, &user)
+        .await
+        .unwrap_transaction();
+    let amount_in = token_0_before - test_env.token_balance(&user, token_0_mint).await;
+    assert!(amount_in > 150_000);
+    assert!(amount_in < 200_000);
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let (_, token_1_price_x32) = pool_state
+        .token_price_x32(test_env.timestamp_now().await as u64)
+        .unwrap();
+    assert!(token_1_price_x32 <= max_price_x32);
+    assert!(token_1_price_x32 > Q32 * 1001 / 1000);
+}
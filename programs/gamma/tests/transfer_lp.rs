@@ -0,0 +1,252 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gamma::{
+    states::{PartnerInfo, UserPoolLiquidity, UserRewardInfo},
+    REWARD_INFO_SEED, USER_REWARD_INFO_SEED,
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn should_transfer_lp_and_update_partner_liquidity() {
+    let user = Keypair::new();
+    let destination = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env =
+        TestEnv::new(vec![user.pubkey(), destination.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000, test_env.token_1_mint)
+        .await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            1000,
+            2000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+
+    let partner_id = 0;
+    test_env
+        .register_partner(&admin, partner_id, 100000, admin.pubkey())
+        .await
+        .unwrap_transaction();
+    let partner_info_key = test_env.init_partner_info(&user, pool_id, partner_id).await;
+    test_env
+        .init_user_pool_liquidity_with_partner(&destination, pool_id, Some(partner_id))
+        .await;
+
+    let user_pool_liquidity_key = user_pool_liquidity_key(pool_id, user.pubkey());
+    let destination_pool_liquidity_key = user_pool_liquidity_key(pool_id, destination.pubkey());
+    let user_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(user_pool_liquidity_key).await;
+    let lp_owned_before = user_pool_liquidity.lp_tokens_owned;
+
+    test_env
+        .transfer_lp(&user, destination.pubkey(), pool_id, 500, vec![])
+        .await;
+
+    let user_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(user_pool_liquidity_key).await;
+    let destination_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(destination_pool_liquidity_key).await;
+    assert_eq!(user_pool_liquidity.lp_tokens_owned, lp_owned_before - 500);
+    assert_eq!(destination_pool_liquidity.lp_tokens_owned, 500);
+
+    // The destination is linked with a partner, so the transferred lp is now counted for it
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    assert_eq!(partner_info.lp_token_linked_with_partner, 500);
+
+    // Moving it back to a wallet without a partner removes it from the partner again
+    test_env
+        .transfer_lp(&destination, user.pubkey(), pool_id, 200, vec![])
+        .await;
+    let partner_info: PartnerInfo = test_env.fetch_account(partner_info_key).await;
+    assert_eq!(partner_info.lp_token_linked_with_partner, 300);
+
+    // The total lp supply is never touched by a transfer
+    let user_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(user_pool_liquidity_key).await;
+    let destination_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(destination_pool_liquidity_key).await;
+    assert_eq!(
+        user_pool_liquidity.lp_tokens_owned + destination_pool_liquidity.lp_tokens_owned,
+        lp_owned_before
+    );
+}
+
+#[tokio::test]
+async fn should_create_destination_position_and_settle_rewards() {
+    let user = Keypair::new();
+    let destination = Keypair::new();
+    let reward_provider = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![
+        user.pubkey(),
+        destination.pubkey(),
+        admin.pubkey(),
+        reward_provider.pubkey(),
+    ])
+    .await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000, test_env.token_1_mint)
+        .await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            1000,
+            2000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+
+    let reward_mint = Keypair::new();
+    test_env
+        .create_token_mint(&reward_mint, &test_env.mint_authority.pubkey(), 9)
+        .await;
+    let reward_provider_token_account = test_env
+        .get_or_create_associated_token_account(
+            reward_provider.pubkey(),
+            reward_mint.pubkey(),
+            &reward_provider,
+        )
+        .await;
+    let reward_amount = 1000000000;
+    test_env
+        .mint_base_tokens(
+            reward_provider_token_account,
+            reward_amount,
+            reward_mint.pubkey(),
+        )
+        .await;
+
+    let timestamp_now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let start_time = timestamp_now + 10;
+    let end_time = timestamp_now + 3000;
+    test_env
+        .create_rewards(
+            &reward_provider,
+            pool_id,
+            start_time,
+            end_time,
+            reward_mint.pubkey(),
+            reward_amount,
+        )
+        .await;
+    let (reward_info_key, _) = Pubkey::find_program_address(
+        &[
+            REWARD_INFO_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+            &start_time.to_le_bytes(),
+            reward_mint.pubkey().to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    );
+
+    test_env.jump_seconds(1000).await;
+
+    let user_pool_liquidity: UserPoolLiquidity = test_env
+        .fetch_account(user_pool_liquidity_key(pool_id, user.pubkey()))
+        .await;
+    let lp_owned = user_pool_liquidity.lp_tokens_owned as u64;
+    test_env
+        .transfer_lp(
+            &user,
+            destination.pubkey(),
+            pool_id,
+            lp_owned,
+            vec![reward_info_key],
+        )
+        .await;
+
+    let (user_reward_info_key, _) = Pubkey::find_program_address(
+        &[
+            USER_REWARD_INFO_SEED.as_bytes(),
+            reward_info_key.to_bytes().as_ref(),
+            user.pubkey().to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    );
+    let (destination_reward_info_key, _) = Pubkey::find_program_address(
+        &[
+            USER_REWARD_INFO_SEED.as_bytes(),
+            reward_info_key.to_bytes().as_ref(),
+            destination.pubkey().to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    );
+
+    // The owner keeps what was earned before the transfer
+    let user_reward_info: UserRewardInfo = test_env.fetch_account(user_reward_info_key).await;
+    assert!(user_reward_info.total_rewards > 0);
+    let rewards_before_transfer = user_reward_info.total_rewards;
+
+    // The destination starts earning from the transfer onwards
+    let destination_reward_info: UserRewardInfo =
+        test_env.fetch_account(destination_reward_info_key).await;
+    assert_eq!(destination_reward_info.total_rewards, 0);
+    assert_eq!(
+        destination_reward_info.rewards_last_calculated_at,
+        user_reward_info.rewards_last_calculated_at
+    );
+
+    test_env.jump_seconds(500000).await;
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    test_env
+        .calculate_rewards(&destination, pool_id, reward_info_key)
+        .await;
+
+    let user_reward_info: UserRewardInfo = test_env.fetch_account(user_reward_info_key).await;
+    let destination_reward_info: UserRewardInfo =
+        test_env.fetch_account(destination_reward_info_key).await;
+    assert_eq!(user_reward_info.total_rewards, rewards_before_transfer);
+    // Some rounding issues cause the total to be less than the reward amount
+    assert!(
+        user_reward_info.total_rewards + destination_reward_info.total_rewards <= reward_amount
+    );
+    assert!(
+        user_reward_info.total_rewards + destination_reward_info.total_rewards >= reward_amount - 2
+    );
+}
@@ -1,22 +1,26 @@
 #![allow(dead_code)]
-pub mod jupiter;
 
 use anchor_spl::associated_token::get_associated_token_address;
 use anchor_spl::token::TokenAccount;
 use anchor_spl::token_2022::spl_token_2022;
 use gamma::curve::TradeDirection;
 use gamma::states::{
-    ObservationState, AMM_CONFIG_SEED, OBSERVATION_NUM, OBSERVATION_SEED, POOL_LP_MINT_SEED,
-    POOL_SEED, POOL_VAULT_SEED, USER_POOL_LIQUIDITY_SEED,
+    LegacyRewardInfo, ObservationState, PoolState, RewardInfo, UserPoolLiquidity, UserRewardInfo,
+    AMM_CONFIG_SEED, LEGACY_PARTNER_ID, LIQUIDITY_LOCK_SEED, OBSERVATION_NUM, OBSERVATION_SEED,
+    PARTNER_INFO_SEED, PARTNER_SEED, POOL_LP_MINT_SEED, POOL_SEED, POOL_VAULT_SEED,
+    USER_POOL_LIQUIDITY_SEED, VESTING_LOCK_SEED, WRAPPED_LP_MINT_SEED,
 };
 use gamma::{AUTH_SEED, REWARD_INFO_SEED, REWARD_VAULT_SEED, USER_REWARD_INFO_SEED};
 use solana_program_runtime::invoke_context::BuiltinFunctionWithContext;
-use solana_sdk::instruction::Instruction;
+use solana_sdk::instruction::{AccountMeta, Instruction};
 use solana_sdk::program_option::COption;
 use solana_sdk::program_pack::Pack;
 
 use anchor_lang::prelude::{Clock, Pubkey, Rent};
-use anchor_lang::{system_program, AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_lang::{
+    system_program, AccountDeserialize, AnchorSerialize, Discriminator, InstructionData,
+    ToAccountMetas,
+};
 use solana_program_test::{
     BanksClientError, BanksTransactionResultWithMetadata, ProgramTest, ProgramTestBanksClientExt,
     ProgramTestContext,
@@ -210,6 +214,113 @@ pub fn get_current_price_token_0_price(observation: ObservationState) -> u128 {
         .unwrap()
 }
 
+pub fn partner_key(partner_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[PARTNER_SEED.as_bytes(), &partner_id.to_le_bytes()],
+        &gamma::id(),
+    )
+    .0
+}
+
+pub fn partner_info_key(pool_id: Pubkey, partner_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            PARTNER_INFO_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+            &partner_id.to_le_bytes(),
+        ],
+        &gamma::id(),
+    )
+    .0
+}
+
+pub fn user_pool_liquidity_key(pool_id: Pubkey, user: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+            user.to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    )
+    .0
+}
+
+pub fn liquidity_lock_key(pool_id: Pubkey, owner: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            LIQUIDITY_LOCK_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+            owner.to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    )
+    .0
+}
+
+pub fn vesting_lock_key(user_pool_liquidity: Pubkey, funder: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            VESTING_LOCK_SEED.as_bytes(),
+            user_pool_liquidity.to_bytes().as_ref(),
+            funder.to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    )
+    .0
+}
+
+pub fn reward_info_key(pool_id: Pubkey, start_time: u64, reward_mint: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            REWARD_INFO_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+            &start_time.to_le_bytes(),
+            reward_mint.to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    )
+    .0
+}
+
+pub fn user_reward_info_key(reward_info: Pubkey, user: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            USER_REWARD_INFO_SEED.as_bytes(),
+            reward_info.to_bytes().as_ref(),
+            user.to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    )
+    .0
+}
+
+/// Appends `[reward_info, user_reward_info for each user]` for every reward as remaining accounts
+pub fn add_user_reward_infos(
+    instruction: &mut Instruction,
+    reward_infos: Vec<Pubkey>,
+    users: &[Pubkey],
+) {
+    for reward_info in reward_infos {
+        instruction
+            .accounts
+            .push(AccountMeta::new(reward_info, false));
+        for user in users {
+            let (user_reward_info, _) = Pubkey::find_program_address(
+                &[
+                    USER_REWARD_INFO_SEED.as_bytes(),
+                    reward_info.to_bytes().as_ref(),
+                    user.to_bytes().as_ref(),
+                ],
+                &gamma::id(),
+            );
+            instruction
+                .accounts
+                .push(AccountMeta::new(user_reward_info, false));
+        }
+    }
+}
+
 pub struct ProgramInfo {
     pub program_name: String,
     pub program_id: Pubkey,
@@ -229,8 +340,17 @@ pub struct AccountConfigInfoBase64 {
 }
 
 impl TestEnv {
-    pub async fn new_with_config(mut accounts: Vec<Pubkey>, programs: Vec<ProgramInfo>) -> TestEnv {
-        let mut program_test = ProgramTest::new("gamma", gamma::id(), None);
+    pub async fn new_with_config(accounts: Vec<Pubkey>, programs: Vec<ProgramInfo>) -> TestEnv {
+        TestEnv::new_with_build("gamma", accounts, programs).await
+    }
+
+    /// Like `new_with_config`, with the program loaded from the `<program_name>.so` build
+    pub async fn new_with_build(
+        program_name: &str,
+        mut accounts: Vec<Pubkey>,
+        programs: Vec<ProgramInfo>,
+    ) -> TestEnv {
+        let mut program_test = ProgramTest::new(program_name, gamma::id(), None);
 
         for program in programs {
             program_test.add_program(
@@ -443,6 +563,15 @@ impl TestEnv {
         associated_token_account
     }
 
+    /// The balance of the associated token account of `owner`, created if needed
+    pub async fn token_balance(&mut self, owner: &Keypair, mint: Pubkey) -> u64 {
+        let token_account = self
+            .get_or_create_associated_token_account(owner.pubkey(), mint, owner)
+            .await;
+        let token_account: TokenAccount = self.fetch_account(token_account).await;
+        token_account.amount
+    }
+
     pub async fn get_or_create_associated_token_account(
         &mut self,
         account: Pubkey,
@@ -635,6 +764,118 @@ impl TestEnv {
         init_amount_1: u64,
         open_time: u64,
         create_pool_fee: Pubkey,
+    ) -> Pubkey {
+        self.initialize_pool_with_curve(
+            user,
+            amm_config_index,
+            init_amount_0,
+            init_amount_1,
+            open_time,
+            create_pool_fee,
+            0,
+            0,
+            0,
+        )
+        .await
+    }
+
+    /// Creates the config 0 and a pool of 1_000_000 token_0 and 2_000_000 token_1, token_1 is
+    /// priced at 0.5 token_0. The user keeps 9_000_000 token_0 and 8_000_000 token_1
+    pub async fn setup_pool(&mut self, user: &Keypair, admin: &Keypair) -> Pubkey {
+        self.setup_pool_with_curve(user, admin, 10_000_000, 1_000_000, 2_000_000, 0, 0, 0)
+            .await
+    }
+
+    /// Creates the config 0 and a pool of 1000 token_0 and 2000 token_1. The user keeps 99000
+    /// token_0 and 98000 token_1
+    pub async fn setup_small_pool(&mut self, user: &Keypair, admin: &Keypair) -> Pubkey {
+        self.setup_pool_with_curve(user, admin, 100000, 1000, 2000, 0, 0, 0)
+            .await
+    }
+
+    /// Creates the config 0 and a pool of the given curve, after minting `user_amount` of each
+    /// token to the user
+    pub async fn setup_pool_with_curve(
+        &mut self,
+        user: &Keypair,
+        admin: &Keypair,
+        user_amount: u64,
+        init_amount_0: u64,
+        init_amount_1: u64,
+        curve_type: u8,
+        amp: u64,
+        token_0_weight: u32,
+    ) -> Pubkey {
+        self.create_config(admin, 0, 100, 20, 5, 0).await;
+
+        let user_token_0_account = self
+            .get_or_create_associated_token_account(user.pubkey(), self.token_0_mint, user)
+            .await;
+        self.mint_base_tokens(user_token_0_account, user_amount, self.token_0_mint)
+            .await;
+        let user_token_1_account = self
+            .get_or_create_associated_token_account(user.pubkey(), self.token_1_mint, user)
+            .await;
+        self.mint_base_tokens(user_token_1_account, user_amount, self.token_1_mint)
+            .await;
+
+        self.initialize_pool_with_curve(
+            user,
+            0,
+            init_amount_0,
+            init_amount_1,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+            curve_type,
+            amp,
+            token_0_weight,
+        )
+        .await
+    }
+
+    pub async fn initialize_pool_with_curve(
+        &mut self,
+        user: &Keypair,
+        amm_config_index: u16,
+        init_amount_0: u64,
+        init_amount_1: u64,
+        open_time: u64,
+        create_pool_fee: Pubkey,
+        curve_type: u8,
+        amp: u64,
+        token_0_weight: u32,
+    ) -> Pubkey {
+        self.initialize_pool_with_launch_fee(
+            user,
+            amm_config_index,
+            init_amount_0,
+            init_amount_1,
+            open_time,
+            create_pool_fee,
+            curve_type,
+            amp,
+            token_0_weight,
+            0,
+            0,
+            0,
+        )
+        .await
+    }
+
+    pub async fn initialize_pool_with_launch_fee(
+        &mut self,
+        user: &Keypair,
+        amm_config_index: u16,
+        init_amount_0: u64,
+        init_amount_1: u64,
+        open_time: u64,
+        create_pool_fee: Pubkey,
+        curve_type: u8,
+        amp: u64,
+        token_0_weight: u32,
+        launch_fee_rate: u64,
+        launch_fee_duration: u64,
+        launch_fee_decay: u8,
     ) -> Pubkey {
         let (amm_config_key, __bump) = Pubkey::find_program_address(
             &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
@@ -721,18 +962,36 @@ impl TestEnv {
             rent: sysvar::rent::id(),
         };
 
-        let data = gamma::instruction::Initialize {
-            init_amount_0,
-            init_amount_1,
-            open_time,
-            max_trade_fee_rate: 0,
-            volatility_factor: 0,
+        // Plain constant product pools go through the original `initialize`, so both entry
+        // points stay covered.
+        let transaction = if (curve_type, amp, token_0_weight, launch_fee_rate) == (0, 0, 0, 0) {
+            let data = gamma::instruction::Initialize {
+                init_amount_0,
+                init_amount_1,
+                open_time,
+                max_trade_fee_rate: 0,
+                volatility_factor: 0,
+            };
+            self.encode_instruction_and_sign_transaction(data, accounts, user)
+                .await
+        } else {
+            let data = gamma::instruction::InitializeV2 {
+                init_amount_0,
+                init_amount_1,
+                open_time,
+                max_trade_fee_rate: 0,
+                volatility_factor: 0,
+                curve_type,
+                amp,
+                token_0_weight,
+                launch_fee_rate,
+                launch_fee_duration,
+                launch_fee_decay,
+            };
+            self.encode_instruction_and_sign_transaction(data, accounts, user)
+                .await
         };
 
-        let transaction = self
-            .encode_instruction_and_sign_transaction(data, accounts, user)
-            .await;
-
         self.program_test_context
             .banks_client
             .process_transaction(transaction)
@@ -742,6 +1001,58 @@ impl TestEnv {
         pool_account_key
     }
 
+    pub async fn update_pool(
+        &mut self,
+        admin: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        param: u32,
+        value: u64,
+    ) {
+        self.update_pool_with_remaining_accounts(
+            admin,
+            pool_id,
+            amm_config_index,
+            param,
+            value,
+            vec![],
+        )
+        .await;
+    }
+
+    pub async fn update_pool_with_remaining_accounts(
+        &mut self,
+        admin: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        param: u32,
+        value: u64,
+        remaining_accounts: Vec<AccountMeta>,
+    ) {
+        let (amm_config_key, __bump) = Pubkey::find_program_address(
+            &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
+            &gamma::ID,
+        );
+
+        let accounts = gamma::accounts::UpdatePool {
+            authority: admin.pubkey(),
+            pool_state: pool_id,
+            amm_config: amm_config_key,
+        };
+        let data = gamma::instruction::UpdatePool { param, value };
+
+        let mut instruction = get_instruction(data, accounts);
+        instruction.accounts.extend(remaining_accounts);
+        let transaction =
+            get_signed_transaction(&mut self.program_test_context, &[instruction], admin).await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
     pub async fn deposit(
         &mut self,
         user: &Keypair,
@@ -751,6 +1062,30 @@ impl TestEnv {
         maximum_token_0_amount: u64,
         maximum_token_1_amount: u64,
     ) {
+        self.deposit_with_rewards(
+            user,
+            pool_id,
+            amm_config_index,
+            lp_token_amount,
+            maximum_token_0_amount,
+            maximum_token_1_amount,
+            vec![],
+        )
+        .await
+        .unwrap_transaction();
+    }
+
+    /// Remaining accounts are added for `reward_infos`, which must be all the rewards of the pool
+    pub async fn deposit_with_rewards(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        lp_token_amount: u64,
+        maximum_token_0_amount: u64,
+        maximum_token_1_amount: u64,
+        reward_infos: Vec<Pubkey>,
+    ) -> ProcessTransactionResult {
         let (amm_config_key, __bump) = Pubkey::find_program_address(
             &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
             &gamma::ID,
@@ -804,6 +1139,7 @@ impl TestEnv {
         )
         .0;
 
+        let partner_info = self.partner_info_of(pool_id, user.pubkey()).await;
         let accounts = gamma::accounts::Deposit {
             owner: user.pubkey(),
             authority,
@@ -818,6 +1154,8 @@ impl TestEnv {
             token_program_2022: spl_token_2022::id(),
             vault_0_mint: self.token_0_mint,
             vault_1_mint: self.token_1_mint,
+            system_program: Some(system_program::ID),
+            partner_info,
         };
 
         let data = gamma::instruction::Deposit {
@@ -826,6 +1164,100 @@ impl TestEnv {
             maximum_token_1_amount,
         };
 
+        let mut instruction = get_instruction(data, accounts);
+        add_user_reward_infos(&mut instruction, reward_infos, &[user.pubkey()]);
+
+        let transaction =
+            get_signed_transaction(&mut self.program_test_context, &[instruction], user).await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn deposit_single_sided(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        input_mint: Pubkey,
+        amount_in: u64,
+        min_lp_out: u64,
+    ) {
+        let (amm_config_key, __bump) = Pubkey::find_program_address(
+            &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
+            &gamma::ID,
+        );
+
+        let (token_0_vault, __bump) = Pubkey::find_program_address(
+            &[
+                POOL_VAULT_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                self.token_0_mint.to_bytes().as_ref(),
+            ],
+            &gamma::ID,
+        );
+        let (token_1_vault, __bump) = Pubkey::find_program_address(
+            &[
+                POOL_VAULT_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                self.token_1_mint.to_bytes().as_ref(),
+            ],
+            &gamma::ID,
+        );
+        let (observation_key, __bump) = Pubkey::find_program_address(
+            &[OBSERVATION_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+            &gamma::ID,
+        );
+
+        let user_token_0_account = self
+            .get_or_create_associated_token_account(user.pubkey(), self.token_0_mint.clone(), &user)
+            .await;
+
+        let user_token_1_account = self
+            .get_or_create_associated_token_account(user.pubkey(), self.token_1_mint.clone(), &user)
+            .await;
+
+        let (authority, __bump) =
+            Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
+
+        let user_pool_liquidity = Pubkey::find_program_address(
+            &[
+                USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                user.pubkey().to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        )
+        .0;
+
+        let partner_info = self.partner_info_of(pool_id, user.pubkey()).await;
+        let accounts = gamma::accounts::DepositSingleSided {
+            owner: user.pubkey(),
+            authority,
+            amm_config: amm_config_key,
+            pool_state: pool_id,
+            user_pool_liquidity,
+            partner_info,
+            token_0_account: user_token_0_account,
+            token_1_account: user_token_1_account,
+            token_0_vault,
+            token_1_vault,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+            vault_0_mint: self.token_0_mint,
+            vault_1_mint: self.token_1_mint,
+            observation_state: observation_key,
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::DepositSingleSided {
+            input_mint,
+            amount_in,
+            min_lp_out,
+        };
+
         let transaction = self
             .encode_instruction_and_sign_transaction(data, accounts, user)
             .await;
@@ -846,6 +1278,30 @@ impl TestEnv {
         minimum_token_0_amount: u64,
         minimum_token_1_amount: u64,
     ) {
+        self.withdraw_with_rewards(
+            user,
+            pool_id,
+            amm_config_index,
+            lp_token_amount,
+            minimum_token_0_amount,
+            minimum_token_1_amount,
+            vec![],
+        )
+        .await
+        .unwrap_transaction();
+    }
+
+    /// Remaining accounts are added for `reward_infos`, which must be all the rewards of the pool
+    pub async fn withdraw_with_rewards(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        lp_token_amount: u64,
+        minimum_token_0_amount: u64,
+        minimum_token_1_amount: u64,
+        reward_infos: Vec<Pubkey>,
+    ) -> ProcessTransactionResult {
         let (amm_config_key, __bump) = Pubkey::find_program_address(
             &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
             &gamma::ID,
@@ -897,6 +1353,7 @@ impl TestEnv {
             .get_or_create_associated_token_account(user.pubkey(), self.token_1_mint.clone(), &user)
             .await;
 
+        let partner_info = self.partner_info_of(pool_id, user.pubkey()).await;
         let accounts = gamma::accounts::Withdraw {
             owner: user.pubkey(),
             authority,
@@ -911,6 +1368,8 @@ impl TestEnv {
             vault_0_mint: self.token_0_mint,
             vault_1_mint: self.token_1_mint,
             memo_program: spl_memo::id(),
+            system_program: Some(system_program::ID),
+            partner_info,
         };
 
         let data = gamma::instruction::Withdraw {
@@ -919,47 +1378,1252 @@ impl TestEnv {
             minimum_token_1_amount,
         };
 
-        let transaction = self
-            .encode_instruction_and_sign_transaction(data, accounts, user)
-            .await;
+        let mut instruction = get_instruction(data, accounts);
+        add_user_reward_infos(&mut instruction, reward_infos, &[user.pubkey()]);
+
+        let transaction =
+            get_signed_transaction(&mut self.program_test_context, &[instruction], user).await;
 
         self.program_test_context
             .banks_client
-            .process_transaction(transaction)
+            .process_transaction_with_metadata(transaction)
             .await
-            .unwrap();
-    }
-
-    pub async fn init_user_pool_liquidity(&mut self, user: &Keypair, pool_id: Pubkey) {
-        self.init_user_pool_liquidity_with_partner(user, pool_id, None)
-            .await;
     }
 
-    pub async fn init_user_pool_liquidity_with_partner(
+    pub async fn withdraw_single_sided(
         &mut self,
         user: &Keypair,
         pool_id: Pubkey,
-        partner: Option<String>,
+        amm_config_index: u16,
+        lp_token_amount: u64,
+        output_mint: Pubkey,
+        minimum_amount_out: u64,
     ) {
-        let user_pool_liquidity = Pubkey::find_program_address(
+        let (amm_config_key, __bump) = Pubkey::find_program_address(
+            &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
+            &gamma::ID,
+        );
+
+        let (token_0_vault, __bump) = Pubkey::find_program_address(
             &[
-                USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                POOL_VAULT_SEED.as_bytes(),
                 pool_id.to_bytes().as_ref(),
-                user.pubkey().to_bytes().as_ref(),
+                self.token_0_mint.to_bytes().as_ref(),
+            ],
+            &gamma::ID,
+        );
+        let (token_1_vault, __bump) = Pubkey::find_program_address(
+            &[
+                POOL_VAULT_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                self.token_1_mint.to_bytes().as_ref(),
+            ],
+            &gamma::ID,
+        );
+        let (observation_key, __bump) = Pubkey::find_program_address(
+            &[OBSERVATION_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+            &gamma::ID,
+        );
+
+        let user_token_0_account = self
+            .get_or_create_associated_token_account(user.pubkey(), self.token_0_mint.clone(), &user)
+            .await;
+
+        let user_token_1_account = self
+            .get_or_create_associated_token_account(user.pubkey(), self.token_1_mint.clone(), &user)
+            .await;
+
+        let (authority, __bump) =
+            Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
+
+        let user_pool_liquidity = Pubkey::find_program_address(
+            &[
+                USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                user.pubkey().to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        )
+        .0;
+
+        let partner_info = self.partner_info_of(pool_id, user.pubkey()).await;
+        let accounts = gamma::accounts::WithdrawSingleSided {
+            owner: user.pubkey(),
+            authority,
+            amm_config: amm_config_key,
+            pool_state: pool_id,
+            user_pool_liquidity,
+            partner_info,
+            token_0_account: user_token_0_account,
+            token_1_account: user_token_1_account,
+            token_0_vault,
+            token_1_vault,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+            vault_0_mint: self.token_0_mint,
+            vault_1_mint: self.token_1_mint,
+            observation_state: observation_key,
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::WithdrawSingleSided {
+            lp_token_amount,
+            output_mint,
+            minimum_amount_out,
+        };
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+    pub async fn init_user_pool_liquidity(&mut self, user: &Keypair, pool_id: Pubkey) {
+        self.init_user_pool_liquidity_with_partner(user, pool_id, None)
+            .await;
+    }
+
+    pub async fn init_user_pool_liquidity_with_partner(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        partner: Option<u64>,
+    ) {
+        self.try_init_user_pool_liquidity_with_partner(user, pool_id, partner)
+            .await
+            .unwrap_transaction();
+    }
+
+    pub async fn try_init_user_pool_liquidity_with_partner(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        partner: Option<u64>,
+    ) -> ProcessTransactionResult {
+        let user_pool_liquidity = Pubkey::find_program_address(
+            &[
+                USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                user.pubkey().to_bytes().as_ref(),
             ],
             &gamma::id(),
         )
         .0;
 
-        let accounts: gamma::accounts::InitUserPoolLiquidity =
-            gamma::accounts::InitUserPoolLiquidity {
+        let accounts: gamma::accounts::InitUserPoolLiquidityV2 =
+            gamma::accounts::InitUserPoolLiquidityV2 {
                 user: user.pubkey(),
                 pool_state: pool_id,
                 user_pool_liquidity,
+                partner_info: partner.map(|partner_id| partner_info_key(pool_id, partner_id)),
                 system_program: system_program::ID,
             };
 
-        let data = gamma::instruction::InitUserPoolLiquidity { partner };
+        let data = gamma::instruction::InitUserPoolLiquidityV2 { partner };
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    /// Creates a position with the instruction of the clients predating the partner registry
+    pub async fn init_user_pool_liquidity_legacy(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        partner: Option<String>,
+    ) {
+        let accounts = gamma::accounts::InitUserPoolLiquidity {
+            user: user.pubkey(),
+            pool_state: pool_id,
+            user_pool_liquidity: user_pool_liquidity_key(pool_id, user.pubkey()),
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::InitUserPoolLiquidity { partner };
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    /// Turns a pool into one created before the partner ledgers, with `partner_slot` as the
+    /// lp tokens linked with the legacy partner and its cumulative fees of token_0 and token_1
+    pub async fn make_legacy_pool(&mut self, pool_id: Pubkey, partner_slot: (u64, u64, u64)) {
+        let mut account = self
+            .program_test_context
+            .banks_client
+            .get_account(pool_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut pool_state = PoolState::try_deserialize(&mut account.data.as_ref()).unwrap();
+        let (lp_token_linked_with_partner, fee_token_0, fee_token_1) = partner_slot;
+        pool_state.layout_version = 0;
+        pool_state.protocol_fees_per_lp_x64_token_0 =
+            u128::from(LEGACY_PARTNER_ID) | (u128::from(lp_token_linked_with_partner) << 64);
+        pool_state.protocol_fees_per_lp_x64_token_1 =
+            u128::from(fee_token_0) | (u128::from(fee_token_1) << 64);
+        // The former lp mint is still in place of the partner fees
+        pool_state.partner_fee_share_lp = u64::MAX;
+        pool_state.partner_fees_token_0 = u64::MAX;
+        pool_state.partner_fees_token_1 = u64::MAX;
+        let data = bytemuck::bytes_of(&pool_state);
+        account.data[8..8 + data.len()].copy_from_slice(data);
+        self.program_test_context
+            .set_account(&pool_id, &account.into());
+    }
+
+    /// Turns a reward into one created before the rewards were accrued per lp token, which the
+    /// pool does not count
+    pub async fn make_legacy_reward_info(&mut self, reward_info_key: Pubkey) {
+        let reward_info: RewardInfo = self.fetch_account(reward_info_key).await;
+        let mut data = RewardInfo::DISCRIMINATOR.to_vec();
+        LegacyRewardInfo {
+            pool: reward_info.pool,
+            start_at: reward_info.start_at,
+            end_rewards_at: reward_info.end_rewards_at,
+            mint: reward_info.mint,
+            total_to_disburse: reward_info.total_to_disburse,
+            rewarded_by: reward_info.rewarded_by,
+        }
+        .serialize(&mut data)
+        .unwrap();
+        let mut account = self
+            .get_account_info(reward_info_key)
+            .await
+            .unwrap()
+            .unwrap();
+        account.data = data;
+        self.program_test_context
+            .set_account(&reward_info_key, &account.into());
+
+        let mut account = self
+            .get_account_info(reward_info.pool)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut pool_state = PoolState::try_deserialize(&mut account.data.as_ref()).unwrap();
+        pool_state.reward_count -= 1;
+        let data = bytemuck::bytes_of(&pool_state);
+        account.data[8..8 + data.len()].copy_from_slice(data);
+        self.program_test_context
+            .set_account(&reward_info.pool, &account.into());
+    }
+
+    /// Turns a user reward into one created before the rewards were accrued per lp token
+    pub async fn make_legacy_user_reward_info(&mut self, reward_info_key: Pubkey, user: Pubkey) {
+        let user_reward_info_key = user_reward_info_key(reward_info_key, user);
+        let mut account = self
+            .get_account_info(user_reward_info_key)
+            .await
+            .unwrap()
+            .unwrap();
+        account.data.truncate(UserRewardInfo::LEGACY_LEN);
+        self.program_test_context
+            .set_account(&user_reward_info_key, &account.into());
+    }
+
+    pub async fn migrate_reward_info(
+        &mut self,
+        payer: &Keypair,
+        pool_id: Pubkey,
+        reward_info_key: Pubkey,
+    ) -> ProcessTransactionResult {
+        let accounts = gamma::accounts::MigrateRewardInfo {
+            payer: payer.pubkey(),
+            pool_state: pool_id,
+            reward_info: reward_info_key,
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::MigrateRewardInfo {};
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, payer)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn migrate_user_reward_info(
+        &mut self,
+        payer: &Keypair,
+        user: Pubkey,
+        reward_info_key: Pubkey,
+    ) -> ProcessTransactionResult {
+        let accounts = gamma::accounts::MigrateUserRewardInfo {
+            payer: payer.pubkey(),
+            user,
+            reward_info: reward_info_key,
+            user_reward_info: user_reward_info_key(reward_info_key, user),
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::MigrateUserRewardInfo {};
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, payer)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn migrate_partner_ledger(
+        &mut self,
+        payer: &Keypair,
+        pool_id: Pubkey,
+    ) -> ProcessTransactionResult {
+        let accounts = gamma::accounts::MigratePartnerLedger {
+            payer: payer.pubkey(),
+            pool_state: pool_id,
+            partner: partner_key(LEGACY_PARTNER_ID),
+            partner_info: partner_info_key(pool_id, LEGACY_PARTNER_ID),
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::MigratePartnerLedger {};
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, payer)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn transfer_lp(
+        &mut self,
+        owner: &Keypair,
+        destination: Pubkey,
+        pool_id: Pubkey,
+        lp_token_amount: u64,
+        reward_infos: Vec<Pubkey>,
+    ) {
+        let user_pool_liquidity = Pubkey::find_program_address(
+            &[
+                USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                owner.pubkey().to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        )
+        .0;
+        let destination_pool_liquidity = Pubkey::find_program_address(
+            &[
+                USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                destination.to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        )
+        .0;
+
+        let partner_info = self.partner_info_of(pool_id, owner.pubkey()).await;
+        let destination_partner_info = self.partner_info_of(pool_id, destination).await;
+        let accounts = gamma::accounts::TransferLp {
+            owner: owner.pubkey(),
+            pool_state: pool_id,
+            user_pool_liquidity,
+            partner_info,
+            destination,
+            destination_pool_liquidity,
+            destination_partner_info,
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::TransferLp { lp_token_amount };
+
+        let mut instruction = get_instruction(data, accounts);
+        add_user_reward_infos(
+            &mut instruction,
+            reward_infos,
+            &[owner.pubkey(), destination],
+        );
+
+        let transaction =
+            get_signed_transaction(&mut self.program_test_context, &[instruction], owner).await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    pub async fn wrap_lp(
+        &mut self,
+        owner: &Keypair,
+        pool_id: Pubkey,
+        lp_token_amount: u64,
+        reward_infos: Vec<Pubkey>,
+    ) -> Pubkey {
+        let (authority, __bump) =
+            Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
+        let user_pool_liquidity = Pubkey::find_program_address(
+            &[
+                USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                owner.pubkey().to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        )
+        .0;
+        let (lp_mint, __bump) = Pubkey::find_program_address(
+            &[WRAPPED_LP_MINT_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+            &gamma::id(),
+        );
+        let owner_lp_token = get_associated_token_address(&owner.pubkey(), &lp_mint);
+
+        let partner_info = self.partner_info_of(pool_id, owner.pubkey()).await;
+        let accounts = gamma::accounts::WrapLp {
+            owner: owner.pubkey(),
+            authority,
+            pool_state: pool_id,
+            user_pool_liquidity,
+            partner_info,
+            lp_mint,
+            owner_lp_token,
+            token_program: spl_token::id(),
+            associated_token_program: spl_associated_token_account::id(),
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::WrapLp { lp_token_amount };
+
+        let mut instruction = get_instruction(data, accounts);
+        add_user_reward_infos(&mut instruction, reward_infos, &[owner.pubkey()]);
+
+        let transaction =
+            get_signed_transaction(&mut self.program_test_context, &[instruction], owner).await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+
+        owner_lp_token
+    }
+
+    pub async fn unwrap_lp(
+        &mut self,
+        owner: &Keypair,
+        pool_id: Pubkey,
+        owner_lp_token: Pubkey,
+        lp_token_amount: u64,
+        reward_infos: Vec<Pubkey>,
+    ) {
+        let user_pool_liquidity = Pubkey::find_program_address(
+            &[
+                USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                owner.pubkey().to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        )
+        .0;
+        let (lp_mint, __bump) = Pubkey::find_program_address(
+            &[WRAPPED_LP_MINT_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+            &gamma::id(),
+        );
+
+        let partner_info = self.partner_info_of(pool_id, owner.pubkey()).await;
+        let accounts = gamma::accounts::UnwrapLp {
+            owner: owner.pubkey(),
+            pool_state: pool_id,
+            user_pool_liquidity,
+            partner_info,
+            lp_mint,
+            owner_lp_token,
+            token_program: spl_token::id(),
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::UnwrapLp { lp_token_amount };
+
+        let mut instruction = get_instruction(data, accounts);
+        add_user_reward_infos(&mut instruction, reward_infos, &[owner.pubkey()]);
+
+        let transaction =
+            get_signed_transaction(&mut self.program_test_context, &[instruction], owner).await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    pub async fn lock_liquidity(
+        &mut self,
+        owner: &Keypair,
+        pool_id: Pubkey,
+        lp_token_amount: u64,
+        unlock_timestamp: u64,
+    ) -> ProcessTransactionResult {
+        let accounts = gamma::accounts::LockLiquidity {
+            owner: owner.pubkey(),
+            pool_state: pool_id,
+            user_pool_liquidity: user_pool_liquidity_key(pool_id, owner.pubkey()),
+            liquidity_lock: liquidity_lock_key(pool_id, owner.pubkey()),
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::LockLiquidity {
+            lp_token_amount,
+            unlock_timestamp,
+        };
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, owner)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn unlock_liquidity(
+        &mut self,
+        owner: &Keypair,
+        pool_id: Pubkey,
+    ) -> ProcessTransactionResult {
+        let accounts = gamma::accounts::UnlockLiquidity {
+            owner: owner.pubkey(),
+            pool_state: pool_id,
+            user_pool_liquidity: user_pool_liquidity_key(pool_id, owner.pubkey()),
+            liquidity_lock: liquidity_lock_key(pool_id, owner.pubkey()),
+        };
+
+        let data = gamma::instruction::UnlockLiquidity {};
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, owner)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    /// Remaining accounts are added for `reward_infos`, which must be all the rewards of the pool
+    pub async fn create_vesting_lock(
+        &mut self,
+        funder: &Keypair,
+        beneficiary: Pubkey,
+        pool_id: Pubkey,
+        lp_token_amount: u64,
+        cliff_seconds: u64,
+        duration: u64,
+        reward_infos: Vec<Pubkey>,
+    ) -> ProcessTransactionResult {
+        let beneficiary_pool_liquidity = user_pool_liquidity_key(pool_id, beneficiary);
+        let funder_partner_info = self.partner_info_of(pool_id, funder.pubkey()).await;
+        let beneficiary_partner_info = self.partner_info_of(pool_id, beneficiary).await;
+        let accounts = gamma::accounts::CreateVestingLock {
+            funder: funder.pubkey(),
+            pool_state: pool_id,
+            funder_pool_liquidity: user_pool_liquidity_key(pool_id, funder.pubkey()),
+            funder_partner_info,
+            beneficiary,
+            beneficiary_pool_liquidity,
+            beneficiary_partner_info,
+            vesting_lock: vesting_lock_key(beneficiary_pool_liquidity, funder.pubkey()),
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::CreateVestingLock {
+            lp_token_amount,
+            cliff_seconds,
+            duration,
+        };
+
+        let mut instruction = get_instruction(data, accounts);
+        add_user_reward_infos(
+            &mut instruction,
+            reward_infos,
+            &[funder.pubkey(), beneficiary],
+        );
+
+        let transaction =
+            get_signed_transaction(&mut self.program_test_context, &[instruction], funder).await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn release_vested_lp(
+        &mut self,
+        beneficiary: &Keypair,
+        pool_id: Pubkey,
+        funder: Pubkey,
+    ) -> ProcessTransactionResult {
+        let user_pool_liquidity = user_pool_liquidity_key(pool_id, beneficiary.pubkey());
+        let accounts = gamma::accounts::ReleaseVestedLp {
+            beneficiary: beneficiary.pubkey(),
+            pool_state: pool_id,
+            user_pool_liquidity,
+            vesting_lock: vesting_lock_key(user_pool_liquidity, funder),
+        };
+
+        let data = gamma::instruction::ReleaseVestedLp {};
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, beneficiary)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    /// Builds a `swap_base_input` instruction, creating the token accounts of the user if needed
+    pub async fn swap_base_input_instruction(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        trade_direction: TradeDirection,
+    ) -> Instruction {
+        let (authority, __bump) =
+            Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
+        let (amm_config_key, __bump) = Pubkey::find_program_address(
+            &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
+            &gamma::ID,
+        );
+
+        let user_token_0_account: Pubkey = self
+            .get_or_create_associated_token_account(user.pubkey(), self.token_0_mint.clone(), &user)
+            .await;
+
+        let user_token_1_account = self
+            .get_or_create_associated_token_account(user.pubkey(), self.token_1_mint.clone(), &user)
+            .await;
+
+        let (token_0_vault, __bump) = Pubkey::find_program_address(
+            &[
+                POOL_VAULT_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                self.token_0_mint.to_bytes().as_ref(),
+            ],
+            &gamma::ID,
+        );
+        let (token_1_vault, __bump) = Pubkey::find_program_address(
+            &[
+                POOL_VAULT_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                self.token_1_mint.to_bytes().as_ref(),
+            ],
+            &gamma::ID,
+        );
+        let (observation_key, __bump) = Pubkey::find_program_address(
+            &[OBSERVATION_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+            &gamma::ID,
+        );
+
+        let (
+            input_token_account,
+            output_token_account,
+            input_token_mint,
+            output_token_mint,
+            input_vault,
+            output_vault,
+            input_token_program,
+            output_token_program,
+        ) = match trade_direction {
+            TradeDirection::ZeroForOne => (
+                user_token_0_account,
+                user_token_1_account,
+                self.token_0_mint,
+                self.token_1_mint,
+                token_0_vault,
+                token_1_vault,
+                spl_token::id(),
+                spl_token::id(),
+            ),
+            TradeDirection::OneForZero => (
+                user_token_1_account,
+                user_token_0_account,
+                self.token_1_mint,
+                self.token_0_mint,
+                token_1_vault,
+                token_0_vault,
+                spl_token::id(),
+                spl_token::id(),
+            ),
+        };
+
+        let accounts = gamma::accounts::Swap {
+            payer: user.pubkey(),
+            authority,
+            amm_config: amm_config_key,
+            pool_state: pool_id,
+            observation_state: observation_key,
+            input_token_account,
+            output_token_account,
+            input_vault,
+            output_vault,
+            input_token_program,
+            output_token_program,
+            input_token_mint,
+            output_token_mint,
+        };
+
+        let data = gamma::instruction::SwapBaseInput {
+            amount_in,
+            minimum_amount_out,
+        };
+
+        get_instruction(data, accounts)
+    }
+
+    pub async fn swap_base_input(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        trade_direction: TradeDirection,
+    ) -> u64 {
+        let instruction = self
+            .swap_base_input_instruction(
+                user,
+                pool_id,
+                amm_config_index,
+                amount_in,
+                minimum_amount_out,
+                trade_direction,
+            )
+            .await;
+        let transaction =
+            get_signed_transaction(&mut self.program_test_context, &[instruction], user).await;
+
+        let result = self
+            .program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await;
+        result.unwrap_transaction();
+        // The compute units consumed by the swap
+        result.unwrap().metadata.unwrap().compute_units_consumed
+    }
+
+    pub async fn swap_base_output(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        amount_out: u64,
+        max_amount_in: u64,
+        trade_direction: TradeDirection,
+    ) {
+        let (authority, __bump) =
+            Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
+        let (amm_config_key, __bump) = Pubkey::find_program_address(
+            &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
+            &gamma::ID,
+        );
+
+        let user_token_0_account: Pubkey = self
+            .get_or_create_associated_token_account(user.pubkey(), self.token_0_mint.clone(), &user)
+            .await;
+
+        let user_token_1_account = self
+            .get_or_create_associated_token_account(user.pubkey(), self.token_1_mint.clone(), &user)
+            .await;
+
+        let (token_0_vault, __bump) = Pubkey::find_program_address(
+            &[
+                POOL_VAULT_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                self.token_0_mint.to_bytes().as_ref(),
+            ],
+            &gamma::ID,
+        );
+        let (token_1_vault, __bump) = Pubkey::find_program_address(
+            &[
+                POOL_VAULT_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                self.token_1_mint.to_bytes().as_ref(),
+            ],
+            &gamma::ID,
+        );
+        let (observation_key, __bump) = Pubkey::find_program_address(
+            &[OBSERVATION_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+            &gamma::ID,
+        );
+
+        let (
+            input_token_account,
+            output_token_account,
+            input_token_mint,
+            output_token_mint,
+            input_vault,
+            output_vault,
+            input_token_program,
+            output_token_program,
+        ) = match trade_direction {
+            TradeDirection::ZeroForOne => (
+                user_token_0_account,
+                user_token_1_account,
+                self.token_0_mint,
+                self.token_1_mint,
+                token_0_vault,
+                token_1_vault,
+                spl_token::id(),
+                spl_token::id(),
+            ),
+            TradeDirection::OneForZero => (
+                user_token_1_account,
+                user_token_0_account,
+                self.token_1_mint,
+                self.token_0_mint,
+                token_1_vault,
+                token_0_vault,
+                spl_token::id(),
+                spl_token::id(),
+            ),
+        };
+
+        let accounts = gamma::accounts::Swap {
+            payer: user.pubkey(),
+            authority,
+            amm_config: amm_config_key,
+            pool_state: pool_id,
+            observation_state: observation_key,
+            input_token_account,
+            output_token_account,
+            input_vault,
+            output_vault,
+            input_token_program,
+            output_token_program,
+            input_token_mint,
+            output_token_mint,
+        };
+
+        let data = gamma::instruction::SwapBaseOutput {
+            amount_out,
+            max_amount_in,
+        };
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    pub async fn increase_observation_cardinality(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        observation_cardinality_next: u16,
+    ) -> ProcessTransactionResult {
+        let (observation_key, __bump) = Pubkey::find_program_address(
+            &[OBSERVATION_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+            &gamma::ID,
+        );
+
+        let accounts = gamma::accounts::IncreaseObservationCardinality {
+            payer: user.pubkey(),
+            pool_state: pool_id,
+            observation_state: observation_key,
+            system_program: system_program::ID,
+        };
+        let data = gamma::instruction::IncreaseObservationCardinality {
+            observation_cardinality_next,
+        };
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn get_twap(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        window_seconds: u64,
+    ) -> ProcessTransactionResult {
+        let (observation_key, __bump) = Pubkey::find_program_address(
+            &[OBSERVATION_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+            &gamma::ID,
+        );
+
+        let accounts = gamma::accounts::GetTwap {
+            pool_state: pool_id,
+            observation_state: observation_key,
+        };
+        let data = gamma::instruction::GetTwap { window_seconds };
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    /// Swaps through the pools of the token pair created with each of `amm_config_indexes`,
+    /// every hop trading in the given direction
+    pub async fn swap_route(
+        &mut self,
+        user: &Keypair,
+        hops: Vec<(u16, TradeDirection)>,
+        amount_in: u64,
+        minimum_amount_out: u64,
+    ) {
+        let (authority, __bump) =
+            Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
+
+        let mut remaining_accounts = vec![];
+        for (amm_config_index, trade_direction) in hops.iter() {
+            let (amm_config_key, __bump) = Pubkey::find_program_address(
+                &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
+                &gamma::ID,
+            );
+            let (pool_id, __bump) = Pubkey::find_program_address(
+                &[
+                    POOL_SEED.as_bytes(),
+                    amm_config_key.to_bytes().as_ref(),
+                    self.token_0_mint.to_bytes().as_ref(),
+                    self.token_1_mint.to_bytes().as_ref(),
+                ],
+                &gamma::ID,
+            );
+            let (token_0_vault, __bump) = Pubkey::find_program_address(
+                &[
+                    POOL_VAULT_SEED.as_bytes(),
+                    pool_id.to_bytes().as_ref(),
+                    self.token_0_mint.to_bytes().as_ref(),
+                ],
+                &gamma::ID,
+            );
+            let (token_1_vault, __bump) = Pubkey::find_program_address(
+                &[
+                    POOL_VAULT_SEED.as_bytes(),
+                    pool_id.to_bytes().as_ref(),
+                    self.token_1_mint.to_bytes().as_ref(),
+                ],
+                &gamma::ID,
+            );
+            let (observation_key, __bump) = Pubkey::find_program_address(
+                &[OBSERVATION_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+                &gamma::ID,
+            );
+            let (input_vault, output_vault, output_token_mint) = match trade_direction {
+                TradeDirection::ZeroForOne => (token_0_vault, token_1_vault, self.token_1_mint),
+                TradeDirection::OneForZero => (token_1_vault, token_0_vault, self.token_0_mint),
+            };
+            remaining_accounts.extend([
+                AccountMeta::new_readonly(amm_config_key, false),
+                AccountMeta::new(pool_id, false),
+                AccountMeta::new(observation_key, false),
+                AccountMeta::new(input_vault, false),
+                AccountMeta::new(output_vault, false),
+                AccountMeta::new_readonly(output_token_mint, false),
+            ]);
+        }
+
+        let input_token_mint = match hops[0].1 {
+            TradeDirection::ZeroForOne => self.token_0_mint,
+            TradeDirection::OneForZero => self.token_1_mint,
+        };
+        let output_token_mint = match hops[hops.len() - 1].1 {
+            TradeDirection::ZeroForOne => self.token_1_mint,
+            TradeDirection::OneForZero => self.token_0_mint,
+        };
+        let input_token_account = self
+            .get_or_create_associated_token_account(user.pubkey(), input_token_mint, &user)
+            .await;
+        let output_token_account = self
+            .get_or_create_associated_token_account(user.pubkey(), output_token_mint, &user)
+            .await;
+
+        let accounts = gamma::accounts::SwapRoute {
+            payer: user.pubkey(),
+            authority,
+            input_token_account,
+            output_token_account,
+            input_token_mint,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+        };
+
+        let data = gamma::instruction::SwapRoute {
+            amount_in,
+            minimum_amount_out,
+        };
+
+        let mut instruction = get_instruction(data, accounts);
+        instruction.accounts.extend(remaining_accounts);
+
+        let transaction =
+            get_signed_transaction(&mut self.program_test_context, &[instruction], user).await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+    pub async fn create_rewards(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        start_time: u64,
+        end_time: u64,
+        reward_mint: Pubkey,
+        reward_amount: u64,
+    ) {
+        let (authority, __bump) =
+            Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
+
+        let (reward_info_key, _) = Pubkey::find_program_address(
+            &[
+                REWARD_INFO_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                &start_time.to_le_bytes(),
+                reward_mint.to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        );
+
+        let (reward_vault_key, _) = Pubkey::find_program_address(
+            &[
+                REWARD_VAULT_SEED.as_bytes(),
+                reward_info_key.to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        );
+
+        let reward_providers_token_account = self
+            .get_or_create_associated_token_account(user.pubkey(), reward_mint, user)
+            .await;
+
+        let accounts = gamma::accounts::CreateRewards {
+            reward_provider: user.pubkey(),
+            authority,
+            pool_state: pool_id,
+            reward_info: reward_info_key,
+            reward_vault: reward_vault_key,
+            reward_providers_token_account,
+            reward_mint,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::CreateRewards {
+            start_time,
+            end_time,
+            reward_amount,
+        };
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    /// Creates a reward funded with `reward_amount` tokens of a new mint, returns the reward info
+    /// and the mint
+    pub async fn create_funded_reward(
+        &mut self,
+        reward_provider: &Keypair,
+        pool_id: Pubkey,
+        start_time: u64,
+        end_time: u64,
+        reward_amount: u64,
+    ) -> (Pubkey, Pubkey) {
+        let reward_mint = Keypair::new();
+        self.create_token_mint(&reward_mint, &self.mint_authority.pubkey(), 9)
+            .await;
+        self.mint_rewards(reward_provider, reward_mint.pubkey(), reward_amount)
+            .await;
+        self.create_rewards(
+            reward_provider,
+            pool_id,
+            start_time,
+            end_time,
+            reward_mint.pubkey(),
+            reward_amount,
+        )
+        .await;
+
+        (
+            reward_info_key(pool_id, start_time, reward_mint.pubkey()),
+            reward_mint.pubkey(),
+        )
+    }
+
+    pub async fn mint_rewards(
+        &mut self,
+        reward_provider: &Keypair,
+        reward_mint: Pubkey,
+        reward_amount: u64,
+    ) {
+        let reward_provider_token_account = self
+            .get_or_create_associated_token_account(
+                reward_provider.pubkey(),
+                reward_mint,
+                reward_provider,
+            )
+            .await;
+        self.mint_base_tokens(reward_provider_token_account, reward_amount, reward_mint)
+            .await;
+    }
+
+    pub async fn fetch_user_reward_info(
+        &mut self,
+        reward_info_key: Pubkey,
+        user: Pubkey,
+    ) -> UserRewardInfo {
+        self.fetch_account(user_reward_info_key(reward_info_key, user))
+            .await
+    }
+
+    pub async fn calculate_rewards(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        reward_info_key: Pubkey,
+    ) {
+        let user_pool_liquidity = Pubkey::find_program_address(
+            &[
+                USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                user.pubkey().to_bytes().as_ref(),
+            ],
+            &gamma::ID,
+        )
+        .0;
+
+        let (user_reward_info_key, _) = Pubkey::find_program_address(
+            &[
+                USER_REWARD_INFO_SEED.as_bytes(),
+                reward_info_key.to_bytes().as_ref(),
+                user.pubkey().to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        );
+
+        let accounts = gamma::accounts::CalculateRewards {
+            user: user.pubkey(),
+            user_reward_info: user_reward_info_key,
+            user_pool_liquidity,
+            pool_state: pool_id,
+            reward_info: reward_info_key,
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::CalculateRewards {};
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    pub async fn claim_rewards(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        reward_info_key: Pubkey,
+        reward_mint: Pubkey,
+    ) {
+        let (authority, __bump) =
+            Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
+
+        let (user_reward_info_key, _) = Pubkey::find_program_address(
+            &[
+                USER_REWARD_INFO_SEED.as_bytes(),
+                reward_info_key.to_bytes().as_ref(),
+                user.pubkey().to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        );
+
+        let (reward_vault_key, _) = Pubkey::find_program_address(
+            &[
+                REWARD_VAULT_SEED.as_bytes(),
+                reward_info_key.to_bytes().as_ref(),
+            ],
+            &gamma::id(),
+        );
+
+        let user_token_account = self
+            .get_or_create_associated_token_account(user.pubkey(), reward_mint, user)
+            .await;
+
+        let accounts = gamma::accounts::ClaimRewards {
+            user: user.pubkey(),
+            user_reward_info: user_reward_info_key,
+            authority,
+            pool_state: pool_id,
+            reward_info: reward_info_key,
+            reward_vault: reward_vault_key,
+            user_token_account,
+            reward_mint,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::ClaimRewards {};
 
         let transaction = self
             .encode_instruction_and_sign_transaction(data, accounts, user)
@@ -972,103 +2636,109 @@ impl TestEnv {
             .unwrap();
     }
 
-    pub async fn swap_base_input(
+    pub async fn fund_rewards(
         &mut self,
         user: &Keypair,
         pool_id: Pubkey,
-        amm_config_index: u16,
-        amount_in: u64,
-        minimum_amount_out: u64,
-        trade_direction: TradeDirection,
-    ) {
-        let (authority, __bump) =
-            Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
-        let (amm_config_key, __bump) = Pubkey::find_program_address(
-            &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
-            &gamma::ID,
+        reward_info_key: Pubkey,
+        reward_mint: Pubkey,
+        reward_amount: u64,
+    ) -> ProcessTransactionResult {
+        let (reward_vault_key, _) = Pubkey::find_program_address(
+            &[
+                REWARD_VAULT_SEED.as_bytes(),
+                reward_info_key.to_bytes().as_ref(),
+            ],
+            &gamma::id(),
         );
 
-        let user_token_0_account: Pubkey = self
-            .get_or_create_associated_token_account(user.pubkey(), self.token_0_mint.clone(), &user)
+        let reward_providers_token_account = self
+            .get_or_create_associated_token_account(user.pubkey(), reward_mint, user)
             .await;
 
-        let user_token_1_account = self
-            .get_or_create_associated_token_account(user.pubkey(), self.token_1_mint.clone(), &user)
+        let accounts = gamma::accounts::FundRewards {
+            reward_provider: user.pubkey(),
+            pool_state: pool_id,
+            reward_info: reward_info_key,
+            reward_providers_token_account,
+            reward_vault: reward_vault_key,
+            reward_mint,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+        };
+
+        let data = gamma::instruction::FundRewards { reward_amount };
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, user)
             .await;
 
-        let (token_0_vault, __bump) = Pubkey::find_program_address(
-            &[
-                POOL_VAULT_SEED.as_bytes(),
-                pool_id.to_bytes().as_ref(),
-                self.token_0_mint.to_bytes().as_ref(),
-            ],
-            &gamma::ID,
-        );
-        let (token_1_vault, __bump) = Pubkey::find_program_address(
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn extend_rewards(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        reward_info_key: Pubkey,
+        end_time: u64,
+    ) -> ProcessTransactionResult {
+        let accounts = gamma::accounts::ExtendRewards {
+            reward_provider: user.pubkey(),
+            pool_state: pool_id,
+            reward_info: reward_info_key,
+        };
+
+        let data = gamma::instruction::ExtendRewards { end_time };
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn close_rewards(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        reward_info_key: Pubkey,
+        reward_mint: Pubkey,
+    ) -> ProcessTransactionResult {
+        let (authority, __bump) =
+            Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
+
+        let (reward_vault_key, _) = Pubkey::find_program_address(
             &[
-                POOL_VAULT_SEED.as_bytes(),
-                pool_id.to_bytes().as_ref(),
-                self.token_1_mint.to_bytes().as_ref(),
+                REWARD_VAULT_SEED.as_bytes(),
+                reward_info_key.to_bytes().as_ref(),
             ],
-            &gamma::ID,
-        );
-        let (observation_key, __bump) = Pubkey::find_program_address(
-            &[OBSERVATION_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
-            &gamma::ID,
+            &gamma::id(),
         );
 
-        let (
-            input_token_account,
-            output_token_account,
-            input_token_mint,
-            output_token_mint,
-            input_vault,
-            output_vault,
-            input_token_program,
-            output_token_program,
-        ) = match trade_direction {
-            TradeDirection::ZeroForOne => (
-                user_token_0_account,
-                user_token_1_account,
-                self.token_0_mint,
-                self.token_1_mint,
-                token_0_vault,
-                token_1_vault,
-                spl_token::id(),
-                spl_token::id(),
-            ),
-            TradeDirection::OneForZero => (
-                user_token_1_account,
-                user_token_0_account,
-                self.token_1_mint,
-                self.token_0_mint,
-                token_1_vault,
-                token_0_vault,
-                spl_token::id(),
-                spl_token::id(),
-            ),
-        };
+        let reward_providers_token_account = self
+            .get_or_create_associated_token_account(user.pubkey(), reward_mint, user)
+            .await;
 
-        let accounts = gamma::accounts::Swap {
-            payer: user.pubkey(),
+        let accounts = gamma::accounts::CloseRewards {
+            reward_provider: user.pubkey(),
             authority,
-            amm_config: amm_config_key,
             pool_state: pool_id,
-            observation_state: observation_key,
-            input_token_account,
-            output_token_account,
-            input_vault,
-            output_vault,
-            input_token_program,
-            output_token_program,
-            input_token_mint,
-            output_token_mint,
+            reward_info: reward_info_key,
+            reward_providers_token_account,
+            reward_vault: reward_vault_key,
+            reward_mint,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
         };
 
-        let data = gamma::instruction::SwapBaseInput {
-            amount_in,
-            minimum_amount_out,
-        };
+        let data = gamma::instruction::CloseRewards {};
 
         let transaction = self
             .encode_instruction_and_sign_transaction(data, accounts, user)
@@ -1076,111 +2746,177 @@ impl TestEnv {
 
         self.program_test_context
             .banks_client
-            .process_transaction(transaction)
+            .process_transaction_with_metadata(transaction)
             .await
-            .unwrap();
     }
 
-    pub async fn swap_base_output(
+    /// Claims the rewards of `claims`, given as `(pool_id, reward_info_key, reward_mint)`, in one transaction
+    pub async fn claim_all_rewards(
         &mut self,
         user: &Keypair,
-        pool_id: Pubkey,
-        amm_config_index: u16,
-        amount_out: u64,
-        max_amount_in: u64,
-        trade_direction: TradeDirection,
-    ) {
+        claims: Vec<(Pubkey, Pubkey, Pubkey)>,
+    ) -> ProcessTransactionResult {
         let (authority, __bump) =
             Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
-        let (amm_config_key, __bump) = Pubkey::find_program_address(
-            &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
-            &gamma::ID,
-        );
 
-        let user_token_0_account: Pubkey = self
-            .get_or_create_associated_token_account(user.pubkey(), self.token_0_mint.clone(), &user)
-            .await;
+        let mut remaining_accounts = vec![];
+        for (pool_id, reward_info_key, reward_mint) in claims {
+            let (user_reward_info_key, _) = Pubkey::find_program_address(
+                &[
+                    USER_REWARD_INFO_SEED.as_bytes(),
+                    reward_info_key.to_bytes().as_ref(),
+                    user.pubkey().to_bytes().as_ref(),
+                ],
+                &gamma::id(),
+            );
+            let (reward_vault_key, _) = Pubkey::find_program_address(
+                &[
+                    REWARD_VAULT_SEED.as_bytes(),
+                    reward_info_key.to_bytes().as_ref(),
+                ],
+                &gamma::id(),
+            );
+            let user_token_account = self
+                .get_or_create_associated_token_account(user.pubkey(), reward_mint, user)
+                .await;
+            let user_pool_liquidity = Pubkey::find_program_address(
+                &[
+                    USER_POOL_LIQUIDITY_SEED.as_bytes(),
+                    pool_id.to_bytes().as_ref(),
+                    user.pubkey().to_bytes().as_ref(),
+                ],
+                &gamma::ID,
+            )
+            .0;
+
+            remaining_accounts.extend([
+                AccountMeta::new(reward_info_key, false),
+                AccountMeta::new(user_reward_info_key, false),
+                AccountMeta::new(reward_vault_key, false),
+                AccountMeta::new(user_token_account, false),
+                AccountMeta::new_readonly(reward_mint, false),
+                AccountMeta::new_readonly(pool_id, false),
+                AccountMeta::new_readonly(user_pool_liquidity, false),
+            ]);
+        }
 
-        let user_token_1_account = self
-            .get_or_create_associated_token_account(user.pubkey(), self.token_1_mint.clone(), &user)
-            .await;
+        let mut accounts = gamma::accounts::ClaimAllRewards {
+            user: user.pubkey(),
+            authority,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+        }
+        .to_account_metas(None);
+        accounts.extend(remaining_accounts);
 
-        let (token_0_vault, __bump) = Pubkey::find_program_address(
-            &[
-                POOL_VAULT_SEED.as_bytes(),
-                pool_id.to_bytes().as_ref(),
-                self.token_0_mint.to_bytes().as_ref(),
-            ],
-            &gamma::ID,
-        );
-        let (token_1_vault, __bump) = Pubkey::find_program_address(
+        let instruction = Instruction {
+            program_id: gamma::id(),
+            accounts,
+            data: gamma::instruction::ClaimAllRewards {}.data(),
+        };
+        let transaction =
+            get_signed_transaction(&mut self.program_test_context, &[instruction], user).await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    /// The ledger of the partner the position of `user` is linked with, if any.
+    /// Legacy pools book the partner liquidity without a ledger.
+    pub async fn partner_info_of(&mut self, pool_id: Pubkey, user: Pubkey) -> Option<Pubkey> {
+        let pool_state: PoolState = self.fetch_account(pool_id).await;
+        if pool_state.is_legacy_layout() {
+            return None;
+        }
+        let user_pool_liquidity = Pubkey::find_program_address(
             &[
-                POOL_VAULT_SEED.as_bytes(),
+                USER_POOL_LIQUIDITY_SEED.as_bytes(),
                 pool_id.to_bytes().as_ref(),
-                self.token_1_mint.to_bytes().as_ref(),
+                user.to_bytes().as_ref(),
             ],
-            &gamma::ID,
-        );
-        let (observation_key, __bump) = Pubkey::find_program_address(
-            &[OBSERVATION_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
-            &gamma::ID,
-        );
+            &gamma::id(),
+        )
+        .0;
+        let user_pool_liquidity: UserPoolLiquidity =
+            self.try_fetch_account(user_pool_liquidity).await.ok()?;
+        user_pool_liquidity
+            .partner
+            .map(|partner_id| partner_info_key(pool_id, partner_id))
+    }
 
-        let (
-            input_token_account,
-            output_token_account,
-            input_token_mint,
-            output_token_mint,
-            input_vault,
-            output_vault,
-            input_token_program,
-            output_token_program,
-        ) = match trade_direction {
-            TradeDirection::ZeroForOne => (
-                user_token_0_account,
-                user_token_1_account,
-                self.token_0_mint,
-                self.token_1_mint,
-                token_0_vault,
-                token_1_vault,
-                spl_token::id(),
-                spl_token::id(),
-            ),
-            TradeDirection::OneForZero => (
-                user_token_1_account,
-                user_token_0_account,
-                self.token_1_mint,
-                self.token_0_mint,
-                token_1_vault,
-                token_0_vault,
-                spl_token::id(),
-                spl_token::id(),
-            ),
+    pub async fn register_partner(
+        &mut self,
+        admin: &Keypair,
+        partner_id: u64,
+        fee_share_rate: u64,
+        authority: Pubkey,
+    ) -> ProcessTransactionResult {
+        let accounts = gamma::accounts::RegisterPartner {
+            owner: admin.pubkey(),
+            partner: partner_key(partner_id),
+            system_program: system_program::ID,
         };
 
-        let accounts = gamma::accounts::Swap {
-            payer: user.pubkey(),
+        let data = gamma::instruction::RegisterPartner {
+            partner_id,
+            fee_share_rate,
             authority,
-            amm_config: amm_config_key,
-            pool_state: pool_id,
-            observation_state: observation_key,
-            input_token_account,
-            output_token_account,
-            input_vault,
-            output_vault,
-            input_token_program,
-            output_token_program,
-            input_token_mint,
-            output_token_mint,
         };
 
-        let data = gamma::instruction::SwapBaseOutput {
-            amount_out,
-            max_amount_in,
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, admin)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn update_partner_authority(
+        &mut self,
+        owner: &Keypair,
+        partner_id: u64,
+        authority: Pubkey,
+    ) -> ProcessTransactionResult {
+        let accounts = gamma::accounts::UpdatePartnerAuthority {
+            owner: owner.pubkey(),
+            partner: partner_key(partner_id),
         };
 
+        let data = gamma::instruction::UpdatePartnerAuthority { authority };
+
         let transaction = self
-            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .encode_instruction_and_sign_transaction(data, accounts, owner)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn init_partner_info(
+        &mut self,
+        payer: &Keypair,
+        pool_id: Pubkey,
+        partner_id: u64,
+    ) -> Pubkey {
+        let partner_info = partner_info_key(pool_id, partner_id);
+        let accounts = gamma::accounts::InitPartnerInfo {
+            payer: payer.pubkey(),
+            pool_state: pool_id,
+            partner: partner_key(partner_id),
+            partner_info,
+            system_program: system_program::ID,
+        };
+
+        let data = gamma::instruction::InitPartnerInfo {};
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, payer)
             .await;
 
         self.program_test_context
@@ -1188,110 +2924,145 @@ impl TestEnv {
             .process_transaction(transaction)
             .await
             .unwrap();
+
+        partner_info
     }
 
-    pub async fn create_rewards(
+    pub async fn collect_protocol_fee(
         &mut self,
-        user: &Keypair,
+        owner: &Keypair,
         pool_id: Pubkey,
-        start_time: u64,
-        end_time: u64,
-        reward_mint: Pubkey,
-        reward_amount: u64,
-    ) {
+        amount_0_requested: u64,
+        amount_1_requested: u64,
+    ) -> ProcessTransactionResult {
+        let pool_state: PoolState = self.fetch_account(pool_id).await;
         let (authority, __bump) =
             Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
+        let recipient_token_0_account = self
+            .get_or_create_associated_token_account(owner.pubkey(), self.token_0_mint, owner)
+            .await;
+        let recipient_token_1_account = self
+            .get_or_create_associated_token_account(owner.pubkey(), self.token_1_mint, owner)
+            .await;
 
-        let (reward_info_key, _) = Pubkey::find_program_address(
+        let accounts = gamma::accounts::CollectProtocolFee {
+            owner: owner.pubkey(),
+            authority,
+            pool_state: pool_id,
+            amm_config: pool_state.amm_config,
+            token_0_vault: pool_state.token_0_vault,
+            token_1_vault: pool_state.token_1_vault,
+            vault_0_mint: self.token_0_mint,
+            vault_1_mint: self.token_1_mint,
+            recipient_token_0_account,
+            recipient_token_1_account,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+        };
+
+        let data = gamma::instruction::CollectProtocolFee {
+            amount_0_requested,
+            amount_1_requested,
+        };
+
+        let transaction = self
+            .encode_instruction_and_sign_transaction(data, accounts, owner)
+            .await;
+
+        self.program_test_context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    pub async fn claim_partner_fees(
+        &mut self,
+        partner_authority: &Keypair,
+        pool_id: Pubkey,
+        partner_id: u64,
+    ) -> ProcessTransactionResult {
+        let (authority, __bump) =
+            Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
+        let (token_0_vault, __bump) = Pubkey::find_program_address(
             &[
-                REWARD_INFO_SEED.as_bytes(),
+                POOL_VAULT_SEED.as_bytes(),
                 pool_id.to_bytes().as_ref(),
-                &start_time.to_le_bytes(),
-                reward_mint.to_bytes().as_ref(),
+                self.token_0_mint.to_bytes().as_ref(),
             ],
-            &gamma::id(),
+            &gamma::ID,
         );
-
-        let (reward_vault_key, _) = Pubkey::find_program_address(
+        let (token_1_vault, __bump) = Pubkey::find_program_address(
             &[
-                REWARD_VAULT_SEED.as_bytes(),
-                reward_info_key.to_bytes().as_ref(),
+                POOL_VAULT_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                self.token_1_mint.to_bytes().as_ref(),
             ],
-            &gamma::id(),
+            &gamma::ID,
         );
-
-        let reward_providers_token_account = self
-            .get_or_create_associated_token_account(user.pubkey(), reward_mint, user)
+        let recipient_token_0_account = self
+            .get_or_create_associated_token_account(
+                partner_authority.pubkey(),
+                self.token_0_mint,
+                partner_authority,
+            )
+            .await;
+        let recipient_token_1_account = self
+            .get_or_create_associated_token_account(
+                partner_authority.pubkey(),
+                self.token_1_mint,
+                partner_authority,
+            )
             .await;
 
-        let accounts = gamma::accounts::CreateRewards {
-            reward_provider: user.pubkey(),
+        let accounts = gamma::accounts::ClaimPartnerFees {
+            partner_authority: partner_authority.pubkey(),
             authority,
             pool_state: pool_id,
-            reward_info: reward_info_key,
-            reward_vault: reward_vault_key,
-            reward_providers_token_account,
-            reward_mint,
+            partner: partner_key(partner_id),
+            partner_info: partner_info_key(pool_id, partner_id),
+            token_0_vault,
+            token_1_vault,
+            vault_0_mint: self.token_0_mint,
+            vault_1_mint: self.token_1_mint,
+            recipient_token_0_account,
+            recipient_token_1_account,
             token_program: spl_token::id(),
             token_program_2022: spl_token_2022::id(),
-            system_program: system_program::ID,
         };
 
-        let data = gamma::instruction::CreateRewards {
-            start_time,
-            end_time,
-            reward_amount,
-        };
+        let data = gamma::instruction::ClaimPartnerFees {};
 
         let transaction = self
-            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .encode_instruction_and_sign_transaction(data, accounts, partner_authority)
             .await;
 
         self.program_test_context
             .banks_client
-            .process_transaction(transaction)
+            .process_transaction_with_metadata(transaction)
             .await
-            .unwrap();
     }
 
-    pub async fn calculate_rewards(
+    pub async fn update_amm_config(
         &mut self,
-        user: &Keypair,
-        pool_id: Pubkey,
-        reward_info_key: Pubkey,
+        admin: &Keypair,
+        amm_config_index: u16,
+        param: u16,
+        value: u64,
     ) {
-        let user_pool_liquidity = Pubkey::find_program_address(
-            &[
-                USER_POOL_LIQUIDITY_SEED.as_bytes(),
-                pool_id.to_bytes().as_ref(),
-                user.pubkey().to_bytes().as_ref(),
-            ],
+        let (amm_config_key, __bump) = Pubkey::find_program_address(
+            &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
             &gamma::ID,
-        )
-        .0;
-
-        let (user_reward_info_key, _) = Pubkey::find_program_address(
-            &[
-                USER_REWARD_INFO_SEED.as_bytes(),
-                reward_info_key.to_bytes().as_ref(),
-                user.pubkey().to_bytes().as_ref(),
-            ],
-            &gamma::id(),
         );
 
-        let accounts = gamma::accounts::CalculateRewards {
-            user: user.pubkey(),
-            user_reward_info: user_reward_info_key,
-            user_pool_liquidity,
-            pool_state: pool_id,
-            reward_info: reward_info_key,
-            system_program: system_program::ID,
+        let accounts = gamma::accounts::UpdateAmmConfig {
+            owner: admin.pubkey(),
+            amm_config: amm_config_key,
         };
 
-        let data = gamma::instruction::CalculateRewards {};
+        let data = gamma::instruction::UpdateAmmConfig { param, value };
 
         let transaction = self
-            .encode_instruction_and_sign_transaction(data, accounts, user)
+            .encode_instruction_and_sign_transaction(data, accounts, admin)
             .await;
 
         self.program_test_context
@@ -1301,61 +3072,162 @@ impl TestEnv {
             .unwrap();
     }
 
-    pub async fn claim_rewards(
+    /// Builds a `flash_borrow` instruction, creating the token account of the borrower if needed
+    pub async fn flash_borrow_instruction(
         &mut self,
-        user: &Keypair,
+        borrower: &Keypair,
         pool_id: Pubkey,
-        reward_info_key: Pubkey,
-        reward_mint: Pubkey,
-    ) {
+        mint: Pubkey,
+        amount: u64,
+    ) -> Instruction {
         let (authority, __bump) =
             Pubkey::find_program_address(&[AUTH_SEED.as_bytes()], &gamma::id());
-
-        let (user_reward_info_key, _) = Pubkey::find_program_address(
+        let (token_vault, __bump) = Pubkey::find_program_address(
             &[
-                USER_REWARD_INFO_SEED.as_bytes(),
-                reward_info_key.to_bytes().as_ref(),
-                user.pubkey().to_bytes().as_ref(),
+                POOL_VAULT_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                mint.to_bytes().as_ref(),
             ],
-            &gamma::id(),
+            &gamma::ID,
         );
+        let borrower_token_account = self
+            .get_or_create_associated_token_account(borrower.pubkey(), mint, borrower)
+            .await;
 
-        let (reward_vault_key, _) = Pubkey::find_program_address(
+        let accounts = gamma::accounts::FlashBorrow {
+            borrower: borrower.pubkey(),
+            authority,
+            pool_state: pool_id,
+            token_vault,
+            vault_mint: mint,
+            borrower_token_account,
+            token_program: spl_token::id(),
+            instruction_sysvar_account: sysvar::instructions::id(),
+        };
+
+        let data = gamma::instruction::FlashBorrow { amount };
+
+        get_instruction(data, accounts)
+    }
+
+    /// Builds a `flash_repay` instruction, creating the token account of the payer if needed
+    pub async fn flash_repay_instruction(
+        &mut self,
+        payer: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        mint: Pubkey,
+    ) -> Instruction {
+        let (amm_config_key, __bump) = Pubkey::find_program_address(
+            &[AMM_CONFIG_SEED.as_bytes(), &amm_config_index.to_be_bytes()],
+            &gamma::ID,
+        );
+        let (token_vault, __bump) = Pubkey::find_program_address(
             &[
-                REWARD_VAULT_SEED.as_bytes(),
-                reward_info_key.to_bytes().as_ref(),
+                POOL_VAULT_SEED.as_bytes(),
+                pool_id.to_bytes().as_ref(),
+                mint.to_bytes().as_ref(),
             ],
-            &gamma::id(),
+            &gamma::ID,
         );
-
-        let user_token_account = self
-            .get_or_create_associated_token_account(user.pubkey(), reward_mint, user)
+        let payer_token_account = self
+            .get_or_create_associated_token_account(payer.pubkey(), mint, payer)
             .await;
 
-        let accounts = gamma::accounts::ClaimRewards {
-            user: user.pubkey(),
-            user_reward_info: user_reward_info_key,
-            authority,
+        let accounts = gamma::accounts::FlashRepay {
+            payer: payer.pubkey(),
             pool_state: pool_id,
-            reward_info: reward_info_key,
-            reward_vault: reward_vault_key,
-            user_token_account,
-            reward_mint,
+            amm_config: amm_config_key,
+            token_vault,
+            vault_mint: mint,
+            payer_token_account,
             token_program: spl_token::id(),
-            token_program_2022: spl_token_2022::id(),
-            system_program: system_program::ID,
         };
 
-        let data = gamma::instruction::ClaimRewards {};
+        let data = gamma::instruction::FlashRepay {};
 
-        let transaction = self
-            .encode_instruction_and_sign_transaction(data, accounts, user)
+        get_instruction(data, accounts)
+    }
+
+    /// Builds a `flash_swap_base_output` instruction, `callback_accounts` are passed to the
+    /// callback program as remaining accounts
+    pub async fn flash_swap_base_output_instruction(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        max_amount_in: u64,
+        amount_out: u64,
+        trade_direction: TradeDirection,
+        callback_program: Pubkey,
+        callback_data: Vec<u8>,
+        callback_accounts: Vec<AccountMeta>,
+    ) -> Instruction {
+        // The swap accounts come first, so the instruction is built from a swap instruction
+        let mut instruction = self
+            .swap_base_input_instruction(user, pool_id, amm_config_index, 0, 0, trade_direction)
+            .await;
+        instruction.data = gamma::instruction::FlashSwapBaseOutput {
+            max_amount_in,
+            amount_out,
+            callback_data,
+        }
+        .data();
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(callback_program, false));
+        instruction.accounts.extend(callback_accounts);
+        instruction
+    }
+
+    /// Builds a `swap_base_input_v2` instruction, creating the token accounts of the user if needed
+    pub async fn swap_base_input_v2_instruction(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        amount_in: u64,
+        minimum_amount_out: u64,
+        max_price_x32: u128,
+        deadline: u64,
+        trade_direction: TradeDirection,
+    ) -> Instruction {
+        // The accounts are the ones of a swap, only the data differs
+        let mut instruction = self
+            .swap_base_input_instruction(user, pool_id, amm_config_index, 0, 0, trade_direction)
             .await;
+        instruction.data = gamma::instruction::SwapBaseInputV2 {
+            amount_in,
+            minimum_amount_out,
+            max_price_x32,
+            deadline,
+        }
+        .data();
+        instruction
+    }
 
-        self.program_test_context
-            .banks_client
-            .process_transaction(transaction)
-            .await
-            .unwrap();
+    /// Builds a `swap_base_output_v2` instruction, creating the token accounts of the user if needed
+    pub async fn swap_base_output_v2_instruction(
+        &mut self,
+        user: &Keypair,
+        pool_id: Pubkey,
+        amm_config_index: u16,
+        max_amount_in: u64,
+        amount_out: u64,
+        max_price_x32: u128,
+        deadline: u64,
+        trade_direction: TradeDirection,
+    ) -> Instruction {
+        let mut instruction = self
+            .swap_base_input_instruction(user, pool_id, amm_config_index, 0, 0, trade_direction)
+            .await;
+        instruction.data = gamma::instruction::SwapBaseOutputV2 {
+            max_amount_in,
+            amount_out,
+            max_price_x32,
+            deadline,
+        }
+        .data();
+        instruction
     }
 }
@@ -0,0 +1,198 @@
+use gamma::{
+    error::GammaError,
+    states::{UserPoolLiquidity, VestingLock},
+};
+use solana_program_test::tokio;
+use solana_sdk::{signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+const DAY: u64 = 60 * 60 * 24;
+
+#[tokio::test]
+async fn should_release_vested_lp_linearly_after_the_cliff() {
+    let funder = Keypair::new();
+    let beneficiary = Keypair::new();
+    let admin = get_admin();
+    let mut test_env =
+        TestEnv::new(vec![funder.pubkey(), beneficiary.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env.setup_small_pool(&funder, &admin).await;
+
+    let funder_pool_liquidity_key = user_pool_liquidity_key(pool_id, funder.pubkey());
+    let beneficiary_pool_liquidity_key = user_pool_liquidity_key(pool_id, beneficiary.pubkey());
+    let funder_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(funder_pool_liquidity_key).await;
+    let funder_lp_before = funder_pool_liquidity.lp_tokens_owned;
+
+    test_env
+        .create_vesting_lock(
+            &funder,
+            beneficiary.pubkey(),
+            pool_id,
+            1000,
+            30 * DAY,
+            100 * DAY,
+            vec![],
+        )
+        .await
+        .unwrap_transaction();
+
+    let funder_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(funder_pool_liquidity_key).await;
+    assert_eq!(
+        funder_pool_liquidity.lp_tokens_owned,
+        funder_lp_before - 1000
+    );
+    let beneficiary_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(beneficiary_pool_liquidity_key).await;
+    assert_eq!(beneficiary_pool_liquidity.lp_tokens_owned, 0);
+    assert_eq!(beneficiary_pool_liquidity.lp_tokens_vesting, 1000);
+
+    // Nothing can be released or withdrawn before the cliff
+    test_env.jump_days(10).await;
+    let result = test_env
+        .release_vested_lp(&beneficiary, pool_id, funder.pubkey())
+        .await;
+    assert_error!(result, GammaError::LiquidityLocked);
+    let result = test_env
+        .withdraw_with_rewards(&beneficiary, pool_id, 0, 1, 0, 0, vec![])
+        .await;
+    assert_error!(result, GammaError::InvalidLpTokenAmount);
+
+    // Half of the duration, half of the lp tokens are vested
+    test_env.jump_days(40).await;
+    test_env
+        .release_vested_lp(&beneficiary, pool_id, funder.pubkey())
+        .await
+        .unwrap_transaction();
+    let beneficiary_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(beneficiary_pool_liquidity_key).await;
+    assert_eq!(beneficiary_pool_liquidity.lp_tokens_owned, 500);
+    assert_eq!(beneficiary_pool_liquidity.lp_tokens_vesting, 500);
+
+    // Only the released lp tokens can be withdrawn
+    let result = test_env
+        .withdraw_with_rewards(&beneficiary, pool_id, 0, 501, 0, 0, vec![])
+        .await;
+    assert_error!(result, GammaError::InvalidLpTokenAmount);
+    test_env.withdraw(&beneficiary, pool_id, 0, 500, 0, 0).await;
+
+    test_env.jump_days(50).await;
+    test_env
+        .release_vested_lp(&beneficiary, pool_id, funder.pubkey())
+        .await
+        .unwrap_transaction();
+    let beneficiary_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(beneficiary_pool_liquidity_key).await;
+    assert_eq!(beneficiary_pool_liquidity.lp_tokens_owned, 500);
+    assert_eq!(beneficiary_pool_liquidity.lp_tokens_vesting, 0);
+    let vesting_lock: VestingLock = test_env
+        .fetch_account(vesting_lock_key(
+            beneficiary_pool_liquidity_key,
+            funder.pubkey(),
+        ))
+        .await;
+    assert_eq!(vesting_lock.lp_tokens_released, 1000);
+
+    // Everything is released
+    test_env.jump_days(1).await;
+    let result = test_env
+        .release_vested_lp(&beneficiary, pool_id, funder.pubkey())
+        .await;
+    assert_error!(result, GammaError::LiquidityLocked);
+}
+
+#[tokio::test]
+async fn should_reject_a_cliff_longer_than_the_duration() {
+    let funder = Keypair::new();
+    let beneficiary = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![funder.pubkey(), admin.pubkey()]).await;
+    let pool_id = test_env.setup_small_pool(&funder, &admin).await;
+
+    let result = test_env
+        .create_vesting_lock(
+            &funder,
+            beneficiary.pubkey(),
+            pool_id,
+            1000,
+            30 * DAY,
+            10 * DAY,
+            vec![],
+        )
+        .await;
+    assert_error!(result, GammaError::InvalidVestingSchedule);
+}
+
+#[tokio::test]
+async fn should_keep_one_vesting_lock_per_funder() {
+    let funder = Keypair::new();
+    let other_funder = Keypair::new();
+    let beneficiary = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![
+        funder.pubkey(),
+        other_funder.pubkey(),
+        beneficiary.pubkey(),
+        admin.pubkey(),
+    ])
+    .await;
+    let pool_id = test_env.setup_small_pool(&funder, &admin).await;
+    test_env
+        .transfer_lp(&funder, other_funder.pubkey(), pool_id, 1, vec![])
+        .await;
+
+    // A dust lock with a long schedule does not hold up the locks of other funders
+    test_env
+        .create_vesting_lock(
+            &other_funder,
+            beneficiary.pubkey(),
+            pool_id,
+            1,
+            1000 * DAY,
+            1000 * DAY,
+            vec![],
+        )
+        .await
+        .unwrap_transaction();
+    test_env
+        .create_vesting_lock(
+            &funder,
+            beneficiary.pubkey(),
+            pool_id,
+            1000,
+            0,
+            10 * DAY,
+            vec![],
+        )
+        .await
+        .unwrap_transaction();
+
+    let beneficiary_pool_liquidity_key = user_pool_liquidity_key(pool_id, beneficiary.pubkey());
+    let beneficiary_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(beneficiary_pool_liquidity_key).await;
+    assert_eq!(beneficiary_pool_liquidity.lp_tokens_vesting, 1001);
+
+    test_env.jump_days(10).await;
+    test_env
+        .release_vested_lp(&beneficiary, pool_id, funder.pubkey())
+        .await
+        .unwrap_transaction();
+    let result = test_env
+        .release_vested_lp(&beneficiary, pool_id, other_funder.pubkey())
+        .await;
+    assert_error!(result, GammaError::LiquidityLocked);
+
+    let beneficiary_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(beneficiary_pool_liquidity_key).await;
+    assert_eq!(beneficiary_pool_liquidity.lp_tokens_owned, 1000);
+    assert_eq!(beneficiary_pool_liquidity.lp_tokens_vesting, 1);
+    let vesting_lock: VestingLock = test_env
+        .fetch_account(vesting_lock_key(
+            beneficiary_pool_liquidity_key,
+            other_funder.pubkey(),
+        ))
+        .await;
+    assert_eq!(vesting_lock.funder, other_funder.pubkey());
+}
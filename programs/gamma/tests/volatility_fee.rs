@@ -0,0 +1,124 @@
+use gamma::{curve::TradeDirection, fees::FeeType, states::PoolState};
+use solana_program_test::tokio;
+use solana_sdk::{signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+/// Compute units the volatility fee may add to a swap over the static fee
+const VOLATILITY_FEE_COMPUTE_UNITS: u64 = 20_000;
+
+/// Build of the program with the floating point volatility fee the fixed point one replaced, made with
+/// `cargo build-sbf --features f64-volatility-fee` and copied from `target/deploy/gamma.so`
+const F64_VOLATILITY_FEE_BUILD: &str = "gamma_f64_volatility_fee";
+
+#[tokio::test]
+async fn volatility_fee_compute_units_should_stay_close_to_static_fee() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env.create_config(&admin, 0, 100, 20, 5, 0).await;
+    test_env.create_config(&admin, 1, 100, 20, 5, 0).await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    let volatility_pool = test_env
+        .initialize_pool(
+            &user,
+            0,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    let static_pool = test_env
+        .initialize_pool(
+            &user,
+            1,
+            10000000,
+            10000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+    test_env
+        .update_pool(&admin, static_pool, 1, 7, FeeType::Static as u64)
+        .await;
+    let static_pool_state: PoolState = test_env.fetch_account(static_pool).await;
+    assert_eq!(static_pool_state.fee_type().unwrap(), FeeType::Static);
+
+    // Fill the observations with moving prices, so that the volatility fee has a range to measure
+    let mut compute_units = [0u64; 2];
+    for (index, (pool_id, amm_index)) in [(volatility_pool, 0), (static_pool, 1)]
+        .into_iter()
+        .enumerate()
+    {
+        for trade_direction in [
+            TradeDirection::ZeroForOne,
+            TradeDirection::ZeroForOne,
+            TradeDirection::OneForZero,
+            TradeDirection::ZeroForOne,
+        ] {
+            test_env.jump_seconds(60).await;
+            compute_units[index] = test_env
+                .swap_base_input(&user, pool_id, amm_index, 500000, 1, trade_direction)
+                .await;
+        }
+    }
+
+    // The volatility fee only adds the price range scan over the observations and three fixed
+    // point logarithms of 63 squarings each
+    assert!(
+        compute_units[0] < compute_units[1] + VOLATILITY_FEE_COMPUTE_UNITS,
+        "volatility fee swap used {} compute units, static fee swap {}",
+        compute_units[0],
+        compute_units[1]
+    );
+}
+
+/// Compute units of a volatility fee swap once the observations hold a price range
+async fn volatility_fee_swap_compute_units(program_name: &str) -> u64 {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let mut test_env =
+        TestEnv::new_with_build(program_name, vec![user.pubkey(), admin.pubkey()], vec![]).await;
+    let pool_id = test_env.setup_pool(&user, &admin).await;
+
+    let mut compute_units = 0;
+    for trade_direction in [
+        TradeDirection::ZeroForOne,
+        TradeDirection::ZeroForOne,
+        TradeDirection::OneForZero,
+        TradeDirection::ZeroForOne,
+    ] {
+        test_env.jump_seconds(60).await;
+        compute_units = test_env
+            .swap_base_input(&user, pool_id, 0, 100000, 1, trade_direction)
+            .await;
+    }
+    compute_units
+}
+
+#[tokio::test]
+#[ignore = "needs the f64 build of the program, see F64_VOLATILITY_FEE_BUILD"]
+async fn fixed_point_volatility_fee_should_use_less_compute_than_f64() {
+    let fixed_point_compute_units = volatility_fee_swap_compute_units("gamma").await;
+    let f64_compute_units = volatility_fee_swap_compute_units(F64_VOLATILITY_FEE_BUILD).await;
+    println!(
+        "volatility fee swap: {} compute units with fixed point, {} with f64",
+        fixed_point_compute_units, f64_compute_units
+    );
+    assert!(fixed_point_compute_units < f64_compute_units);
+}
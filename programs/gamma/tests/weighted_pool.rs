@@ -0,0 +1,147 @@
+use anchor_spl::token::TokenAccount;
+use gamma::{
+    curve::TradeDirection,
+    states::{PoolState, Q32},
+};
+use solana_program_test::tokio;
+use solana_sdk::{signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn weighted_pool_should_price_by_weights() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    // An 80/20 pool holding 4 times more token_0 than token_1 prices them at par
+    let pool_id = test_env
+        .initialize_pool_with_curve(
+            &user,
+            amm_index,
+            8000000,
+            2000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+            2,
+            0,
+            80,
+        )
+        .await;
+
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq!(pool_state.curve_type, 2);
+    assert_eq!(pool_state.token_0_weight, 80);
+    assert_eq!(pool_state.token_1_weight, 20);
+    let (token_0_price_x32, token_1_price_x32) = pool_state
+        .token_price_x32(test_env.timestamp_now().await as u64)
+        .unwrap();
+    assert_eq!(token_0_price_x32, Q32);
+    assert_eq!(token_1_price_x32, Q32);
+
+    for (trade_direction, user_output_account) in [
+        (TradeDirection::ZeroForOne, user_token_1_account),
+        (TradeDirection::OneForZero, user_token_0_account),
+    ] {
+        let user_output_before: TokenAccount = test_env.fetch_account(user_output_account).await;
+        test_env
+            .swap_base_input(&user, pool_id, amm_index, 10000, 1, trade_direction)
+            .await;
+        let user_output_after: TokenAccount = test_env.fetch_account(user_output_account).await;
+
+        // Close to par, a constant product pool with the same vaults would give 4 times more or less
+        let received = user_output_after.amount - user_output_before.amount;
+        assert!(received > 9900);
+        assert!(received < 10100);
+    }
+}
+
+#[tokio::test]
+async fn weighted_pool_should_keep_weights_on_deposit_and_withdraw() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    let pool_id = test_env
+        .initialize_pool_with_curve(
+            &user,
+            amm_index,
+            8000000,
+            2000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+            2,
+            0,
+            80,
+        )
+        .await;
+
+    let pool_state_before: PoolState = test_env.fetch_account(pool_id).await;
+    let lp_token_amount = pool_state_before.lp_supply / 2;
+    test_env
+        .deposit(
+            &user,
+            pool_id,
+            amm_index,
+            lp_token_amount,
+            100000000,
+            100000000,
+        )
+        .await;
+
+    // Deposits are proportional, so 80% of their value is in token_0
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq!(
+        pool_state.lp_supply,
+        pool_state_before.lp_supply + lp_token_amount
+    );
+    assert_eq!(
+        pool_state.token_0_vault_amount,
+        4 * pool_state.token_1_vault_amount
+    );
+
+    test_env
+        .withdraw(&user, pool_id, amm_index, lp_token_amount, 0, 0)
+        .await;
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq!(pool_state.lp_supply, pool_state_before.lp_supply);
+    assert_eq!(
+        pool_state.token_0_vault_amount,
+        4 * pool_state.token_1_vault_amount
+    );
+}
@@ -0,0 +1,112 @@
+use anchor_spl::token::TokenAccount;
+use gamma::states::{PoolState, UserPoolLiquidity, USER_POOL_LIQUIDITY_SEED};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn should_withdraw_single_sided_into_either_token() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000000, test_env.token_1_mint)
+        .await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            10000000,
+            20000000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+
+    let user_pool_liquidity_key = Pubkey::find_program_address(
+        &[
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+            user.pubkey().to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    )
+    .0;
+
+    for (output_mint, user_output_account) in [
+        (test_env.token_0_mint, user_token_0_account),
+        (test_env.token_1_mint, user_token_1_account),
+    ] {
+        let pool_state_before: PoolState = test_env.fetch_account(pool_id).await;
+        let user_pool_liquidity_before: UserPoolLiquidity =
+            test_env.fetch_account(user_pool_liquidity_key).await;
+        let user_output_account_before: TokenAccount =
+            test_env.fetch_account(user_output_account).await;
+        let user_other_account = if output_mint == test_env.token_0_mint {
+            user_token_1_account
+        } else {
+            user_token_0_account
+        };
+        let user_other_account_before: TokenAccount =
+            test_env.fetch_account(user_other_account).await;
+
+        let lp_token_amount = pool_state_before.lp_supply / 10;
+        test_env
+            .withdraw_single_sided(&user, pool_id, amm_index, lp_token_amount, output_mint, 1)
+            .await;
+
+        let pool_state_after: PoolState = test_env.fetch_account(pool_id).await;
+        let user_pool_liquidity_after: UserPoolLiquidity =
+            test_env.fetch_account(user_pool_liquidity_key).await;
+        assert_eq!(
+            pool_state_before.lp_supply - pool_state_after.lp_supply,
+            lp_token_amount
+        );
+        assert_eq!(
+            user_pool_liquidity_before.lp_tokens_owned - user_pool_liquidity_after.lp_tokens_owned,
+            lp_token_amount as u128
+        );
+
+        // Only the output token is sent to the owner
+        let user_other_account_after: TokenAccount =
+            test_env.fetch_account(user_other_account).await;
+        assert_eq!(
+            user_other_account_after.amount,
+            user_other_account_before.amount
+        );
+
+        // The lp share is worth close to twice its output side, only the swap fee and
+        // price impact of swapping the other side are lost.
+        let user_output_account_after: TokenAccount =
+            test_env.fetch_account(user_output_account).await;
+        let received = user_output_account_after.amount - user_output_account_before.amount;
+        let (vault_0_before, vault_1_before) =
+            pool_state_before.vault_amount_without_fee().unwrap();
+        let output_vault_before = if output_mint == test_env.token_0_mint {
+            vault_0_before
+        } else {
+            vault_1_before
+        };
+        let output_value_of_lp = lp_token_amount as u128 * 2 * output_vault_before as u128
+            / pool_state_before.lp_supply as u128;
+        assert!((received as u128) < output_value_of_lp);
+        assert!(received as u128 >= output_value_of_lp * 85 / 100);
+    }
+}
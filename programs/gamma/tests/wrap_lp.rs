@@ -0,0 +1,187 @@
+use anchor_spl::token::{Mint, TokenAccount};
+use gamma::{
+    states::{
+        PoolState, UserPoolLiquidity, UserRewardInfo, USER_POOL_LIQUIDITY_SEED,
+        WRAPPED_LP_MINT_SEED,
+    },
+    LOCK_LP_AMOUNT, REWARD_INFO_SEED, USER_REWARD_INFO_SEED,
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+mod utils;
+
+use utils::*;
+
+#[tokio::test]
+async fn should_wrap_and_unwrap_lp() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+
+    let user_token_0_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_0_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_0_account, 100000, test_env.token_0_mint)
+        .await;
+    let user_token_1_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), test_env.token_1_mint, &user)
+        .await;
+    test_env
+        .mint_base_tokens(user_token_1_account, 100000, test_env.token_1_mint)
+        .await;
+
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            1000,
+            2000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+
+    let user_pool_liquidity_key = Pubkey::find_program_address(
+        &[
+            USER_POOL_LIQUIDITY_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+            user.pubkey().to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    )
+    .0;
+    let (lp_mint, _) = Pubkey::find_program_address(
+        &[WRAPPED_LP_MINT_SEED.as_bytes(), pool_id.to_bytes().as_ref()],
+        &gamma::id(),
+    );
+    let user_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(user_pool_liquidity_key).await;
+    let lp_owned_before = user_pool_liquidity.lp_tokens_owned;
+
+    let owner_lp_token = test_env.wrap_lp(&user, pool_id, 600, vec![]).await;
+
+    let user_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(user_pool_liquidity_key).await;
+    assert_eq!(user_pool_liquidity.lp_tokens_owned, lp_owned_before - 600);
+    let owner_lp_token_account: TokenAccount = test_env.fetch_account(owner_lp_token).await;
+    assert_eq!(owner_lp_token_account.amount, 600);
+    let lp_mint_account: Mint = test_env.fetch_account(lp_mint).await;
+    assert_eq!(lp_mint_account.supply, 600);
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq_with_copy!(pool_state.lp_tokens_wrapped, 600);
+    assert_eq!(
+        pool_state.lp_supply_in_positions().unwrap(),
+        pool_state.lp_supply - 600
+    );
+
+    test_env
+        .unwrap_lp(&user, pool_id, owner_lp_token, 200, vec![])
+        .await;
+
+    let user_pool_liquidity: UserPoolLiquidity =
+        test_env.fetch_account(user_pool_liquidity_key).await;
+    assert_eq!(user_pool_liquidity.lp_tokens_owned, lp_owned_before - 400);
+    let owner_lp_token_account: TokenAccount = test_env.fetch_account(owner_lp_token).await;
+    assert_eq!(owner_lp_token_account.amount, 400);
+    let lp_mint_account: Mint = test_env.fetch_account(lp_mint).await;
+    assert_eq!(lp_mint_account.supply, 400);
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    assert_eq_with_copy!(pool_state.lp_tokens_wrapped, 400);
+}
+
+#[tokio::test]
+async fn wrapped_lp_should_not_dilute_rewards() {
+    let user = Keypair::new();
+    let admin = get_admin();
+    let amm_index = 0;
+    let mut test_env = TestEnv::new(vec![user.pubkey(), admin.pubkey()]).await;
+    test_env
+        .create_config(&admin, amm_index, 100, 20, 5, 0)
+        .await;
+    for mint in [test_env.token_0_mint, test_env.token_1_mint] {
+        let token_account = test_env
+            .get_or_create_associated_token_account(user.pubkey(), mint, &user)
+            .await;
+        test_env.mint_base_tokens(token_account, 100000, mint).await;
+    }
+    let pool_id = test_env
+        .initialize_pool(
+            &user,
+            amm_index,
+            1000,
+            2000,
+            0,
+            gamma::create_pool_fee_reveiver::id(),
+        )
+        .await;
+
+    // Half of the liquidity of the only position is wrapped
+    let pool_state: PoolState = test_env.fetch_account(pool_id).await;
+    let lp_in_position = pool_state.lp_supply - LOCK_LP_AMOUNT;
+    test_env
+        .wrap_lp(&user, pool_id, lp_in_position / 2, vec![])
+        .await;
+
+    let reward_mint = Keypair::new();
+    test_env
+        .create_token_mint(&reward_mint, &test_env.mint_authority.pubkey(), 9)
+        .await;
+    let user_reward_token_account = test_env
+        .get_or_create_associated_token_account(user.pubkey(), reward_mint.pubkey(), &user)
+        .await;
+    let reward_amount = 1000000000;
+    test_env
+        .mint_base_tokens(
+            user_reward_token_account,
+            reward_amount,
+            reward_mint.pubkey(),
+        )
+        .await;
+    let start_time = test_env.timestamp_now().await as u64 + 10;
+    test_env
+        .create_rewards(
+            &user,
+            pool_id,
+            start_time,
+            start_time + 1000,
+            reward_mint.pubkey(),
+            reward_amount,
+        )
+        .await;
+    let reward_info_key = Pubkey::find_program_address(
+        &[
+            REWARD_INFO_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+            &start_time.to_le_bytes(),
+            reward_mint.pubkey().to_bytes().as_ref(),
+        ],
+        &gamma::id(),
+    )
+    .0;
+    test_env.jump_seconds(2000).await;
+
+    // The position earns every reward, the wrapped half does not dilute it
+    test_env
+        .calculate_rewards(&user, pool_id, reward_info_key)
+        .await;
+    let user_reward_info: UserRewardInfo = test_env
+        .fetch_account(
+            Pubkey::find_program_address(
+                &[
+                    USER_REWARD_INFO_SEED.as_bytes(),
+                    reward_info_key.to_bytes().as_ref(),
+                    user.pubkey().to_bytes().as_ref(),
+                ],
+                &gamma::id(),
+            )
+            .0,
+        )
+        .await;
+    assert!(user_reward_info.total_rewards <= reward_amount);
+    assert!(user_reward_info.total_rewards >= reward_amount - 1);
+}